@@ -1,4 +1,4 @@
-use crate::provider::RemoteTable;
+use crate::provider::{RemotePartitioning, RemoteTable};
 use arrow_schema::SchemaRef;
 use async_trait::async_trait;
 use datafusion::datasource::datasource::TableProviderFactory;
@@ -9,6 +9,9 @@ use datafusion_expr::CreateExternalTable;
 use std::ops::Deref;
 use std::sync::Arc;
 
+// Number of partitions of a remote table scan, if it's partitioned but the number isn't specified
+const DEFAULT_PARTITIONS: usize = 4;
+
 /// Factory for creating remote tables
 pub struct RemoteTableFactory {}
 
@@ -19,6 +22,22 @@ impl TableProviderFactory for RemoteTableFactory {
         _ctx: &SessionState,
         cmd: &CreateExternalTable,
     ) -> Result<Arc<dyn TableProvider>> {
+        // Optionally, split the scans into ranges of an integer column's values
+        let partitioning = match cmd.options.get("partition_on") {
+            Some(column) => Some(RemotePartitioning {
+                column: column.clone(),
+                partitions: match cmd.options.get("partitions") {
+                    Some(partitions) => partitions.parse().map_err(|_| {
+                        DataFusionError::Execution(format!(
+                            "Invalid 'partitions' option {partitions:?}"
+                        ))
+                    })?,
+                    None => DEFAULT_PARTITIONS,
+                },
+            }),
+            None => None,
+        };
+
         let table = RemoteTable::new(
             cmd.options
                 .get("name")
@@ -28,6 +47,7 @@ impl TableProviderFactory for RemoteTableFactory {
                 .clone(),
             cmd.location.clone(),
             SchemaRef::from(cmd.schema.deref().clone()),
+            partitioning,
         )
         .await?;
 
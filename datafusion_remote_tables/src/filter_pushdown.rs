@@ -6,7 +6,7 @@ use datafusion::common::{Column, DataFusionError};
 use datafusion::error::Result;
 use datafusion::scalar::ScalarValue;
 use datafusion_expr::expr_visitor::{ExprVisitable, ExpressionVisitor, Recursion};
-use datafusion_expr::{BinaryExpr, Expr, Operator};
+use datafusion_expr::{BinaryExpr, Expr, JoinType, Operator};
 use itertools::Itertools;
 
 pub struct FilterPushdownVisitor<T: FilterPushdownConverter> {
@@ -44,6 +44,15 @@ impl FilterPushdownConverter for SQLiteFilterPushdown {
             _ => Some(op.to_string()),
         }
     }
+
+    fn join_type_to_sql(&self, join_type: &JoinType) -> Option<String> {
+        // RIGHT and FULL joins are only supported in recent SQLite versions
+        match join_type {
+            JoinType::Inner => Some("INNER JOIN".to_string()),
+            JoinType::Left => Some("LEFT JOIN".to_string()),
+            _ => None,
+        }
+    }
 }
 
 impl FilterPushdownConverter for MySQLFilterPushdown {
@@ -61,6 +70,24 @@ impl FilterPushdownConverter for MySQLFilterPushdown {
             _ => Some(op.to_string()),
         }
     }
+
+    fn sort_to_sql(&self, expr_sql: &str, asc: bool, nulls_first: bool) -> String {
+        // MySQL doesn't support NULLS FIRST/LAST, so we sort on the nullness first
+        format!(
+            "{expr_sql} IS NULL {}, {expr_sql} {}",
+            if nulls_first { "DESC" } else { "ASC" },
+            if asc { "ASC" } else { "DESC" }
+        )
+    }
+
+    fn join_type_to_sql(&self, join_type: &JoinType) -> Option<String> {
+        match join_type {
+            JoinType::Inner => Some("INNER JOIN".to_string()),
+            JoinType::Left => Some("LEFT JOIN".to_string()),
+            JoinType::Right => Some("RIGHT JOIN".to_string()),
+            _ => None,
+        }
+    }
 }
 
 pub trait FilterPushdownConverter {
@@ -105,6 +132,47 @@ pub trait FilterPushdownConverter {
     fn op_to_sql(&self, op: &Operator) -> Option<String> {
         Some(op.to_string())
     }
+
+    fn sort_to_sql(&self, expr_sql: &str, asc: bool, nulls_first: bool) -> String {
+        format!(
+            "{expr_sql} {} NULLS {}",
+            if asc { "ASC" } else { "DESC" },
+            if nulls_first { "FIRST" } else { "LAST" }
+        )
+    }
+
+    // Semi/anti joins have no direct SQL equivalent, so they aren't pushed down
+    fn join_type_to_sql(&self, join_type: &JoinType) -> Option<String> {
+        match join_type {
+            JoinType::Inner => Some("INNER JOIN".to_string()),
+            JoinType::Left => Some("LEFT JOIN".to_string()),
+            JoinType::Right => Some("RIGHT JOIN".to_string()),
+            JoinType::Full => Some("FULL JOIN".to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FilterPushdownConverter + ?Sized> FilterPushdownConverter for Box<T> {
+    fn col_to_sql(&self, col: &Column) -> String {
+        self.as_ref().col_to_sql(col)
+    }
+
+    fn scalar_value_to_sql(&self, value: &ScalarValue) -> Option<String> {
+        self.as_ref().scalar_value_to_sql(value)
+    }
+
+    fn op_to_sql(&self, op: &Operator) -> Option<String> {
+        self.as_ref().op_to_sql(op)
+    }
+
+    fn sort_to_sql(&self, expr_sql: &str, asc: bool, nulls_first: bool) -> String {
+        self.as_ref().sort_to_sql(expr_sql, asc, nulls_first)
+    }
+
+    fn join_type_to_sql(&self, join_type: &JoinType) -> Option<String> {
+        self.as_ref().join_type_to_sql(join_type)
+    }
 }
 
 impl<T: FilterPushdownConverter> ExpressionVisitor for FilterPushdownVisitor<T> {
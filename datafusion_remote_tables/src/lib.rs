@@ -1,3 +1,5 @@
 pub mod factory;
 pub mod filter_pushdown;
+pub mod plan_pushdown;
 pub mod provider;
+pub mod scan;
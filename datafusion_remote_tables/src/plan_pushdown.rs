@@ -0,0 +1,744 @@
+//! Optimizer rule pushing whole parts of a query plan (aggregations, joins, sorts with a limit)
+//! that only touch remote tables from the same database down to that database, instead of
+//! fetching all the rows and processing them locally.
+//!
+//! Each plan node gets unparsed into a subquery in the remote SQL dialect, with the output
+//! columns named by their position (`c0`, `c1`, ...), so that we never have to worry about
+//! clashing or unrepresentable column names. The pushed down part of the plan gets replaced
+//! with a scan of a remote table standing for the resulting query.
+use crate::filter_pushdown::{filter_expr_to_sql, FilterPushdownConverter};
+use crate::provider::RemoteTable;
+use arrow::datatypes::{DataType, Field, Schema};
+use datafusion::common::{Column, DFSchema};
+use datafusion::datasource::{provider_as_source, source_as_provider, TableProvider};
+use datafusion::error::Result;
+use datafusion::optimizer::optimizer::{OptimizerConfig, OptimizerRule};
+use datafusion::optimizer::utils::{conjunction, optimize_children};
+use datafusion::scalar::ScalarValue;
+use datafusion_expr::logical_plan::{
+    Aggregate, Join, Limit, Projection, Sort, SubqueryAlias, TableScan,
+};
+use datafusion_expr::utils::{expr_to_columns, from_plan};
+use datafusion_expr::{
+    AggregateFunction, Expr, ExprSchemable, JoinType, LogicalPlan, LogicalPlanBuilder,
+    Operator,
+};
+use itertools::Itertools;
+use log::debug;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Name of the remote table standing for a pushed down query in the rewritten plan
+const PUSHED_DOWN_TABLE: &str = "remote_query";
+
+/// Name of an output column of a pushed down (sub)query
+fn column_alias(index: usize) -> String {
+    format!("c{index}")
+}
+
+pub struct RemotePlanPushdown {}
+
+impl OptimizerRule for RemotePlanPushdown {
+    fn optimize(
+        &self,
+        plan: &LogicalPlan,
+        optimizer_config: &mut OptimizerConfig,
+    ) -> Result<LogicalPlan> {
+        // Push down the largest possible parts of the plan, so try the parent nodes first
+        match push_down_plan(plan)? {
+            Some(new_plan) => Ok(new_plan),
+            None => optimize_children(self, plan, optimizer_config),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "remote_plan_pushdown"
+    }
+}
+
+// Get all tables scanned by the plan, if they're all remote tables from the same database
+fn remote_scans(plan: &LogicalPlan) -> Option<Vec<Arc<dyn TableProvider>>> {
+    let mut scans = vec![];
+    collect_remote_scans(plan, &mut scans)?;
+
+    let first = scans.first()?.as_any().downcast_ref::<RemoteTable>()?;
+    let same_source =
+        scans
+            .iter()
+            .all(|scan| match scan.as_any().downcast_ref::<RemoteTable>() {
+                Some(table) => table.has_same_source(first),
+                None => false,
+            });
+
+    same_source.then_some(scans)
+}
+
+fn collect_remote_scans(
+    plan: &LogicalPlan,
+    scans: &mut Vec<Arc<dyn TableProvider>>,
+) -> Option<()> {
+    if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
+        let provider = source_as_provider(source).ok()?;
+        provider.as_any().downcast_ref::<RemoteTable>()?;
+        scans.push(provider);
+    }
+
+    for input in plan.inputs() {
+        collect_remote_scans(input, scans)?;
+    }
+    Some(())
+}
+
+fn has_node(plan: &LogicalPlan, predicate: &dyn Fn(&LogicalPlan) -> bool) -> bool {
+    predicate(plan)
+        || plan
+            .inputs()
+            .into_iter()
+            .any(|input| has_node(input, predicate))
+}
+
+// Whether pushing down the plan means fetching less data from the remote database: plain
+// projections and filters already get pushed down into the table scans, and sorting is only
+// worth it if there's a limit too.
+fn is_worth_pushing_down(plan: &LogicalPlan, scans: &[Arc<dyn TableProvider>]) -> bool {
+    if has_node(plan, &|node: &LogicalPlan| {
+        matches!(node, LogicalPlan::Aggregate(_) | LogicalPlan::Join(_))
+    }) {
+        return true;
+    }
+
+    // Don't sort the results of an already pushed down query over and over again
+    let already_pushed_down = scans.iter().any(|scan| {
+        scan.as_any()
+            .downcast_ref::<RemoteTable>()
+            .map_or(false, |table| table.is_pushed_down())
+    });
+
+    !already_pushed_down
+        && has_node(plan, &|node: &LogicalPlan| match node {
+            LogicalPlan::Sort(Sort { fetch, .. }) => fetch.is_some(),
+            LogicalPlan::Limit(Limit { input, .. }) => {
+                matches!(input.as_ref(), LogicalPlan::Sort(_))
+            }
+            _ => false,
+        })
+}
+
+// Try to replace the whole plan with a scan of a query against the remote database
+fn push_down_plan(plan: &LogicalPlan) -> Result<Option<LogicalPlan>> {
+    let scans = match remote_scans(plan) {
+        Some(scans) => scans,
+        None => return Ok(None),
+    };
+    if !is_worth_pushing_down(plan, &scans) {
+        return Ok(None);
+    }
+
+    let table = scans[0]
+        .as_any()
+        .downcast_ref::<RemoteTable>()
+        .expect("Remote table expected");
+    let dialect = match table.dialect() {
+        Some(dialect) => dialect,
+        None => return Ok(None),
+    };
+
+    let query = match (PlanUnparser { dialect }).plan_to_sql(plan, true) {
+        Some(query) => query,
+        None => {
+            debug!("Plan not shippable to the remote database:\n{plan:?}");
+            return Ok(None);
+        }
+    };
+    debug!("Pushing down plan as remote query {query}");
+
+    // Scan the results of the query, and rename the columns back to what the rest of the
+    // plan expects to see
+    let schema = plan.schema();
+    let remote_schema = Schema::new(
+        schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, f)| Field::new(&column_alias(i), f.data_type().clone(), true))
+            .collect(),
+    );
+    let remote_table =
+        table.for_pushed_down_query(&query, PUSHED_DOWN_TABLE, Arc::new(remote_schema));
+
+    let scan = LogicalPlanBuilder::scan(
+        PUSHED_DOWN_TABLE,
+        provider_as_source(Arc::new(remote_table)),
+        None,
+    )?
+    .build()?;
+
+    let projection = LogicalPlan::Projection(Projection {
+        expr: schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                Expr::Column(Column::new(Some(PUSHED_DOWN_TABLE), column_alias(i)))
+                    .alias(f.name())
+            })
+            .collect(),
+        input: Arc::new(scan),
+        schema: schema.clone(),
+    });
+
+    restore_ordering(plan, projection).map(Some)
+}
+
+// We can't tell DataFusion that the results of a remote query are already sorted, so keep
+// the sort on top of it (the remote database has already done the heavy lifting by then)
+fn restore_ordering(plan: &LogicalPlan, pushed_down: LogicalPlan) -> Result<LogicalPlan> {
+    match plan {
+        LogicalPlan::Sort(_) => from_plan(plan, &plan.expressions(), &[pushed_down]),
+        LogicalPlan::Limit(Limit { input, .. })
+            if matches!(input.as_ref(), LogicalPlan::Sort(_)) =>
+        {
+            let sort = restore_ordering(input, pushed_down)?;
+            from_plan(plan, &plan.expressions(), &[sort])
+        }
+        _ => Ok(pushed_down),
+    }
+}
+
+// Strings get compared using the remote database's collation, which might not match ours
+// (e.g. MySQL's default collations are case-insensitive), so sorts, MIN/MAX, grouping,
+// DISTINCT and join keys over strings have to happen locally. Expressions of unknown type
+// are treated as strings.
+fn is_string_expr(expr: &Expr, schema: &DFSchema) -> bool {
+    !matches!(
+        expr.get_type(schema),
+        Ok(data_type) if !matches!(
+            data_type,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Dictionary(_, _)
+        )
+    )
+}
+
+// Converts expressions over the outputs of pushed down subqueries to the remote dialect,
+// referring to the columns of each subquery by their position
+struct SubqueryConverter<'a> {
+    dialect: &'a dyn FilterPushdownConverter,
+    inputs: &'a [(&'a str, &'a DFSchema)],
+}
+
+impl FilterPushdownConverter for SubqueryConverter<'_> {
+    fn col_to_sql(&self, col: &Column) -> String {
+        self.inputs
+            .iter()
+            .find_map(|(alias, schema)| {
+                schema
+                    .index_of_column(col)
+                    .ok()
+                    .map(|i| format!("{alias}.{}", column_alias(i)))
+            })
+            // Can't happen, since we only convert expressions whose columns all resolve
+            .unwrap_or_else(|| self.dialect.col_to_sql(col))
+    }
+
+    fn scalar_value_to_sql(&self, value: &ScalarValue) -> Option<String> {
+        self.dialect.scalar_value_to_sql(value)
+    }
+
+    fn op_to_sql(&self, op: &Operator) -> Option<String> {
+        self.dialect.op_to_sql(op)
+    }
+
+    fn sort_to_sql(&self, expr_sql: &str, asc: bool, nulls_first: bool) -> String {
+        self.dialect.sort_to_sql(expr_sql, asc, nulls_first)
+    }
+
+    fn join_type_to_sql(&self, join_type: &JoinType) -> Option<String> {
+        self.dialect.join_type_to_sql(join_type)
+    }
+}
+
+fn strip_alias(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Alias(inner, _) => strip_alias(inner),
+        _ => expr,
+    }
+}
+
+/// Converts a logical plan into an equivalent query for the remote database, returning `None`
+/// if any part of it isn't supported
+struct PlanUnparser {
+    dialect: Box<dyn FilterPushdownConverter>,
+}
+
+impl PlanUnparser {
+    fn expr_to_sql(&self, expr: &Expr, inputs: &[(&str, &DFSchema)]) -> Option<String> {
+        let expr = strip_alias(expr);
+
+        let mut columns = HashSet::new();
+        expr_to_columns(expr, &mut columns).ok()?;
+        if !columns.iter().all(|col| {
+            inputs
+                .iter()
+                .any(|(_, schema)| schema.index_of_column(col).is_ok())
+        }) {
+            return None;
+        }
+
+        filter_expr_to_sql(
+            expr,
+            SubqueryConverter {
+                dialect: self.dialect.as_ref(),
+                inputs,
+            },
+        )
+        .map_err(|err| debug!("Failed constructing SQL for expression {expr}: {err}"))
+        .ok()
+    }
+
+    fn aggregate_to_sql(
+        &self,
+        expr: &Expr,
+        inputs: &[(&str, &DFSchema)],
+    ) -> Option<String> {
+        match strip_alias(expr) {
+            Expr::AggregateFunction {
+                fun,
+                args,
+                distinct,
+                ..
+            } => {
+                let compares_strings = *distinct
+                    || matches!(fun, AggregateFunction::Min | AggregateFunction::Max);
+                if compares_strings
+                    && args.iter().any(|arg| is_string_expr(arg, inputs[0].1))
+                {
+                    return None;
+                }
+
+                let fun = match fun {
+                    AggregateFunction::Count => "COUNT",
+                    AggregateFunction::Sum => "SUM",
+                    AggregateFunction::Min => "MIN",
+                    AggregateFunction::Max => "MAX",
+                    AggregateFunction::Avg => "AVG",
+                    _ => return None,
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.expr_to_sql(arg, inputs))
+                    .collect::<Option<Vec<_>>>()?
+                    .join(", ");
+                let distinct = if *distinct { "DISTINCT " } else { "" };
+
+                Some(format!("{fun}({distinct}{args})"))
+            }
+            _ => None,
+        }
+    }
+
+    fn sort_to_sql(
+        &self,
+        sort: &Sort,
+        fetch: Option<usize>,
+        skip: usize,
+    ) -> Option<String> {
+        let input_sql = self.plan_to_sql(&sort.input, false)?;
+        let inputs = [("t", sort.input.schema().as_ref())];
+
+        let order_by = sort
+            .expr
+            .iter()
+            .map(|expr| match expr {
+                Expr::Sort {
+                    expr,
+                    asc,
+                    nulls_first,
+                } if !is_string_expr(expr, inputs[0].1) => {
+                    Some(self.dialect.sort_to_sql(
+                        &self.expr_to_sql(expr, &inputs)?,
+                        *asc,
+                        *nulls_first,
+                    ))
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?
+            .join(", ");
+
+        let limit_clause =
+            fetch.map_or("".to_string(), |fetch| format!(" LIMIT {fetch}"));
+        let offset_clause = if skip > 0 {
+            format!(" OFFSET {skip}")
+        } else {
+            "".to_string()
+        };
+
+        Some(format!(
+            "SELECT * FROM ({input_sql}) AS t ORDER BY {order_by}{limit_clause}{offset_clause}"
+        ))
+    }
+
+    // The order of the results of a subquery isn't guaranteed to survive in the outer query,
+    // so sorts are only allowed at the root of the plan (or right below a limit there).
+    fn plan_to_sql(&self, plan: &LogicalPlan, allow_sort: bool) -> Option<String> {
+        match plan {
+            LogicalPlan::TableScan(TableScan {
+                source,
+                projected_schema,
+                filters,
+                fetch,
+                ..
+            }) => {
+                let provider = source_as_provider(source).ok()?;
+                let table = provider.as_any().downcast_ref::<RemoteTable>()?;
+
+                let columns = projected_schema
+                    .fields()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        format!(
+                            "{} AS {}",
+                            table.quote_identifier(f.name()),
+                            column_alias(i)
+                        )
+                    })
+                    .join(", ");
+                let where_clause = match conjunction(filters.clone()) {
+                    Some(filter) => {
+                        format!(" WHERE {}", table.filter_expr_to_sql(&filter)?)
+                    }
+                    None => "".to_string(),
+                };
+                let limit_clause =
+                    fetch.map_or("".to_string(), |fetch| format!(" LIMIT {fetch}"));
+
+                Some(format!(
+                    "SELECT {columns} FROM {}{where_clause}{limit_clause}",
+                    table.name()
+                ))
+            }
+            // The output columns are positional, so aliasing the input doesn't change anything
+            LogicalPlan::SubqueryAlias(SubqueryAlias { input, .. }) => {
+                self.plan_to_sql(input, false)
+            }
+            LogicalPlan::Projection(Projection { expr, input, .. }) => {
+                let input_sql = self.plan_to_sql(input, false)?;
+                let inputs = [("t", input.schema().as_ref())];
+
+                let columns = expr
+                    .iter()
+                    .enumerate()
+                    .map(|(i, expr)| {
+                        Some(format!(
+                            "{} AS {}",
+                            self.expr_to_sql(expr, &inputs)?,
+                            column_alias(i)
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()?
+                    .join(", ");
+
+                Some(format!("SELECT {columns} FROM ({input_sql}) AS t"))
+            }
+            LogicalPlan::Filter(_) => {
+                let input = plan.inputs()[0];
+                let input_sql = self.plan_to_sql(input, false)?;
+                let predicate = self.expr_to_sql(
+                    &plan.expressions()[0],
+                    &[("t", input.schema().as_ref())],
+                )?;
+
+                Some(format!(
+                    "SELECT * FROM ({input_sql}) AS t WHERE {predicate}"
+                ))
+            }
+            LogicalPlan::Aggregate(Aggregate {
+                input,
+                group_expr,
+                aggr_expr,
+                ..
+            }) => {
+                let input_sql = self.plan_to_sql(input, false)?;
+                let inputs = [("t", input.schema().as_ref())];
+                if group_expr
+                    .iter()
+                    .any(|expr| is_string_expr(expr, inputs[0].1))
+                {
+                    return None;
+                }
+
+                let group_by = group_expr
+                    .iter()
+                    .map(|expr| self.expr_to_sql(expr, &inputs))
+                    .collect::<Option<Vec<_>>>()?;
+                let aggregates = aggr_expr
+                    .iter()
+                    .map(|expr| self.aggregate_to_sql(expr, &inputs))
+                    .collect::<Option<Vec<_>>>()?;
+
+                // The output of an aggregation is the grouping columns, followed by the aggregates
+                let columns = group_by
+                    .iter()
+                    .chain(aggregates.iter())
+                    .enumerate()
+                    .map(|(i, sql)| format!("{sql} AS {}", column_alias(i)))
+                    .join(", ");
+                let group_by_clause = if group_by.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" GROUP BY {}", group_by.join(", "))
+                };
+
+                Some(format!(
+                    "SELECT {columns} FROM ({input_sql}) AS t{group_by_clause}"
+                ))
+            }
+            LogicalPlan::Join(Join {
+                left,
+                right,
+                on,
+                filter,
+                join_type,
+                null_equals_null,
+                ..
+            }) => {
+                let (left_schema, right_schema) = (left.schema(), right.schema());
+                if *null_equals_null
+                    || on.is_empty()
+                    || on.iter().any(|(l, r)| {
+                        is_string_expr(&Expr::Column(l.clone()), left_schema)
+                            || is_string_expr(&Expr::Column(r.clone()), right_schema)
+                    })
+                {
+                    return None;
+                }
+                let join = self.dialect.join_type_to_sql(join_type)?;
+
+                let left_sql = self.plan_to_sql(left, false)?;
+                let right_sql = self.plan_to_sql(right, false)?;
+
+                let mut conditions = on
+                    .iter()
+                    .map(|(l, r)| {
+                        Some(format!(
+                            "l.{} = r.{}",
+                            column_alias(left_schema.index_of_column(l).ok()?),
+                            column_alias(right_schema.index_of_column(r).ok()?)
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if let Some(filter) = filter {
+                    let inputs =
+                        [("l", left_schema.as_ref()), ("r", right_schema.as_ref())];
+                    conditions.push(format!("({})", self.expr_to_sql(filter, &inputs)?));
+                }
+
+                // The output of a join is the columns of the left side, followed by the right side
+                let left_fields = left_schema.fields().len();
+                let columns = (0..left_fields)
+                    .map(|i| format!("l.{} AS {}", column_alias(i), column_alias(i)))
+                    .chain((0..right_schema.fields().len()).map(|i| {
+                        format!(
+                            "r.{} AS {}",
+                            column_alias(i),
+                            column_alias(left_fields + i)
+                        )
+                    }))
+                    .join(", ");
+
+                Some(format!(
+                    "SELECT {columns} FROM ({left_sql}) AS l {join} ({right_sql}) AS r ON {}",
+                    conditions.join(" AND ")
+                ))
+            }
+            LogicalPlan::Limit(Limit { skip, fetch, input }) => {
+                // Not all databases support an OFFSET without a LIMIT
+                let fetch = (*fetch)?;
+
+                match input.as_ref() {
+                    LogicalPlan::Sort(sort) if allow_sort => {
+                        // Sorting with a limit itself only keeps its first rows
+                        let fetch = match sort.fetch {
+                            Some(sort_fetch) => {
+                                fetch.min(sort_fetch.saturating_sub(*skip))
+                            }
+                            None => fetch,
+                        };
+                        self.sort_to_sql(sort, Some(fetch), *skip)
+                    }
+                    _ => {
+                        let input_sql = self.plan_to_sql(input, false)?;
+                        let offset_clause = if *skip > 0 {
+                            format!(" OFFSET {skip}")
+                        } else {
+                            "".to_string()
+                        };
+                        Some(format!(
+                            "SELECT * FROM ({input_sql}) AS t LIMIT {fetch}{offset_clause}"
+                        ))
+                    }
+                }
+            }
+            LogicalPlan::Sort(sort) if allow_sort => {
+                self.sort_to_sql(sort, sort.fetch, 0)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plan_pushdown::RemotePlanPushdown;
+    use crate::provider::RemoteTable;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::datasource::source_as_provider;
+    use datafusion::optimizer::optimizer::{OptimizerConfig, OptimizerRule};
+    use datafusion::prelude::SessionContext;
+    use datafusion_expr::logical_plan::TableScan;
+    use datafusion_expr::LogicalPlan;
+    use std::sync::Arc;
+
+    async fn make_context() -> SessionContext {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("some value", DataType::Utf8, true),
+        ]));
+
+        let ctx = SessionContext::new();
+        for (name, conn) in [
+            ("t1", "postgresql://user@localhost:5432/db"),
+            ("t2", "postgresql://user@localhost:5432/db"),
+            ("other", "postgresql://user@localhost:5432/other_db"),
+        ] {
+            let table = RemoteTable::new(
+                name.to_string(),
+                conn.to_string(),
+                schema.clone(),
+                None,
+            )
+            .await
+            .unwrap();
+            ctx.register_table(name, Arc::new(table)).unwrap();
+        }
+        ctx
+    }
+
+    fn optimize(ctx: &SessionContext, query: &str) -> LogicalPlan {
+        let plan = ctx.create_logical_plan(query).unwrap();
+        RemotePlanPushdown {}
+            .optimize(&plan, &mut OptimizerConfig::new())
+            .unwrap()
+    }
+
+    // Find the query pushed down into a remote table scan (if any)
+    fn pushed_down_query(plan: &LogicalPlan) -> Option<String> {
+        if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
+            let provider = source_as_provider(source).unwrap();
+            let table = provider.as_any().downcast_ref::<RemoteTable>().unwrap();
+            return table.is_pushed_down().then(|| table.name().to_string());
+        }
+        plan.inputs().into_iter().find_map(pushed_down_query)
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_pushdown() {
+        let ctx = make_context().await;
+        let plan = optimize(
+            &ctx,
+            "SELECT id, COUNT(*), MAX(id) FROM t1 WHERE id > 1 GROUP BY id",
+        );
+
+        assert!(matches!(plan, LogicalPlan::Projection(_)));
+        assert_eq!(
+            pushed_down_query(&plan).unwrap(),
+            "(SELECT t.c0 AS c0, t.c1 AS c1, t.c2 AS c2 FROM (\
+            SELECT t.c0 AS c0, COUNT(1) AS c1, MAX(t.c0) AS c2 FROM (\
+            SELECT * FROM (SELECT \"id\" AS c0, \"some value\" AS c1 FROM t1) AS t WHERE t.c0 > 1\
+            ) AS t GROUP BY t.c0) AS t) AS remote_query"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_join_sort_pushdown() {
+        let ctx = make_context().await;
+        let plan = optimize(
+            &ctx,
+            "SELECT t1.id, t2.\"some value\" FROM t1 JOIN t2 ON t1.id = t2.id \
+            ORDER BY t1.id DESC LIMIT 10",
+        );
+
+        // The results still get sorted locally
+        let sort = match &plan {
+            LogicalPlan::Limit(limit) => limit.input.as_ref(),
+            _ => panic!("Expected a limit, got {plan:?}"),
+        };
+        assert!(matches!(sort, LogicalPlan::Sort(_)));
+        assert_eq!(
+            pushed_down_query(&plan).unwrap(),
+            "(SELECT * FROM (\
+            SELECT t.c0 AS c0, t.c3 AS c1 FROM (\
+            SELECT l.c0 AS c0, l.c1 AS c1, r.c0 AS c2, r.c1 AS c3 FROM (\
+            SELECT \"id\" AS c0, \"some value\" AS c1 FROM t1) AS l INNER JOIN (\
+            SELECT \"id\" AS c0, \"some value\" AS c1 FROM t2) AS r ON l.c0 = r.c0\
+            ) AS t) AS t ORDER BY t.c0 DESC NULLS FIRST LIMIT 10) AS remote_query"
+        );
+
+        // Strings might get sorted differently by the remote database, so only the join
+        // gets pushed down
+        let plan = optimize(
+            &ctx,
+            "SELECT t1.id, t2.\"some value\" FROM t1 JOIN t2 ON t1.id = t2.id \
+            ORDER BY t2.\"some value\" DESC LIMIT 10",
+        );
+        let query = pushed_down_query(&plan).unwrap();
+        assert!(query.contains("INNER JOIN"));
+        assert!(!query.contains("ORDER BY"));
+    }
+
+    #[tokio::test]
+    async fn test_no_pushdown() {
+        let ctx = make_context().await;
+
+        // Nothing to gain from pushing down plain projections and filters
+        let plan = optimize(&ctx, "SELECT id FROM t1 WHERE id > 1");
+        assert_eq!(pushed_down_query(&plan), None);
+
+        // Tables from different databases
+        let plan = optimize(
+            &ctx,
+            "SELECT COUNT(*) FROM t1 JOIN other ON t1.id = other.id",
+        );
+        assert_eq!(pushed_down_query(&plan), None);
+
+        // Unsupported aggregation
+        let plan = optimize(&ctx, "SELECT STDDEV(id) FROM t1");
+        assert_eq!(pushed_down_query(&plan), None);
+
+        // MIN/MAX of strings depend on the collation
+        let plan = optimize(&ctx, "SELECT MAX(\"some value\") FROM t1");
+        assert_eq!(pushed_down_query(&plan), None);
+    }
+
+    #[tokio::test]
+    async fn test_no_string_key_pushdown() {
+        let ctx = make_context().await;
+
+        // Grouping, DISTINCT and join keys over strings depend on the collation too
+        for query in [
+            "SELECT \"some value\", COUNT(*) FROM t1 GROUP BY \"some value\"",
+            "SELECT COUNT(DISTINCT \"some value\") FROM t1",
+            "SELECT t1.id FROM t1 JOIN t2 ON t1.\"some value\" = t2.\"some value\"",
+        ] {
+            let plan = optimize(&ctx, query);
+            assert_eq!(pushed_down_query(&plan), None, "{query}");
+        }
+
+        // Numeric keys can still be pushed down
+        let plan = optimize(&ctx, "SELECT COUNT(DISTINCT id) FROM t1");
+        assert!(pushed_down_query(&plan)
+            .unwrap()
+            .contains("COUNT(DISTINCT t.c0)"));
+    }
+}
@@ -1,26 +1,66 @@
 use crate::filter_pushdown::{
     filter_expr_to_sql, quote_identifier_backticks, quote_identifier_double_quotes,
-    MySQLFilterPushdown, PostgresFilterPushdown, SQLiteFilterPushdown,
+    FilterPushdownConverter, MySQLFilterPushdown, PostgresFilterPushdown,
+    SQLiteFilterPushdown,
 };
+use crate::scan::{query_slots, run_remote_query, RemoteScanExec, RemoteScanQuery};
 use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
-use connectorx::prelude::{get_arrow, ArrowDestination, CXQuery, SourceConn, SourceType};
+use connectorx::prelude::{CXQuery, SourceConn, SourceType};
 use datafusion::common::DataFusionError;
 use datafusion::datasource::TableProvider;
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
 use datafusion::optimizer::utils::conjunction;
-use datafusion::physical_expr::expressions::{cast, col};
-use datafusion::physical_expr::PhysicalExpr;
-use datafusion::physical_plan::memory::MemoryExec;
-use datafusion::physical_plan::projection::ProjectionExec;
 use datafusion::physical_plan::ExecutionPlan;
 use datafusion_expr::{Expr, TableProviderFilterPushDown, TableType};
 use log::debug;
 use std::any::Any;
 use std::ops::Deref;
 use std::sync::Arc;
-use tokio::task;
+use tokio::sync::Semaphore;
+
+/// Splits the scans of a remote table into multiple queries (fetched concurrently) by ranges
+/// of values of an integer column
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemotePartitioning {
+    pub column: String,
+    pub partitions: usize,
+}
+
+/// Split the range of values of the partitioning column into roughly equal parts, returning
+/// the predicate for each part. NULLs go into the first partition. The first and the last
+/// partitions are open-ended, so that no rows get missed if the range has changed since.
+pub(crate) fn partition_predicates(
+    column: &str,
+    min: i64,
+    max: i64,
+    partitions: usize,
+) -> Vec<String> {
+    let (min, max) = (min as i128, max as i128);
+    let partitions = partitions.max(1) as i128;
+    // Round up, so that the partitions cover the whole range
+    let step = (max - min + partitions) / partitions;
+
+    // Lower bounds of all partitions but the first one
+    let bounds = (1..partitions)
+        .map(|i| min + i * step)
+        .take_while(|lower| *lower <= max)
+        .collect::<Vec<_>>();
+    if bounds.is_empty() {
+        return vec![];
+    }
+
+    let mut predicates = vec![format!("({column} < {} OR {column} IS NULL)", bounds[0])];
+    predicates.extend(
+        bounds.windows(2).map(|range| {
+            format!("({column} >= {} AND {column} < {})", range[0], range[1])
+        }),
+    );
+    predicates.push(format!("({column} >= {})", bounds[bounds.len() - 1]));
+    predicates
+}
 
 // Implementation of a remote table, capable of querying Postgres, MySQL, SQLite, etc...
 pub struct RemoteTable {
@@ -29,10 +69,19 @@ pub struct RemoteTable {
     name: Arc<str>,
     schema: SchemaRef,
     source_conn: SourceConn,
+    query_slots: Arc<Semaphore>,
+    partitioning: Option<RemotePartitioning>,
+    // Whether this table stands for a query that has been pushed down from a larger plan
+    pushed_down: bool,
 }
 
 impl RemoteTable {
-    pub async fn new(name: String, conn: String, schema: SchemaRef) -> Result<Self> {
+    pub async fn new(
+        name: String,
+        conn: String,
+        schema: SchemaRef,
+        partitioning: Option<RemotePartitioning>,
+    ) -> Result<Self> {
         let mut source_conn = SourceConn::try_from(conn.as_str()).map_err(|e| {
             DataFusionError::Execution(format!(
                 "Failed initialising the remote table connection {e:?}"
@@ -47,48 +96,87 @@ impl RemoteTable {
             name: Arc::from(name.clone()),
             schema: schema.clone(),
             source_conn,
+            query_slots: query_slots(&conn),
+            partitioning,
+            pushed_down: false,
         };
 
         if schema.fields().is_empty() {
-            let one_row = vec![CXQuery::from(
-                format!("SELECT * FROM {name} LIMIT 1").as_str(),
-            )];
-
             // Introspect the schema
-            remote_table.schema = remote_table.run_queries(one_row).await?.arrow_schema();
+            let (one_row_schema, _) = remote_table
+                .run_query(format!("SELECT * FROM {name} LIMIT 1"))
+                .await?;
+            remote_table.schema = one_row_schema;
         }
 
         Ok(remote_table)
     }
 
-    async fn run_queries(
+    /// A table standing for the results of a query (pushed down from a larger plan) against
+    /// the same remote database as this table
+    pub(crate) fn for_pushed_down_query(
         &self,
-        queries: Vec<CXQuery<String>>,
-    ) -> Result<ArrowDestination> {
-        // TODO: prettify the errors a bit
-        let source_conn = self.source_conn.clone();
+        query: &str,
+        alias: &str,
+        schema: SchemaRef,
+    ) -> Self {
+        Self {
+            name: Arc::from(format!("({query}) AS {alias}")),
+            schema,
+            source_conn: self.source_conn.clone(),
+            query_slots: self.query_slots.clone(),
+            partitioning: None,
+            pushed_down: true,
+        }
+    }
 
-        task::spawn_blocking(move || {
-            get_arrow(&source_conn, None, queries.as_slice()).map_err(|e| {
-                DataFusionError::Execution(format!(
-                    "Failed running the remote query {e:?}"
-                ))
-            })
-        })
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn is_pushed_down(&self) -> bool {
+        self.pushed_down
+    }
+
+    /// Whether the two tables live in the same remote database (and so can be queried together)
+    pub(crate) fn has_same_source(&self, other: &RemoteTable) -> bool {
+        // The connection URL also determines the type of the database
+        self.source_conn.conn == other.source_conn.conn
+    }
+
+    async fn run_query(&self, query: String) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+        // TODO: prettify the errors a bit
+        run_remote_query(
+            self.source_conn.clone(),
+            self.query_slots.clone(),
+            CXQuery::from(query.as_str()),
+        )
         .await
-        .map_err(|e| {
-            DataFusionError::Execution(format!("Failed executing the remote query {e:?}"))
-        })?
+    }
+
+    pub(crate) fn quote_identifier(&self, identifier: &str) -> String {
+        match self.source_conn.ty {
+            SourceType::MySQL => quote_identifier_backticks(identifier),
+            _ => quote_identifier_double_quotes(identifier),
+        }
+    }
+
+    // Get the converter of expressions to the SQL dialect of the remote data source, if supported
+    pub(crate) fn dialect(&self) -> Option<Box<dyn FilterPushdownConverter>> {
+        match self.source_conn.ty {
+            SourceType::Postgres => Some(Box::new(PostgresFilterPushdown {})),
+            SourceType::SQLite => Some(Box::new(SQLiteFilterPushdown {})),
+            SourceType::MySQL => Some(Box::new(MySQLFilterPushdown {})),
+            _ => None,
+        }
     }
 
     // Convert the DataFusion expression representing a filter to an equivalent SQL string for the
     // remote data source if the entire filter can be pushed down.
-    fn filter_expr_to_sql(&self, filter: &Expr) -> Option<String> {
-        let result = match self.source_conn.ty {
-            SourceType::Postgres => filter_expr_to_sql(filter, PostgresFilterPushdown {}),
-            SourceType::SQLite => filter_expr_to_sql(filter, SQLiteFilterPushdown {}),
-            SourceType::MySQL => filter_expr_to_sql(filter, MySQLFilterPushdown {}),
-            _ => {
+    pub(crate) fn filter_expr_to_sql(&self, filter: &Expr) -> Option<String> {
+        let dialect = match self.dialect() {
+            Some(dialect) => dialect,
+            None => {
                 debug!(
                     "Filter not shippable due to unsupported source type {:?}",
                     self.source_conn.ty
@@ -97,7 +185,7 @@ impl RemoteTable {
             }
         };
 
-        result
+        filter_expr_to_sql(filter, dialect)
             .map_err(|err| debug!("Failed constructing SQL for filter {filter}: {err}"))
             .ok()
     }
@@ -124,8 +212,6 @@ impl TableProvider for RemoteTable {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        // TODO: partition query by some column to utilize concurrent fetching
-
         // Scope down the schema and query column specifiers if a projection is specified
         let mut schema = self.schema.deref().clone();
         let mut columns = "*".to_string();
@@ -135,21 +221,15 @@ impl TableProvider for RemoteTable {
             columns = schema
                 .fields()
                 .iter()
-                .map(|f| match self.source_conn.ty {
-                    SourceType::MySQL => quote_identifier_backticks(f.name()),
-                    _ => quote_identifier_double_quotes(f.name()),
-                })
+                .map(|f| self.quote_identifier(f.name()))
                 .collect::<Vec<String>>()
                 .join(", ")
         }
 
-        // Apply LIMIT if any
-        let limit_clause = limit.map_or("".to_string(), |size| format!(" LIMIT {size}"));
-
         // Try to construct the WHERE clause: all passed filters should be eligible for pushdown as
         // they've past the checks in `supports_filter_pushdown`
-        let where_clause = if filters.is_empty() {
-            "".to_string()
+        let filters_sql = if filters.is_empty() {
+            None
         } else {
             // NB: Given that all supplied filters have passed the shipabilty check individually,
             // there should be no harm in merging them together and converting that to equivalent SQL
@@ -164,60 +244,35 @@ impl TableProvider for RemoteTable {
                         "Failed converting filter to SQL {merged_filter}"
                     ))
                 })?;
-            format!(" WHERE {filters_sql}")
+            Some(filters_sql)
         };
 
-        // Construct and run the remote query
-        let queries = vec![CXQuery::from(
-            format!(
-                "SELECT {} FROM {}{}{}",
-                columns, self.name, where_clause, limit_clause
-            )
-            .as_str(),
-        )];
-
-        let arrow_data = self.run_queries(queries).await?;
-        let src_schema = arrow_data.arrow_schema().deref().clone();
-        let data = arrow_data.arrow().map_err(|e| {
-            DataFusionError::Execution(format!(
-                "Failed extracting the fetched data {e:?}"
-            ))
-        })?;
-
-        let mut plan: Arc<dyn ExecutionPlan> = Arc::new(MemoryExec::try_new(
-            &[data],
-            Arc::new(src_schema.clone()),
-            None,
-        )?);
-
-        if src_schema != schema {
-            // Try to cast each referenced column to the data type originally specified by the user
-            let cast_exprs: Result<Vec<(Arc<dyn PhysicalExpr>, String)>> = schema
-                .fields
-                .iter()
-                .zip(src_schema.fields().iter())
-                .map(|(f, src_f)| {
-                    let col_expr = col(f.name(), &schema)?;
-
-                    if src_f != f {
-                        Ok((
-                            cast(col_expr, &src_schema, f.data_type().clone())?,
-                            f.name().to_string(),
-                        ))
-                    } else {
-                        Ok((col_expr, f.name().to_string()))
-                    }
-                })
-                .collect();
-
-            debug!(
-                "Using cast for projecting remote table {} fields: {:?}",
-                self.name, cast_exprs
-            );
-            plan = Arc::new(ProjectionExec::try_new(cast_exprs?, plan)?)
-        }
+        // Split the scan into partitions (unless it's limited, since the limit would have to
+        // apply to all of them together). The partitions' ranges only get looked up when the
+        // scan is executed.
+        let partitioning = match &self.partitioning {
+            Some(partitioning) if partitioning.partitions > 1 && limit.is_none() => {
+                Some((
+                    self.quote_identifier(&partitioning.column),
+                    partitioning.partitions,
+                ))
+            }
+            _ => None,
+        };
 
-        Ok(plan)
+        // The fetched columns get cast to the data types originally specified by the user
+        Ok(Arc::new(RemoteScanExec::new(
+            self.source_conn.clone(),
+            self.query_slots.clone(),
+            Arc::new(schema),
+            RemoteScanQuery {
+                table: self.name.clone(),
+                columns,
+                filters_sql,
+                limit,
+                partitioning,
+            },
+        )))
     }
 
     fn supports_filter_pushdown(
@@ -235,3 +290,39 @@ impl TableProvider for RemoteTable {
         Ok(TableProviderFilterPushDown::Exact)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::provider::partition_predicates;
+
+    #[test]
+    fn test_partition_predicates() {
+        assert_eq!(
+            partition_predicates("\"id\"", 1, 10, 3),
+            vec![
+                "(\"id\" < 5 OR \"id\" IS NULL)",
+                "(\"id\" >= 5 AND \"id\" < 9)",
+                "(\"id\" >= 9)",
+            ]
+        );
+
+        // Don't make empty partitions
+        assert_eq!(
+            partition_predicates("id", 1, 2, 4),
+            vec!["(id < 2 OR id IS NULL)", "(id >= 2)"]
+        );
+
+        // Nothing to split
+        assert!(partition_predicates("id", 1, 1, 4).is_empty());
+        assert!(partition_predicates("id", i64::MIN, i64::MAX, 1).is_empty());
+
+        // The whole range of values is covered
+        assert_eq!(
+            partition_predicates("id", i64::MIN, i64::MAX, 2),
+            vec![
+                "(id < 0 OR id IS NULL)".to_string(),
+                "(id >= 0)".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use crate::provider::partition_predicates;
+use arrow::array::{Array, Int64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::error::{ArrowError, Result as ArrowResult};
+use arrow::record_batch::RecordBatch;
+use connectorx::prelude::{get_arrow, new_record_batch_iter, CXQuery, SourceConn};
+use datafusion::common::DataFusionError;
+use datafusion::error::Result;
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::stream::RecordBatchReceiverStream;
+use datafusion::physical_plan::{
+    DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream, Statistics,
+};
+use lazy_static::lazy_static;
+use log::debug;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, OnceCell, OwnedSemaphorePermit, Semaphore};
+use tokio::task::{self, JoinError};
+
+// Maximum number of queries that we run against a single remote database at the same time.
+// Each query opens (and closes) its own connection: connectorx builds a new pool for every
+// source it runs queries on and doesn't let us pass in one we keep around, so this is only
+// a limit on the number of connections open at once, not a connection pool.
+const MAX_QUERIES_PER_SOURCE: usize = 8;
+
+// Number of fetched batches that can be waiting for the consumer of a partition
+const STREAM_BUFFER_SIZE: usize = 2;
+
+lazy_static! {
+    // Query slots for each remote database, shared by all scans (and tables) pointing to it,
+    // so that a query against a heavily partitioned table can't exhaust its connections
+    static ref QUERY_SLOTS: Mutex<HashMap<String, Arc<Semaphore>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Get the query slots for a remote database
+pub fn query_slots(conn: &str) -> Arc<Semaphore> {
+    QUERY_SLOTS
+        .lock()
+        .unwrap()
+        .entry(conn.to_string())
+        .or_insert_with(|| Arc::new(Semaphore::new(MAX_QUERIES_PER_SOURCE)))
+        .clone()
+}
+
+/// Wait for a free query slot of a remote database. The slot should be held for as long
+/// as the query's connection is open.
+async fn acquire_query_slot(query_slots: Arc<Semaphore>) -> Result<OwnedSemaphorePermit> {
+    query_slots.acquire_owned().await.map_err(|e| {
+        DataFusionError::Execution(format!("Failed acquiring a remote query slot {e:?}"))
+    })
+}
+
+/// Describe the error of a blocking task that ran a remote query (connectorx panics on some
+/// errors, e.g. if it can't connect)
+fn remote_task_error(e: JoinError) -> DataFusionError {
+    let message = match e.try_into_panic() {
+        Ok(panic) => panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "unknown error".to_string()),
+        Err(e) => format!("{e:?}"),
+    };
+    DataFusionError::Execution(format!("Failed executing the remote query: {message}"))
+}
+
+/// Run a query against the remote database, waiting for a free query slot first.
+/// Returns the schema of the result along with its batches. This loads the whole result
+/// into memory, so it's only meant for small queries (e.g. introspection); scans stream
+/// their results instead.
+pub async fn run_remote_query(
+    source_conn: SourceConn,
+    query_slots: Arc<Semaphore>,
+    query: CXQuery<String>,
+) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    let _permit = acquire_query_slot(query_slots).await?;
+
+    task::spawn_blocking(move || {
+        let destination = get_arrow(&source_conn, None, &[query]).map_err(|e| {
+            DataFusionError::Execution(format!("Failed running the remote query {e:?}"))
+        })?;
+        let schema = destination.arrow_schema();
+        let batches = destination.arrow().map_err(|e| {
+            DataFusionError::Execution(format!(
+                "Failed extracting the fetched data {e:?}"
+            ))
+        })?;
+        Ok((schema, batches))
+    })
+    .await
+    .map_err(remote_task_error)?
+}
+
+/// Run a query against the remote database, passing its batches on as they get fetched.
+/// The query slot is held until the whole result has been fetched (or the receiver
+/// has gone away).
+async fn stream_remote_query(
+    source_conn: SourceConn,
+    query_slots: Arc<Semaphore>,
+    query: CXQuery<String>,
+    schema: SchemaRef,
+    batch_size: usize,
+    tx: mpsc::Sender<ArrowResult<RecordBatch>>,
+) -> Result<()> {
+    let permit = acquire_query_slot(query_slots).await?;
+
+    task::spawn_blocking(move || {
+        let _permit = permit;
+        let mut batches = new_record_batch_iter(&source_conn, None, &[query], batch_size);
+        batches.prepare();
+
+        while let Some(batch) = batches.next_batch() {
+            // Stop if the consumer has gone away
+            if tx.blocking_send(cast_batch(batch, &schema)).is_err() {
+                break;
+            }
+        }
+    })
+    .await
+    .map_err(remote_task_error)
+}
+
+/// Cast the columns of a fetched batch to the data types originally specified by the user
+fn cast_batch(batch: RecordBatch, schema: &SchemaRef) -> ArrowResult<RecordBatch> {
+    if batch.schema().fields() == schema.fields() {
+        return Ok(batch);
+    }
+
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| {
+            if column.data_type() == field.data_type() {
+                Ok(column.clone())
+            } else {
+                cast(column, field.data_type())
+            }
+        })
+        .collect::<ArrowResult<Vec<_>>>()?;
+
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+/// Query that a scan of a remote table runs against the remote database
+#[derive(Debug)]
+pub(crate) struct RemoteScanQuery {
+    // The (possibly aliased) table, or the subquery, to select from
+    pub table: Arc<str>,
+    // The (quoted) columns to select
+    pub columns: String,
+    // Filters that have been pushed down, as SQL
+    pub filters_sql: Option<String>,
+    pub limit: Option<usize>,
+    // Split the scan into ranges of values of this (quoted) column
+    pub partitioning: Option<(String, usize)>,
+}
+
+impl RemoteScanQuery {
+    fn partition_count(&self) -> usize {
+        self.partitioning
+            .as_ref()
+            .map_or(1, |(_, partitions)| *partitions)
+    }
+
+    fn to_sql(&self, predicate: Option<&str>) -> String {
+        let where_clause = match (&self.filters_sql, predicate) {
+            (Some(sql), Some(predicate)) => format!(" WHERE ({sql}) AND {predicate}"),
+            (Some(sql), None) => format!(" WHERE {sql}"),
+            (None, Some(predicate)) => format!(" WHERE {predicate}"),
+            (None, None) => "".to_string(),
+        };
+        let limit_clause = self
+            .limit
+            .map_or("".to_string(), |size| format!(" LIMIT {size}"));
+
+        format!(
+            "SELECT {} FROM {}{where_clause}{limit_clause}",
+            self.columns, self.table
+        )
+    }
+
+    /// Build the queries for each partition of the scan. This looks up the range of values
+    /// of the partitioning column, so it only happens when the scan gets executed.
+    async fn partition_queries(
+        &self,
+        source_conn: SourceConn,
+        query_slots: Arc<Semaphore>,
+    ) -> Result<Vec<CXQuery<String>>> {
+        let predicates = match &self.partitioning {
+            Some((column, partitions)) if *partitions > 1 && self.limit.is_none() => {
+                let where_clause = self
+                    .filters_sql
+                    .as_ref()
+                    .map_or("".to_string(), |sql| format!(" WHERE {sql}"));
+                let (_, bounds) = run_remote_query(
+                    source_conn,
+                    query_slots,
+                    CXQuery::from(
+                        format!(
+                            "SELECT MIN({column}), MAX({column}) FROM {}{where_clause}",
+                            self.table
+                        )
+                        .as_str(),
+                    ),
+                )
+                .await?;
+
+                let bound = |i: usize| -> Result<Option<i64>> {
+                    let array = match bounds.first() {
+                        Some(batch) if batch.num_rows() == 1 => {
+                            cast(batch.column(i), &DataType::Int64)?
+                        }
+                        _ => return Ok(None),
+                    };
+                    let array = array.as_any().downcast_ref::<Int64Array>().unwrap();
+                    Ok((!array.is_null(0)).then(|| array.value(0)))
+                };
+
+                match (bound(0)?, bound(1)?) {
+                    (Some(min), Some(max)) => {
+                        partition_predicates(column, min, max, *partitions)
+                    }
+                    // Nothing to partition (or only NULLs)
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        };
+
+        Ok(if predicates.len() > 1 {
+            predicates
+                .iter()
+                .map(|predicate| CXQuery::from(self.to_sql(Some(predicate)).as_str()))
+                .collect()
+        } else {
+            vec![CXQuery::from(self.to_sql(None).as_str())]
+        })
+    }
+}
+
+/// Scan of a remote table, running one query per partition. Each partition's batches get
+/// passed on as soon as its query returns them, so partitions don't wait on each other and
+/// only a few batches of each partition have to be held in memory at a time.
+#[derive(Debug)]
+pub struct RemoteScanExec {
+    source_conn: SourceConn,
+    query_slots: Arc<Semaphore>,
+    schema: SchemaRef,
+    query: Arc<RemoteScanQuery>,
+    // Queries for each partition, built when the first partition gets executed
+    partition_queries: Arc<OnceCell<Vec<CXQuery<String>>>>,
+}
+
+impl RemoteScanExec {
+    pub(crate) fn new(
+        source_conn: SourceConn,
+        query_slots: Arc<Semaphore>,
+        schema: SchemaRef,
+        query: RemoteScanQuery,
+    ) -> Self {
+        Self {
+            source_conn,
+            query_slots,
+            schema,
+            query: Arc::new(query),
+            partition_queries: Arc::new(OnceCell::new()),
+        }
+    }
+}
+
+impl ExecutionPlan for RemoteScanExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.query.partition_count())
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        if partition >= self.query.partition_count() {
+            return Err(DataFusionError::Internal(format!(
+                "Remote scan has no partition {partition}"
+            )));
+        }
+
+        let source_conn = self.source_conn.clone();
+        let query_slots = self.query_slots.clone();
+        let schema = self.schema.clone();
+        let query = self.query.clone();
+        let partition_queries = self.partition_queries.clone();
+        let batch_size = context.session_config().batch_size();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+
+        let join_handle = task::spawn(async move {
+            let result = async {
+                let queries = partition_queries
+                    .get_or_try_init(|| {
+                        query.partition_queries(source_conn.clone(), query_slots.clone())
+                    })
+                    .await?;
+
+                // There might be fewer partitions than planned, e.g. for small ranges
+                match queries.get(partition) {
+                    Some(query) => {
+                        debug!("Running remote query {query:?}");
+                        stream_remote_query(
+                            source_conn,
+                            query_slots,
+                            query.clone(),
+                            schema,
+                            batch_size,
+                            tx.clone(),
+                        )
+                        .await
+                    }
+                    None => Ok(()),
+                }
+            }
+            .await;
+
+            if let Err(e) = result {
+                let _ = tx.send(Err(ArrowError::ExternalError(Box::new(e)))).await;
+            }
+        });
+
+        Ok(RecordBatchReceiverStream::create(
+            &self.schema,
+            rx,
+            join_handle,
+        ))
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(
+                    f,
+                    "RemoteScanExec: partitions={}",
+                    self.query.partition_count()
+                )
+            }
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scan::cast_batch;
+    use arrow::array::{ArrayRef, Int32Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cast_batch() {
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)])),
+            vec![Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef],
+        )
+        .unwrap();
+
+        // Nothing to cast
+        let cast = cast_batch(batch.clone(), &batch.schema()).unwrap();
+        assert_eq!(cast, batch);
+
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let cast = cast_batch(batch, &schema).unwrap();
+        assert_eq!(cast.schema(), schema);
+        assert_eq!(
+            cast.column(0)
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .unwrap(),
+            &Int32Array::from(vec![1, 2])
+        );
+    }
+}
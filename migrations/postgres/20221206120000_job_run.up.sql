@@ -0,0 +1,8 @@
+CREATE TABLE job_run (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    job_name VARCHAR NOT NULL,
+    start_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT(now()),
+    duration_ms BIGINT,
+    status VARCHAR NOT NULL,
+    error VARCHAR
+);
@@ -0,0 +1,3 @@
+ALTER TABLE "function" ADD COLUMN kind VARCHAR NOT NULL DEFAULT 'Scalar';
+-- JSON-serialized columns returned by table functions
+ALTER TABLE "function" ADD COLUMN return_columns VARCHAR NOT NULL DEFAULT '[]';
@@ -0,0 +1,2 @@
+-- JSON-serialized resource limits of the function (fuel, timeout and memory)
+ALTER TABLE "function" ADD COLUMN limits VARCHAR NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+ALTER TABLE "function" DROP CONSTRAINT function_signature_unique;
+ALTER TABLE "function" ADD CONSTRAINT function_name_unique UNIQUE(name, database_id);
+ALTER TABLE "function" DROP COLUMN signature;
@@ -0,0 +1,16 @@
+-- Allow overloading functions by their input types. Overloads are told apart by their
+-- signature: the JSON array of input type names, with aliases of the same type (e.g. INT
+-- and I32) collapsed into one (see `function_signature`).
+ALTER TABLE "function" ADD COLUMN signature VARCHAR;
+UPDATE "function" SET signature = REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(input_types,
+    '"i32"', '"int"'),
+    '"i64"', '"bigint"'),
+    '"f32"', '"real"'),
+    '"float"', '"real"'),
+    '"f64"', '"double"'),
+    '"char"', '"text"'),
+    '"varchar"', '"text"');
+ALTER TABLE "function" ALTER COLUMN signature SET NOT NULL;
+
+ALTER TABLE "function" DROP CONSTRAINT function_name_unique;
+ALTER TABLE "function" ADD CONSTRAINT function_signature_unique UNIQUE(name, database_id, signature);
@@ -0,0 +1,2 @@
+ALTER TABLE "function" DROP COLUMN sha256;
+ALTER TABLE "function" DROP COLUMN location;
@@ -0,0 +1,2 @@
+-- JSON-serialized WASI capabilities of the function (environment, clocks and randomness)
+ALTER TABLE "function" ADD COLUMN wasi VARCHAR NOT NULL DEFAULT '{}';
@@ -0,0 +1,6 @@
+-- Either 'table' (a regular versioned Seafowl table) or 'view'
+ALTER TABLE "table" ADD COLUMN kind VARCHAR NOT NULL DEFAULT 'table';
+-- SQL text of the query defining a view
+ALTER TABLE "table" ADD COLUMN definition VARCHAR;
+-- JSON-serialized Arrow schema of the view's output
+ALTER TABLE "table" ADD COLUMN schema VARCHAR;
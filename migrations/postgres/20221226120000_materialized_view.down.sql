@@ -0,0 +1,2 @@
+UPDATE "table" SET kind = 'table', definition = NULL WHERE kind = 'materialized_view';
+ALTER TABLE "table" DROP COLUMN auto_refresh;
@@ -0,0 +1,4 @@
+-- Materialized views are regular tables with kind = 'materialized_view' and the query
+-- that populates them stored in `definition`. This flags the ones that should get
+-- refreshed whenever one of the tables they read from gets a new version.
+ALTER TABLE "table" ADD COLUMN auto_refresh BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE physical_partition_column DROP COLUMN distinct_sketch;
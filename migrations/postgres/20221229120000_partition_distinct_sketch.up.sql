@@ -0,0 +1,2 @@
+-- Serialized HyperLogLog sketch of the column's distinct values in the partition (see src/statistics.rs)
+ALTER TABLE physical_partition_column ADD COLUMN distinct_sketch BYTEA;
@@ -0,0 +1 @@
+ALTER TABLE table_column DROP COLUMN distinct_count;
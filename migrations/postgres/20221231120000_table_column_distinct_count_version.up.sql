@@ -0,0 +1,3 @@
+-- Table version that the distinct count was computed at by ANALYZE TABLE, so that new
+-- versions can stop inheriting it once it gets too old
+ALTER TABLE table_column ADD COLUMN distinct_count_table_version_id BIGINT;
@@ -0,0 +1,8 @@
+CREATE TABLE job_run (
+    id INTEGER NOT NULL PRIMARY KEY,
+    job_name VARCHAR NOT NULL,
+    start_time INTEGER(4) NOT NULL DEFAULT((strftime('%s','now'))),
+    duration_ms BIGINT,
+    status VARCHAR NOT NULL,
+    error VARCHAR
+);
@@ -0,0 +1,16 @@
+CREATE TABLE external_table (
+    id INTEGER NOT NULL PRIMARY KEY,
+    collection_id BIGINT NOT NULL REFERENCES collection(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    -- Location with the credentials (if any) stripped out
+    location VARCHAR NOT NULL,
+    -- `user:password` part of the location URL, in plaintext. Locations (and option values)
+    -- can be `env:NAME` references to environment variables instead, which don't get stored.
+    credentials VARCHAR,
+    file_type VARCHAR NOT NULL,
+    -- JSON-serialized ExternalTableOptions (in plaintext too)
+    options VARCHAR NOT NULL,
+    -- JSON-serialized Arrow schema
+    schema VARCHAR NOT NULL,
+    CONSTRAINT external_table_name_unique UNIQUE(name, collection_id)
+);
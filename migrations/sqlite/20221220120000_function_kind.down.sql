@@ -0,0 +1,2 @@
+ALTER TABLE "function" DROP COLUMN return_columns;
+ALTER TABLE "function" DROP COLUMN kind;
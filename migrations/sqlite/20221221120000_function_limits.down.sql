@@ -0,0 +1 @@
+ALTER TABLE "function" DROP COLUMN limits;
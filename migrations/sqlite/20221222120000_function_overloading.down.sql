@@ -0,0 +1,27 @@
+CREATE TABLE function_old (
+    id INTEGER NOT NULL PRIMARY KEY,
+    database_id BIGINT NOT NULL REFERENCES database(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    entrypoint VARCHAR NOT NULL,
+    language VARCHAR NOT NULL,
+    input_types VARCHAR NOT NULL,
+    return_type VARCHAR NOT NULL,
+    data VARCHAR NOT NULL,
+    volatility VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL DEFAULT 'Scalar',
+    return_columns VARCHAR NOT NULL DEFAULT '[]',
+    limits VARCHAR NOT NULL DEFAULT '{}',
+    CONSTRAINT function_name_unique UNIQUE(name, database_id)
+);
+
+INSERT INTO function_old (
+    id, database_id, name, entrypoint, language, input_types, return_type, data, volatility,
+    kind, return_columns, limits
+)
+SELECT
+    id, database_id, name, entrypoint, language, input_types, return_type, data, volatility,
+    kind, return_columns, limits
+FROM "function";
+
+DROP TABLE "function";
+ALTER TABLE function_old RENAME TO "function";
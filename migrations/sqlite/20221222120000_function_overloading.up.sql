@@ -0,0 +1,40 @@
+-- Allow overloading functions by their input types. Overloads are told apart by their
+-- signature: the JSON array of input type names, with aliases of the same type (e.g. INT
+-- and I32) collapsed into one (see `function_signature`). SQLite can't drop constraints,
+-- so we have to recreate the table.
+CREATE TABLE function_new (
+    id INTEGER NOT NULL PRIMARY KEY,
+    database_id BIGINT NOT NULL REFERENCES database(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    entrypoint VARCHAR NOT NULL,
+    language VARCHAR NOT NULL,
+    input_types VARCHAR NOT NULL,
+    return_type VARCHAR NOT NULL,
+    data VARCHAR NOT NULL,
+    volatility VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL DEFAULT 'Scalar',
+    return_columns VARCHAR NOT NULL DEFAULT '[]',
+    limits VARCHAR NOT NULL DEFAULT '{}',
+    signature VARCHAR NOT NULL,
+    CONSTRAINT function_signature_unique UNIQUE(name, database_id, signature)
+);
+
+INSERT INTO function_new (
+    id, database_id, name, entrypoint, language, input_types, return_type, data, volatility,
+    kind, return_columns, limits, signature
+)
+SELECT
+    id, database_id, name, entrypoint, language, input_types, return_type, data, volatility,
+    kind, return_columns, limits,
+    REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(input_types,
+        '"i32"', '"int"'),
+        '"i64"', '"bigint"'),
+        '"f32"', '"real"'),
+        '"float"', '"real"'),
+        '"f64"', '"double"'),
+        '"char"', '"text"'),
+        '"varchar"', '"text"')
+FROM "function";
+
+DROP TABLE "function";
+ALTER TABLE function_new RENAME TO "function";
@@ -0,0 +1,4 @@
+-- URL of the function's WASM module (stored content-addressed in the internal object
+-- store) and its SHA-256 digest, for functions that don't have their module inline
+ALTER TABLE "function" ADD COLUMN location VARCHAR;
+ALTER TABLE "function" ADD COLUMN sha256 VARCHAR;
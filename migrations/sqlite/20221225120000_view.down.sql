@@ -0,0 +1,4 @@
+DELETE FROM "table" WHERE kind = 'view';
+ALTER TABLE "table" DROP COLUMN schema;
+ALTER TABLE "table" DROP COLUMN definition;
+ALTER TABLE "table" DROP COLUMN kind;
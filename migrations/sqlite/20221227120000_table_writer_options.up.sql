@@ -0,0 +1,2 @@
+-- JSON-serialized Parquet writer settings for the table's partitions (compression etc.)
+ALTER TABLE "table" ADD COLUMN writer_options VARCHAR;
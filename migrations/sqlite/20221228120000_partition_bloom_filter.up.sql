@@ -0,0 +1,2 @@
+-- Serialized Bloom filter over the column's values in the partition (see src/bloom_filter.rs)
+ALTER TABLE physical_partition_column ADD COLUMN bloom_filter BLOB;
@@ -0,0 +1,2 @@
+-- Estimated number of distinct values in the column, as of the last ANALYZE TABLE
+ALTER TABLE table_column ADD COLUMN distinct_count BIGINT;
@@ -0,0 +1,19 @@
+;; Source of arrow_identity.wasm, used by the WASM + Arrow UDF tests
+(module
+  (memory (export "memory") 16)
+  (global $heap (mut i32) (i32.const 1024))
+  (func $alloc (export "alloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+    (local.get $ptr))
+  (func (export "dealloc") (param i32 i32))
+  ;; Return a copy of the (length-prefixed) Arrow IPC input. For a single argument,
+  ;; that's a valid result too, which makes this the identity function.
+  (func (export "identity") (param $input i32) (result i32)
+    (local $size i32)
+    (local $ptr i32)
+    (local.set $size (i32.add (i32.load (local.get $input)) (i32.const 4)))
+    (local.set $ptr (call $alloc (local.get $size)))
+    (memory.copy (local.get $ptr) (local.get $input) (local.get $size))
+    (local.get $ptr)))
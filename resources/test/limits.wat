@@ -0,0 +1,21 @@
+;; Source of limits.wasm, used by the WASM resource limit tests
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (func $alloc (export "alloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+    (local.get $ptr))
+  (func (export "dealloc") (param i32 i32))
+  ;; Loop forever
+  (func (export "spin") (param i32) (result i32)
+    (loop $forever
+      (br $forever))
+    (unreachable))
+  ;; Keep growing the memory until that fails
+  (func (export "grow") (param i32) (result i32)
+    (loop $more
+      (br_if $more
+        (i32.ne (memory.grow (i32.const 1)) (i32.const -1))))
+    (unreachable)))
@@ -0,0 +1,66 @@
+;; Source of messagepack_aggregate.wasm, used by the aggregate and table function tests
+(module
+  (memory (export "memory") 16)
+  (global $heap (mut i32) (i32.const 1024))
+  (func $alloc (export "alloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+    (local.get $ptr))
+  (func (export "dealloc") (param i32 i32))
+  ;; The state is a MessagePack bin 8 holding an i32
+  (func $write_state (param $value i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (call $alloc (i32.const 10)))
+    (i32.store (local.get $ptr) (i32.const 6))
+    (i32.store8 offset=4 (local.get $ptr) (i32.const 0xc4))
+    (i32.store8 offset=5 (local.get $ptr) (i32.const 4))
+    (i32.store offset=6 (local.get $ptr) (local.get $value))
+    (local.get $ptr))
+  (func $write_u32 (param $value i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (call $alloc (i32.const 9)))
+    (i32.store (local.get $ptr) (i32.const 5))
+    (i32.store8 offset=4 (local.get $ptr) (i32.const 0xce))
+    (i32.store8 offset=5 (local.get $ptr) (i32.shr_u (local.get $value) (i32.const 24)))
+    (i32.store8 offset=6 (local.get $ptr) (i32.shr_u (local.get $value) (i32.const 16)))
+    (i32.store8 offset=7 (local.get $ptr) (i32.shr_u (local.get $value) (i32.const 8)))
+    (i32.store8 offset=8 (local.get $ptr) (local.get $value))
+    (local.get $ptr))
+  ;; [] -> 0
+  (func (export "count_init") (param i32) (result i32)
+    (call $write_state (i32.const 0)))
+  ;; [state, ...] -> state + 1
+  (func (export "count_update") (param $input i32) (result i32)
+    (call $write_state
+      (i32.add (i32.load offset=7 (local.get $input)) (i32.const 1))))
+  ;; [state, other_state] -> state + other_state
+  (func (export "count_merge") (param $input i32) (result i32)
+    (call $write_state
+      (i32.add
+        (i32.load offset=7 (local.get $input))
+        (i32.load offset=13 (local.get $input)))))
+  ;; [state] -> state
+  (func (export "count_finalize") (param $input i32) (result i32)
+    (call $write_u32 (i32.load offset=7 (local.get $input))))
+  ;; [n] (n < 16) -> [[0], [1], ..., [n - 1]]
+  (func (export "series") (param $input i32) (result i32)
+    (local $n i32) (local $ptr i32) (local $i i32)
+    (local.set $n (i32.load8_u offset=5 (local.get $input)))
+    (local.set $ptr
+      (call $alloc (i32.add (i32.const 5) (i32.shl (local.get $n) (i32.const 1)))))
+    (i32.store (local.get $ptr)
+      (i32.add (i32.const 1) (i32.shl (local.get $n) (i32.const 1))))
+    (i32.store8 offset=4 (local.get $ptr) (i32.or (i32.const 0x90) (local.get $n)))
+    (block $done
+      (loop $rows
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (i32.store8 offset=5
+          (i32.add (local.get $ptr) (i32.shl (local.get $i) (i32.const 1)))
+          (i32.const 0x91))
+        (i32.store8 offset=6
+          (i32.add (local.get $ptr) (i32.shl (local.get $i) (i32.const 1)))
+          (local.get $i))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $rows)))
+    (local.get $ptr)))
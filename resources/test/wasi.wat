@@ -0,0 +1,21 @@
+;; Source of wasi.wasm, used by the WASI capability tests
+(module
+  (import "wasi_snapshot_preview1" "clock_time_get"
+    (func $clock_time_get (param i32 i64 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "random_get"
+    (func $random_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "environ_sizes_get"
+    (func $environ_sizes_get (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  ;; Current real time, in nanoseconds since the Unix epoch
+  (func (export "now") (param i32) (result i64)
+    (drop (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 0)))
+    (i64.load (i32.const 0)))
+  ;; 8 random bytes
+  (func (export "random") (param i32) (result i64)
+    (drop (call $random_get (i32.const 0) (i32.const 8)))
+    (i64.load (i32.const 0)))
+  ;; Number of environment variables
+  (func (export "env_count") (param i32) (result i32)
+    (drop (call $environ_sizes_get (i32.const 0) (i32.const 4)))
+    (i32.load (i32.const 0))))
@@ -10,6 +10,16 @@ pub enum Principal {
     Reader,
 }
 
+impl Principal {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Principal::Anonymous => "anonymous",
+            Principal::Writer => "writer",
+            Principal::Reader => "reader",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Resource {
     Database,
@@ -0,0 +1,170 @@
+//! Per-partition Bloom filters over the values of selected columns (the table's
+//! `bloom_filter_columns`). These are stored in the catalog next to the min/max statistics
+//! and let us skip partitions that can't contain the value we're looking for in point
+//! lookups (e.g. `WHERE user_id = 'abc'`) on unsorted data, where min/max pruning is useless.
+
+use std::f64::consts::LN_2;
+
+use datafusion::scalar::ScalarValue;
+
+use crate::statistics::hash_scalar;
+
+// Bump this if the hashing or the layout change, so that old filters get ignored
+const FORMAT_VERSION: u8 = 2;
+const FALSE_POSITIVE_PROBABILITY: f64 = 0.01;
+const MIN_BITS: usize = 64;
+// Cap the filter at 1 MiB per partition column
+const MAX_BITS: usize = 8 * 1024 * 1024;
+const MAX_HASHES: u32 = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Make an empty filter sized for the given number of values
+    pub fn with_capacity(num_values: usize) -> Self {
+        let num_values = num_values.max(1) as f64;
+        let num_bits = (-num_values * FALSE_POSITIVE_PROBABILITY.ln() / (LN_2 * LN_2))
+            .ceil() as usize;
+        let num_bits = num_bits.clamp(MIN_BITS, MAX_BITS);
+        let num_hashes = ((num_bits as f64 / num_values) * LN_2)
+            .round()
+            .clamp(1.0, MAX_HASHES as f64) as u32;
+
+        Self {
+            bits: vec![0; (num_bits + 63) / 64],
+            num_hashes,
+        }
+    }
+
+    /// Build a filter from the hashes of the distinct values (see `hash_array`)
+    pub fn from_hashes(hashes: &[u64]) -> Self {
+        let mut filter = Self::with_capacity(hashes.len());
+        for hash in hashes {
+            filter.insert_hash(*hash);
+        }
+        filter
+    }
+
+    /// Check whether the filter might contain the value. This has to be of the exact same
+    /// type as the column the filter was built for.
+    pub fn might_contain(&self, value: &ScalarValue) -> bool {
+        match hash_scalar(value) {
+            Some(hash) => self
+                .bit_positions(hash)
+                .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0),
+            // Be conservative about the values we can't hash
+            None => true,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(5 + self.bits.len() * 8);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.num_hashes.to_le_bytes());
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a filter, returning `None` if it's invalid or was written in a
+    /// different format
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&version, bytes) = bytes.split_first()?;
+        if version != FORMAT_VERSION || bytes.len() < 4 || (bytes.len() - 4) % 8 != 0 {
+            return None;
+        }
+
+        let num_hashes = u32::from_le_bytes(bytes[..4].try_into().ok()?);
+        let bits: Vec<u64> = bytes[4..]
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+
+        if bits.is_empty() || num_hashes == 0 || num_hashes > MAX_HASHES {
+            return None;
+        }
+
+        Some(Self { bits, num_hashes })
+    }
+
+    fn insert_hash(&mut self, hash: u64) {
+        for bit in self.bit_positions(hash).collect::<Vec<_>>() {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    // Derive the bit positions from a single hash (Kirsch-Mitzenmacher double hashing)
+    fn bit_positions(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let num_bits = self.bits.len() as u64 * 64;
+        // The hashes are already well mixed, so we can just use their two halves
+        let h1 = hash;
+        let h2 = (hash >> 32) | 1;
+
+        (0..self.num_hashes as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{ArrayRef, StringArray};
+    use datafusion::scalar::ScalarValue;
+
+    use crate::statistics::hash_array;
+
+    use super::BloomFilter;
+
+    #[test]
+    fn test_bloom_filter() {
+        let values: ArrayRef = Arc::new(
+            (0..1000)
+                .map(|i| {
+                    if i % 10 == 0 {
+                        None
+                    } else {
+                        Some(format!("v{i}"))
+                    }
+                })
+                .collect::<StringArray>(),
+        );
+
+        let mut hashes: Vec<u64> =
+            hash_array(&values).unwrap().into_iter().flatten().collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+
+        let filter = BloomFilter::from_hashes(&hashes);
+        let filter = BloomFilter::from_bytes(&filter.to_bytes()).unwrap();
+
+        // No false negatives
+        for i in (0..1000).filter(|i| i % 10 != 0) {
+            assert!(filter.might_contain(&ScalarValue::Utf8(Some(format!("v{i}")))));
+        }
+
+        // Few false positives
+        let false_positives = (1000..11000)
+            .filter(|i| filter.might_contain(&ScalarValue::Utf8(Some(format!("v{i}")))))
+            .count();
+        assert!(false_positives < 500, "{false_positives} false positives");
+
+        // Can't rule out NULLs (they aren't hashed)
+        assert!(filter.might_contain(&ScalarValue::Utf8(None)));
+    }
+
+    #[test]
+    fn test_bloom_filter_invalid_bytes() {
+        assert_eq!(BloomFilter::from_bytes(&[]), None);
+        assert_eq!(
+            BloomFilter::from_bytes(&[3, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            None
+        );
+        assert_eq!(BloomFilter::from_bytes(&[2, 1, 0, 0, 0, 0, 0]), None);
+    }
+}
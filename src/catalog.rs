@@ -1,34 +1,42 @@
 use std::str::FromStr;
 use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
+use arrow_integration_test::schema_from_json;
 use async_trait::async_trait;
 use datafusion::catalog::schema::MemorySchemaProvider;
 use datafusion::error::DataFusionError;
 use itertools::Itertools;
+use log::warn;
 #[cfg(test)]
 use mockall::automock;
 use parking_lot::RwLock;
 
+use crate::metrics::time_catalog_query;
 use crate::provider::SeafowlFunction;
-use crate::repository::interface::TablePartitionsResult;
+use crate::query_log::QueryLog;
+use crate::repository::interface::{
+    AllDatabaseMaterializedViewsResult, AllDatabaseViewsResult, AllExternalTablesResult,
+    PartitionColumnsResult, TableColumnsResult, TablePartitionsResult,
+};
 use crate::system_tables::SystemSchemaProvider;
 use crate::wasm_udf::data_types::{
-    CreateFunctionDataType, CreateFunctionDetails, CreateFunctionLanguage,
-    CreateFunctionVolatility,
+    CreateFunctionColumn, CreateFunctionDataType, CreateFunctionDetails,
+    CreateFunctionKind, CreateFunctionLanguage, CreateFunctionVolatility,
+    WasiCapabilities, WasmLimits,
 };
 use crate::{
     data_types::{
-        CollectionId, DatabaseId, FunctionId, PhysicalPartitionId, TableId,
-        TableVersionId,
+        CollectionId, DatabaseId, ExternalTableDetails, ExternalTableId, FunctionId,
+        JobRunId, ParquetWriterOptions, PhysicalPartitionId, TableId, TableVersionId,
     },
     provider::{
-        PartitionColumn, SeafowlCollection, SeafowlDatabase, SeafowlPartition,
-        SeafowlTable,
+        PartitionColumn, SeafowlCollection, SeafowlDatabase, SeafowlExternalTable,
+        SeafowlPartition, SeafowlTable, SeafowlView,
     },
     repository::interface::{
         AllDatabaseColumnsResult, AllDatabaseFunctionsResult,
-        AllTablePartitionColumnsResult, Error as RepositoryError, Repository,
-        TableVersionsResult,
+        AllTablePartitionColumnsResult, Error as RepositoryError, JobRunsResult,
+        Repository, TableVersionsResult,
     },
     schema::Schema,
 };
@@ -51,7 +59,10 @@ pub enum Error {
     DatabaseAlreadyExists { name: String },
     CollectionAlreadyExists { name: String },
     FunctionAlreadyExists { name: String },
+    FunctionDoesNotExist { id: FunctionId },
     FunctionDeserializationError { reason: String },
+    ExternalTableDeserializationError { name: String, reason: String },
+    ViewDeserializationError { name: String, reason: String },
     // Creating a table in / dropping the staging schema
     UsedStagingSchema,
     SqlxError(sqlx::Error),
@@ -122,6 +133,16 @@ impl From<Error> for DataFusionError {
             Error::FunctionDeserializationError { reason } => DataFusionError::Internal(
                 format!("Error deserializing function: {reason:?}"),
             ),
+            Error::ExternalTableDeserializationError { name, reason } => {
+                DataFusionError::Internal(format!(
+                    "Error deserializing external table {name:?}: {reason:?}"
+                ))
+            }
+            Error::ViewDeserializationError { name, reason } => {
+                DataFusionError::Internal(format!(
+                    "Error deserializing view {name:?}: {reason:?}"
+                ))
+            }
 
             // Errors that are the user's fault.
 
@@ -140,6 +161,9 @@ impl From<Error> for DataFusionError {
             Error::FunctionAlreadyExists { name } => {
                 DataFusionError::Plan(format!("Function {name:?} already exists"))
             }
+            Error::FunctionDoesNotExist { id } => {
+                DataFusionError::Internal(format!("Function with ID {id} doesn't exist"))
+            }
             Error::UsedStagingSchema => DataFusionError::Plan(
                 "The staging schema can only be referenced via CREATE EXTERNAL TABLE"
                     .to_string(),
@@ -186,6 +210,7 @@ pub trait TableCatalog: Sync + Send {
         collection_id: CollectionId,
         table_name: &str,
         schema: &Schema,
+        writer_options: &ParquetWriterOptions,
     ) -> Result<(TableId, TableVersionId)>;
 
     async fn delete_old_table_versions(
@@ -193,12 +218,23 @@ pub trait TableCatalog: Sync + Send {
         table_id: Option<TableId>,
     ) -> Result<u64, Error>;
 
+    async fn delete_table_versions(
+        &self,
+        table_version_ids: Vec<TableVersionId>,
+    ) -> Result<u64, Error>;
+
     async fn create_new_table_version(
         &self,
         from_version: TableVersionId,
         inherit_partitions: bool,
     ) -> Result<TableVersionId>;
 
+    async fn set_column_distinct_counts(
+        &self,
+        table_version_id: TableVersionId,
+        distinct_counts: &[(String, i64)],
+    ) -> Result<()>;
+
     async fn get_all_table_versions(
         &self,
         database_name: &str,
@@ -210,6 +246,67 @@ pub trait TableCatalog: Sync + Send {
         database_name: &str,
     ) -> Result<Vec<TablePartitionsResult>>;
 
+    async fn get_all_table_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<TableColumnsResult>>;
+
+    async fn get_all_partition_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<PartitionColumnsResult>>;
+
+    async fn create_external_table(
+        &self,
+        collection_id: CollectionId,
+        table_name: &str,
+        details: &ExternalTableDetails,
+        schema: &Schema,
+    ) -> Result<ExternalTableId>;
+
+    async fn get_all_external_tables(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<SeafowlExternalTable>>;
+
+    async fn drop_external_table(&self, external_table_id: ExternalTableId)
+        -> Result<()>;
+
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> Result<TableId>;
+
+    async fn set_materialized_view(
+        &self,
+        table_id: TableId,
+        definition: &str,
+        auto_refresh: bool,
+    ) -> Result<()>;
+
+    async fn get_materialized_views(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseMaterializedViewsResult>>;
+
+    async fn create_job_run(&self, job_name: &str) -> Result<JobRunId>;
+
+    async fn finish_job_run(
+        &self,
+        job_run_id: JobRunId,
+        status: &str,
+        duration_ms: i64,
+        error: Option<String>,
+    ) -> Result<()>;
+
+    async fn get_all_job_runs(&self) -> Result<Vec<JobRunsResult>>;
+
+    async fn delete_old_job_runs(&self, keep: u32) -> Result<u64>;
+
     async fn move_table(
         &self,
         table_id: TableId,
@@ -239,6 +336,21 @@ pub trait PartitionCatalog: Sync + Send {
         table_version_id: TableVersionId,
     ) -> Result<Vec<SeafowlPartition>>;
 
+    /// Load the table version's partitions along with the distinct value sketches of their
+    /// columns, which only `ANALYZE TABLE` needs
+    async fn load_table_partitions_with_distinct_sketches(
+        &self,
+        table_version_id: TableVersionId,
+    ) -> Result<Vec<SeafowlPartition>>;
+
+    /// Load the serialized Bloom filters of these columns in the table version's partitions,
+    /// keyed by the column name and then the partition ID
+    async fn load_bloom_filters(
+        &self,
+        table_version_id: TableVersionId,
+        column_names: Vec<String>,
+    ) -> Result<HashMap<String, HashMap<PhysicalPartitionId, Vec<u8>>>>;
+
     async fn append_partitions_to_table(
         &self,
         partition_ids: Vec<PhysicalPartitionId>,
@@ -261,12 +373,18 @@ pub trait FunctionCatalog: Sync + Send {
         database_id: DatabaseId,
         function_name: &str,
         details: &CreateFunctionDetails,
+        or_replace: bool,
     ) -> Result<FunctionId>;
 
     async fn get_all_functions_in_database(
         &self,
         database_id: DatabaseId,
     ) -> Result<Vec<SeafowlFunction>>;
+
+    // Locations of the modules of all functions (in all databases)
+    async fn get_all_function_locations(&self) -> Result<Vec<String>>;
+
+    async fn drop_function(&self, function_id: FunctionId) -> Result<()>;
 }
 
 #[derive(Clone)]
@@ -275,14 +393,18 @@ pub struct DefaultCatalog {
 
     // DataFusion's in-memory schema provider for staging external tables
     staging_schema: Arc<MemorySchemaProvider>,
+
+    // Log of recently executed statements, exposed as `system.queries`
+    query_log: Arc<QueryLog>,
 }
 
 impl DefaultCatalog {
-    pub fn new(repository: Arc<dyn Repository>) -> Self {
+    pub fn new(repository: Arc<dyn Repository>, query_log: Arc<QueryLog>) -> Self {
         let staging_schema = Arc::new(MemorySchemaProvider::new());
         Self {
             repository,
             staging_schema,
+            query_log,
         }
     }
 
@@ -294,6 +416,35 @@ impl DefaultCatalog {
         })
     }
 
+    fn parse_external_table(
+        item: &AllExternalTablesResult,
+    ) -> std::result::Result<SeafowlExternalTable, Box<dyn std::error::Error>> {
+        Ok(SeafowlExternalTable {
+            external_table_id: item.id,
+            collection_name: Arc::from(item.collection_name.clone()),
+            name: Arc::from(item.name.clone()),
+            details: ExternalTableDetails {
+                location: item.location.clone(),
+                credentials: item.credentials.clone(),
+                file_type: item.file_type.clone(),
+                options: serde_json::from_str(&item.options)?,
+            },
+            schema: Arc::new(schema_from_json(&serde_json::from_str(&item.schema)?)?),
+        })
+    }
+
+    fn parse_view(
+        item: &AllDatabaseViewsResult,
+    ) -> std::result::Result<SeafowlView, Box<dyn std::error::Error>> {
+        Ok(SeafowlView {
+            view_id: item.id,
+            collection_name: Arc::from(item.collection_name.clone()),
+            name: Arc::from(item.name.clone()),
+            definition: item.definition.clone(),
+            schema: Arc::new(schema_from_json(&serde_json::from_str(&item.schema)?)?),
+        })
+    }
+
     fn build_partition<'a, I>(&self, partition_columns: I) -> SeafowlPartition
     where
         I: Iterator<Item = &'a AllTablePartitionColumnsResult>,
@@ -313,6 +464,10 @@ impl DefaultCatalog {
                     min_value: Arc::new(partition.min_value.clone()),
                     max_value: Arc::new(partition.max_value.clone()),
                     null_count: partition.null_count,
+                    // Loaded separately (see `load_bloom_filters`), only when a query can use them
+                    bloom_filter: Arc::new(None),
+                    // Only needed for ANALYZE (see `load_table_partitions_with_distinct_sketches`)
+                    distinct_sketch: Arc::new(None),
                 })
                 .collect(),
             ),
@@ -341,6 +496,31 @@ impl DefaultCatalog {
             .get(0)
             .map_or_else(|| (0, 0), |v| (v.table_id, v.table_version_id));
 
+        // Tables created before we had writer options don't have them set
+        let writer_options = table_columns_vec
+            .get(0)
+            .and_then(|v| v.writer_options.as_ref())
+            .and_then(|options| match serde_json::from_str(options) {
+                Ok(options) => Some(options),
+                Err(e) => {
+                    warn!(
+                        "Failed to deserialize the writer options of table {}: {:?}",
+                        table_name, e
+                    );
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        // Only set for the columns that went through an ANALYZE TABLE
+        let distinct_counts = table_columns_vec
+            .iter()
+            .filter_map(|col| {
+                col.distinct_count
+                    .map(|count| (col.column_name.clone(), count as u64))
+            })
+            .collect();
+
         let table = SeafowlTable {
             name: Arc::from(table_name.to_string()),
             table_id,
@@ -350,6 +530,8 @@ impl DefaultCatalog {
                     .iter()
                     .map(|col| (&col.column_name, &col.column_type)),
             )),
+            writer_options: Arc::new(writer_options),
+            distinct_counts: Arc::new(distinct_counts),
 
             catalog: Arc::new(self.clone()),
         };
@@ -376,6 +558,8 @@ impl DefaultCatalog {
             Arc::new(SeafowlCollection {
                 name: Arc::from(collection_name.to_string()),
                 tables: RwLock::new(tables),
+                external_tables: RwLock::new(HashMap::new()),
+                views: RwLock::new(HashMap::new()),
             }),
         )
     }
@@ -384,24 +568,56 @@ impl DefaultCatalog {
 #[async_trait]
 impl TableCatalog for DefaultCatalog {
     async fn load_database(&self, database_id: DatabaseId) -> Result<SeafowlDatabase> {
-        let all_columns = self
-            .repository
-            .get_all_columns_in_database(database_id, None)
-            .await
-            .map_err(Self::to_sqlx_error)?;
+        let all_columns = time_catalog_query(
+            "load_database",
+            self.repository
+                .get_all_columns_in_database(database_id, None),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
 
         // NB we can't distinguish between a database without tables and a database
         // that doesn't exist at all due to our query.
 
         // Turn the list of all collections, tables and their columns into a nested map.
 
-        let collections: HashMap<Arc<str>, Arc<SeafowlCollection>> = all_columns
+        let mut collections: HashMap<Arc<str>, Arc<SeafowlCollection>> = all_columns
             .iter()
             .group_by(|col| &col.collection_name)
             .into_iter()
             .map(|(cn, cc)| self.build_collection(cn, cc))
             .collect();
 
+        // Views don't have any columns in the catalog, so add them separately
+        let all_views = time_catalog_query(
+            "get_all_views",
+            self.repository.get_all_views_in_database(database_id),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
+
+        for item in &all_views {
+            let view =
+                Self::parse_view(item).map_err(|e| Error::ViewDeserializationError {
+                    name: item.name.clone(),
+                    reason: e.to_string(),
+                })?;
+
+            collections
+                .entry(view.collection_name.clone())
+                .or_insert_with(|| {
+                    Arc::new(SeafowlCollection {
+                        name: view.collection_name.clone(),
+                        tables: RwLock::new(HashMap::new()),
+                        external_tables: RwLock::new(HashMap::new()),
+                        views: RwLock::new(HashMap::new()),
+                    })
+                })
+                .views
+                .write()
+                .insert(view.name.clone(), Arc::new(view));
+        }
+
         // TODO load the database name too
         let name: Arc<str> = Arc::from(DEFAULT_DB);
 
@@ -412,6 +628,8 @@ impl TableCatalog for DefaultCatalog {
             system_schema: Arc::new(SystemSchemaProvider::new(
                 name,
                 Arc::new(self.clone()),
+                Arc::new(self.clone()),
+                self.query_log.clone(),
             )),
         })
     }
@@ -442,9 +660,10 @@ impl TableCatalog for DefaultCatalog {
         collection_id: CollectionId,
         table_name: &str,
         schema: &Schema,
+        writer_options: &ParquetWriterOptions,
     ) -> Result<(TableId, TableVersionId)> {
         self.repository
-            .create_table(collection_id, table_name, schema)
+            .create_table(collection_id, table_name, schema, writer_options)
             .await
             .map_err(|e| match e {
                 RepositoryError::UniqueConstraintViolation(_) => {
@@ -469,6 +688,16 @@ impl TableCatalog for DefaultCatalog {
             .map_err(Self::to_sqlx_error)
     }
 
+    async fn delete_table_versions(
+        &self,
+        table_version_ids: Vec<TableVersionId>,
+    ) -> Result<u64, Error> {
+        self.repository
+            .delete_table_versions(table_version_ids)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
     async fn get_collection_id_by_name(
         &self,
         database_name: &str,
@@ -541,15 +770,29 @@ impl TableCatalog for DefaultCatalog {
         from_version: TableVersionId,
         inherit_partitions: bool,
     ) -> Result<TableVersionId> {
+        time_catalog_query(
+            "create_new_table_version",
+            self.repository
+                .create_new_table_version(from_version, inherit_partitions),
+        )
+        .await
+        .map_err(|e| match e {
+            RepositoryError::FKConstraintViolation(_) => {
+                Error::TableVersionDoesNotExist { id: from_version }
+            }
+            _ => Self::to_sqlx_error(e),
+        })
+    }
+
+    async fn set_column_distinct_counts(
+        &self,
+        table_version_id: TableVersionId,
+        distinct_counts: &[(String, i64)],
+    ) -> Result<()> {
         self.repository
-            .create_new_table_version(from_version, inherit_partitions)
+            .set_table_column_distinct_counts(table_version_id, distinct_counts)
             .await
-            .map_err(|e| match e {
-                RepositoryError::FKConstraintViolation(_) => {
-                    Error::TableVersionDoesNotExist { id: from_version }
-                }
-                _ => Self::to_sqlx_error(e),
-            })
+            .map_err(Self::to_sqlx_error)
     }
 
     async fn get_all_table_versions(
@@ -573,6 +816,178 @@ impl TableCatalog for DefaultCatalog {
             .map_err(Self::to_sqlx_error)
     }
 
+    async fn get_all_table_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<TableColumnsResult>> {
+        self.repository
+            .get_all_table_columns(database_name)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn get_all_partition_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<PartitionColumnsResult>> {
+        self.repository
+            .get_all_partition_columns(database_name)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn create_external_table(
+        &self,
+        collection_id: CollectionId,
+        table_name: &str,
+        details: &ExternalTableDetails,
+        schema: &Schema,
+    ) -> Result<ExternalTableId> {
+        self.repository
+            .create_external_table(collection_id, table_name, details, schema)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    Error::TableAlreadyExists {
+                        name: table_name.to_string(),
+                    }
+                }
+                RepositoryError::FKConstraintViolation(_) => {
+                    Error::CollectionDoesNotExist { id: collection_id }
+                }
+                RepositoryError::SqlxError(e) => Error::SqlxError(e),
+            })
+    }
+
+    async fn get_all_external_tables(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<SeafowlExternalTable>> {
+        let all_external_tables = time_catalog_query(
+            "get_all_external_tables",
+            self.repository
+                .get_all_external_tables_in_database(database_id),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
+
+        all_external_tables
+            .iter()
+            .map(|item| {
+                Self::parse_external_table(item).map_err(|e| {
+                    Error::ExternalTableDeserializationError {
+                        name: item.name.clone(),
+                        reason: e.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    async fn drop_external_table(
+        &self,
+        external_table_id: ExternalTableId,
+    ) -> Result<()> {
+        self.repository
+            .drop_external_table(external_table_id)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    Error::TableDoesNotExist {
+                        id: external_table_id,
+                    }
+                }
+                _ => Self::to_sqlx_error(e),
+            })
+    }
+
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> Result<TableId> {
+        self.repository
+            .create_view(collection_id, view_name, definition, schema, or_replace)
+            .await
+            .map_err(|e| match e {
+                // With OR REPLACE, we don't get a row back if the name is taken by a table
+                RepositoryError::UniqueConstraintViolation(_)
+                | RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    Error::TableAlreadyExists {
+                        name: view_name.to_string(),
+                    }
+                }
+                RepositoryError::FKConstraintViolation(_) => {
+                    Error::CollectionDoesNotExist { id: collection_id }
+                }
+                RepositoryError::SqlxError(e) => Error::SqlxError(e),
+            })
+    }
+
+    async fn set_materialized_view(
+        &self,
+        table_id: TableId,
+        definition: &str,
+        auto_refresh: bool,
+    ) -> Result<()> {
+        self.repository
+            .set_materialized_view(table_id, definition, auto_refresh)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    Error::TableDoesNotExist { id: table_id }
+                }
+                _ => Self::to_sqlx_error(e),
+            })
+    }
+
+    async fn get_materialized_views(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseMaterializedViewsResult>> {
+        self.repository
+            .get_all_materialized_views_in_database(database_id)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn create_job_run(&self, job_name: &str) -> Result<JobRunId> {
+        self.repository
+            .create_job_run(job_name)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn finish_job_run(
+        &self,
+        job_run_id: JobRunId,
+        status: &str,
+        duration_ms: i64,
+        error: Option<String>,
+    ) -> Result<()> {
+        self.repository
+            .finish_job_run(job_run_id, status, duration_ms, error)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn get_all_job_runs(&self) -> Result<Vec<JobRunsResult>> {
+        self.repository
+            .get_all_job_runs()
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn delete_old_job_runs(&self, keep: u32) -> Result<u64> {
+        self.repository
+            .delete_old_job_runs(keep)
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
     async fn move_table(
         &self,
         table_id: TableId,
@@ -656,11 +1071,13 @@ impl PartitionCatalog for DefaultCatalog {
     ) -> Result<Vec<SeafowlPartition>> {
         // NB: currently the query can't distinguish between a non-existent table version
         // and an empty table version
-        let all_partitions = self
-            .repository
-            .get_all_table_partition_columns(table_version_id)
-            .await
-            .map_err(Self::to_sqlx_error)?;
+        let all_partitions = time_catalog_query(
+            "load_table_partitions",
+            self.repository
+                .get_all_table_partition_columns(table_version_id),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
 
         Ok(all_partitions
             .iter()
@@ -670,6 +1087,78 @@ impl PartitionCatalog for DefaultCatalog {
             .collect())
     }
 
+    async fn load_table_partitions_with_distinct_sketches(
+        &self,
+        table_version_id: TableVersionId,
+    ) -> Result<Vec<SeafowlPartition>> {
+        let partitions = self.load_table_partitions(table_version_id).await?;
+        let distinct_sketches = time_catalog_query(
+            "load_distinct_sketches",
+            self.repository
+                .get_table_partition_distinct_sketches(table_version_id),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
+
+        let mut distinct_sketches: HashMap<(PhysicalPartitionId, String), Vec<u8>> =
+            distinct_sketches
+                .into_iter()
+                .map(|sketch| {
+                    (
+                        (
+                            sketch.table_partition_id as PhysicalPartitionId,
+                            sketch.column_name,
+                        ),
+                        sketch.distinct_sketch,
+                    )
+                })
+                .collect();
+
+        Ok(partitions
+            .into_iter()
+            .map(|partition| SeafowlPartition {
+                columns: Arc::new(
+                    partition
+                        .columns
+                        .iter()
+                        .map(|column| PartitionColumn {
+                            distinct_sketch: Arc::new(distinct_sketches.remove(&(
+                                partition.partition_id.unwrap(),
+                                column.name.to_string(),
+                            ))),
+                            ..column.clone()
+                        })
+                        .collect(),
+                ),
+                ..partition
+            })
+            .collect())
+    }
+
+    async fn load_bloom_filters(
+        &self,
+        table_version_id: TableVersionId,
+        column_names: Vec<String>,
+    ) -> Result<HashMap<String, HashMap<PhysicalPartitionId, Vec<u8>>>> {
+        let bloom_filters = time_catalog_query(
+            "load_bloom_filters",
+            self.repository
+                .get_table_partition_bloom_filters(table_version_id, &column_names),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
+
+        let mut result: HashMap<String, HashMap<PhysicalPartitionId, Vec<u8>>> =
+            HashMap::new();
+        for bloom_filter in bloom_filters {
+            result.entry(bloom_filter.column_name).or_default().insert(
+                bloom_filter.table_partition_id as PhysicalPartitionId,
+                bloom_filter.bloom_filter,
+            );
+        }
+        Ok(result)
+    }
+
     async fn append_partitions_to_table(
         &self,
         partition_ids: Vec<PhysicalPartitionId>,
@@ -727,6 +1216,12 @@ impl DefaultCatalog {
             return_type,
             data,
             volatility,
+            kind,
+            return_columns,
+            limits,
+            location,
+            sha256,
+            wasi,
         } = item;
 
         Ok(CreateFunctionDetails {
@@ -739,7 +1234,15 @@ impl DefaultCatalog {
                 &return_type.as_str().to_ascii_uppercase(),
             )?,
             data: data.to_string(),
+            location: location.clone(),
+            sha256: sha256.clone(),
             volatility: CreateFunctionVolatility::from_str(volatility.as_str())?,
+            kind: CreateFunctionKind::from_str(kind.as_str())?,
+            return_columns: serde_json::from_str::<Vec<CreateFunctionColumn>>(
+                return_columns,
+            )?,
+            limits: serde_json::from_str::<WasmLimits>(limits)?,
+            wasi: serde_json::from_str::<WasiCapabilities>(wasi)?,
         })
     }
 }
@@ -751,16 +1254,16 @@ impl FunctionCatalog for DefaultCatalog {
         database_id: DatabaseId,
         function_name: &str,
         details: &CreateFunctionDetails,
+        or_replace: bool,
     ) -> Result<FunctionId> {
         self.repository
-            .create_function(database_id, function_name, details)
+            .create_function(database_id, function_name, details, or_replace)
             .await
             .map_err(|e| match e {
                 RepositoryError::FKConstraintViolation(_) => {
                     Error::DatabaseDoesNotExist { id: database_id }
                 }
                 RepositoryError::UniqueConstraintViolation(_) => {
-                    // TODO overwrite function defns instead?
                     Error::FunctionAlreadyExists {
                         name: function_name.to_string(),
                     }
@@ -773,11 +1276,12 @@ impl FunctionCatalog for DefaultCatalog {
         &self,
         database_id: DatabaseId,
     ) -> Result<Vec<SeafowlFunction>> {
-        let all_functions = self
-            .repository
-            .get_all_functions_in_database(database_id)
-            .await
-            .map_err(Self::to_sqlx_error)?;
+        let all_functions = time_catalog_query(
+            "get_all_functions_in_database",
+            self.repository.get_all_functions_in_database(database_id),
+        )
+        .await
+        .map_err(Self::to_sqlx_error)?;
 
         all_functions
             .iter()
@@ -794,4 +1298,23 @@ impl FunctionCatalog for DefaultCatalog {
             })
             .collect::<Result<Vec<SeafowlFunction>>>()
     }
+
+    async fn get_all_function_locations(&self) -> Result<Vec<String>> {
+        self.repository
+            .get_all_function_locations()
+            .await
+            .map_err(Self::to_sqlx_error)
+    }
+
+    async fn drop_function(&self, function_id: FunctionId) -> Result<()> {
+        self.repository
+            .drop_function(function_id)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    Error::FunctionDoesNotExist { id: function_id }
+                }
+                _ => Self::to_sqlx_error(e),
+            })
+    }
 }
@@ -12,20 +12,30 @@ use crate::{
 use datafusion::datasource::datasource::TableProviderFactory;
 use datafusion::{
     error::DataFusionError,
+    execution::context::SessionState,
     execution::runtime_env::{RuntimeConfig, RuntimeEnv},
     prelude::{SessionConfig, SessionContext},
 };
 #[cfg(feature = "delta-tables")]
 use deltalake::delta_datafusion::DeltaTableFactory;
 use object_store::{local::LocalFileSystem, memory::InMemory, ObjectStore};
+use tokio::sync::Notify;
 
 #[cfg(feature = "catalog-postgres")]
 use crate::repository::postgres::PostgresRepository;
 
+use crate::metrics::{register_memory_manager, MEMORY_POOL_SIZE};
 use crate::object_store::http::add_http_object_store;
+use crate::object_store::metered::MeteredObjectStore;
 use crate::object_store::wrapped::InternalObjectStore;
+use crate::query_log::{pg_cancel_backend_udf, QueryLog};
+use crate::table_function::SeafowlQueryPlanner;
+use crate::wasm_udf::module_cache::set_module_cache_dir;
+use crate::wasm_udf::wasm::set_max_wasm_instances;
 #[cfg(feature = "remote-tables")]
 use datafusion_remote_tables::factory::RemoteTableFactory;
+#[cfg(feature = "remote-tables")]
+use datafusion_remote_tables::plan_pushdown::RemotePlanPushdown;
 #[cfg(feature = "object-store-s3")]
 use object_store::aws::AmazonS3Builder;
 
@@ -33,6 +43,7 @@ use super::schema::{self, MEBIBYTES, MEMORY_FRACTION, S3};
 
 async fn build_catalog(
     config: &schema::SeafowlConfig,
+    query_log: Arc<QueryLog>,
 ) -> (
     Arc<dyn TableCatalog>,
     Arc<dyn PartitionCatalog>,
@@ -66,7 +77,7 @@ async fn build_catalog(
         ),
     };
 
-    let catalog = Arc::new(DefaultCatalog::new(repository));
+    let catalog = Arc::new(DefaultCatalog::new(repository, query_log));
 
     (catalog.clone(), catalog.clone(), catalog)
 }
@@ -111,6 +122,7 @@ pub async fn build_context(
     if let Some(max_memory) = cfg.runtime.max_memory {
         runtime_config = runtime_config
             .with_memory_limit((max_memory * MEBIBYTES) as usize, MEMORY_FRACTION);
+        MEMORY_POOL_SIZE.set(((max_memory * MEBIBYTES) as f64 * MEMORY_FRACTION) as i64);
     }
 
     if let Some(temp_dir) = &cfg.runtime.temp_dir {
@@ -140,11 +152,21 @@ pub async fn build_context(
     }
 
     let mut runtime_env = RuntimeEnv::new(runtime_config)?;
+    register_memory_manager(runtime_env.memory_manager.clone());
     runtime_env.register_table_factories(table_factories);
 
-    let context = SessionContext::with_config_rt(session_config, Arc::new(runtime_env));
+    let mut state = SessionState::with_config_rt(session_config, Arc::new(runtime_env))
+        .with_query_planner(Arc::new(SeafowlQueryPlanner {}));
+    #[cfg(feature = "remote-tables")]
+    {
+        // Push aggregations, joins etc. over remote tables down to the remote database
+        state = state.add_optimizer_rule(Arc::new(RemotePlanPushdown {}));
+    }
+
+    let mut context = SessionContext::with_state(state);
 
-    let object_store = build_object_store(cfg);
+    let object_store: Arc<dyn ObjectStore> =
+        Arc::new(MeteredObjectStore::new(build_object_store(cfg), "internal"));
     context.runtime_env().register_object_store(
         INTERNAL_OBJECT_STORE_SCHEME,
         "",
@@ -154,7 +176,18 @@ pub async fn build_context(
     // Register the HTTP object store for external tables
     add_http_object_store(&context);
 
-    let (tables, partitions, functions) = build_catalog(cfg).await;
+    if let Some(wasm_module_cache_dir) = &cfg.runtime.wasm_module_cache_dir {
+        set_module_cache_dir(wasm_module_cache_dir)?;
+    }
+    if let Some(wasm_max_instances) = cfg.runtime.wasm_max_instances {
+        set_max_wasm_instances(wasm_max_instances);
+    }
+
+    // Set up the query log and the function to cancel running queries from it
+    let query_log = Arc::new(QueryLog::new(cfg.misc.query_log_size));
+    context.register_udf(pg_cancel_backend_udf(query_log.clone()));
+
+    let (tables, partitions, functions) = build_catalog(cfg, query_log.clone()).await;
 
     // Create default DB/collection
     let default_db = match tables.get_database_id_by_name(DEFAULT_DB).await? {
@@ -188,6 +221,15 @@ pub async fn build_context(
         database: DEFAULT_DB.to_string(),
         database_id: default_db,
         max_partition_size: cfg.misc.max_partition_size,
+        query_log,
+        query_limits: cfg.runtime.query_limits.clone(),
+        wasm_limits: cfg.runtime.wasm_limits.clone(),
+        wasm_module_locations: cfg.runtime.wasm_module_locations.clone(),
+        external_tables: Default::default(),
+        table_functions: Default::default(),
+        registered_functions: Default::default(),
+        materialized_view_refresh: Notify::new(),
+        materialized_view_locks: Default::default(),
     })
 }
 
@@ -219,7 +261,12 @@ mod tests {
                     read_access: schema::AccessSettings::Any,
                     write_access: schema::AccessSettings::Any,
                     upload_data_max_length: 256 * 1024 * 1024,
+                    enable_metrics: false,
+                    admission: Default::default(),
+                    result_cache: None,
                 }),
+                #[cfg(feature = "frontend-flight")]
+                flight: None,
             },
             runtime: schema::Runtime {
                 max_memory: Some(512 * 1024 * 1024),
@@ -228,7 +275,12 @@ mod tests {
             misc: schema::Misc {
                 max_partition_size: 1024 * 1024,
                 gc_interval: 0,
+                gc_schedule: None,
+                retention: Default::default(),
+                query_log_size: 1000,
+                job_run_history: 1000,
             },
+            jobs: vec![],
         };
 
         let context = build_context(&config).await.unwrap();
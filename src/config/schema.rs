@@ -1,6 +1,8 @@
 use std::{
+    collections::HashMap,
     fmt::{self, Display},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use config::{Config, ConfigError, Environment, File, FileFormat, Map};
@@ -11,6 +13,8 @@ use serde::Deserialize;
 use sha2::{Digest, Sha256};
 use sqlx::sqlite::SqliteJournalMode;
 
+use crate::wasm_udf::data_types::WasmLimits;
+
 pub const DEFAULT_DATA_DIR: &str = "seafowl-data";
 pub const DEFAULT_SQLITE_DB: &str = "seafowl.sqlite";
 pub const ENV_PREFIX: &str = "SEAFOWL";
@@ -41,6 +45,8 @@ pub struct SeafowlConfig {
     pub runtime: Runtime,
     #[serde(default)]
     pub misc: Misc,
+    #[serde(default)]
+    pub jobs: Vec<Job>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -167,6 +173,8 @@ pub struct Frontend {
     #[cfg(feature = "frontend-postgres")]
     pub postgres: Option<PostgresFrontend>,
     pub http: Option<HttpFrontend>,
+    #[cfg(feature = "frontend-flight")]
+    pub flight: Option<FlightFrontend>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -185,6 +193,43 @@ impl Default for PostgresFrontend {
     }
 }
 
+/// Arrow Flight SQL frontend, for clients that want the results in the Arrow format
+/// (e.g. ADBC or the Flight SQL JDBC driver). The access settings work the same way as
+/// in the HTTP frontend: clients pass the password as a bearer token (or as the password
+/// in the Basic auth handshake).
+///
+/// ```toml
+/// [frontend.flight]
+/// bind_host = "0.0.0.0"
+/// bind_port = 47470
+/// read_access = "any"
+/// write_access = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
+/// ```
+#[cfg(feature = "frontend-flight")]
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct FlightFrontend {
+    pub bind_host: String,
+    pub bind_port: u16,
+    pub read_access: AccessSettings,
+    pub write_access: AccessSettings,
+    // Limits on concurrent queries, separate from the HTTP frontend's
+    pub admission: AdmissionControl,
+}
+
+#[cfg(feature = "frontend-flight")]
+impl Default for FlightFrontend {
+    fn default() -> Self {
+        Self {
+            bind_host: "127.0.0.1".to_string(),
+            bind_port: 47470,
+            read_access: AccessSettings::Any,
+            write_access: AccessSettings::Off,
+            admission: AdmissionControl::default(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum AccessSettings {
     Any,
@@ -248,6 +293,11 @@ pub struct HttpFrontend {
     pub read_access: AccessSettings,
     pub write_access: AccessSettings,
     pub upload_data_max_length: u64,
+    // Expose Prometheus metrics at GET /metrics
+    pub enable_metrics: bool,
+    pub admission: AdmissionControl,
+    // Server-side cache for the results of GET /q/[query hash] (disabled if unset)
+    pub result_cache: Option<ResultCache>,
 }
 
 impl Default for HttpFrontend {
@@ -258,6 +308,82 @@ impl Default for HttpFrontend {
             read_access: AccessSettings::Any,
             write_access: AccessSettings::Off,
             upload_data_max_length: 256,
+            enable_metrics: false,
+            admission: AdmissionControl::default(),
+            result_cache: None,
+        }
+    }
+}
+
+/// Limits on how many queries a frontend (HTTP or Flight) runs at the same time. Read and
+/// write statements get separate pools, so that a burst of cache misses can't block writes
+/// (and vice versa). Queries that can't get a slot wait in a bounded queue; once that's
+/// full (or they've waited for too long), they get rejected (with a 429 response over
+/// HTTP and `RESOURCE_EXHAUSTED` over Flight).
+///
+/// ```toml
+/// [frontend.http.admission]
+/// max_concurrent_queries = 16
+/// max_concurrent_reads = 12
+/// max_concurrent_writes = 4
+/// max_queued = 64
+/// ```
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct AdmissionControl {
+    // Limits on the number of queries running at the same time (unlimited if unset)
+    pub max_concurrent_queries: Option<usize>,
+    pub max_concurrent_reads: Option<usize>,
+    pub max_concurrent_writes: Option<usize>,
+    // How many queries can wait for a slot to free up
+    pub max_queued: usize,
+    // How long a query can wait in the queue before being rejected
+    pub queue_timeout_secs: u64,
+    // Value of the Retry-After header returned with rejections
+    pub retry_after_secs: u64,
+}
+
+impl Default for AdmissionControl {
+    fn default() -> Self {
+        Self {
+            max_concurrent_queries: None,
+            max_concurrent_reads: None,
+            max_concurrent_writes: None,
+            max_queued: 0,
+            queue_timeout_secs: 30,
+            retry_after_secs: 1,
+        }
+    }
+}
+
+/// Server-side cache for the responses of the cached GET endpoint, so that a CDN miss doesn't
+/// have to re-run the query if the tables it reads haven't changed. Entries get invalidated
+/// when a new version of one of these tables is created (since that changes the ETag).
+///
+/// ```toml
+/// [frontend.http.result_cache]
+/// max_size_mb = 512
+/// ttl_secs = 3600
+/// # Keep the responses on disk instead of in memory
+/// dir = "/var/cache/seafowl"
+/// ```
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct ResultCache {
+    // Total size of the cached responses
+    pub max_size_mb: u64,
+    // How long to keep a response for (as long as the tables don't change if unset)
+    pub ttl_secs: Option<u64>,
+    // Directory to store the responses in (in memory if unset)
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 256,
+            ttl_secs: None,
+            dir: None,
         }
     }
 }
@@ -266,8 +392,16 @@ impl Default for HttpFrontend {
 #[serde(default)]
 pub struct Misc {
     pub max_partition_size: u32,
-    // Perhaps make this accept a cron job format and use tokio-cron-scheduler?
+    // How often (in hours) to run the partition GC; 0 disables it. Superseded by `gc_schedule`.
     pub gc_interval: u16,
+    // Cron schedule (sec min hour day_of_month month day_of_week) on which the job scheduler
+    // runs the partition GC
+    pub gc_schedule: Option<String>,
+    pub retention: Retention,
+    // How many recent statements to keep in the query log (`system.queries`)
+    pub query_log_size: usize,
+    // How many of the most recent job runs to keep in the catalog (`system.job_runs`)
+    pub job_run_history: u32,
 }
 
 impl Default for Misc {
@@ -275,15 +409,147 @@ impl Default for Misc {
         Self {
             max_partition_size: 1024 * 1024,
             gc_interval: 0,
+            gc_schedule: None,
+            retention: Retention::default(),
+            query_log_size: 1000,
+            job_run_history: 1000,
         }
     }
 }
 
+/// Table version retention settings, enforced by a background task. Each table keeps
+/// its latest version, as well as any version that satisfies at least one of the
+/// configured criteria; if neither criterion is set, all versions are kept.
+#[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone)]
+#[serde(default)]
+pub struct Retention {
+    // How often (in hours) to enforce the retention policy; 0 disables the task
+    pub interval: u16,
+    // Global defaults
+    pub keep_versions: Option<u32>,
+    pub keep_duration_secs: Option<u64>,
+    // Per-table overrides, keyed by `schema_name.table_name`
+    pub tables: HashMap<String, RetentionPolicy>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub keep_versions: Option<u32>,
+    pub keep_duration_secs: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.keep_versions.is_none() && self.keep_duration_secs.is_none()
+    }
+}
+
+impl Retention {
+    /// Get the policy for a given table, falling back to the global one
+    pub fn policy_for(&self, collection_name: &str, table_name: &str) -> RetentionPolicy {
+        match self.tables.get(&format!("{collection_name}.{table_name}")) {
+            Some(policy) => policy.clone(),
+            None => RetentionPolicy {
+                keep_versions: self.keep_versions,
+                keep_duration_secs: self.keep_duration_secs,
+            },
+        }
+    }
+}
+
+/// A maintenance job that runs some SQL on a cron schedule, e.g.
+///
+/// ```toml
+/// [[jobs]]
+/// name = "nightly_vacuum"
+/// # sec min hour day_of_month month day_of_week
+/// schedule = "0 0 3 * * *"
+/// sql = "VACUUM TABLES"
+///
+/// [[jobs]]
+/// name = "hourly_dashboard_refresh"
+/// schedule = "0 0 * * * *"
+/// sql = "REFRESH MATERIALIZED VIEW dashboard"
+/// ```
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Job {
+    pub name: String,
+    pub schedule: String,
+    pub sql: String,
+}
+
 #[derive(Default, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(default)]
 pub struct Runtime {
     pub max_memory: Option<u64>,
     pub temp_dir: Option<PathBuf>,
+    pub query_limits: QueryLimits,
+    // Defaults for the fuel, time and memory WASM functions can use, e.g.
+    //
+    // [runtime.wasm_limits]
+    // max_fuel = 1000000000
+    // timeout_ms = 1000
+    // max_memory = 64
+    pub wasm_limits: WasmLimits,
+    // Directory to persist compiled WASM modules in, so that we don't have to recompile
+    // all functions after a restart
+    pub wasm_module_cache_dir: Option<PathBuf>,
+    // How many WASM function calls can run at the same time. Every call gets an instance
+    // slot from a preallocated pool, which reserves address space for the instance's
+    // linear memory (default 256, see `wasm::DEFAULT_MAX_INSTANCES`).
+    pub wasm_max_instances: Option<u32>,
+    // URL prefixes that CREATE FUNCTION can load WASM modules from, on top of the internal
+    // object store (`seafowl://`), e.g.
+    //
+    // [runtime]
+    // wasm_module_locations = ["https://example.com/udfs/", "s3://my-bucket/udfs/"]
+    pub wasm_module_locations: Vec<String>,
+}
+
+/// Limits applied to every statement run through the frontends. Per-principal overrides
+/// are keyed by the principal name (`anonymous`, `reader` or `writer`), e.g.
+///
+/// ```toml
+/// [runtime.query_limits]
+/// timeout_secs = 30
+/// max_rows = 100000
+///
+/// [runtime.query_limits.principals.writer]
+/// timeout_secs = 600
+/// ```
+#[derive(Default, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct QueryLimits {
+    // Memory (in MiB) that a query's operators and buffered results can take up. It gets
+    // reserved from the `runtime.max_memory` pool while the query runs.
+    pub max_memory: Option<u64>,
+    // Wall-clock time a statement can take to plan and execute
+    pub timeout_secs: Option<u64>,
+    // Number of rows a query can return
+    pub max_rows: Option<u64>,
+    // Per-principal overrides
+    pub principals: HashMap<String, QueryLimit>,
+}
+
+#[derive(Default, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct QueryLimit {
+    pub max_memory: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    pub max_rows: Option<u64>,
+}
+
+impl QueryLimits {
+    /// Get the limits for a principal, falling back to the global ones for each limit
+    pub fn limit_for(&self, principal: &str) -> QueryLimit {
+        let limit = self.principals.get(principal).cloned().unwrap_or_default();
+        QueryLimit {
+            max_memory: limit.max_memory.or(self.max_memory),
+            timeout_secs: limit.timeout_secs.or(self.timeout_secs),
+            max_rows: limit.max_rows.or(self.max_rows),
+        }
+    }
 }
 
 pub fn validate_config(config: SeafowlConfig) -> Result<SeafowlConfig, ConfigError> {
@@ -312,6 +578,43 @@ pub fn validate_config(config: SeafowlConfig) -> Result<SeafowlConfig, ConfigErr
         ));
     }
 
+    let retention = &config.misc.retention;
+    if retention.keep_versions == Some(0)
+        || retention
+            .tables
+            .values()
+            .any(|p| p.keep_versions == Some(0))
+    {
+        return Err(ConfigError::Message(
+            "misc.retention.keep_versions has to be at least 1".to_string(),
+        ));
+    }
+
+    for job in &config.jobs {
+        if let Err(e) = cron::Schedule::from_str(&job.schedule) {
+            return Err(ConfigError::Message(format!(
+                "Invalid schedule {:?} for job {}: {e}",
+                job.schedule, job.name
+            )));
+        }
+    }
+
+    if let Some(gc_schedule) = &config.misc.gc_schedule {
+        if let Err(e) = cron::Schedule::from_str(gc_schedule) {
+            return Err(ConfigError::Message(format!(
+                "Invalid misc.gc_schedule {gc_schedule:?}: {e}"
+            )));
+        }
+    }
+
+    for location in &config.runtime.wasm_module_locations {
+        if let Err(e) = url::Url::parse(location) {
+            return Err(ConfigError::Message(format!(
+                "Invalid runtime.wasm_module_locations entry {location:?}: {e}"
+            )));
+        }
+    }
+
     if let Some(max_memory) = config.runtime.max_memory {
         if max_memory < MIN_MEMORY {
             return Err(ConfigError::Message(format!(
@@ -356,9 +659,13 @@ pub fn load_config_from_string(
 mod tests {
     use super::{
         build_default_config, load_config_from_string, AccessSettings, Catalog, Frontend,
-        HttpFrontend, Local, ObjectStore, Postgres, Runtime, SeafowlConfig, S3,
+        HttpFrontend, Job, Local, ObjectStore, Postgres, Runtime, SeafowlConfig, S3,
+    };
+    use crate::config::schema::{
+        AdmissionControl, Misc, QueryLimit, QueryLimits, ResultCache, Retention,
+        RetentionPolicy, Sqlite,
     };
-    use crate::config::schema::{Misc, Sqlite};
+    use crate::wasm_udf::data_types::WasmLimits;
     use sqlx::sqlite::SqliteJournalMode;
     use std::{collections::HashMap, path::PathBuf};
 
@@ -411,6 +718,134 @@ bind_port = 80
 read_access = "any"
 write_access = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
 upload_data_max_length = 1
+"#;
+
+    const TEST_CONFIG_RETENTION: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[misc.retention]
+interval = 6
+keep_versions = 10
+
+[misc.retention.tables."public.logs"]
+keep_duration_secs = 3600
+"#;
+
+    const TEST_CONFIG_QUERY_LIMITS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[runtime.query_limits]
+timeout_secs = 30
+max_rows = 1000
+
+[runtime.query_limits.principals.writer]
+timeout_secs = 600
+max_memory = 256
+"#;
+
+    const TEST_CONFIG_WASM_LIMITS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[runtime.wasm_limits]
+max_fuel = 1000000
+timeout_ms = 500
+"#;
+
+    const TEST_CONFIG_ADMISSION: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.http.admission]
+max_concurrent_reads = 8
+max_concurrent_writes = 2
+max_queued = 32
+"#;
+
+    const TEST_CONFIG_RESULT_CACHE: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.http.result_cache]
+max_size_mb = 64
+dir = "/tmp/seafowl-results"
+"#;
+
+    #[cfg(feature = "frontend-flight")]
+    const TEST_CONFIG_FLIGHT: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.flight]
+bind_port = 47471
+write_access = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
+
+[frontend.flight.admission]
+max_concurrent_queries = 8
+"#;
+
+    const TEST_CONFIG_JOBS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[misc]
+gc_schedule = "0 30 4 * * *"
+job_run_history = 50
+
+[[jobs]]
+name = "nightly_vacuum"
+schedule = "0 0 3 * * *"
+sql = "VACUUM TABLES"
+
+[[jobs]]
+name = "refresh"
+schedule = "0 */15 * * * *"
+sql = "CREATE TABLE summary AS SELECT 1"
+"#;
+
+    // Invalid config: unparseable cron schedule
+    const TEST_CONFIG_INVALID_JOB: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[[jobs]]
+name = "broken"
+schedule = "every day"
+sql = "VACUUM TABLES"
 "#;
 
     const TEST_CONFIG_ERROR: &str = r#"
@@ -476,17 +911,32 @@ upload_data_max_length = 1
                         bind_port: 80,
                         read_access: AccessSettings::Any,
                         write_access: AccessSettings::Off,
-                        upload_data_max_length: 256
-                    })
+                        upload_data_max_length: 256,
+                        enable_metrics: false,
+                        admission: AdmissionControl::default(),
+                        result_cache: None,
+                    }),
+                    #[cfg(feature = "frontend-flight")]
+                    flight: None,
                 },
                 runtime: Runtime {
                     max_memory: Some(512),
                     temp_dir: Some(PathBuf::from("/tmp/seafowl")),
+                    query_limits: QueryLimits::default(),
+                    wasm_limits: WasmLimits::default(),
+                    wasm_module_cache_dir: None,
+                    wasm_max_instances: None,
+                    wasm_module_locations: vec![],
                 },
                 misc: Misc {
                     max_partition_size: 1024 * 1024,
                     gc_interval: 0,
+                    gc_schedule: None,
+                    retention: Retention::default(),
+                    query_log_size: 1000,
+                    job_run_history: 1000,
                 },
+                jobs: vec![],
             }
         )
     }
@@ -506,7 +956,10 @@ upload_data_max_length = 1
                         "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
                             .to_string()
                 },
-                upload_data_max_length: 1
+                upload_data_max_length: 1,
+                enable_metrics: false,
+                admission: AdmissionControl::default(),
+                result_cache: None,
             }
         );
     }
@@ -568,21 +1021,200 @@ upload_data_max_length = 1
                             "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
                                 .to_string()
                         },
-                        upload_data_max_length: 256
-                    })
+                        upload_data_max_length: 256,
+                        enable_metrics: false,
+                        admission: AdmissionControl::default(),
+                        result_cache: None,
+                    }),
+                    #[cfg(feature = "frontend-flight")]
+                    flight: None,
                 },
                                  runtime: Runtime {
                     max_memory: Some(512),
                     temp_dir: Some(PathBuf::from("/tmp/seafowl")),
+                    query_limits: QueryLimits::default(),
+                    wasm_limits: WasmLimits::default(),
+                    wasm_module_cache_dir: None,
+                    wasm_max_instances: None,
+                    wasm_module_locations: vec![],
                 },
                 misc: Misc {
                     max_partition_size: 1024 * 1024,
                     gc_interval: 0,
+                    gc_schedule: None,
+                    retention: Retention::default(),
+                    query_log_size: 1000,
+                    job_run_history: 1000,
                 },
+                jobs: vec![],
             }
         )
     }
 
+    #[test]
+    fn test_parse_config_retention() {
+        let config = load_config_from_string(TEST_CONFIG_RETENTION, false, None).unwrap();
+
+        assert_eq!(
+            config.misc.retention,
+            Retention {
+                interval: 6,
+                keep_versions: Some(10),
+                keep_duration_secs: None,
+                tables: HashMap::from([(
+                    "public.logs".to_string(),
+                    RetentionPolicy {
+                        keep_versions: None,
+                        keep_duration_secs: Some(3600),
+                    }
+                )]),
+            }
+        );
+
+        assert_eq!(
+            config.misc.retention.policy_for("public", "logs"),
+            RetentionPolicy {
+                keep_versions: None,
+                keep_duration_secs: Some(3600),
+            }
+        );
+        assert_eq!(
+            config.misc.retention.policy_for("public", "other"),
+            RetentionPolicy {
+                keep_versions: Some(10),
+                keep_duration_secs: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_query_limits() {
+        let config =
+            load_config_from_string(TEST_CONFIG_QUERY_LIMITS, false, None).unwrap();
+        let limits = config.runtime.query_limits;
+
+        assert_eq!(
+            limits.limit_for("writer"),
+            QueryLimit {
+                max_memory: Some(256),
+                timeout_secs: Some(600),
+                max_rows: Some(1000),
+            }
+        );
+        assert_eq!(
+            limits.limit_for("anonymous"),
+            QueryLimit {
+                max_memory: None,
+                timeout_secs: Some(30),
+                max_rows: Some(1000),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_wasm_limits() {
+        let config =
+            load_config_from_string(TEST_CONFIG_WASM_LIMITS, false, None).unwrap();
+
+        assert_eq!(
+            config.runtime.wasm_limits,
+            WasmLimits {
+                max_fuel: Some(1000000),
+                timeout_ms: Some(500),
+                max_memory: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_admission() {
+        let config = load_config_from_string(TEST_CONFIG_ADMISSION, false, None).unwrap();
+
+        assert_eq!(
+            config.frontend.http.unwrap().admission,
+            AdmissionControl {
+                max_concurrent_queries: None,
+                max_concurrent_reads: Some(8),
+                max_concurrent_writes: Some(2),
+                max_queued: 32,
+                queue_timeout_secs: 30,
+                retry_after_secs: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_result_cache() {
+        let config =
+            load_config_from_string(TEST_CONFIG_RESULT_CACHE, false, None).unwrap();
+
+        assert_eq!(
+            config.frontend.http.unwrap().result_cache,
+            Some(ResultCache {
+                max_size_mb: 64,
+                ttl_secs: None,
+                dir: Some(PathBuf::from("/tmp/seafowl-results")),
+            })
+        );
+    }
+
+    #[cfg(feature = "frontend-flight")]
+    #[test]
+    fn test_parse_config_flight() {
+        let config = load_config_from_string(TEST_CONFIG_FLIGHT, false, None).unwrap();
+
+        assert_eq!(
+            config.frontend.flight,
+            Some(super::FlightFrontend {
+                bind_host: "127.0.0.1".to_string(),
+                bind_port: 47471,
+                read_access: AccessSettings::Any,
+                write_access: AccessSettings::Password {
+                    sha256_hash:
+                        "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
+                            .to_string()
+                },
+                admission: AdmissionControl {
+                    max_concurrent_queries: Some(8),
+                    ..Default::default()
+                },
+            })
+        );
+        assert_eq!(config.frontend.http, None);
+    }
+
+    #[test]
+    fn test_parse_config_jobs() {
+        let config = load_config_from_string(TEST_CONFIG_JOBS, false, None).unwrap();
+
+        assert_eq!(
+            config.jobs,
+            vec![
+                Job {
+                    name: "nightly_vacuum".to_string(),
+                    schedule: "0 0 3 * * *".to_string(),
+                    sql: "VACUUM TABLES".to_string(),
+                },
+                Job {
+                    name: "refresh".to_string(),
+                    schedule: "0 */15 * * * *".to_string(),
+                    sql: "CREATE TABLE summary AS SELECT 1".to_string(),
+                }
+            ]
+        );
+        assert_eq!(config.misc.gc_schedule, Some("0 30 4 * * *".to_string()));
+        assert_eq!(config.misc.job_run_history, 50);
+    }
+
+    #[test]
+    fn test_parse_config_invalid_job() {
+        let error =
+            load_config_from_string(TEST_CONFIG_INVALID_JOB, false, None).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid schedule \"every day\" for job broken"))
+    }
+
     #[test]
     fn test_parse_config_erroneous() {
         let error = load_config_from_string(TEST_CONFIG_ERROR, false, None).unwrap_err();
@@ -1,3 +1,9 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
 pub type DatabaseId = i64;
 pub type CollectionId = i64;
 pub type TableId = i64;
@@ -7,6 +13,8 @@ pub type TableColumnId = i64;
 pub type PhysicalPartitionId = i64;
 pub type PhysicalPartitionColumnId = i64;
 pub type FunctionId = i64;
+pub type JobRunId = i64;
+pub type ExternalTableId = i64;
 
 // TODO: most of these structs currently aren't used (we use versions
 // without IDs since they can be passed to db-writing routines before
@@ -66,3 +74,96 @@ pub struct TablePartition {
     pub table_version_id: TableVersionId,
     pub physical_partition_id: PhysicalPartitionId,
 }
+
+/// Definition of an external table (`CREATE EXTERNAL TABLE`) that's persisted in the catalog,
+/// so that it can be recreated when the schema is reloaded.
+///
+/// The credentials and options are stored in plaintext, so they're never exposed in the
+/// system tables or `information_schema`, and are redacted from the `Debug` output. To keep
+/// secrets out of the catalog altogether, the location and option values can be `env:NAME`
+/// references to environment variables, which only get read when the table is loaded.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ExternalTableDetails {
+    // Location with the credentials stripped out, so that it's safe to display
+    pub location: String,
+    // Percent-encoded `user:password` taken out of the location URL
+    pub credentials: Option<String>,
+    pub file_type: String,
+    pub options: ExternalTableOptions,
+}
+
+impl fmt::Debug for ExternalTableDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalTableDetails")
+            .field("location", &self.location)
+            .field(
+                "credentials",
+                &self.credentials.as_ref().map(|_| "<redacted>"),
+            )
+            .field("file_type", &self.file_type)
+            .field("options", &self.options.options.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ExternalTableDetails {
+    /// Split the credentials (e.g. of a remote database's connection string) out of a location
+    pub fn split_credentials(location: &str) -> (String, Option<String>) {
+        match Url::parse(location) {
+            Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+                let credentials = match url.password() {
+                    Some(password) => format!("{}:{password}", url.username()),
+                    None => url.username().to_string(),
+                };
+                // These only fail for URLs that can't have credentials in the first place
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                (url.to_string(), Some(credentials))
+            }
+            _ => (location.to_string(), None),
+        }
+    }
+
+    /// The original location, including the credentials
+    pub fn full_location(&self) -> String {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => return self.location.clone(),
+        };
+
+        match Url::parse(&self.location) {
+            Ok(mut url) => {
+                let (username, password) = match credentials.split_once(':') {
+                    Some((username, password)) => (username, Some(password)),
+                    None => (credentials.as_str(), None),
+                };
+                let _ = url.set_username(username);
+                let _ = url.set_password(password);
+                url.to_string()
+            }
+            Err(_) => self.location.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalTableOptions {
+    pub has_header: bool,
+    pub delimiter: char,
+    pub table_partition_cols: Vec<String>,
+    pub file_compression_type: String,
+    // Whether to only scan the files with the extension matching the file type
+    pub filter_suffix: bool,
+    pub options: HashMap<String, String>,
+}
+
+/// Parquet writer settings for the partitions of a table, set with `CREATE TABLE ... WITH (...)`.
+/// Settings that aren't specified fall back to the ones we use for all other tables.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParquetWriterOptions {
+    pub compression: Option<String>,
+    pub row_group_size: Option<usize>,
+    pub bloom_filter_columns: Vec<String>,
+    pub dictionary: Option<bool>,
+}
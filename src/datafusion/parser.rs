@@ -25,9 +25,8 @@
 //!
 //! Declares a SQL parser based on sqlparser that handles custom formats that we need.
 
-pub use datafusion::sql::parser::Statement;
 use datafusion::sql::parser::{CreateExternalTable, DescribeTable};
-use sqlparser::ast::ObjectName;
+use sqlparser::ast::{DataType, ObjectName};
 use sqlparser::tokenizer::Word;
 use sqlparser::{
     ast::{ColumnDef, ColumnOptionDef, Statement as SQLStatement, TableConstraint},
@@ -56,7 +55,54 @@ fn parse_file_compression_type(s: &str) -> Result<String, ParserError> {
 
 // XXX SEAFOWL: removed the struct definitions here because we want to use
 // the original datafusion::sql::parser structs in order to pass them back
-// to its logical planner
+// to its logical planner. We do have our own Statement enum though, so that we can
+// add the statements that sqlparser doesn't support.
+
+/// `CREATE [OR REPLACE] FUNCTION name AS 'details'`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateFunction {
+    pub or_replace: bool,
+    pub name: ObjectName,
+    /// JSON-serialized function details
+    pub details: String,
+}
+
+/// One of the functions in `DROP FUNCTION`, optionally restricted to a single overload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropFunctionDesc {
+    pub name: ObjectName,
+    pub args: Option<Vec<DataType>>,
+}
+
+/// `DROP FUNCTION [IF EXISTS] name [(type, ...)] [, ...]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropFunction {
+    pub if_exists: bool,
+    pub func_desc: Vec<DropFunctionDesc>,
+}
+
+/// `REFRESH MATERIALIZED VIEW name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshMaterializedView {
+    pub name: ObjectName,
+}
+
+/// Tokens parsed by `DFParser` are converted into these values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    /// ANSI SQL AST node
+    Statement(Box<SQLStatement>),
+    /// Extension: `CREATE EXTERNAL TABLE`
+    CreateExternalTable(CreateExternalTable),
+    /// Extension: `DESCRIBE TABLE`
+    DescribeTable(DescribeTable),
+    /// Extension: `CREATE [OR REPLACE] [AGGREGATE] FUNCTION`
+    CreateFunction(CreateFunction),
+    /// Extension: `DROP FUNCTION`
+    DropFunction(DropFunction),
+    /// Extension: `REFRESH MATERIALIZED VIEW`
+    RefreshMaterializedView(RefreshMaterializedView),
+}
 
 /// SQL Parser
 pub struct DFParser<'a> {
@@ -67,6 +113,9 @@ pub struct DFParser<'a> {
 #[strum(serialize_all = "UPPERCASE")]
 enum KeywordExtensions {
     Vacuum,
+    Aggregate,
+    Returns,
+    Refresh,
 }
 
 impl<'a> DFParser<'a> {
@@ -142,6 +191,15 @@ impl<'a> DFParser<'a> {
                         // use custom parsing
                         self.parse_create()
                     }
+                    Word {
+                        keyword: Keyword::DROP,
+                        ..
+                    } => {
+                        // move one token forward
+                        self.parser.next_token();
+                        // use custom parsing
+                        self.parse_drop()
+                    }
                     Word {
                         keyword: Keyword::DESCRIBE,
                         ..
@@ -160,6 +218,15 @@ impl<'a> DFParser<'a> {
                         // use custom parsing
                         self.parse_vacuum()
                     }
+                    Word { value, .. }
+                        if value.to_uppercase()
+                            == KeywordExtensions::Refresh.to_string() =>
+                    {
+                        // move one token forward
+                        self.parser.next_token();
+                        // use custom parsing
+                        self.parse_refresh()
+                    }
                     _ => {
                         // use the native parser
                         Ok(Statement::Statement(Box::from(
@@ -207,23 +274,168 @@ impl<'a> DFParser<'a> {
         })))
     }
 
+    // XXX SEAFOWL: parse `REFRESH MATERIALIZED VIEW name`
+    pub fn parse_refresh(&mut self) -> Result<Statement, ParserError> {
+        self.parser
+            .expect_keywords(&[Keyword::MATERIALIZED, Keyword::VIEW])?;
+        let name = self.parser.parse_object_name()?;
+
+        Ok(Statement::RefreshMaterializedView(
+            RefreshMaterializedView { name },
+        ))
+    }
+
+    /// Parse a SQL DROP statement
+    pub fn parse_drop(&mut self) -> Result<Statement, ParserError> {
+        // XXX SEAFOWL: sqlparser doesn't support DROP FUNCTION
+        if self.parser.parse_keyword(Keyword::FUNCTION) {
+            self.parse_drop_function()
+        } else {
+            // Let sqlparser handle the DROP statement from the start
+            self.parser.prev_token();
+            Ok(Statement::Statement(Box::from(
+                self.parser.parse_statement()?,
+            )))
+        }
+    }
+
+    // XXX SEAFOWL: parse `DROP FUNCTION [IF EXISTS] name [(type, ...)] [, ...]`
+    fn parse_drop_function(&mut self) -> Result<Statement, ParserError> {
+        let if_exists = self.parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+
+        let mut func_desc = vec![];
+        loop {
+            let name = self.parser.parse_object_name()?;
+            let args = if self.parser.consume_token(&Token::LParen) {
+                let mut args = vec![];
+                if !self.parser.consume_token(&Token::RParen) {
+                    loop {
+                        args.push(self.parser.parse_data_type()?);
+                        if self.parser.consume_token(&Token::RParen) {
+                            break;
+                        }
+                        self.parser.expect_token(&Token::Comma)?;
+                    }
+                }
+                Some(args)
+            } else {
+                None
+            };
+            func_desc.push(DropFunctionDesc { name, args });
+
+            if !self.parser.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+
+        Ok(Statement::DropFunction(DropFunction {
+            if_exists,
+            func_desc,
+        }))
+    }
+
     /// Parse a SQL CREATE statement
     pub fn parse_create(&mut self) -> Result<Statement, ParserError> {
         if self.parser.parse_keyword(Keyword::EXTERNAL) {
             self.parse_create_external_table()
         }
         // XXX SEAFOWL: this is the change to get CREATE FUNCTION parsing working
-        else if self.parser.parse_keyword(Keyword::FUNCTION) {
-            // assume we don't have CREATE TEMPORARY FUNCTION (since we don't care about TEMPORARY)
-            Ok(Statement::Statement(Box::from(
-                self.parser.parse_create_function(false)?,
-            )))
+        else if let Some(statement) = self.maybe_parse_create_function()? {
+            Ok(statement)
         // XXX SEAFOWL: change ends here
         } else {
             Ok(Statement::Statement(Box::from(self.parser.parse_create()?)))
         }
     }
 
+    // XXX SEAFOWL: parse `CREATE [OR REPLACE] [AGGREGATE] FUNCTION`, leaving the parser
+    // where it was if this isn't a CREATE FUNCTION statement
+    fn maybe_parse_create_function(&mut self) -> Result<Option<Statement>, ParserError> {
+        let or_replace = self.parser.parse_keywords(&[Keyword::OR, Keyword::REPLACE]);
+
+        if self.parser.parse_keyword(Keyword::FUNCTION) {
+            // assume we don't have CREATE TEMPORARY FUNCTION (since we don't care about TEMPORARY)
+            self.parse_create_function(false, or_replace).map(Some)
+        } else if self.parse_keyword_extension(KeywordExtensions::Aggregate) {
+            self.parser.expect_keyword(Keyword::FUNCTION)?;
+            self.parse_create_function(true, or_replace).map(Some)
+        } else {
+            if or_replace {
+                // Let sqlparser handle e.g. CREATE OR REPLACE VIEW
+                self.parser.prev_token();
+                self.parser.prev_token();
+            }
+            Ok(None)
+        }
+    }
+
+    // XXX SEAFOWL: parsing for our own CREATE FUNCTION extensions
+    fn parse_keyword_extension(&mut self, keyword: KeywordExtensions) -> bool {
+        match self.parser.peek_token() {
+            Token::Word(Word { value, .. })
+                if value.to_uppercase() == keyword.to_string() =>
+            {
+                self.parser.next_token();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Parse `CREATE [OR REPLACE] [AGGREGATE] FUNCTION name [RETURNS TABLE (columns)] AS 'details'`,
+    /// with the function kind and the returned columns merged into the JSON details.
+    fn parse_create_function(
+        &mut self,
+        aggregate: bool,
+        or_replace: bool,
+    ) -> Result<Statement, ParserError> {
+        let name = self.parser.parse_object_name()?;
+        let return_columns = if self.parse_keyword_extension(KeywordExtensions::Returns) {
+            self.parser.expect_keyword(Keyword::TABLE)?;
+            Some(self.parse_columns()?.0)
+        } else {
+            None
+        };
+        self.parser.expect_keyword(Keyword::AS)?;
+        let mut details = self.parser.parse_literal_string()?;
+
+        if aggregate || return_columns.is_some() {
+            let mut parsed: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&details).map_err(|e| {
+                    ParserError::ParserError(format!(
+                        "Error parsing function details: {e}"
+                    ))
+                })?;
+
+            if aggregate {
+                parsed.insert("kind".to_string(), "aggregate".into());
+            }
+            if let Some(columns) = return_columns {
+                parsed.insert("return_type".to_string(), "table".into());
+                parsed.insert(
+                    "return_columns".to_string(),
+                    columns
+                        .iter()
+                        .map(|c| {
+                            serde_json::json!({
+                                "name": c.name.value,
+                                "type": c.data_type.to_string(),
+                            })
+                        })
+                        .collect(),
+                );
+            }
+            details = serde_json::Value::Object(parsed).to_string();
+        }
+
+        Ok(Statement::CreateFunction(CreateFunction {
+            or_replace,
+            name,
+            details,
+        }))
+    }
+    // XXX SEAFOWL: change ends here
+
     fn parse_partitions(&mut self) -> Result<Vec<String>, ParserError> {
         let mut partitions: Vec<String> = vec![];
         if !self.parser.consume_token(&Token::LParen)
@@ -897,7 +897,7 @@ pub fn visit_values_row<'ast, V: VisitorMut<'ast> + ?Sized>(
 
 #[cfg(test)]
 mod tests {
-    use datafusion::sql::parser::Statement;
+    use crate::datafusion::parser::Statement;
     use rstest::rstest;
     use sqlparser::ast::{
         Expr, FunctionArg, ObjectName, Statement as SQLStatement, TableAlias,
@@ -0,0 +1,233 @@
+//! Admission control for the HTTP and Flight frontends: caps the number of queries that
+//! run at the same time (overall and separately for reads and writes) and makes the rest
+//! wait in a bounded queue, rejecting them if it's full.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::schema::AdmissionControl;
+use crate::metrics::{QUERIES_QUEUED, QUERIES_REJECTED};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryKind {
+    Read,
+    Write,
+}
+
+impl QueryKind {
+    fn name(&self) -> &'static str {
+        match self {
+            QueryKind::Read => "read",
+            QueryKind::Write => "write",
+        }
+    }
+}
+
+/// The query couldn't be admitted; the client should retry after this many seconds
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rejected {
+    pub retry_after: u64,
+}
+
+/// Held for as long as the query is running; dropping it frees up the slots
+pub struct AdmissionPermit {
+    _pool: Option<OwnedSemaphorePermit>,
+    _total: Option<OwnedSemaphorePermit>,
+}
+
+pub struct AdmissionController {
+    total: Option<Arc<Semaphore>>,
+    reads: Option<Arc<Semaphore>>,
+    writes: Option<Arc<Semaphore>>,
+    queued: AtomicUsize,
+    max_queued: usize,
+    queue_timeout: Duration,
+    retry_after: u64,
+}
+
+// Decrements the queue length when a query leaves the queue (admitted or not)
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+        QUERIES_QUEUED.dec();
+    }
+}
+
+impl AdmissionController {
+    pub fn new(config: &AdmissionControl) -> Self {
+        let semaphore = |limit: Option<usize>| limit.map(|l| Arc::new(Semaphore::new(l)));
+
+        Self {
+            total: semaphore(config.max_concurrent_queries),
+            reads: semaphore(config.max_concurrent_reads),
+            writes: semaphore(config.max_concurrent_writes),
+            queued: AtomicUsize::new(0),
+            max_queued: config.max_queued,
+            queue_timeout: Duration::from_secs(config.queue_timeout_secs),
+            retry_after: config.retry_after_secs,
+        }
+    }
+
+    fn pool(&self, kind: QueryKind) -> &Option<Arc<Semaphore>> {
+        match kind {
+            QueryKind::Read => &self.reads,
+            QueryKind::Write => &self.writes,
+        }
+    }
+
+    fn reject(&self, kind: QueryKind) -> Rejected {
+        QUERIES_REJECTED.with_label_values(&[kind.name()]).inc();
+        Rejected {
+            retry_after: self.retry_after,
+        }
+    }
+
+    fn try_admit(&self, kind: QueryKind) -> Option<AdmissionPermit> {
+        let pool = match self.pool(kind) {
+            Some(pool) => Some(pool.clone().try_acquire_owned().ok()?),
+            None => None,
+        };
+        let total = match &self.total {
+            Some(total) => Some(total.clone().try_acquire_owned().ok()?),
+            None => None,
+        };
+
+        Some(AdmissionPermit {
+            _pool: pool,
+            _total: total,
+        })
+    }
+
+    async fn wait_for_admission(&self, kind: QueryKind) -> AdmissionPermit {
+        // Always take the pool permit before the global one, so that a query waiting
+        // for a full pool doesn't hold up the queries from the other one
+        let pool = match self.pool(kind) {
+            Some(pool) => Some(
+                pool.clone()
+                    .acquire_owned()
+                    .await
+                    .expect("admission semaphores are never closed"),
+            ),
+            None => None,
+        };
+        let total = match &self.total {
+            Some(total) => Some(
+                total
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("admission semaphores are never closed"),
+            ),
+            None => None,
+        };
+
+        AdmissionPermit {
+            _pool: pool,
+            _total: total,
+        }
+    }
+
+    /// Wait for a slot to run a query in, or reject it if the queue is full
+    pub async fn admit(&self, kind: QueryKind) -> Result<AdmissionPermit, Rejected> {
+        if let Some(permit) = self.try_admit(kind) {
+            return Ok(permit);
+        }
+
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(self.reject(kind));
+        }
+        QUERIES_QUEUED.inc();
+        let _guard = QueueGuard(&self.queued);
+
+        tokio::time::timeout(self.queue_timeout, self.wait_for_admission(kind))
+            .await
+            .map_err(|_| self.reject(kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::schema::AdmissionControl;
+
+    use super::{AdmissionController, QueryKind, Rejected};
+
+    #[tokio::test]
+    async fn test_separate_pools() {
+        let controller = AdmissionController::new(&AdmissionControl {
+            max_concurrent_reads: Some(1),
+            max_concurrent_writes: Some(1),
+            retry_after_secs: 5,
+            ..Default::default()
+        });
+
+        let read = controller.admit(QueryKind::Read).await.unwrap();
+        assert_eq!(
+            controller.admit(QueryKind::Read).await.err(),
+            Some(Rejected { retry_after: 5 })
+        );
+
+        // Writes have their own pool
+        let _write = controller.admit(QueryKind::Write).await.unwrap();
+
+        drop(read);
+        assert!(controller.admit(QueryKind::Read).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_total_limit() {
+        let controller = AdmissionController::new(&AdmissionControl {
+            max_concurrent_queries: Some(1),
+            ..Default::default()
+        });
+
+        let _read = controller.admit(QueryKind::Read).await.unwrap();
+        assert!(controller.admit(QueryKind::Write).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_queue() {
+        let controller = AdmissionController::new(&AdmissionControl {
+            max_concurrent_queries: Some(1),
+            max_queued: 1,
+            queue_timeout_secs: 3600,
+            ..Default::default()
+        });
+
+        let running = controller.admit(QueryKind::Read).await.unwrap();
+
+        let (queued, _) = tokio::join!(controller.admit(QueryKind::Read), async {
+            // Wait for the second query to get queued: a third one gets rejected,
+            // since the queue is full
+            while controller.queued.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert!(controller.admit(QueryKind::Read).await.is_err());
+            drop(running);
+        });
+
+        assert!(queued.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_queue_timeout() {
+        let controller = AdmissionController::new(&AdmissionControl {
+            max_concurrent_queries: Some(0),
+            max_queued: 1,
+            queue_timeout_secs: 0,
+            ..Default::default()
+        });
+
+        assert!(controller.admit(QueryKind::Read).await.is_err());
+        assert_eq!(
+            controller.queued.load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
+    }
+}
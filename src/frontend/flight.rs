@@ -0,0 +1,1197 @@
+//! Arrow Flight SQL frontend. Statements go through the same parsing, authorization,
+//! admission control, query limits and query log as in the HTTP frontend, but the results
+//! are streamed back as Arrow IPC messages while the statement runs. We don't keep any
+//! per-client state: prepared statement handles and tickets are just the query text and
+//! the access token is the password itself.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use arrow::array::{as_string_array, ArrayRef, BinaryArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::ipc::writer::IpcWriteOptions;
+use arrow::record_batch::RecordBatch;
+use arrow_flight::{
+    flight_service_server::{FlightService, FlightServiceServer},
+    sql::{
+        server::FlightSqlService, ActionClosePreparedStatementRequest,
+        ActionCreatePreparedStatementRequest, ActionCreatePreparedStatementResult,
+        CommandGetCatalogs, CommandGetCrossReference, CommandGetDbSchemas,
+        CommandGetExportedKeys, CommandGetImportedKeys, CommandGetPrimaryKeys,
+        CommandGetSqlInfo, CommandGetTableTypes, CommandGetTables,
+        CommandPreparedStatementQuery, CommandPreparedStatementUpdate,
+        CommandStatementQuery, CommandStatementUpdate, ProstMessageExt, SqlInfo,
+        TicketStatementQuery,
+    },
+    utils::flight_data_from_arrow_batch,
+    Action as FlightAction, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, IpcMessage, SchemaAsIpc, Ticket,
+};
+use async_trait::async_trait;
+use datafusion::error::DataFusionError;
+use futures::channel::mpsc;
+use futures::{stream, SinkExt, Stream, StreamExt};
+use log::info;
+use prost::Message;
+use sqlparser::ast::{Ident, Value};
+use tokio::sync::broadcast::Receiver;
+use tonic::metadata::MetadataMap;
+use tonic::transport::Server;
+use tonic::{Code, Request, Response, Status, Streaming};
+use warp::hyper::StatusCode;
+
+use crate::auth::{token_to_principal, AccessPolicy, Action, UserContext};
+use crate::config::schema::FlightFrontend;
+use crate::context::{is_statement_read_only, SeafowlContext};
+use crate::datafusion::parser::Statement as DFStatement;
+use crate::query_log::Frontend;
+
+use super::admission::{AdmissionController, AdmissionPermit, QueryKind};
+use super::http_utils::ApiError;
+
+const AUTHORIZATION: &str = "authorization";
+const BEARER_PREFIX: &str = "Bearer ";
+const BASIC_PREFIX: &str = "Basic ";
+
+type FlightDataStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send>>;
+type HandshakeStream =
+    Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>>;
+
+fn api_error_to_status(error: ApiError) -> Status {
+    let (status, message) = error.status_code_body();
+    let code = match status {
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN | StatusCode::METHOD_NOT_ALLOWED => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        StatusCode::UNPROCESSABLE_ENTITY | StatusCode::TOO_MANY_REQUESTS => {
+            Code::ResourceExhausted
+        }
+        StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        status if status.is_server_error() => Code::Internal,
+        _ => Code::InvalidArgument,
+    };
+    Status::new(code, message)
+}
+
+// Get the access token from the `authorization` metadata. Besides the bearer token (same as
+// in the HTTP frontend), we accept Basic auth, since that's what most Flight SQL drivers
+// send in the handshake. The username is ignored.
+fn metadata_to_token(metadata: &MetadataMap) -> Result<Option<String>, ApiError> {
+    let header = match metadata.get(AUTHORIZATION) {
+        Some(header) => header
+            .to_str()
+            .map_err(|_| ApiError::InvalidAuthorizationHeader)?,
+        None => return Ok(None),
+    };
+
+    if let Some(token) = header.strip_prefix(BEARER_PREFIX) {
+        Ok(Some(token.to_string()))
+    } else if let Some(credentials) = header.strip_prefix(BASIC_PREFIX) {
+        let credentials = base64::decode(credentials)
+            .ok()
+            .and_then(|c| String::from_utf8(c).ok())
+            .ok_or(ApiError::InvalidAuthorizationHeader)?;
+        match credentials.split_once(':') {
+            Some((_, "")) => Ok(None),
+            Some((_, password)) => Ok(Some(password.to_string())),
+            None => Err(ApiError::InvalidAuthorizationHeader),
+        }
+    } else {
+        Err(ApiError::InvalidAuthorizationHeader)
+    }
+}
+
+fn schema_to_ipc(schema: &Schema) -> Result<Vec<u8>, ApiError> {
+    let message: IpcMessage = SchemaAsIpc::new(schema, &IpcWriteOptions::default())
+        .try_into()
+        .map_err(DataFusionError::ArrowError)?;
+    Ok(message.0)
+}
+
+// Build a `FlightInfo` with a single endpoint on this server (no locations) that can be
+// fetched with the given ticket
+fn flight_info(
+    schema: &Schema,
+    ticket: impl ProstMessageExt,
+    descriptor: FlightDescriptor,
+) -> Result<Response<FlightInfo>, Status> {
+    let message: IpcMessage = SchemaAsIpc::new(schema, &IpcWriteOptions::default())
+        .try_into()
+        .map_err(|e| Status::internal(format!("Error encoding the schema: {e}")))?;
+
+    let endpoint = FlightEndpoint {
+        ticket: Some(Ticket {
+            ticket: ticket.as_any().encode_to_vec(),
+        }),
+        location: vec![],
+    };
+
+    Ok(Response::new(FlightInfo::new(
+        message,
+        Some(descriptor),
+        vec![endpoint],
+        -1,
+        -1,
+    )))
+}
+
+fn batches_to_response(
+    schema: &Schema,
+    batches: &[RecordBatch],
+) -> Response<FlightDataStream> {
+    let options = IpcWriteOptions::default();
+    let mut flight_data = vec![FlightData::from(SchemaAsIpc::new(schema, &options))];
+
+    for batch in batches {
+        let (dictionaries, batch) = flight_data_from_arrow_batch(batch, &options);
+        flight_data.extend(dictionaries);
+        flight_data.push(batch);
+    }
+
+    let output: FlightDataStream =
+        Box::pin(stream::iter(flight_data.into_iter().map(Ok)));
+    Response::new(output)
+}
+
+fn handle_to_query(handle: Vec<u8>) -> Result<String, Status> {
+    String::from_utf8(handle)
+        .map_err(|_| Status::invalid_argument("Invalid statement handle"))
+}
+
+fn unimplemented<T>(command: &str) -> Result<T, Status> {
+    Err(Status::unimplemented(format!(
+        "{command} isn't supported by Seafowl"
+    )))
+}
+
+// Schemas of the results of the metadata commands, as required by the Flight SQL spec
+fn catalogs_schema() -> Schema {
+    Schema::new(vec![Field::new("catalog_name", DataType::Utf8, false)])
+}
+
+fn db_schemas_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("catalog_name", DataType::Utf8, true),
+        Field::new("db_schema_name", DataType::Utf8, false),
+    ])
+}
+
+fn tables_schema(include_schema: bool) -> Schema {
+    let mut fields = vec![
+        Field::new("catalog_name", DataType::Utf8, true),
+        Field::new("db_schema_name", DataType::Utf8, true),
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("table_type", DataType::Utf8, false),
+    ];
+    if include_schema {
+        fields.push(Field::new("table_schema", DataType::Binary, false));
+    }
+    Schema::new(fields)
+}
+
+fn table_types_schema() -> Schema {
+    Schema::new(vec![Field::new("table_type", DataType::Utf8, false)])
+}
+
+fn sql_string(value: &str) -> String {
+    Value::SingleQuotedString(value.to_string()).to_string()
+}
+
+fn where_clause(filters: Vec<String>) -> String {
+    if filters.is_empty() {
+        "".to_string()
+    } else {
+        format!("WHERE {}", filters.join(" AND "))
+    }
+}
+
+pub struct SeafowlFlightSqlService {
+    context: Arc<dyn SeafowlContext>,
+    policy: AccessPolicy,
+    admission: AdmissionController,
+}
+
+impl SeafowlFlightSqlService {
+    pub fn new(
+        context: Arc<dyn SeafowlContext>,
+        policy: AccessPolicy,
+        admission: AdmissionController,
+    ) -> Self {
+        Self {
+            context,
+            policy,
+            admission,
+        }
+    }
+
+    fn user_context<T>(&self, request: &Request<T>) -> Result<UserContext, Status> {
+        let token = metadata_to_token(request.metadata()).map_err(api_error_to_status)?;
+        let principal =
+            token_to_principal(token, &self.policy).map_err(api_error_to_status)?;
+
+        Ok(UserContext {
+            principal,
+            policy: self.policy.clone(),
+        })
+    }
+
+    fn check_read(&self, user_context: &UserContext) -> Result<(), Status> {
+        if user_context.can_perform_action(Action::Read) {
+            Ok(())
+        } else {
+            Err(api_error_to_status(ApiError::ReadForbidden))
+        }
+    }
+
+    // Parse a single statement, checking that the user is allowed to run it
+    async fn parse_statement(
+        &self,
+        user_context: &UserContext,
+        query: &str,
+    ) -> Result<DFStatement, ApiError> {
+        let mut statements = self.context.parse_query(query).await?;
+        let statement = match statements.len() {
+            0 => return Err(ApiError::EmptyMultiStatement),
+            1 => statements.pop().expect("one statement"),
+            _ => {
+                return Err(DataFusionError::NotImplemented(
+                    "Flight SQL requests can only contain one statement".to_string(),
+                )
+                .into())
+            }
+        };
+
+        let (action, error) = if is_statement_read_only(&statement) {
+            (Action::Read, ApiError::ReadForbidden)
+        } else {
+            (Action::Write, ApiError::WriteForbidden)
+        };
+        if !user_context.can_perform_action(action) {
+            return Err(error);
+        };
+
+        Ok(statement)
+    }
+
+    // Get the schema of a statement's results without running it
+    async fn plan_schema(
+        &self,
+        user_context: &UserContext,
+        query: &str,
+    ) -> Result<Schema, ApiError> {
+        let statement = self.parse_statement(user_context, query).await?;
+        let plan = self
+            .context
+            .create_logical_plan_from_statement(statement)
+            .await?;
+        Ok(plan.schema().as_ref().into())
+    }
+
+    // Wait for a slot to run the statement in
+    async fn admit(&self, statement: &DFStatement) -> Result<AdmissionPermit, ApiError> {
+        let kind = if is_statement_read_only(statement) {
+            QueryKind::Read
+        } else {
+            QueryKind::Write
+        };
+        Ok(self.admission.admit(kind).await?)
+    }
+
+    // Run a statement, discarding its results
+    async fn execute(
+        &self,
+        user_context: &UserContext,
+        query: &str,
+    ) -> Result<(), ApiError> {
+        let statement = self.parse_statement(user_context, query).await?;
+        let _permit = self.admit(&statement).await?;
+
+        let context = self.context.clone();
+        let limits = self.context.query_limits(&user_context.principal);
+        let statement_limits = limits.clone();
+
+        self.context
+            .query_log()
+            .track(
+                Frontend::Flight,
+                query,
+                &user_context.principal,
+                limits.run(async move {
+                    let logical = context
+                        .create_logical_plan_from_statement(statement)
+                        .await?;
+                    let physical = context.create_physical_plan(&logical).await?;
+                    context
+                        .collect_with_limits(physical, &statement_limits)
+                        .await
+                }),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // Run a statement, sending its results to the client as they're produced. This happens
+    // in a separate task that holds on to the admission slot until the statement is done
+    // and stops early if the client goes away. The response is returned once the statement
+    // has been planned, so that planning errors fail the request itself.
+    async fn execute_stream(
+        &self,
+        user_context: &UserContext,
+        query: &str,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let statement = self
+            .parse_statement(user_context, query)
+            .await
+            .map_err(api_error_to_status)?;
+        let permit = self.admit(&statement).await.map_err(api_error_to_status)?;
+
+        let context = self.context.clone();
+        let query = query.to_string();
+        let principal = user_context.principal.clone();
+        let limits = self.context.query_limits(&principal);
+        let (mut sender, mut receiver) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            let mut output = sender.clone();
+
+            let result = context
+                .query_log()
+                .track_streamed(
+                    Frontend::Flight,
+                    &query,
+                    &principal,
+                    limits.run(async {
+                        let logical = context
+                            .create_logical_plan_from_statement(statement)
+                            .await?;
+                        let physical = context.create_physical_plan(&logical).await?;
+
+                        let options = IpcWriteOptions::default();
+                        let schema =
+                            SchemaAsIpc::new(&physical.schema(), &options).into();
+                        let mut stream = context
+                            .execute_stream_with_limits(physical, &limits)
+                            .await?;
+
+                        // Sending fails if the client has dropped the response
+                        let (mut rows, mut bytes) = (0, 0);
+                        if output.send(Ok(schema)).await.is_err() {
+                            return Ok((rows, bytes));
+                        }
+
+                        while let Some(batch) = stream.next().await {
+                            let batch = batch?;
+                            rows += batch.num_rows() as i64;
+                            bytes += batch
+                                .columns()
+                                .iter()
+                                .map(|c| c.get_array_memory_size() as i64)
+                                .sum::<i64>();
+
+                            let (dictionaries, batch) =
+                                flight_data_from_arrow_batch(&batch, &options);
+                            for flight_data in dictionaries.into_iter().chain([batch]) {
+                                if output.send(Ok(flight_data)).await.is_err() {
+                                    return Ok((rows, bytes));
+                                }
+                            }
+                        }
+
+                        Ok((rows, bytes))
+                    }),
+                )
+                .await;
+
+            // Free up the slot before the response ends
+            drop(permit);
+            if let Err(err) = result {
+                // Nothing to do if the client is gone
+                let _ = sender.send(Err(api_error_to_status(err.into()))).await;
+            }
+        });
+
+        // The first message is either the schema or the error we got before the statement
+        // started producing results
+        match receiver.next().await {
+            Some(Ok(schema)) => {
+                let output: FlightDataStream =
+                    Box::pin(stream::once(async { Ok(schema) }).chain(receiver));
+                Ok(Response::new(output))
+            }
+            Some(Err(status)) => Err(status),
+            None => Err(Status::internal(
+                "The statement stopped without any results",
+            )),
+        }
+    }
+
+    // Run an internal query against `information_schema`, relabeling the results with the
+    // column names and nullability that Flight SQL expects
+    async fn metadata_query(
+        &self,
+        sql: &str,
+        schema: SchemaRef,
+    ) -> Result<Vec<RecordBatch>, ApiError> {
+        let plan = self.context.plan_query(sql).await?;
+        let batches = self.context.collect(plan).await?;
+
+        Ok(batches
+            .into_iter()
+            .map(|batch| RecordBatch::try_new(schema.clone(), batch.columns().to_vec()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DataFusionError::ArrowError)?)
+    }
+
+    // Add the IPC-encoded schema of each table to the `information_schema.tables` results
+    async fn add_table_schemas(
+        &self,
+        batch: RecordBatch,
+    ) -> Result<Vec<ArrayRef>, ApiError> {
+        let mut table_schemas = Vec::with_capacity(batch.num_rows());
+        let catalogs = as_string_array(batch.column(0));
+        let schemas = as_string_array(batch.column(1));
+        let tables = as_string_array(batch.column(2));
+
+        for row in 0..batch.num_rows() {
+            let reference = [catalogs.value(row), schemas.value(row), tables.value(row)]
+                .iter()
+                .map(|name| Ident::with_quote('"', *name).to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            let plan = self
+                .context
+                .create_logical_plan(&format!("SELECT * FROM {reference}"))
+                .await?;
+            table_schemas.push(schema_to_ipc(&plan.schema().as_ref().into())?);
+        }
+
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(BinaryArray::from_iter_values(table_schemas)));
+        Ok(columns)
+    }
+}
+
+#[async_trait]
+impl FlightSqlService for SeafowlFlightSqlService {
+    type FlightService = SeafowlFlightSqlService;
+
+    // Validate the credentials and hand them back as the bearer token that the client
+    // should send with the rest of its requests
+    async fn do_handshake(
+        &self,
+        request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<HandshakeStream>, Status> {
+        let token = metadata_to_token(request.metadata()).map_err(api_error_to_status)?;
+        token_to_principal(token.clone(), &self.policy).map_err(api_error_to_status)?;
+
+        let output: HandshakeStream = Box::pin(stream::empty());
+        let mut response = Response::new(output);
+        if let Some(token) = token {
+            let value = format!("{BEARER_PREFIX}{token}")
+                .parse()
+                .map_err(|_| api_error_to_status(ApiError::InvalidAuthorizationHeader))?;
+            response.metadata_mut().insert(AUTHORIZATION, value);
+        }
+        Ok(response)
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let user_context = self.user_context(&request)?;
+        let schema = self
+            .plan_schema(&user_context, &query.query)
+            .await
+            .map_err(api_error_to_status)?;
+
+        let ticket = TicketStatementQuery {
+            statement_handle: query.query.into_bytes(),
+        };
+        flight_info(&schema, ticket, request.into_inner())
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let user_context = self.user_context(&request)?;
+        let sql = handle_to_query(query.prepared_statement_handle.clone())?;
+        let schema = self
+            .plan_schema(&user_context, &sql)
+            .await
+            .map_err(api_error_to_status)?;
+
+        flight_info(&schema, query, request.into_inner())
+    }
+
+    async fn get_flight_info_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+        flight_info(&catalogs_schema(), query, request.into_inner())
+    }
+
+    async fn get_flight_info_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+        flight_info(&db_schemas_schema(), query, request.into_inner())
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+        let schema = tables_schema(query.include_schema);
+        flight_info(&schema, query, request.into_inner())
+    }
+
+    async fn get_flight_info_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+        flight_info(&table_types_schema(), query, request.into_inner())
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        _query: CommandGetSqlInfo,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        unimplemented("GetSqlInfo")
+    }
+
+    async fn get_flight_info_primary_keys(
+        &self,
+        _query: CommandGetPrimaryKeys,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        unimplemented("GetPrimaryKeys")
+    }
+
+    async fn get_flight_info_exported_keys(
+        &self,
+        _query: CommandGetExportedKeys,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        unimplemented("GetExportedKeys")
+    }
+
+    async fn get_flight_info_imported_keys(
+        &self,
+        _query: CommandGetImportedKeys,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        unimplemented("GetImportedKeys")
+    }
+
+    async fn get_flight_info_cross_reference(
+        &self,
+        _query: CommandGetCrossReference,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        unimplemented("GetCrossReference")
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let user_context = self.user_context(&request)?;
+        let query = handle_to_query(ticket.statement_handle)?;
+        self.execute_stream(&user_context, &query).await
+    }
+
+    async fn do_get_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let user_context = self.user_context(&request)?;
+        let query = handle_to_query(query.prepared_statement_handle)?;
+        self.execute_stream(&user_context, &query).await
+    }
+
+    async fn do_get_catalogs(
+        &self,
+        _query: CommandGetCatalogs,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+
+        let schema = Arc::new(catalogs_schema());
+        let batches = self
+            .metadata_query(
+                "SELECT DISTINCT table_catalog FROM information_schema.tables \
+                ORDER BY table_catalog",
+                schema.clone(),
+            )
+            .await
+            .map_err(api_error_to_status)?;
+
+        Ok(batches_to_response(&schema, &batches))
+    }
+
+    // DataFusion doesn't have `information_schema.schemata` yet, so this doesn't return
+    // schemas without any tables
+    async fn do_get_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+
+        let mut filters = vec![];
+        if let Some(catalog) = &query.catalog {
+            filters.push(format!("table_catalog = {}", sql_string(catalog)));
+        }
+        if let Some(pattern) = &query.db_schema_filter_pattern {
+            filters.push(format!("table_schema LIKE {}", sql_string(pattern)));
+        }
+
+        let schema = Arc::new(db_schemas_schema());
+        let batches = self
+            .metadata_query(
+                &format!(
+                    "SELECT DISTINCT table_catalog, table_schema \
+                    FROM information_schema.tables {} \
+                    ORDER BY table_catalog, table_schema",
+                    where_clause(filters)
+                ),
+                schema.clone(),
+            )
+            .await
+            .map_err(api_error_to_status)?;
+
+        Ok(batches_to_response(&schema, &batches))
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+
+        let mut filters = vec![];
+        if let Some(catalog) = &query.catalog {
+            filters.push(format!("table_catalog = {}", sql_string(catalog)));
+        }
+        if let Some(pattern) = &query.db_schema_filter_pattern {
+            filters.push(format!("table_schema LIKE {}", sql_string(pattern)));
+        }
+        if let Some(pattern) = &query.table_name_filter_pattern {
+            filters.push(format!("table_name LIKE {}", sql_string(pattern)));
+        }
+        if !query.table_types.is_empty() {
+            filters.push(format!(
+                "table_type IN ({})",
+                query
+                    .table_types
+                    .iter()
+                    .map(|t| sql_string(t))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let sql = format!(
+            "SELECT table_catalog, table_schema, table_name, table_type \
+            FROM information_schema.tables {} \
+            ORDER BY table_catalog, table_schema, table_name",
+            where_clause(filters)
+        );
+
+        let schema = Arc::new(tables_schema(query.include_schema));
+        let batches = self
+            .metadata_query(&sql, Arc::new(tables_schema(false)))
+            .await
+            .map_err(api_error_to_status)?;
+
+        let batches = if query.include_schema {
+            let mut with_schemas = Vec::with_capacity(batches.len());
+            for batch in batches {
+                let columns = self
+                    .add_table_schemas(batch)
+                    .await
+                    .map_err(api_error_to_status)?;
+                with_schemas.push(
+                    RecordBatch::try_new(schema.clone(), columns)
+                        .map_err(|e| Status::internal(e.to_string()))?,
+                );
+            }
+            with_schemas
+        } else {
+            batches
+        };
+
+        Ok(batches_to_response(&schema, &batches))
+    }
+
+    async fn do_get_table_types(
+        &self,
+        _query: CommandGetTableTypes,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        self.check_read(&self.user_context(&request)?)?;
+
+        let schema = Arc::new(table_types_schema());
+        let batches = self
+            .metadata_query(
+                "SELECT DISTINCT table_type FROM information_schema.tables \
+                ORDER BY table_type",
+                schema.clone(),
+            )
+            .await
+            .map_err(api_error_to_status)?;
+
+        Ok(batches_to_response(&schema, &batches))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        _query: CommandGetSqlInfo,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        unimplemented("GetSqlInfo")
+    }
+
+    async fn do_get_primary_keys(
+        &self,
+        _query: CommandGetPrimaryKeys,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        unimplemented("GetPrimaryKeys")
+    }
+
+    async fn do_get_exported_keys(
+        &self,
+        _query: CommandGetExportedKeys,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        unimplemented("GetExportedKeys")
+    }
+
+    async fn do_get_imported_keys(
+        &self,
+        _query: CommandGetImportedKeys,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        unimplemented("GetImportedKeys")
+    }
+
+    async fn do_get_cross_reference(
+        &self,
+        _query: CommandGetCrossReference,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        unimplemented("GetCrossReference")
+    }
+
+    // We don't know how many rows a statement affected, which Flight SQL signals with -1
+    async fn do_put_statement_update(
+        &self,
+        ticket: CommandStatementUpdate,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<i64, Status> {
+        let user_context = self.user_context(&request)?;
+        self.execute(&user_context, &ticket.query)
+            .await
+            .map_err(api_error_to_status)?;
+        Ok(-1)
+    }
+
+    // Binding parameters to a prepared statement
+    async fn do_put_prepared_statement_query(
+        &self,
+        _query: CommandPreparedStatementQuery,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<<Self as FlightService>::DoPutStream>, Status> {
+        unimplemented("Binding parameters to prepared statements")
+    }
+
+    async fn do_put_prepared_statement_update(
+        &self,
+        query: CommandPreparedStatementUpdate,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<i64, Status> {
+        let user_context = self.user_context(&request)?;
+        let query = handle_to_query(query.prepared_statement_handle)?;
+        self.execute(&user_context, &query)
+            .await
+            .map_err(api_error_to_status)?;
+        Ok(-1)
+    }
+
+    // Prepared statements don't take parameters, so we just check that the query plans
+    // and use its text as the handle
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        request: Request<FlightAction>,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        let user_context = self.user_context(&request)?;
+        let schema = self
+            .plan_schema(&user_context, &query.query)
+            .await
+            .map_err(api_error_to_status)?;
+
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: query.query.into_bytes(),
+            dataset_schema: schema_to_ipc(&schema).map_err(api_error_to_status)?,
+            parameter_schema: vec![],
+        })
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        _query: ActionClosePreparedStatementRequest,
+        _request: Request<FlightAction>,
+    ) {
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+pub async fn run_flight_server(
+    context: Arc<dyn SeafowlContext>,
+    config: FlightFrontend,
+    mut shutdown: Receiver<()>,
+) {
+    let policy = AccessPolicy {
+        read: config.read_access.clone(),
+        write: config.write_access.clone(),
+    };
+    let admission = AdmissionController::new(&config.admission);
+    let service = SeafowlFlightSqlService::new(context, policy, admission);
+
+    let socket_addr: SocketAddr = format!("{}:{}", config.bind_host, config.bind_port)
+        .parse()
+        .expect("Error parsing the listen address");
+    Server::builder()
+        .add_service(FlightServiceServer::new(service))
+        .serve_with_shutdown(socket_addr, async move {
+            shutdown.recv().await.unwrap();
+            info!("Shutting down the Flight SQL server...");
+        })
+        .await
+        .expect("Error running the Flight SQL server")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use arrow::array::{as_string_array, BinaryArray, Int32Array};
+    use arrow::datatypes::Schema;
+    use arrow_flight::sql::{
+        server::FlightSqlService, CommandGetTables, CommandStatementQuery,
+        TicketStatementQuery,
+    };
+    use arrow_flight::utils::flight_data_to_arrow_batch;
+    use arrow_flight::{FlightData, FlightDescriptor, IpcMessage, Ticket};
+    use futures::TryStreamExt;
+    use tonic::{Code, Request, Response};
+
+    use crate::auth::AccessPolicy;
+    use crate::config::schema::AdmissionControl;
+    use crate::context::{test_utils::in_memory_context, SeafowlContext};
+    use crate::frontend::admission::AdmissionController;
+
+    use super::{FlightDataStream, SeafowlFlightSqlService};
+
+    async fn service_with_single_table(
+        policy: AccessPolicy,
+        admission: AdmissionControl,
+    ) -> SeafowlFlightSqlService {
+        let context = Arc::new(in_memory_context().await);
+
+        for query in [
+            "CREATE TABLE test_table(col_1 INT)",
+            "INSERT INTO test_table VALUES (1), (2)",
+        ] {
+            context
+                .collect(context.plan_query(query).await.unwrap())
+                .await
+                .unwrap();
+        }
+
+        SeafowlFlightSqlService::new(
+            context,
+            policy,
+            AdmissionController::new(&admission),
+        )
+    }
+
+    fn with_token<T>(message: T, token: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse().unwrap());
+        request
+    }
+
+    async fn collect_flight_data(
+        response: Response<FlightDataStream>,
+    ) -> (Schema, Vec<FlightData>) {
+        let mut flight_data: Vec<FlightData> =
+            response.into_inner().try_collect().await.unwrap();
+        let schema = Schema::try_from(&flight_data.remove(0)).unwrap();
+        (schema, flight_data)
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_statement_query() {
+        let service = service_with_single_table(
+            AccessPolicy::free_for_all(),
+            AdmissionControl::default(),
+        )
+        .await;
+        let query = "SELECT col_1 FROM test_table ORDER BY col_1".to_string();
+
+        let info = service
+            .get_flight_info_statement(
+                CommandStatementQuery {
+                    query: query.clone(),
+                },
+                Request::new(FlightDescriptor::default()),
+            )
+            .await
+            .unwrap()
+            .into_inner();
+        let info_schema = Schema::try_from(IpcMessage(info.schema)).unwrap();
+        assert_eq!(info_schema.fields()[0].name(), "col_1");
+        assert_eq!(info.endpoint.len(), 1);
+
+        let response = service
+            .do_get_statement(
+                TicketStatementQuery {
+                    statement_handle: query.into_bytes(),
+                },
+                Request::new(Ticket::default()),
+            )
+            .await
+            .unwrap();
+        let (schema, flight_data) = collect_flight_data(response).await;
+        assert_eq!(schema, info_schema);
+
+        let batch = flight_data_to_arrow_batch(
+            &flight_data[0],
+            Arc::new(schema),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .unwrap(),
+            &Int32Array::from(vec![1, 2])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_statement_query_planning_error() {
+        let service = service_with_single_table(
+            AccessPolicy::free_for_all(),
+            AdmissionControl::default(),
+        )
+        .await;
+
+        let err = service
+            .do_get_statement(
+                TicketStatementQuery {
+                    statement_handle: b"SELECT missing_col FROM test_table".to_vec(),
+                },
+                Request::new(Ticket::default()),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        assert!(err.message().contains("missing_col"));
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_admission() {
+        let service = service_with_single_table(
+            AccessPolicy::free_for_all(),
+            AdmissionControl {
+                max_concurrent_reads: Some(1),
+                ..Default::default()
+            },
+        )
+        .await;
+        let ticket = || TicketStatementQuery {
+            statement_handle: b"SELECT col_1 FROM test_table".to_vec(),
+        };
+
+        // The slot is taken for as long as the results are being streamed...
+        let response = service
+            .do_get_statement(ticket(), Request::new(Ticket::default()))
+            .await
+            .unwrap();
+        let err = service
+            .do_get_statement(ticket(), Request::new(Ticket::default()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), Code::ResourceExhausted);
+
+        // ...and freed up once they've been sent
+        collect_flight_data(response).await;
+        service
+            .do_get_statement(ticket(), Request::new(Ticket::default()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_authorization() {
+        let service = service_with_single_table(
+            AccessPolicy::free_for_all().with_write_password("somepw"),
+            AdmissionControl::default(),
+        )
+        .await;
+        let command = || CommandStatementQuery {
+            query: "CREATE TABLE other_table(col_1 INT)".to_string(),
+        };
+
+        // Anonymous users can still read...
+        service
+            .get_flight_info_statement(
+                CommandStatementQuery {
+                    query: "SELECT * FROM test_table".to_string(),
+                },
+                Request::new(FlightDescriptor::default()),
+            )
+            .await
+            .unwrap();
+
+        // ...but not write
+        let err = service
+            .get_flight_info_statement(
+                command(),
+                Request::new(FlightDescriptor::default()),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        assert_eq!(err.message(), "WRITE_FORBIDDEN");
+
+        let err = service
+            .get_flight_info_statement(
+                command(),
+                with_token(FlightDescriptor::default(), "otherpw"),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+        assert_eq!(err.message(), "INVALID_ACCESS_TOKEN");
+
+        service
+            .get_flight_info_statement(
+                command(),
+                with_token(FlightDescriptor::default(), "somepw"),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_read_authorization() {
+        let service = service_with_single_table(
+            AccessPolicy::free_for_all().with_read_password("readpw"),
+            AdmissionControl::default(),
+        )
+        .await;
+
+        let err = service
+            .get_flight_info_statement(
+                CommandStatementQuery {
+                    query: "SELECT * FROM test_table".to_string(),
+                },
+                Request::new(FlightDescriptor::default()),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        assert_eq!(err.message(), "READ_FORBIDDEN");
+
+        let err = service
+            .do_get_tables(CommandGetTables::default(), Request::new(Ticket::default()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        assert_eq!(err.message(), "READ_FORBIDDEN");
+
+        service
+            .do_get_tables(
+                CommandGetTables::default(),
+                with_token(Ticket::default(), "readpw"),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_get_tables() {
+        let service = service_with_single_table(
+            AccessPolicy::free_for_all(),
+            AdmissionControl::default(),
+        )
+        .await;
+
+        let response = service
+            .do_get_tables(
+                CommandGetTables {
+                    table_name_filter_pattern: Some("test_%".to_string()),
+                    include_schema: true,
+                    ..Default::default()
+                },
+                Request::new(Ticket::default()),
+            )
+            .await
+            .unwrap();
+        let (schema, flight_data) = collect_flight_data(response).await;
+        let batch = flight_data_to_arrow_batch(
+            &flight_data[0],
+            Arc::new(schema),
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(as_string_array(batch.column(0)).value(0), "default");
+        assert_eq!(as_string_array(batch.column(1)).value(0), "public");
+        assert_eq!(as_string_array(batch.column(2)).value(0), "test_table");
+        assert_eq!(as_string_array(batch.column(3)).value(0), "BASE TABLE");
+
+        let table_schemas = batch
+            .column(4)
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .unwrap();
+        let table_schema =
+            Schema::try_from(IpcMessage(table_schemas.value(0).to_vec())).unwrap();
+        assert_eq!(table_schema.fields()[0].name(), "col_1");
+    }
+}
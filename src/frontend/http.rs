@@ -2,10 +2,10 @@ use arrow::csv::ReaderBuilder;
 use arrow::datatypes::Schema;
 use arrow::error::ArrowError;
 use datafusion::error::DataFusionError;
-use datafusion::physical_plan::ExecutionPlan;
 use std::error::Error;
 use std::io::Cursor;
-use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use std::time::Duration;
+use std::{net::SocketAddr, sync::Arc};
 use warp::Rejection;
 
 use arrow::json::LineDelimitedWriter;
@@ -13,10 +13,9 @@ use arrow::record_batch::{RecordBatch, RecordBatchReader};
 use arrow_integration_test::schema_from_json;
 use bytes::{BufMut, Bytes};
 
-use datafusion::datasource::DefaultTableSource;
 use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReader;
 use datafusion::physical_plan::memory::MemoryExec;
-use datafusion_expr::logical_plan::{LogicalPlan, PlanVisitor, TableScan};
+use datafusion_expr::logical_plan::LogicalPlan;
 use futures::{future, TryStreamExt};
 use hex::encode;
 use log::{debug, info};
@@ -29,18 +28,25 @@ use warp::multipart::{FormData, Part};
 use warp::reply::{with_header, Response};
 use warp::{hyper::header, hyper::StatusCode, Filter, Reply};
 
-use crate::auth::{token_to_principal, AccessPolicy, Action, UserContext};
+use crate::auth::{token_to_principal, AccessPolicy, Action, Principal, UserContext};
 use crate::config::schema::{AccessSettings, MEBIBYTES};
+use crate::datafusion::parser::Statement as DFStatement;
+use crate::metrics::gather_metrics;
+use crate::query_limits::statement_timeout;
+use crate::query_log::{accesses_query_log, Frontend, QueryId};
 use crate::{
     config::schema::{str_to_hex_hash, HttpFrontend},
     context::{is_read_only, is_statement_read_only, SeafowlContext},
-    data_types::TableVersionId,
-    provider::SeafowlTable,
+    provider::{SeafowlCacheablePlanVisitor, SeafowlTableVersionsVisitor},
 };
 
+use super::admission::{AdmissionController, QueryKind};
 use super::http_utils::{handle_rejection, into_response, ApiError};
+use super::result_cache::ResultCache;
 
 const QUERY_HEADER: &str = "X-Seafowl-Query";
+const TIMEOUT_HEADER: &str = "X-Seafowl-Timeout";
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
 const BEARER_PREFIX: &str = "Bearer ";
 // We have a very lax CORS on this, so we don't mind browsers
 // caching it for as long as possible.
@@ -50,35 +56,8 @@ const CORS_MAXAGE: u32 = 86400;
 // so we can't cache the response in the browser if the origin changes.
 const VARY: &str = "Content-Type, Origin, X-Seafowl-Query";
 
-#[derive(Default)]
-struct ETagBuilderVisitor {
-    table_versions: Vec<TableVersionId>,
-}
-
-impl PlanVisitor for ETagBuilderVisitor {
-    type Error = Infallible;
-
-    fn pre_visit(&mut self, plan: &LogicalPlan) -> Result<bool, Self::Error> {
-        if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
-            // TODO handle external Parquet tables too
-            if let Some(default_table_source) =
-                source.as_any().downcast_ref::<DefaultTableSource>()
-            {
-                if let Some(table) = default_table_source
-                    .table_provider
-                    .as_any()
-                    .downcast_ref::<SeafowlTable>()
-                {
-                    self.table_versions.push(table.table_version_id)
-                }
-            }
-        }
-        Ok(true)
-    }
-}
-
 fn plan_to_etag(plan: &LogicalPlan) -> String {
-    let mut visitor = ETagBuilderVisitor::default();
+    let mut visitor = SeafowlTableVersionsVisitor::default();
     plan.accept(&mut visitor).unwrap();
 
     debug!("Extracted table versions: {:?}", visitor.table_versions);
@@ -88,17 +67,21 @@ fn plan_to_etag(plan: &LogicalPlan) -> String {
     encode(hasher.finalize())
 }
 
+// Whether the results of the plan can be stored in the result cache, which is keyed by
+// the ETag and so by the versions of the tables the plan reads from
+fn is_result_cacheable(plan: &LogicalPlan) -> Result<bool, DataFusionError> {
+    let mut visitor = SeafowlCacheablePlanVisitor::default();
+    plan.accept(&mut visitor)?;
+    Ok(visitor.cacheable)
+}
+
 #[derive(Debug, Deserialize)]
 struct QueryBody {
     query: String,
 }
 
-/// Execute a physical plan and output its results to a JSON Lines format
-async fn physical_plan_to_json(
-    context: Arc<dyn SeafowlContext>,
-    physical: Arc<dyn ExecutionPlan>,
-) -> Result<Vec<u8>, DataFusionError> {
-    let batches = context.collect(physical).await?;
+/// Output the results of a query in the JSON Lines format
+fn batches_to_json(batches: Vec<RecordBatch>) -> Result<Vec<u8>, DataFusionError> {
     let mut buf = Vec::new();
     let mut writer = LineDelimitedWriter::new(&mut buf);
     writer
@@ -108,17 +91,47 @@ async fn physical_plan_to_json(
     Ok(buf)
 }
 
+// Parse the timeout (in seconds) requested by the client
+fn header_to_timeout(header: Option<String>) -> Result<Option<Duration>, ApiError> {
+    header
+        .map(|h| match h.trim().parse::<f64>() {
+            Ok(secs) if secs > 0.0 && secs < u64::MAX as f64 => {
+                Ok(Duration::from_secs_f64(secs))
+            }
+            _ => Err(ApiError::InvalidTimeoutHeader(h)),
+        })
+        .transpose()
+}
+
 /// POST /q
 pub async fn uncached_read_write_query(
     user_context: UserContext,
     query: String,
+    timeout_header: Option<String>,
+    admission: Arc<AdmissionController>,
     context: Arc<dyn SeafowlContext>,
 ) -> Result<Vec<u8>, ApiError> {
-    let statements = context.parse_query(&query).await?;
+    let mut requested_timeout = header_to_timeout(timeout_header)?;
+    let parsed = context.parse_query(&query).await?;
+
+    if parsed.is_empty() {
+        return Err(ApiError::EmptyMultiStatement);
+    };
+    let multi_statement = parsed.len() > 1;
+
+    // `SET statement_timeout` isn't executed by DataFusion: it applies to the rest of
+    // the statements in the request instead
+    let mut statements = Vec::with_capacity(parsed.len());
+    for statement in parsed {
+        match statement_timeout(&statement) {
+            Some(timeout) => requested_timeout = timeout?,
+            None => statements.push(statement),
+        }
+    }
 
     // We assume that there's at least one statement throughout the rest of this function
     if statements.is_empty() {
-        return Err(ApiError::EmptyMultiStatement);
+        return Ok(vec![]);
     };
 
     let reads = statements
@@ -126,12 +139,14 @@ pub async fn uncached_read_write_query(
         .filter(|s| is_statement_read_only(s))
         .count();
 
-    // Check for authorization
-    if !user_context.can_perform_action(if reads == statements.len() {
-        Action::Read
+    let (action, kind) = if reads == statements.len() {
+        (Action::Read, QueryKind::Read)
     } else {
-        Action::Write
-    }) {
+        (Action::Write, QueryKind::Write)
+    };
+
+    // Check for authorization
+    if !user_context.can_perform_action(action) {
         return Err(ApiError::WriteForbidden);
     };
 
@@ -148,24 +163,60 @@ pub async fn uncached_read_write_query(
         return Err(ApiError::InvalidMultiStatement);
     }
 
-    // Execute all statements up until the last one.
-    let mut plan_to_output = None;
-
-    for statement in statements {
-        let logical = context
-            .create_logical_plan_from_statement(statement)
+    // Wait for a slot to run the statements in, holding it until we're done
+    let _permit = admission.admit(kind).await?;
+
+    // Execute all statements, only collecting the results of the last one. Each statement
+    // gets its own entry in the query log and is subject to the query limits separately.
+    let statement_count = statements.len();
+    let query_log = context.query_log();
+    let limits = context
+        .query_limits(&user_context.principal)
+        .with_requested_timeout(requested_timeout);
+    let mut output = vec![];
+
+    for (i, statement) in statements.into_iter().enumerate() {
+        let text = if multi_statement {
+            statement_text(&statement, &query)
+        } else {
+            query.clone()
+        };
+        let is_last = i == statement_count - 1;
+        let ctx = context.clone();
+        let statement_limits = limits.clone();
+
+        output = query_log
+            .track(
+                Frontend::Http,
+                &text,
+                &user_context.principal,
+                limits.run(async move {
+                    let logical =
+                        ctx.create_logical_plan_from_statement(statement).await?;
+                    let physical = ctx.create_physical_plan(&logical).await?;
+                    if is_last {
+                        ctx.collect_with_limits(physical, &statement_limits).await
+                    } else {
+                        Ok(vec![])
+                    }
+                }),
+            )
             .await?;
-        plan_to_output = Some(context.create_physical_plan(&logical).await?);
     }
 
-    let buf = physical_plan_to_json(
-        context,
-        plan_to_output.expect("at least one statement in the list"),
-    )
-    .await?;
+    let buf = batches_to_json(output)?;
     Ok(buf)
 }
 
+// Get the text of a single statement from a multi-statement query. Only the standard SQL
+// statements can be turned back into text, so we fall back to the whole query otherwise.
+fn statement_text(statement: &DFStatement, query: &str) -> String {
+    match statement {
+        DFStatement::Statement(s) => s.to_string(),
+        _ => query.to_string(),
+    }
+}
+
 fn header_to_user_context(
     header: Option<String>,
     policy: &AccessPolicy,
@@ -216,8 +267,15 @@ pub async fn cached_read_query(
     query_hash: String,
     raw_query: String,
     if_none_match: Option<String>,
+    timeout_header: Option<String>,
+    admission: Arc<AdmissionController>,
+    result_cache: Option<Arc<ResultCache>>,
     context: Arc<dyn SeafowlContext>,
 ) -> Result<Response, ApiError> {
+    let limits = context
+        .query_limits(&Principal::Anonymous)
+        .with_requested_timeout(header_to_timeout(timeout_header)?);
+
     // Ignore dots at the end
     let query_hash = query_hash.split('.').next().unwrap();
 
@@ -239,8 +297,9 @@ pub async fn cached_read_query(
     let plan = context.create_logical_plan(&decoded_query).await?;
     debug!("Query plan: {:?}", plan);
 
-    // Write queries should come in as POST requests
-    if !is_read_only(&plan) {
+    // Write queries (including reading or cancelling other users' queries) should come in
+    // as POST requests
+    if !is_read_only(&plan) || accesses_query_log(&decoded_query) {
         return Err(ApiError::NotReadOnlyQuery);
     };
 
@@ -258,11 +317,44 @@ pub async fn cached_read_query(
         }
     }
 
+    // Only use the result cache if the ETag captures everything the results depend on
+    let result_cache =
+        result_cache.filter(|_| is_result_cacheable(&plan).unwrap_or(false));
+
+    // Another client might have already run the query against the same table versions
+    if let Some(result_cache) = &result_cache {
+        if let Some(buf) = result_cache.get(query_hash, &etag).await {
+            return Ok(cached_response(buf.to_vec(), etag));
+        }
+    }
+
     // Guess we'll have to actually run the query
-    let physical = context.create_physical_plan(&plan).await?;
-    let buf = physical_plan_to_json(context, physical).await?;
+    let _permit = admission.admit(QueryKind::Read).await?;
+    let batches = context
+        .query_log()
+        .track(
+            Frontend::Http,
+            &decoded_query,
+            &Principal::Anonymous,
+            limits.run(async {
+                let physical = context.create_physical_plan(&plan).await?;
+                context.collect_with_limits(physical, &limits).await
+            }),
+        )
+        .await?;
+    let buf = batches_to_json(batches)?;
+
+    if let Some(result_cache) = &result_cache {
+        result_cache
+            .put(query_hash, &etag, Bytes::from(buf.clone()))
+            .await;
+    }
 
-    Ok(warp::reply::with_header(buf, header::ETAG, etag).into_response())
+    Ok(cached_response(buf, etag))
+}
+
+fn cached_response(buf: Vec<u8>, etag: String) -> Response {
+    warp::reply::with_header(buf, header::ETAG, etag).into_response()
 }
 
 /// POST /upload/[schema]/[table]
@@ -271,6 +363,7 @@ pub async fn upload(
     table_name: String,
     user_context: UserContext,
     form: FormData,
+    admission: Arc<AdmissionController>,
     context: Arc<dyn SeafowlContext>,
 ) -> Result<Response, ApiError> {
     if !user_context.can_perform_action(Action::Write) {
@@ -281,6 +374,8 @@ pub async fn upload(
         .await
         .map_err(ApiError::UploadBodyLoadError)?;
 
+    let _permit = admission.admit(QueryKind::Write).await?;
+
     let mut has_header = true;
     let mut csv_schema: Option<Schema> = None;
     let mut filename = String::new();
@@ -347,6 +442,29 @@ pub async fn upload(
     Ok(warp::reply::with_status(Ok("done"), StatusCode::OK).into_response())
 }
 
+/// DELETE /queries/[query id]
+pub async fn cancel_query(
+    query_id: QueryId,
+    user_context: UserContext,
+    context: Arc<dyn SeafowlContext>,
+) -> Result<Response, ApiError> {
+    if !user_context.can_perform_action(Action::Write) {
+        return Err(ApiError::WriteForbidden);
+    };
+
+    if !context.query_log().cancel(query_id) {
+        return Err(ApiError::QueryNotRunning(query_id));
+    }
+
+    Ok(warp::reply::with_status(Ok("done"), StatusCode::OK).into_response())
+}
+
+/// GET /metrics
+pub async fn metrics() -> Result<Response, ApiError> {
+    let buf = gather_metrics().map_err(ApiError::MetricsError)?;
+    Ok(with_header(buf, header::CONTENT_TYPE, METRICS_CONTENT_TYPE).into_response())
+}
+
 async fn load_part(p: Part) -> Result<Vec<u8>, warp::Error> {
     p.stream()
         .try_fold(Vec::new(), |mut vec, data| {
@@ -412,16 +530,23 @@ pub fn filters(
         .allow_any_origin()
         .allow_headers(vec![
             "X-Seafowl-Query",
+            TIMEOUT_HEADER,
             header::AUTHORIZATION.as_str(),
             header::CONTENT_TYPE.as_str(),
         ])
-        .allow_methods(vec!["GET", "POST"])
+        .allow_methods(vec!["GET", "POST", "DELETE"])
         .max_age(CORS_MAXAGE);
 
     let log = warp::log(module_path!());
+    let admission = Arc::new(AdmissionController::new(&config.admission));
+    let result_cache = config
+        .result_cache
+        .as_ref()
+        .map(|c| Arc::new(ResultCache::new(c).expect("Error creating the result cache")));
 
     // Cached read query
     let ctx = context.clone();
+    let adm = admission.clone();
     let cached_read_query_route = warp::path!("q" / String)
         .and(warp::get())
         .and(cached_read_query_authz(access_policy.clone()))
@@ -434,12 +559,16 @@ pub fn filters(
         .and(warp::header::optional::<String>(
             header::IF_NONE_MATCH.as_str(),
         ))
+        .and(warp::header::optional::<String>(TIMEOUT_HEADER))
+        .and(warp::any().map(move || adm.clone()))
+        .and(warp::any().map(move || result_cache.clone()))
         .and(warp::any().map(move || ctx.clone()))
         .then(cached_read_query)
         .map(into_response);
 
     // Uncached read/write query
     let ctx = context.clone();
+    let adm = admission.clone();
     let uncached_read_write_query_route = warp::path!("q")
         .and(warp::post())
         .and(with_auth(access_policy.clone()))
@@ -447,25 +576,55 @@ pub fn filters(
             // Extract the query from the JSON body
             warp::body::json().map(|b: QueryBody| b.query),
         )
+        .and(warp::header::optional::<String>(TIMEOUT_HEADER))
+        .and(warp::any().map(move || adm.clone()))
         .and(warp::any().map(move || ctx.clone()))
         .then(uncached_read_write_query)
         .map(into_response);
 
     // Upload endpoint
     let ctx = context.clone();
+    let adm = admission.clone();
     let upload_route = warp::path!("upload" / String / String)
         .and(warp::post())
-        .and(with_auth(access_policy))
+        .and(with_auth(access_policy.clone()))
         .and(
             warp::multipart::form().max_length(config.upload_data_max_length * MEBIBYTES),
         )
+        .and(warp::any().map(move || adm.clone()))
         .and(warp::any().map(move || ctx.clone()))
         .then(upload)
         .map(into_response);
 
+    // Cancel a running query
+    let ctx = context.clone();
+    let cancel_query_route = warp::path!("queries" / QueryId)
+        .and(warp::delete())
+        .and(with_auth(access_policy))
+        .and(warp::any().map(move || ctx.clone()))
+        .then(cancel_query)
+        .map(into_response);
+
+    // Prometheus metrics
+    let enable_metrics = config.enable_metrics;
+    let metrics_route = warp::path!("metrics")
+        .and(warp::get())
+        .and_then(move || {
+            if enable_metrics {
+                future::ok(())
+            } else {
+                future::err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+        .then(metrics)
+        .map(into_response);
+
     cached_read_query_route
         .or(uncached_read_write_query_route)
         .or(upload_route)
+        .or(cancel_query_route)
+        .or(metrics_route)
         .with(cors)
         .with(log)
         .map(|r| with_header(r, header::VARY, VARY))
@@ -509,10 +668,13 @@ mod tests {
 
     use crate::auth::AccessPolicy;
 
-    use crate::config::schema::{str_to_hex_hash, HttpFrontend};
+    use crate::config::schema::{
+        str_to_hex_hash, AdmissionControl, HttpFrontend, QueryLimit, QueryLimits,
+        ResultCache,
+    };
     use crate::{
         context::{test_utils::in_memory_context, SeafowlContext},
-        frontend::http::{filters, QUERY_HEADER},
+        frontend::http::{filters, QUERY_HEADER, TIMEOUT_HEADER},
     };
 
     fn http_config_from_access_policy(access_policy: AccessPolicy) -> HttpFrontend {
@@ -625,7 +787,7 @@ mod tests {
                 .map(|s| s.trim())
                 .sorted()
                 .collect::<Vec<&str>>(),
-            vec!["GET", "POST"]
+            vec!["DELETE", "GET", "POST"]
         );
     }
 
@@ -791,6 +953,154 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_get_cached_etag_materialized_view_refresh() {
+        // Refreshing a materialized view makes a new version of it, which changes the ETag
+        let context = in_memory_context_with_single_table().await;
+        for query in [
+            "CREATE MATERIALIZED VIEW test_mv AS SELECT COUNT(*) AS c FROM test_table",
+            "INSERT INTO test_table VALUES (2)",
+        ] {
+            context
+                .collect(context.plan_query(query).await.unwrap())
+                .await
+                .unwrap();
+        }
+        let handler = filters(
+            context.clone(),
+            http_config_from_access_policy(free_for_all()),
+        );
+
+        let query = "SELECT c FROM test_mv";
+        let get_cached = |etag: Option<String>| {
+            let mut builder = request()
+                .method("GET")
+                .path(format!("/q/{}", str_to_hex_hash(query)).as_str())
+                .header(QUERY_HEADER, query);
+            if let Some(etag) = etag {
+                builder = builder.header(IF_NONE_MATCH, etag);
+            }
+            builder.reply(&handler)
+        };
+
+        let resp = get_cached(None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let resp = get_cached(Some(etag.clone())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        context
+            .collect(
+                context
+                    .plan_query("REFRESH MATERIALIZED VIEW test_mv")
+                    .await
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let resp = get_cached(Some(etag.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+        assert_ne!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            etag
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_cached_result_cache() {
+        let context = in_memory_context_with_single_table().await;
+        let handler = filters(
+            context,
+            HttpFrontend {
+                result_cache: Some(ResultCache::default()),
+                ..http_config_from_access_policy(free_for_all())
+            },
+        );
+
+        let get_cached = || {
+            request()
+                .method("GET")
+                .path(format!("/q/{SELECT_QUERY_HASH}").as_str())
+                .header(QUERY_HEADER, SELECT_QUERY)
+                .reply(&handler)
+        };
+
+        // Both the execution and the cache hit return the same response
+        for _ in 0..2 {
+            let resp = get_cached().await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.body(), "{\"c\":1}\n");
+            assert_eq!(
+                resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+                V1_ETAG
+            );
+        }
+
+        // Writing to the table invalidates the cached result
+        let resp = query_uncached_endpoint(&handler, INSERT_QUERY).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = get_cached().await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            V2_ETAG
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_cached_result_cache_uncacheable() {
+        let context = in_memory_context_with_single_table().await;
+        let handler = filters(
+            context,
+            HttpFrontend {
+                result_cache: Some(ResultCache::default()),
+                ..http_config_from_access_policy(free_for_all())
+            },
+        );
+
+        let get_cached = |query: &'static str| {
+            request()
+                .method("GET")
+                .path(format!("/q/{}", str_to_hex_hash(query)).as_str())
+                .header(QUERY_HEADER, query)
+                .reply(&handler)
+        };
+
+        // Volatile functions return something different on every run
+        let query = "SELECT random() AS r";
+        let first = get_cached(query).await;
+        let second = get_cached(query).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_ne!(first.body(), second.body());
+
+        // Non-Seafowl tables (like information_schema) aren't versioned, so the ETag
+        // doesn't change when they do
+        let query = "SELECT COUNT(*) AS c FROM information_schema.tables \
+            WHERE table_schema = 'public'";
+        let resp = get_cached(query).await;
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+
+        let resp =
+            query_uncached_endpoint(&handler, "CREATE TABLE other_table (c INT)").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = get_cached(query).await;
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+    }
+
     async fn _query_uncached_endpoint<R, H>(
         handler: &H,
         query: &'_ str,
@@ -1187,6 +1497,226 @@ mod tests {
             .contains("Only one read statement is allowed"));
     }
 
+    #[tokio::test]
+    async fn test_query_log() {
+        let context = in_memory_context_with_single_table().await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "INSERT INTO test_table VALUES (2);SELECT * FROM test_table",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = query_uncached_endpoint(&handler, "SELECT * FROM missing_table").await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT query_id, query, principal, status, rows, error IS NOT NULL AS has_error \
+            FROM system.queries ORDER BY query_id",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body(),
+            r#"{"has_error":false,"principal":"anonymous","query":"INSERT INTO test_table VALUES (2)","query_id":1,"rows":0,"status":"succeeded"}
+{"has_error":false,"principal":"anonymous","query":"SELECT * FROM test_table","query_id":2,"rows":2,"status":"succeeded"}
+{"has_error":true,"principal":"anonymous","query":"SELECT * FROM missing_table","query_id":3,"status":"failed"}
+{"has_error":false,"principal":"anonymous","query":"SELECT query_id, query, principal, status, rows, error IS NOT NULL AS has_error FROM system.queries ORDER BY query_id","query_id":4,"status":"running"}
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_query() {
+        let context = in_memory_context_with_single_table().await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_password("somepw"),
+            ),
+        );
+
+        let resp = request()
+            .method("DELETE")
+            .path("/queries/123")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        let resp = request()
+            .method("DELETE")
+            .path("/queries/123")
+            .header(header::AUTHORIZATION, "Bearer somepw")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.body(), "Query 123 isn't running");
+
+        // Cancelling through pg_cancel_backend also needs write access
+        let cancel_query = "SELECT pg_cancel_backend(123) AS cancelled";
+        let resp = query_uncached_endpoint(&handler, cancel_query).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{}", str_to_hex_hash(cancel_query)).as_str())
+            .header(QUERY_HEADER, cancel_query)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.body(), "NOT_READ_ONLY_QUERY");
+
+        let resp = query_uncached_endpoint_token(&handler, cancel_query, "somepw").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"cancelled\":false}\n");
+
+        // Same with reading the log, which has everyone's queries
+        let log_query = "SELECT count(*) AS count FROM system.queries";
+        let resp = query_uncached_endpoint(&handler, log_query).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        let resp = query_uncached_endpoint_token(&handler, log_query, "somepw").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_admission_control() {
+        let context = in_memory_context_with_single_table().await;
+        let handler = filters(
+            context,
+            HttpFrontend {
+                admission: AdmissionControl {
+                    max_concurrent_reads: Some(0),
+                    retry_after_secs: 5,
+                    ..Default::default()
+                },
+                ..http_config_from_access_policy(free_for_all())
+            },
+        );
+
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "5");
+        assert_eq!(resp.body(), "TOO_MANY_QUERIES");
+
+        // Writes go through their own pool
+        let resp =
+            query_uncached_endpoint(&handler, "INSERT INTO test_table VALUES (2)").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_query_limits() {
+        let mut context = in_memory_context().await;
+        context.query_limits = QueryLimits {
+            max_rows: Some(1),
+            principals: HashMap::from([(
+                "writer".to_string(),
+                QueryLimit {
+                    max_rows: Some(10),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let context = Arc::new(context);
+        for query in [
+            "CREATE TABLE test_table(col_1 INT)",
+            "INSERT INTO test_table VALUES (1), (2)",
+        ] {
+            context
+                .collect(context.plan_query(query).await.unwrap())
+                .await
+                .unwrap();
+        }
+
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_password("somepw"),
+            ),
+        );
+
+        let resp = query_uncached_endpoint(&handler, "SELECT * FROM test_table").await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            resp.body(),
+            "QUERY_MAX_ROWS_EXCEEDED: Query returned more than the maximum of 1 rows"
+        );
+
+        // The writer has a higher limit
+        let resp =
+            query_uncached_endpoint_token(&handler, "SELECT * FROM test_table", "somepw")
+                .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Requests can set their own timeout
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SET statement_timeout = '30s'; SELECT 1 AS c",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+
+        let resp = request()
+            .method("POST")
+            .path("/q")
+            .json(&HashMap::from([("query", "SELECT 1")]))
+            .header(TIMEOUT_HEADER, "soon")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body(),
+            "Invalid timeout \"soon\", expected a number of seconds"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let context = in_memory_context_with_single_table().await;
+
+        let handler = filters(
+            context.clone(),
+            http_config_from_access_policy(free_for_all()),
+        );
+        let resp = request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let handler = filters(
+            context,
+            HttpFrontend {
+                enable_metrics: true,
+                ..http_config_from_access_policy(free_for_all())
+            },
+        );
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(resp.body().to_vec()).unwrap();
+        assert!(body
+            .contains("seafowl_queries_total{frontend=\"http\",status=\"succeeded\"}"));
+        assert!(body.contains("seafowl_query_duration_seconds_bucket"));
+        assert!(body.contains("seafowl_catalog_query_duration_seconds_bucket"));
+    }
+
     #[tokio::test]
     async fn test_http_type_conversion() {
         let context = Arc::new(in_memory_context().await);
@@ -41,7 +41,11 @@
 //   (maybe we need a recover for every route to minimize the amount of back and forth with Warp?)
 use datafusion::error::DataFusionError;
 
-use warp::hyper::{Body, Response, StatusCode};
+use crate::frontend::admission::Rejected;
+use crate::query_limits::{limit_exceeded, QueryLimitExceeded};
+use crate::query_log::QueryId;
+
+use warp::hyper::{header, Body, Response, StatusCode};
 use warp::reject::Reject;
 use warp::{Rejection, Reply};
 
@@ -52,6 +56,7 @@ pub enum ApiError {
     NotReadOnlyQuery,
     ReadOnlyEndpointDisabled,
     WriteForbidden,
+    ReadForbidden,
     NeedAccessToken,
     UselessAccessToken,
     WrongAccessToken,
@@ -68,13 +73,27 @@ pub enum ApiError {
     UploadHasHeaderParseError,
     UploadUnsupportedFileFormat(String),
     QueryDecodeError,
+    QueryNotRunning(QueryId),
+    MetricsError(prometheus::Error),
+    InvalidTimeoutHeader(String),
+    QueryLimitExceeded(QueryLimitExceeded),
+    TooManyQueries(u64),
 }
 
 // Wrap DataFusion errors so that we can automagically return an
 // `ApiError(DataFusionError)` by using the `?` operator
 impl From<DataFusionError> for ApiError {
     fn from(err: DataFusionError) -> Self {
-        ApiError::DataFusionError(err)
+        match limit_exceeded(&err) {
+            Some(limit) => ApiError::QueryLimitExceeded(limit.clone()),
+            None => ApiError::DataFusionError(err),
+        }
+    }
+}
+
+impl From<Rejected> for ApiError {
+    fn from(rejected: Rejected) -> Self {
+        ApiError::TooManyQueries(rejected.retry_after)
     }
 }
 
@@ -86,7 +105,7 @@ impl From<std::str::Utf8Error> for ApiError {
 }
 
 impl ApiError {
-    fn status_code_body(self: &ApiError) -> (StatusCode, String) {
+    pub(crate) fn status_code_body(self: &ApiError) -> (StatusCode, String) {
         match self {
             // TODO: figure out which DF errors to propagate, we have ones that are the server's fault
             // here too (e.g. ResourcesExhausted) and potentially some that leak internal
@@ -96,6 +115,7 @@ impl ApiError {
             ApiError::NotReadOnlyQuery => (StatusCode::METHOD_NOT_ALLOWED, "NOT_READ_ONLY_QUERY".to_string()),
             ApiError::ReadOnlyEndpointDisabled => (StatusCode::METHOD_NOT_ALLOWED, "READ_ONLY_ENDPOINT_DISABLED".to_string()),
             ApiError::WriteForbidden => (StatusCode::FORBIDDEN, "WRITE_FORBIDDEN".to_string()),
+            ApiError::ReadForbidden => (StatusCode::FORBIDDEN, "READ_FORBIDDEN".to_string()),
             ApiError::NeedAccessToken => (StatusCode::UNAUTHORIZED, "NEED_ACCESS_TOKEN".to_string()),
             ApiError::UselessAccessToken => (StatusCode::BAD_REQUEST, "USELESS_ACCESS_TOKEN".to_string()),
             ApiError::WrongAccessToken => (StatusCode::UNAUTHORIZED, "INVALID_ACCESS_TOKEN".to_string()),
@@ -112,13 +132,24 @@ impl ApiError {
             ApiError::UploadHasHeaderParseError => (StatusCode::BAD_REQUEST, "Invalid has_header".to_string()),
             ApiError::UploadUnsupportedFileFormat(filename) => (StatusCode::BAD_REQUEST, format!("File {filename} not supported")),
             ApiError::QueryDecodeError => (StatusCode::BAD_REQUEST, "QUERY_DECODE_ERROR".to_string()),
+            ApiError::QueryNotRunning(id) => (StatusCode::NOT_FOUND, format!("Query {id} isn't running")),
+            ApiError::MetricsError(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Error gathering metrics: {e:}")),
+            ApiError::InvalidTimeoutHeader(value) => (StatusCode::BAD_REQUEST, format!("Invalid timeout {value:?}, expected a number of seconds")),
+            ApiError::QueryLimitExceeded(limit @ QueryLimitExceeded::Timeout(_)) => (StatusCode::GATEWAY_TIMEOUT, limit.to_string()),
+            ApiError::QueryLimitExceeded(limit @ QueryLimitExceeded::MaxRows(_)) => (StatusCode::UNPROCESSABLE_ENTITY, format!("QUERY_MAX_ROWS_EXCEEDED: {limit}")),
+            ApiError::QueryLimitExceeded(limit @ QueryLimitExceeded::MaxMemory(_)) => (StatusCode::UNPROCESSABLE_ENTITY, format!("QUERY_MAX_MEMORY_EXCEEDED: {limit}")),
+            ApiError::QueryLimitExceeded(limit @ QueryLimitExceeded::MemoryPoolFull(_)) => (StatusCode::SERVICE_UNAVAILABLE, format!("MEMORY_POOL_FULL: {limit}")),
+            ApiError::TooManyQueries(_) => (StatusCode::TOO_MANY_REQUESTS, "TOO_MANY_QUERIES".to_string()),
         }
     }
 
     fn response(&self) -> Response<Body> {
         let (status, body) = self.status_code_body();
-        Response::builder()
-            .status(status)
+        let mut builder = Response::builder().status(status);
+        if let ApiError::TooManyQueries(retry_after) = self {
+            builder = builder.header(header::RETRY_AFTER, retry_after.to_string());
+        }
+        builder
             .body(body.into())
             .expect("Could not construct Response")
     }
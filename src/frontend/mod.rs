@@ -1,4 +1,8 @@
+pub mod admission;
+#[cfg(feature = "frontend-flight")]
+pub mod flight;
 pub mod http;
 pub mod http_utils;
 #[cfg(feature = "frontend-postgres")]
 pub mod postgres;
+pub mod result_cache;
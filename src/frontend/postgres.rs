@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 
@@ -9,13 +10,22 @@ use convergence::{
     server::{self, BindOptions},
 };
 use convergence_arrow::table::{record_batch_to_rows, schema_to_field_desc};
-use datafusion::{error::DataFusionError, physical_plan::ExecutionPlan};
+use datafusion::error::DataFusionError;
 
-use crate::{config::schema::PostgresFrontend, context::SeafowlContext};
+use crate::{
+    auth::Principal,
+    config::schema::PostgresFrontend,
+    context::SeafowlContext,
+    query_limits::{set_statement_timeout, Limits},
+    query_log::Frontend,
+};
 use sqlparser::ast::Statement;
 
 pub struct SeafowlPortal {
-    plan: Arc<dyn ExecutionPlan>,
+    // None for statements that were handled when creating the portal (e.g. SET) or that
+    // have already been executed
+    query: Option<String>,
+    limits: Limits,
     context: Arc<dyn SeafowlContext>,
 }
 
@@ -26,12 +36,33 @@ fn df_err_to_sql(err: DataFusionError) -> ErrorResponse {
 #[async_trait]
 impl Portal for SeafowlPortal {
     async fn fetch(&mut self, batch: &mut DataRowBatch) -> Result<(), ErrorResponse> {
-        for arrow_batch in self
+        // Plan and run the query in one go, so that both are recorded in the query log
+        // (and can be cancelled). The PG frontend doesn't support authentication. Taking the
+        // query out makes sure that fetching from the same portal again doesn't re-run it
+        // (and repeat its writes): the results have all been sent by then.
+        let query = match self.query.take() {
+            Some(query) => query,
+            None => return Ok(()),
+        };
+        let context = self.context.clone();
+        let limits = self.limits.clone();
+        let statement = query.clone();
+        let batches = self
             .context
-            .collect(self.plan.clone())
+            .query_log()
+            .track(
+                Frontend::Postgres,
+                &query,
+                &Principal::Anonymous,
+                self.limits.run(async move {
+                    let plan = context.plan_query(&statement).await?;
+                    context.collect_with_limits(plan, &limits).await
+                }),
+            )
             .await
-            .map_err(df_err_to_sql)?
-        {
+            .map_err(df_err_to_sql)?;
+
+        for arrow_batch in batches {
             record_batch_to_rows(&arrow_batch, batch)?;
         }
         Ok(())
@@ -40,6 +71,8 @@ impl Portal for SeafowlPortal {
 
 struct SeafowlConvergenceEngine {
     context: Arc<dyn SeafowlContext>,
+    // Set for the lifetime of the connection with `SET statement_timeout`
+    statement_timeout: Option<Duration>,
 }
 
 #[async_trait]
@@ -50,6 +83,10 @@ impl Engine for SeafowlConvergenceEngine {
         &mut self,
         statement: &Statement,
     ) -> Result<Vec<FieldDescription>, ErrorResponse> {
+        if set_statement_timeout(statement).is_some() {
+            return Ok(vec![]);
+        }
+
         let plan = self
             .context
             .create_logical_plan(&statement.to_string())
@@ -63,13 +100,20 @@ impl Engine for SeafowlConvergenceEngine {
         &mut self,
         statement: &Statement,
     ) -> Result<Self::PortalType, ErrorResponse> {
-        let plan = self
-            .context
-            .plan_query(&statement.to_string())
-            .await
-            .map_err(df_err_to_sql)?;
+        let query = match set_statement_timeout(statement) {
+            Some(timeout) => {
+                self.statement_timeout = timeout.map_err(df_err_to_sql)?;
+                None
+            }
+            None => Some(statement.to_string()),
+        };
+
         Ok(SeafowlPortal {
-            plan,
+            query,
+            limits: self
+                .context
+                .query_limits(&Principal::Anonymous)
+                .with_requested_timeout(self.statement_timeout),
             context: self.context.clone(),
         })
     }
@@ -85,6 +129,7 @@ pub async fn run_pg_server(context: Arc<dyn SeafowlContext>, config: PostgresFro
             Box::pin(async move {
                 SeafowlConvergenceEngine {
                     context: context.clone(),
+                    statement_timeout: None,
                 }
             })
         }),
@@ -0,0 +1,221 @@
+//! Server-side cache for the responses of the cached GET endpoint. Entries are keyed by the
+//! query hash and remember the ETag (derived from the versions of the tables the query reads)
+//! they were computed for: once a new version of one of these tables gets created, the ETag
+//! changes and the entry gets dropped on the next lookup.
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use log::{debug, warn};
+use moka::future::{Cache, CacheBuilder, ConcurrentCacheExt};
+use tokio::fs;
+
+use crate::config::schema::{ResultCache as ResultCacheConfig, MEBIBYTES};
+use crate::metrics::{RESULT_CACHE_HITS, RESULT_CACHE_MISSES};
+
+#[derive(Debug, Clone)]
+enum CachedBody {
+    Memory(Bytes),
+    Disk(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct CachedResult {
+    etag: String,
+    size: u64,
+    body: CachedBody,
+}
+
+pub struct ResultCache {
+    cache: Cache<String, CachedResult>,
+    // Directory to store the responses in (in memory if unset)
+    dir: Option<PathBuf>,
+    max_size: u64,
+    // Used to give every response stored on disk its own file name, so that replacing an
+    // entry never deletes the file of the one replacing it
+    next_file_id: AtomicU64,
+}
+
+impl ResultCache {
+    pub fn new(config: &ResultCacheConfig) -> std::io::Result<Self> {
+        if let Some(dir) = &config.dir {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let max_size = config.max_size_mb * MEBIBYTES;
+        let mut builder =
+            CacheBuilder::<String, CachedResult, Cache<String, CachedResult>>::new(
+                max_size,
+            )
+            .weigher(|_, v: &CachedResult| v.size.try_into().unwrap_or(u32::MAX))
+            .eviction_listener_with_queued_delivery_mode(
+                |key, value, cause| {
+                    debug!("Evicted the cached result of {} ({:?})", key, cause);
+                    if let CachedBody::Disk(path) = value.body {
+                        if let Err(e) = std::fs::remove_file(&path) {
+                            warn!(
+                                "Failed to remove the cached result at {:?}: {}",
+                                path, e
+                            );
+                        }
+                    }
+                },
+            );
+        if let Some(ttl_secs) = config.ttl_secs {
+            builder = builder.time_to_live(Duration::from_secs(ttl_secs));
+        }
+
+        Ok(Self {
+            cache: builder.build(),
+            dir: config.dir.clone(),
+            max_size,
+            next_file_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Get the cached response to the query, if it was computed for the same ETag
+    pub async fn get(&self, query_hash: &str, etag: &str) -> Option<Bytes> {
+        let body = match self.cache.get(query_hash) {
+            Some(result) if result.etag == etag => match result.body {
+                CachedBody::Memory(bytes) => Some(bytes),
+                CachedBody::Disk(path) => match fs::read(&path).await {
+                    Ok(bytes) => Some(Bytes::from(bytes)),
+                    Err(e) => {
+                        warn!("Failed to read the cached result at {:?}: {}", path, e);
+                        self.cache.invalidate(query_hash).await;
+                        None
+                    }
+                },
+            },
+            Some(_) => {
+                // The query reads tables that changed since
+                self.cache.invalidate(query_hash).await;
+                None
+            }
+            None => None,
+        };
+
+        if body.is_some() {
+            RESULT_CACHE_HITS.inc();
+        } else {
+            RESULT_CACHE_MISSES.inc();
+        }
+        body
+    }
+
+    pub async fn put(&self, query_hash: &str, etag: &str, body: Bytes) {
+        let size = body.len() as u64;
+        if size > self.max_size {
+            debug!(
+                "Not caching the result of {}: {} bytes is over the cache size",
+                query_hash, size
+            );
+            return;
+        }
+
+        let body = match &self.dir {
+            None => CachedBody::Memory(body),
+            Some(dir) => {
+                let file_id = self.next_file_id.fetch_add(1, Ordering::Relaxed);
+                let path = dir.join(format!("{query_hash}-{file_id}.json"));
+                if let Err(e) = fs::write(&path, &body).await {
+                    warn!("Failed to cache the result at {:?}: {}", path, e);
+                    return;
+                }
+                CachedBody::Disk(path)
+            }
+        };
+
+        self.cache
+            .insert(
+                query_hash.to_string(),
+                CachedResult {
+                    etag: etag.to_string(),
+                    size,
+                    body,
+                },
+            )
+            .await;
+    }
+}
+
+impl Drop for ResultCache {
+    fn drop(&mut self) {
+        // We can't find the responses stored on disk after a restart, so clean them up
+        // (after letting the eviction listener remove the files of the evicted entries)
+        self.cache.sync();
+        for (_, result) in self.cache.iter() {
+            if let CachedBody::Disk(path) = &result.body {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use tempfile::TempDir;
+
+    use crate::config::schema::ResultCache as ResultCacheConfig;
+
+    use super::ResultCache;
+
+    async fn test_get_put(cache: ResultCache) {
+        assert_eq!(cache.get("query", "etag-1").await, None);
+
+        cache
+            .put("query", "etag-1", Bytes::from("{\"c\":1}\n"))
+            .await;
+        assert_eq!(
+            cache.get("query", "etag-1").await,
+            Some(Bytes::from("{\"c\":1}\n"))
+        );
+        assert_eq!(cache.get("other-query", "etag-1").await, None);
+
+        // The table changed: the entry gets dropped
+        assert_eq!(cache.get("query", "etag-2").await, None);
+        assert_eq!(cache.get("query", "etag-1").await, None);
+
+        cache
+            .put("query", "etag-2", Bytes::from("{\"c\":2}\n"))
+            .await;
+        assert_eq!(
+            cache.get("query", "etag-2").await,
+            Some(Bytes::from("{\"c\":2}\n"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_result_cache_memory() {
+        let cache = ResultCache::new(&ResultCacheConfig::default()).unwrap();
+        test_get_put(cache).await;
+    }
+
+    #[tokio::test]
+    async fn test_result_cache_disk() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cache = ResultCache::new(&ResultCacheConfig {
+            dir: Some(tmp_dir.path().to_path_buf()),
+            ..Default::default()
+        })
+        .unwrap();
+        test_get_put(cache).await;
+
+        // Dropping the cache cleans up the responses it stored
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_result_cache_too_large() {
+        let cache = ResultCache::new(&ResultCacheConfig {
+            max_size_mb: 0,
+            ..Default::default()
+        })
+        .unwrap();
+
+        cache.put("query", "etag", Bytes::from("{\"c\":1}\n")).await;
+        assert_eq!(cache.get("query", "etag").await, None);
+    }
+}
@@ -1,18 +1,26 @@
 pub mod auth;
+pub mod bloom_filter;
 pub mod catalog;
 pub mod config;
 pub mod context;
 pub mod data_types;
 pub mod datafusion;
 pub mod frontend;
+pub mod metrics;
 pub mod nodes;
 pub mod object_store;
 pub mod provider;
+pub mod query_limits;
+pub mod query_log;
 pub mod repository;
+pub mod scheduler;
 pub mod schema;
+pub mod statistics;
 pub mod system_tables;
+pub mod table_function;
 pub mod utils;
 pub mod version;
+pub mod view;
 pub mod wasm_udf;
 
 extern crate lazy_static;
@@ -19,7 +19,11 @@ use seafowl::{
     },
     context::SeafowlContext,
     frontend::http::run_server,
-    utils::{gc_partitions, run_one_off_command},
+    scheduler::{
+        gc_trigger, refresh_stale_materialized_views, run_gc_schedule, run_job_schedule,
+        run_materialized_view_refreshes,
+    },
+    utils::{enforce_retention, run_one_off_command},
 };
 use tokio::signal::ctrl_c;
 #[cfg(unix)]
@@ -27,6 +31,8 @@ use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::broadcast::{channel, Sender};
 use tokio::time::{interval, Duration};
 
+#[cfg(feature = "frontend-flight")]
+use seafowl::frontend::flight::run_flight_server;
 #[cfg(feature = "frontend-postgres")]
 use seafowl::frontend::postgres::run_pg_server;
 
@@ -82,6 +88,21 @@ fn prepare_frontends(
         }));
     };
 
+    #[cfg(feature = "frontend-flight")]
+    if let Some(flight) = &config.frontend.flight {
+        let shutdown_r = shutdown.subscribe();
+        let server = run_flight_server(context.clone(), flight.to_owned(), shutdown_r);
+        info!(
+            "Starting the Arrow Flight SQL frontend on {}:{}",
+            flight.bind_host, flight.bind_port
+        );
+        info!(
+            "Flight SQL access settings: read {}, write {}",
+            flight.read_access, flight.write_access
+        );
+        result.push(server.boxed());
+    };
+
     if let Some(http) = &config.frontend.http {
         let shutdown_r = shutdown.subscribe();
         let server = run_server(context, http.to_owned(), shutdown_r);
@@ -171,7 +192,9 @@ async fn main() {
     let context = Arc::new(build_context(&config).await.unwrap());
 
     if let Some(one_off_cmd) = args.one_off {
-        run_one_off_command(context, &one_off_cmd, io::stdout()).await;
+        run_one_off_command(context.clone(), &one_off_cmd, io::stdout()).await;
+        // There's no background task to pick up the writes, so refresh the views right away
+        refresh_stale_materialized_views(&context, config.misc.job_run_history).await;
         return;
     };
 
@@ -188,18 +211,44 @@ Run Seafowl with --one-off instead to run a one-off command from the CLI."
         exit(-1);
     }
 
-    // Add a GC task for purging obsolete objects from the catalog and the store
-    if config.misc.gc_interval > 0 {
+    // Add a task for each of the scheduled maintenance jobs
+    for job in &config.jobs {
+        info!("Scheduling job {} ({})", job.name, job.schedule);
+        tasks.push(
+            run_job_schedule(
+                context.clone(),
+                job.clone(),
+                config.misc.job_run_history,
+                shutdown.subscribe(),
+            )
+            .boxed(),
+        );
+    }
+
+    // Add a task for refreshing the auto-refreshed materialized views after writes
+    tasks.push(
+        run_materialized_view_refreshes(
+            context.clone(),
+            config.misc.job_run_history,
+            shutdown.subscribe(),
+        )
+        .boxed(),
+    );
+
+    // Add a task for deleting table versions that fall outside of the retention policy
+    let retention = config.misc.retention.clone();
+    if retention.interval > 0 {
+        let context = context.clone();
         let mut shutdown_r = shutdown.subscribe();
         let mut interval =
-            interval(Duration::from_secs((config.misc.gc_interval * 3600) as u64));
+            interval(Duration::from_secs((retention.interval as u64) * 3600));
         tasks.push(
             async move {
                 loop {
                     tokio::select! {
-                        _ = interval.tick() => gc_partitions(&context).await,
+                        _ = interval.tick() => enforce_retention(&context, &retention).await,
                         _ = shutdown_r.recv() => {
-                            info!("GC task received shutdown signal, exiting");
+                            info!("Retention task received shutdown signal, exiting");
                             break;
                         }
                     }
@@ -209,6 +258,19 @@ Run Seafowl with --one-off instead to run a one-off command from the CLI."
         );
     }
 
+    // Add a scheduled job for purging obsolete objects from the catalog and the store
+    if let Some(trigger) = gc_trigger(&config.misc) {
+        tasks.push(
+            run_gc_schedule(
+                context,
+                trigger,
+                config.misc.job_run_history,
+                shutdown.subscribe(),
+            )
+            .boxed(),
+        );
+    }
+
     // Add a task that will wait for a termination signal and tell frontends to stop
     tasks.push(
         async move {
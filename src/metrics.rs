@@ -0,0 +1,200 @@
+//! Prometheus metrics exported by Seafowl. All metrics are registered in the default
+//! registry, which gets rendered in the text exposition format by the `/metrics` endpoint.
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use std::time::Instant;
+
+use datafusion::execution::memory_manager::MemoryManager;
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    TextEncoder,
+};
+
+lazy_static! {
+    // Queries executed through the frontends
+    pub static ref QUERIES: IntCounterVec = register_int_counter_vec!(
+        "seafowl_queries_total",
+        "Number of statements executed, by frontend and outcome",
+        &["frontend", "status"]
+    )
+    .unwrap();
+    pub static ref QUERY_DURATION: HistogramVec = register_histogram_vec!(
+        "seafowl_query_duration_seconds",
+        "Time taken to plan and execute a statement, by frontend and outcome",
+        &["frontend", "status"]
+    )
+    .unwrap();
+    pub static ref QUERY_ROWS: IntCounterVec = register_int_counter_vec!(
+        "seafowl_query_rows_returned_total",
+        "Number of rows returned by statements, by frontend",
+        &["frontend"]
+    )
+    .unwrap();
+    pub static ref QUERY_BYTES: IntCounterVec = register_int_counter_vec!(
+        "seafowl_query_bytes_returned_total",
+        "In-memory size of the results returned by statements, by frontend",
+        &["frontend"]
+    )
+    .unwrap();
+
+    // HTTP admission control
+    pub static ref QUERIES_QUEUED: IntGauge = register_int_gauge!(
+        "seafowl_http_queries_queued",
+        "Number of HTTP queries waiting for a free slot"
+    )
+    .unwrap();
+    pub static ref QUERIES_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "seafowl_http_queries_rejected_total",
+        "Number of HTTP queries rejected by admission control, by kind (read or write)",
+        &["kind"]
+    )
+    .unwrap();
+
+    // HTTP object store cache
+    pub static ref CACHE_HITS: IntCounter = register_int_counter!(
+        "seafowl_object_store_cache_hits_total",
+        "Number of chunks served from the HTTP object store cache"
+    )
+    .unwrap();
+    pub static ref CACHE_MISSES: IntCounter = register_int_counter!(
+        "seafowl_object_store_cache_misses_total",
+        "Number of chunks that had to be fetched by the HTTP object store cache"
+    )
+    .unwrap();
+    pub static ref CACHE_EVICTIONS: IntCounter = register_int_counter!(
+        "seafowl_object_store_cache_evictions_total",
+        "Number of chunks evicted from the HTTP object store cache"
+    )
+    .unwrap();
+
+    // HTTP result cache
+    pub static ref RESULT_CACHE_HITS: IntCounter = register_int_counter!(
+        "seafowl_http_result_cache_hits_total",
+        "Number of cached GET queries served from the result cache"
+    )
+    .unwrap();
+    pub static ref RESULT_CACHE_MISSES: IntCounter = register_int_counter!(
+        "seafowl_http_result_cache_misses_total",
+        "Number of cached GET queries that had to be executed"
+    )
+    .unwrap();
+
+    // Object stores
+    pub static ref OBJECT_STORE_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "seafowl_object_store_requests_total",
+        "Number of object store requests, by store and operation",
+        &["store", "operation"]
+    )
+    .unwrap();
+
+    // Memory pool
+    pub static ref MEMORY_POOL_SIZE: IntGauge = register_int_gauge!(
+        "seafowl_memory_pool_size_bytes",
+        "Size of the memory pool available to queries (0 if unbounded)"
+    )
+    .unwrap();
+    pub static ref MEMORY_POOL_RESERVED: IntGauge = register_int_gauge!(
+        "seafowl_memory_pool_reserved_bytes",
+        "Memory currently reserved from the memory pool by query operators"
+    )
+    .unwrap();
+    // Memory manager that the reserved memory gets sampled from when the metrics are gathered
+    static ref MEMORY_MANAGER: RwLock<Option<Arc<MemoryManager>>> = RwLock::new(None);
+    // Pools of the queries with a memory limit, with the amount each one has reserved from
+    // the shared memory manager
+    static ref QUERY_MEMORY_MANAGERS: Mutex<Vec<(Weak<MemoryManager>, usize)>> =
+        Mutex::new(Vec::new());
+
+    // Catalog
+    pub static ref CATALOG_QUERY_DURATION: HistogramVec = register_histogram_vec!(
+        "seafowl_catalog_query_duration_seconds",
+        "Time taken by catalog operations, by operation",
+        &["operation"]
+    )
+    .unwrap();
+
+    // Partition writing
+    pub static ref PARTITION_COLUMN_STATS_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "seafowl_partition_column_stats_failures_total",
+        "Number of partition columns written without min/max statistics, by Arrow type",
+        &["data_type"]
+    )
+    .unwrap();
+
+    // Maintenance
+    pub static ref GC_PARTITIONS_DELETED: IntCounter = register_int_counter!(
+        "seafowl_gc_partitions_deleted_total",
+        "Number of orphan partitions deleted by GC"
+    )
+    .unwrap();
+    pub static ref GC_PARTITION_DELETE_FAILURES: IntCounter = register_int_counter!(
+        "seafowl_gc_partition_delete_failures_total",
+        "Number of orphan partitions that GC failed to delete from the object store"
+    )
+    .unwrap();
+    pub static ref TABLE_VERSIONS_DELETED: IntCounterVec = register_int_counter_vec!(
+        "seafowl_table_versions_deleted_total",
+        "Number of table versions deleted, by reason (vacuum or retention)",
+        &["reason"]
+    )
+    .unwrap();
+}
+
+/// Run a catalog operation, recording how long it took
+pub async fn time_catalog_query<F: Future>(operation: &str, future: F) -> F::Output {
+    let start = Instant::now();
+    let result = future.await;
+    CATALOG_QUERY_DURATION
+        .with_label_values(&[operation])
+        .observe(start.elapsed().as_secs_f64());
+    result
+}
+
+/// Set the memory manager whose usage gets exported as `seafowl_memory_pool_reserved_bytes`
+pub fn register_memory_manager(memory_manager: Arc<MemoryManager>) {
+    *MEMORY_MANAGER.write() = Some(memory_manager);
+}
+
+/// Track the memory pool of a query with a memory limit, which has reserved `reserved`
+/// bytes from the shared memory manager
+pub fn register_query_memory_manager(
+    memory_manager: &Arc<MemoryManager>,
+    reserved: usize,
+) {
+    let mut query_managers = QUERY_MEMORY_MANAGERS.lock();
+    query_managers.retain(|(m, _)| m.strong_count() > 0);
+    query_managers.push((Arc::downgrade(memory_manager), reserved));
+}
+
+/// Memory reserved by query operators across the shared pool and the pools of the
+/// queries with a memory limit. The latter are counted by what their operators have
+/// reserved instead of by the whole limit that they took out of the shared pool.
+fn memory_pool_reserved(shared: &MemoryManager) -> usize {
+    let mut query_managers = QUERY_MEMORY_MANAGERS.lock();
+    query_managers.retain(|(m, _)| m.strong_count() > 0);
+
+    let (limits, used) = query_managers
+        .iter()
+        .filter_map(|(m, reserved)| {
+            m.upgrade().map(|m| (*reserved, m.get_requester_total()))
+        })
+        .fold((0, 0), |(limits, used), (reserved, total)| {
+            (limits + reserved, used + total)
+        });
+    shared.get_requester_total().saturating_sub(limits) + used
+}
+
+/// Render all metrics in the Prometheus text format
+pub fn gather_metrics() -> Result<Vec<u8>, prometheus::Error> {
+    if let Some(memory_manager) = MEMORY_MANAGER.read().as_ref() {
+        MEMORY_POOL_RESERVED.set(memory_pool_reserved(memory_manager) as i64);
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder.encode(&prometheus::gather(), &mut buffer)?;
+    Ok(buffer)
+}
@@ -6,8 +6,11 @@ use std::{any::Any, fmt, sync::Arc, vec};
 
 use datafusion_expr::{Expr, LogicalPlan, UserDefinedLogicalNode};
 
-use crate::data_types::TableId;
-use crate::{provider::SeafowlTable, wasm_udf::data_types::CreateFunctionDetails};
+use crate::data_types::{ParquetWriterOptions, TableId};
+use crate::{
+    provider::SeafowlTable,
+    wasm_udf::data_types::{CreateFunctionDataType, CreateFunctionDetails},
+};
 
 #[derive(Debug, Clone)]
 pub struct CreateTable {
@@ -17,6 +20,8 @@ pub struct CreateTable {
     pub name: String,
     /// Option to not error if table already exists
     pub if_not_exists: bool,
+    /// Parquet writer settings for the table's partitions
+    pub writer_options: ParquetWriterOptions,
 
     /// Dummy result schema for the plan (empty)
     pub output_schema: DFSchemaRef,
@@ -63,6 +68,19 @@ pub struct CreateFunction {
     /// The function name
     pub name: String,
     pub details: CreateFunctionDetails,
+    /// Replace the function with the same name and input types if it exists
+    pub or_replace: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone)]
+pub struct DropFunction {
+    /// Functions to drop, with the input types of the overload to drop (all overloads
+    /// of the function get dropped if these aren't specified)
+    pub functions: Vec<(String, Option<Vec<CreateFunctionDataType>>)>,
+    /// Option to not error if a function doesn't exist
+    pub if_exists: bool,
     /// Dummy result schema for the plan (empty)
     pub output_schema: DFSchemaRef,
 }
@@ -85,6 +103,46 @@ pub struct DropSchema {
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone)]
+pub struct DropView {
+    /// The views to drop
+    pub names: Vec<String>,
+    /// Option to not error if a view doesn't exist
+    pub if_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateMaterializedView {
+    /// The name of the table backing the materialized view
+    pub name: String,
+    /// The query populating the materialized view
+    pub input: Arc<LogicalPlan>,
+    /// SQL text of the query, to be re-run on every refresh
+    pub definition: String,
+    /// Refresh the materialized view whenever one of its source tables changes
+    pub auto_refresh: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone)]
+pub struct RefreshMaterializedView {
+    /// The materialized view to refresh
+    pub name: String,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalyzeTable {
+    /// The table to collect the statistics of
+    pub table: Arc<SeafowlTable>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(Debug, Clone)]
 pub struct Vacuum {
     /// Denotes whether to vacuum the partitions
@@ -103,8 +161,13 @@ pub enum SeafowlExtensionNode {
     Update(Update),
     Delete(Delete),
     CreateFunction(CreateFunction),
+    DropFunction(DropFunction),
     RenameTable(RenameTable),
     DropSchema(DropSchema),
+    DropView(DropView),
+    CreateMaterializedView(CreateMaterializedView),
+    RefreshMaterializedView(RefreshMaterializedView),
+    AnalyzeTable(AnalyzeTable),
     Vacuum(Vacuum),
 }
 
@@ -156,6 +219,10 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
     fn inputs(&self) -> Vec<&LogicalPlan> {
         match self {
             SeafowlExtensionNode::Insert(Insert { input, .. }) => vec![input.as_ref()],
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                input,
+                ..
+            }) => vec![input.as_ref()],
 
             // For UPDATE/DELETE, the optimizer needs to know the schema of the "input" (in this
             // case, our tables we're updating) in order to optimize this node's expressions (in our
@@ -186,12 +253,29 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
                 output_schema,
                 ..
             }) => output_schema,
+            SeafowlExtensionNode::DropFunction(DropFunction {
+                output_schema, ..
+            }) => output_schema,
             SeafowlExtensionNode::RenameTable(RenameTable { output_schema, .. }) => {
                 output_schema
             }
             SeafowlExtensionNode::DropSchema(DropSchema { output_schema, .. }) => {
                 output_schema
             }
+            SeafowlExtensionNode::DropView(DropView { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                output_schema,
+                ..
+            }) => output_schema,
+            SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                output_schema,
+                ..
+            }) => output_schema,
+            SeafowlExtensionNode::AnalyzeTable(AnalyzeTable {
+                output_schema, ..
+            }) => output_schema,
             SeafowlExtensionNode::Vacuum(Vacuum { output_schema, .. }) => output_schema,
         }
     }
@@ -265,6 +349,17 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::CreateFunction(CreateFunction { name, .. }) => {
                 write!(f, "CreateFunction: {name}")
             }
+            SeafowlExtensionNode::DropFunction(DropFunction { functions, .. }) => {
+                write!(
+                    f,
+                    "DropFunction: {}",
+                    functions
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
             SeafowlExtensionNode::RenameTable(RenameTable {
                 table, new_name, ..
             }) => {
@@ -273,6 +368,24 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::DropSchema(DropSchema { name, .. }) => {
                 write!(f, "DropSchema: {name}")
             }
+            SeafowlExtensionNode::DropView(DropView { names, .. }) => {
+                write!(f, "DropView: {}", names.join(", "))
+            }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                name,
+                ..
+            }) => {
+                write!(f, "CreateMaterializedView: {name}")
+            }
+            SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                name,
+                ..
+            }) => {
+                write!(f, "RefreshMaterializedView: {name}")
+            }
+            SeafowlExtensionNode::AnalyzeTable(AnalyzeTable { table, .. }) => {
+                write!(f, "AnalyzeTable: {}", table.name)
+            }
             SeafowlExtensionNode::Vacuum(Vacuum { partitions, .. }) => {
                 write!(
                     f,
@@ -302,6 +415,25 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
                 output_schema: output_schema.clone(),
             })),
 
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                name,
+                input,
+                definition,
+                auto_refresh,
+                output_schema,
+            }) => Arc::new(SeafowlExtensionNode::CreateMaterializedView(
+                CreateMaterializedView {
+                    name: name.clone(),
+                    input: match inputs.first() {
+                        Some(new_input) => Arc::new(new_input.clone()),
+                        None => input.clone(),
+                    },
+                    definition: definition.clone(),
+                    auto_refresh: *auto_refresh,
+                    output_schema: output_schema.clone(),
+                },
+            )),
+
             SeafowlExtensionNode::Update(Update {
                 table,
                 selection,
@@ -2,6 +2,7 @@
 /// Partially inspired by https://docs.rs/moka/latest/moka/future/struct.Cache.html#example-eviction-listener,
 /// with some additions to weigh it by the file size.
 use crate::config::schema::str_to_hex_hash;
+use crate::metrics::{CACHE_EVICTIONS, CACHE_HITS, CACHE_MISSES};
 use async_trait::async_trait;
 use bytes::{Buf, BufMut, Bytes};
 use futures::stream::BoxStream;
@@ -19,6 +20,7 @@ use std::io::ErrorKind;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::io::AsyncWrite;
 use tokio::{fs, sync::RwLock};
@@ -131,6 +133,10 @@ impl CachingObjectStore {
             key, value, cause
         );
 
+        if matches!(cause, RemovalCause::Size | RemovalCause::Expired) {
+            CACHE_EVICTIONS.inc();
+        }
+
         let rt = tokio::runtime::Handle::current();
         let _guard = rt.enter();
         rt.block_on(async {
@@ -206,9 +212,14 @@ impl CachingObjectStore {
             range: range.clone(),
         };
 
+        // Track whether we had to fetch the chunk ourselves (cache miss)
+        let fetched = AtomicBool::new(false);
+        let fetched_ref = &fetched;
+
         let value = self
             .cache
             .try_get_with::<_, object_store::Error>(key.clone(), async move {
+                fetched_ref.store(true, Ordering::Relaxed);
                 let mut manager = self.file_manager.write().await;
                 let data = self.inner.get_range(path, range).await?;
                 let path = manager.write_file(key, &data).await.map_err(|e| {
@@ -229,6 +240,12 @@ impl CachingObjectStore {
                 source: Box::new(e),
             })?;
 
+        if fetched.load(Ordering::Relaxed) {
+            CACHE_MISSES.inc();
+        } else {
+            CACHE_HITS.inc();
+        }
+
         {
             let manager = self.file_manager.read().await;
             let data = manager.read_file(value.path).await.unwrap();
@@ -10,6 +10,7 @@ use object_store::{GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore};
 use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 
 use crate::object_store::cache::CachingObjectStore;
+use crate::object_store::metered::MeteredObjectStore;
 use datafusion::prelude::SessionContext;
 use lazy_static::lazy_static;
 use log::warn;
@@ -329,14 +330,20 @@ pub fn add_http_object_store(context: &SessionContext) {
     let path = tmp_dir.into_path();
 
     let http_object_store = CachingObjectStore::new(
-        Arc::new(HttpObjectStore::new("http".to_string())),
+        Arc::new(MeteredObjectStore::new(
+            Arc::new(HttpObjectStore::new("http".to_string())),
+            "http",
+        )),
         &path,
         MIN_FETCH_SIZE,
         HTTP_CACHE_CAPACITY,
     );
     let https_object_store = CachingObjectStore::new_from_sibling(
         &http_object_store,
-        Arc::new(HttpObjectStore::new("https".to_string())),
+        Arc::new(MeteredObjectStore::new(
+            Arc::new(HttpObjectStore::new("https".to_string())),
+            "https",
+        )),
     );
 
     context.runtime_env().register_object_store(
@@ -0,0 +1,122 @@
+/// Object store wrapper that counts the requests made to the inner store
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::{GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore};
+
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
+use tokio::io::AsyncWrite;
+
+use crate::metrics::OBJECT_STORE_REQUESTS;
+
+#[derive(Debug)]
+pub struct MeteredObjectStore {
+    inner: Arc<dyn ObjectStore>,
+    // Label for the store in the exported metrics (e.g. "internal" or "http")
+    store: &'static str,
+}
+
+impl MeteredObjectStore {
+    pub fn new(inner: Arc<dyn ObjectStore>, store: &'static str) -> Self {
+        Self { inner, store }
+    }
+
+    fn count(&self, operation: &str) {
+        OBJECT_STORE_REQUESTS
+            .with_label_values(&[self.store, operation])
+            .inc();
+    }
+}
+
+impl Display for MeteredObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MeteredObjectStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        self.count("put");
+        self.inner.put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.count("put_multipart");
+        self.inner.put_multipart(location).await
+    }
+
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.count("abort_multipart");
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        self.count("get");
+        self.inner.get(location).await
+    }
+
+    async fn get_range(
+        &self,
+        location: &Path,
+        range: Range<usize>,
+    ) -> object_store::Result<Bytes> {
+        self.count("get_range");
+        self.inner.get_range(location, range).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.count("head");
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.count("delete");
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.count("list");
+        self.inner.list(prefix).await
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<ListResult> {
+        self.count("list_with_delimiter");
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.count("copy");
+        self.inner.copy(from, to).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.count("rename");
+        self.inner.rename(from, to).await
+    }
+
+    async fn copy_if_not_exists(
+        &self,
+        from: &Path,
+        to: &Path,
+    ) -> object_store::Result<()> {
+        self.count("copy_if_not_exists");
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
@@ -1,5 +1,6 @@
 pub mod cache;
 pub mod http;
+pub mod metered;
 pub mod wrapped;
 
 #[cfg(test)]
@@ -1,13 +1,19 @@
 use arrow::array::{ArrayRef, UInt64Array};
 use arrow::compute::{cast_with_options, CastOptions};
 use arrow::datatypes::{DataType, SchemaRef};
+use std::convert::Infallible;
+use std::iter::zip;
 use std::ops::Deref;
-use std::{any::Any, collections::HashMap, sync::Arc};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 
 use datafusion::common::{Column, ToDFSchema};
-use datafusion::config::ConfigOptions;
+use datafusion::config::{ConfigOptions, OPT_PARQUET_ENABLE_PAGE_INDEX};
 use datafusion::execution::context::ExecutionProps;
 use datafusion::logical_expr::TableProviderFilterPushDown;
 use datafusion::physical_expr::expressions::{case, cast, col};
@@ -26,7 +32,7 @@ use datafusion::{
     datasource::{
         file_format::{parquet::ParquetFormat, FileFormat},
         listing::PartitionedFile,
-        TableProvider,
+        DefaultTableSource, TableProvider,
     },
     execution::context::{SessionState, TaskContext},
     logical_expr::TableType,
@@ -36,7 +42,9 @@ use datafusion::{
         SendableRecordBatchStream, Statistics,
     },
 };
-use datafusion_expr::Expr;
+use datafusion_expr::expr_visitor::{ExprVisitable, ExpressionVisitor, Recursion};
+use datafusion_expr::logical_plan::{LogicalPlan, PlanVisitor, TableScan};
+use datafusion_expr::{BinaryExpr, Expr, Operator, Volatility};
 use datafusion_proto::protobuf;
 
 use futures::future;
@@ -47,11 +55,13 @@ use prost::Message;
 
 use object_store::path::Path;
 
-use crate::data_types::PhysicalPartitionId;
+use crate::bloom_filter::BloomFilter;
+use crate::data_types::{ExternalTableDetails, ExternalTableId, PhysicalPartitionId};
+use crate::statistics::table_statistics;
 use crate::system_tables::{SystemSchemaProvider, SYSTEM_SCHEMA};
 use crate::{
     catalog::PartitionCatalog,
-    data_types::{TableId, TableVersionId},
+    data_types::{ParquetWriterOptions, TableId, TableVersionId},
     schema::Schema,
 };
 use crate::{catalog::STAGING_SCHEMA, wasm_udf::data_types::CreateFunctionDetails};
@@ -91,6 +101,10 @@ impl CatalogProvider for SeafowlDatabase {
 pub struct SeafowlCollection {
     pub name: Arc<str>,
     pub tables: RwLock<HashMap<Arc<str>, Arc<SeafowlTable>>>,
+    // Providers for the external tables persisted in this collection
+    pub external_tables: RwLock<HashMap<Arc<str>, Arc<dyn TableProvider>>>,
+    // Views persisted in this collection (expanded into their definition at planning time)
+    pub views: RwLock<HashMap<Arc<str>, Arc<SeafowlView>>>,
 }
 
 impl SchemaProvider for SeafowlCollection {
@@ -100,17 +114,30 @@ impl SchemaProvider for SeafowlCollection {
 
     fn table_names(&self) -> Vec<String> {
         let tables = self.tables.read();
-        tables.keys().map(|s| s.to_string()).collect()
+        let external_tables = self.external_tables.read();
+        let views = self.views.read();
+        tables
+            .keys()
+            .chain(external_tables.keys())
+            .chain(views.keys())
+            .map(|s| s.to_string())
+            .collect()
     }
 
     fn table(&self, name: &str) -> Option<Arc<dyn TableProvider>> {
         let tables = self.tables.read();
-        tables.get(name).map(|c| Arc::clone(c) as _)
+        tables
+            .get(name)
+            .map(|c| Arc::clone(c) as _)
+            .or_else(|| self.external_tables.read().get(name).cloned())
+            .or_else(|| self.views.read().get(name).map(|v| Arc::clone(v) as _))
     }
 
     fn table_exist(&self, name: &str) -> bool {
         let tables = self.tables.read();
         tables.contains_key(name)
+            || self.external_tables.read().contains_key(name)
+            || self.views.read().contains_key(name)
     }
 
     fn register_table(
@@ -158,6 +185,12 @@ pub struct PartitionColumn {
     pub min_value: Arc<Option<Vec<u8>>>,
     pub max_value: Arc<Option<Vec<u8>>>,
     pub null_count: Option<i32>,
+    // Serialized `BloomFilter`, only present for the table's `bloom_filter_columns` when writing
+    // the partition (the catalog only loads them on demand, see `load_bloom_filters`)
+    pub bloom_filter: Arc<Option<Vec<u8>>>,
+    // Serialized `HyperLogLog` sketch of the distinct values (only loaded for ANALYZE, see
+    // `load_table_partitions_with_distinct_sketches`)
+    pub distinct_sketch: Arc<Option<Vec<u8>>>,
 }
 
 #[derive(Clone)]
@@ -166,6 +199,10 @@ pub struct SeafowlTable {
     pub schema: Arc<Schema>,
     pub table_id: TableId,
     pub table_version_id: TableVersionId,
+    // Settings for writing the table's partitions
+    pub writer_options: Arc<ParquetWriterOptions>,
+    // Estimated number of distinct values per column, as of the last ANALYZE TABLE
+    pub distinct_counts: Arc<HashMap<String, u64>>,
 
     // We have to keep a reference to the original catalog here. This is
     // because we need it to load the partitions for a given table at query plan
@@ -212,22 +249,76 @@ impl SeafowlTable {
             }))
             .await?;
 
+        // Let DataFusion use the partition statistics for planning (e.g. join ordering)
+        let total_byte_size: usize = partitioned_file_lists
+            .iter()
+            .flatten()
+            .map(|file| file.object_meta.size)
+            .sum();
+        let statistics = table_statistics(
+            &partitions,
+            &self.schema(),
+            &self.distinct_counts,
+            Some(total_byte_size),
+        );
+
+        // On top of pruning the row groups by their min/max values, use the page index
+        // to skip pages within row groups for selective filters
+        let mut config_options = ConfigOptions::new();
+        config_options.set_bool(OPT_PARQUET_ENABLE_PAGE_INDEX, true);
+
         let config = FileScanConfig {
             object_store_url: internal_object_store_url(),
             file_schema: self.schema(),
             file_groups: partitioned_file_lists,
-            statistics: Statistics::default(),
+            statistics,
             projection: projection.cloned(),
             limit,
             table_partition_cols: vec![],
             output_ordering: None,
-            config_options: Arc::new(Default::default()),
+            config_options: Arc::new(RwLock::new(config_options)),
         };
 
         let format = ParquetFormat::new(Arc::new(RwLock::new(ConfigOptions::new())));
         format.create_physical_plan(config, filters).await
     }
 
+    /// Build the statistics for pruning some of this table's partitions with these filters,
+    /// loading the Bloom filters of the columns that the filters could use them for
+    pub async fn pruning_statistics(
+        &self,
+        partitions: Vec<SeafowlPartition>,
+        filters: &[Expr],
+    ) -> Result<SeafowlPruningStatistics> {
+        let mut pruning_stats =
+            SeafowlPruningStatistics::from_partitions(partitions, self.schema())?;
+
+        let mut columns = HashSet::new();
+        for expr in filters {
+            SeafowlPruningStatistics::bloom_filter_columns(expr, &mut columns);
+        }
+        let columns: Vec<String> = columns
+            .into_iter()
+            .filter(|column| self.writer_options.bloom_filter_columns.contains(column))
+            .collect();
+
+        if !columns.is_empty() {
+            match self
+                .catalog
+                .load_bloom_filters(self.table_version_id, columns)
+                .await
+            {
+                Ok(bloom_filters) => pruning_stats.add_bloom_filters(bloom_filters),
+                Err(error) => warn!(
+                    "Failed loading the Bloom filters for table {} (version: {}): {:?}",
+                    self.name, self.table_version_id, error
+                ),
+            }
+        }
+
+        Ok(pruning_stats)
+    }
+
     // Wrap a base scan over the supplied partitions with a filter plan
     pub async fn partition_filter_plan(
         &self,
@@ -272,10 +363,7 @@ impl TableProvider for SeafowlTable {
 
         // Try to prune away redundant partitions
         if !filters.is_empty() {
-            match SeafowlPruningStatistics::from_partitions(
-                partitions.clone(),
-                self.schema(),
-            ) {
+            match self.pruning_statistics(partitions.clone(), filters).await {
                 Ok(pruning_stats) => partitions = pruning_stats.prune(filters).await,
                 Err(error) => {
                     warn!(
@@ -306,6 +394,101 @@ impl TableProvider for SeafowlTable {
     }
 }
 
+/// Collects the versions of the Seafowl tables scanned by a logical plan
+#[derive(Default)]
+pub struct SeafowlTableVersionsVisitor {
+    pub table_versions: Vec<TableVersionId>,
+}
+
+impl PlanVisitor for SeafowlTableVersionsVisitor {
+    type Error = Infallible;
+
+    fn pre_visit(
+        &mut self,
+        plan: &LogicalPlan,
+    ) -> std::result::Result<bool, Self::Error> {
+        if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
+            // TODO handle external Parquet tables too
+            if let Some(default_table_source) =
+                source.as_any().downcast_ref::<DefaultTableSource>()
+            {
+                if let Some(table) = default_table_source
+                    .table_provider
+                    .as_any()
+                    .downcast_ref::<SeafowlTable>()
+                {
+                    self.table_versions.push(table.table_version_id)
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Checks whether the results of a logical plan only depend on the versions of the Seafowl
+/// tables it scans, so that they can be cached by those versions: it can't read from any
+/// other kind of table, nor call any functions that aren't immutable (e.g. `now()` or
+/// volatile UDFs).
+pub struct SeafowlCacheablePlanVisitor {
+    pub cacheable: bool,
+}
+
+impl Default for SeafowlCacheablePlanVisitor {
+    fn default() -> Self {
+        Self { cacheable: true }
+    }
+}
+
+struct ImmutableExprVisitor {
+    immutable: bool,
+}
+
+impl ExpressionVisitor for ImmutableExprVisitor {
+    fn pre_visit(mut self, expr: &Expr) -> Result<Recursion<Self>> {
+        let volatility = match expr {
+            Expr::ScalarFunction { fun, .. } => Some(fun.volatility()),
+            Expr::ScalarUDF { fun, .. } => Some(fun.signature.volatility),
+            Expr::AggregateUDF { fun, .. } => Some(fun.signature.volatility),
+            _ => None,
+        };
+
+        if volatility.map_or(false, |v| v != Volatility::Immutable) {
+            self.immutable = false;
+            return Ok(Recursion::Stop(self));
+        }
+        Ok(Recursion::Continue(self))
+    }
+}
+
+impl PlanVisitor for SeafowlCacheablePlanVisitor {
+    type Error = DataFusionError;
+
+    fn pre_visit(&mut self, plan: &LogicalPlan) -> Result<bool> {
+        if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
+            let is_seafowl_table = source
+                .as_any()
+                .downcast_ref::<DefaultTableSource>()
+                .map_or(false, |source| {
+                    source.table_provider.as_any().is::<SeafowlTable>()
+                });
+            if !is_seafowl_table {
+                self.cacheable = false;
+            }
+        }
+
+        for expr in plan.expressions() {
+            if !expr
+                .accept(ImmutableExprVisitor { immutable: true })?
+                .immutable
+            {
+                self.cacheable = false;
+            }
+        }
+
+        Ok(self.cacheable)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SeafowlPruningStatistics {
     pub partition_count: usize,
@@ -314,6 +497,7 @@ pub struct SeafowlPruningStatistics {
     pub min_values: HashMap<String, Vec<ScalarValue>>,
     pub max_values: HashMap<String, Vec<ScalarValue>>,
     pub null_counts: HashMap<String, Vec<Option<u64>>>,
+    pub bloom_filters: HashMap<String, Vec<Option<BloomFilter>>>,
 }
 
 impl SeafowlPruningStatistics {
@@ -361,9 +545,68 @@ impl SeafowlPruningStatistics {
             min_values,
             max_values,
             null_counts,
+            bloom_filters: HashMap::new(),
         })
     }
 
+    /// Add the serialized Bloom filters of some columns (keyed by the column name and then
+    /// the partition ID), so that they can be used for pruning too
+    pub fn add_bloom_filters(
+        &mut self,
+        bloom_filters: HashMap<String, HashMap<PhysicalPartitionId, Vec<u8>>>,
+    ) {
+        for (column, partition_bloom_filters) in bloom_filters {
+            let column_bloom_filters = self
+                .partitions
+                .iter()
+                .map(|partition| {
+                    partition
+                        .partition_id
+                        .and_then(|id| partition_bloom_filters.get(&id))
+                        .and_then(|bytes| BloomFilter::from_bytes(bytes))
+                })
+                .collect();
+            self.bloom_filters.insert(column, column_bloom_filters);
+        }
+    }
+
+    /// Collect the columns whose Bloom filters could be used to prune partitions for this
+    /// expression (the ones compared to literals in equality/IN list predicates)
+    pub fn bloom_filter_columns(expr: &Expr, columns: &mut HashSet<String>) {
+        match expr {
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::And | Operator::Or,
+                right,
+            }) => {
+                Self::bloom_filter_columns(left, columns);
+                Self::bloom_filter_columns(right, columns);
+            }
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Eq,
+                right,
+            }) => {
+                if let (Expr::Column(column), Expr::Literal(_))
+                | (Expr::Literal(_), Expr::Column(column)) =
+                    (left.as_ref(), right.as_ref())
+                {
+                    columns.insert(column.name.clone());
+                }
+            }
+            Expr::InList {
+                expr,
+                negated: false,
+                ..
+            } => {
+                if let Expr::Column(column) = expr.as_ref() {
+                    columns.insert(column.name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Try to deserialize min/max statistics stored as raw bytes
     ///
     /// ```
@@ -453,6 +696,14 @@ impl SeafowlPruningStatistics {
                         );
                     }
                 }
+
+                if let Some(expr_mask) = self.bloom_filter_mask(expr) {
+                    partition_mask = partition_mask
+                        .iter()
+                        .zip(expr_mask)
+                        .map(|(&a, b)| a && b)
+                        .collect()
+                }
             }
         }
 
@@ -463,6 +714,91 @@ impl SeafowlPruningStatistics {
             .collect()
     }
 
+    // Use the partitions' Bloom filters to find the ones that can't match equality/IN list
+    // predicates on a column. Returns `None` if the filters can't tell anything about the expression.
+    fn bloom_filter_mask(&self, expr: &Expr) -> Option<Vec<bool>> {
+        match expr {
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::And,
+                right,
+            }) => match (self.bloom_filter_mask(left), self.bloom_filter_mask(right)) {
+                (Some(left), Some(right)) => {
+                    Some(zip(left, right).map(|(a, b)| a && b).collect())
+                }
+                (mask, None) | (None, mask) => mask,
+            },
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Or,
+                right,
+            }) => {
+                let left = self.bloom_filter_mask(left)?;
+                let right = self.bloom_filter_mask(right)?;
+                Some(zip(left, right).map(|(a, b)| a || b).collect())
+            }
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Eq,
+                right,
+            }) => match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(column), Expr::Literal(value))
+                | (Expr::Literal(value), Expr::Column(column)) => {
+                    self.bloom_filter_values_mask(column, &[value])
+                }
+                _ => None,
+            },
+            Expr::InList {
+                expr,
+                list,
+                negated: false,
+            } => match expr.as_ref() {
+                Expr::Column(column) => {
+                    let values = list
+                        .iter()
+                        .map(|item| match item {
+                            Expr::Literal(value) => Some(value),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    self.bloom_filter_values_mask(column, &values)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn bloom_filter_values_mask(
+        &self,
+        column: &Column,
+        values: &[&ScalarValue],
+    ) -> Option<Vec<bool>> {
+        let bloom_filters = self.bloom_filters.get(column.name.as_str())?;
+        let data_type = self.schema.field_with_name(&column.name).ok()?.data_type();
+
+        // The values have to hash the same way as the column's values did, so don't try
+        // to deal with any casts here
+        if values
+            .iter()
+            .any(|value| value.get_datatype() != *data_type)
+        {
+            return None;
+        }
+
+        Some(
+            bloom_filters
+                .iter()
+                .map(|bloom_filter| match bloom_filter {
+                    Some(bloom_filter) => {
+                        values.iter().any(|value| bloom_filter.might_contain(value))
+                    }
+                    None => true,
+                })
+                .collect(),
+        )
+    }
+
     // Try to convert the vector of min/max scalar values for a column into a generic array reference
     fn get_values_array(&self, values: Option<&Vec<ScalarValue>>) -> Option<ArrayRef> {
         values.and_then(|stats| {
@@ -596,11 +932,64 @@ impl ExecutionPlan for SeafowlBaseTableScanNode {
     }
 
     fn statistics(&self) -> Statistics {
-        Statistics::default()
+        self.inner.statistics()
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeafowlExternalTable {
+    pub external_table_id: ExternalTableId,
+    pub collection_name: Arc<str>,
+    pub name: Arc<str>,
+    pub details: ExternalTableDetails,
+    pub schema: ArrowSchemaRef,
+}
+
+/// A view persisted in the catalog. We don't scan views directly: instead, references to
+/// them get replaced with their definition before the query is planned (see `ViewProcessor`),
+/// so that the definition can use Seafowl-specific syntax like time travel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeafowlView {
+    pub view_id: TableId,
+    pub collection_name: Arc<str>,
+    pub name: Arc<str>,
+    pub definition: String,
+    pub schema: ArrowSchemaRef,
+}
+
+#[async_trait]
+impl TableProvider for SeafowlView {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> ArrowSchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::View
+    }
+
+    fn get_table_definition(&self) -> Option<&str> {
+        Some(&self.definition)
+    }
+
+    async fn scan(
+        &self,
+        _ctx: &SessionState,
+        _projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Err(DataFusionError::Plan(format!(
+            "View {}.{} can't be used in this statement",
+            self.collection_name, self.name
+        )))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SeafowlFunction {
     pub function_id: FunctionId,
     pub name: String,
@@ -698,6 +1087,8 @@ mod tests {
             }),
             table_id: 1,
             table_version_id: 1,
+            writer_options: Default::default(),
+            distinct_counts: Default::default(),
             catalog: Arc::new(catalog),
         };
 
@@ -817,6 +1208,8 @@ mod tests {
                     min_value,
                     max_value,
                     null_count: *null_count,
+                    bloom_filter: Arc::new(None),
+                    distinct_sketch: Arc::new(None),
                 }]),
             })
         }
@@ -0,0 +1,401 @@
+//! Enforcement of the per-query limits (timeouts, result rows and memory) configured in
+//! `[runtime.query_limits]`, optionally tightened per request by the client.
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::arrow::error::ArrowError;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::memory_manager::proxy::MemoryConsumerProxy;
+use datafusion::execution::memory_manager::{
+    MemoryConsumerId, MemoryManager, MemoryManagerConfig,
+};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::{future, StreamExt};
+use sqlparser::ast::{Expr, Statement, Value};
+
+use crate::config::schema::{QueryLimit, MEBIBYTES};
+use crate::datafusion::parser::Statement as DFStatement;
+use crate::metrics::register_query_memory_manager;
+
+pub const STATEMENT_TIMEOUT: &str = "statement_timeout";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryLimitExceeded {
+    Timeout(Duration),
+    MaxRows(u64),
+    MaxMemory(u64),
+    MemoryPoolFull(u64),
+}
+
+impl Display for QueryLimitExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryLimitExceeded::Timeout(timeout) => {
+                write!(f, "Query exceeded the timeout of {timeout:?}")
+            }
+            QueryLimitExceeded::MaxRows(max_rows) => {
+                write!(f, "Query returned more than the maximum of {max_rows} rows")
+            }
+            QueryLimitExceeded::MaxMemory(max_bytes) => {
+                write!(f, "Query exceeded the memory limit of {max_bytes} bytes")
+            }
+            QueryLimitExceeded::MemoryPoolFull(max_bytes) => {
+                write!(
+                    f,
+                    "Not enough memory left in the memory pool to reserve {max_bytes} bytes for the query"
+                )
+            }
+        }
+    }
+}
+
+impl Error for QueryLimitExceeded {}
+
+impl From<QueryLimitExceeded> for DataFusionError {
+    fn from(err: QueryLimitExceeded) -> Self {
+        DataFusionError::External(Box::new(err))
+    }
+}
+
+/// Limits that apply to a single statement
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    pub max_rows: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl From<QueryLimit> for Limits {
+    fn from(limit: QueryLimit) -> Self {
+        Self {
+            timeout: limit.timeout_secs.map(Duration::from_secs),
+            max_rows: limit.max_rows,
+            max_bytes: limit.max_memory.map(|m| m * MEBIBYTES),
+        }
+    }
+}
+
+impl Limits {
+    /// Apply a timeout requested by the client. This can only make the configured
+    /// timeout stricter, so that clients can't escape the limits set by the operator.
+    pub fn with_requested_timeout(self, timeout: Option<Duration>) -> Self {
+        let timeout = match (self.timeout, timeout) {
+            (Some(configured), Some(requested)) => Some(configured.min(requested)),
+            (configured, requested) => configured.or(requested),
+        };
+        Self { timeout, ..self }
+    }
+
+    /// Check the amount of results a query has produced so far
+    pub fn check_results(&self, rows: u64, bytes: u64) -> Result<()> {
+        match (self.max_rows, self.max_bytes) {
+            (Some(max_rows), _) if rows > max_rows => {
+                Err(QueryLimitExceeded::MaxRows(max_rows).into())
+            }
+            (_, Some(max_bytes)) if bytes > max_bytes => {
+                Err(QueryLimitExceeded::MaxMemory(max_bytes).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Enforce the row and memory limits on a stream of query results. The memory limit
+    /// applies both to the results and to the query's memory pool (see
+    /// `QueryMemory`), which is released when the stream is dropped. The stream ends
+    /// after the first error.
+    pub fn limit_stream(
+        &self,
+        stream: SendableRecordBatchStream,
+        memory: Option<QueryMemory>,
+    ) -> SendableRecordBatchStream {
+        let schema = stream.schema();
+        let limits = self.clone();
+
+        let stream =
+            stream.scan((0u64, 0u64, false), move |(rows, bytes, failed), batch| {
+                let _reservation = &memory;
+                if *failed {
+                    return future::ready(None);
+                }
+
+                let result = batch
+                    .map_err(DataFusionError::ArrowError)
+                    .and_then(|batch| {
+                        *rows += batch.num_rows() as u64;
+                        *bytes += batch
+                            .columns()
+                            .iter()
+                            .map(|c| c.get_array_memory_size() as u64)
+                            .sum::<u64>();
+                        limits.check_results(*rows, *bytes)?;
+                        Ok(batch)
+                    })
+                    .map_err(|err| match (root_error(&err), limits.max_bytes) {
+                        (DataFusionError::ResourcesExhausted(_), Some(max_bytes)) => {
+                            QueryLimitExceeded::MaxMemory(max_bytes).into()
+                        }
+                        _ => err,
+                    });
+
+                *failed = result.is_err();
+                future::ready(Some(result.map_err(ArrowError::from)))
+            });
+
+        Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+    }
+
+    /// Run a future, aborting it if it takes longer than the timeout
+    pub async fn run<F, T>(&self, future: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .unwrap_or_else(|_| Err(QueryLimitExceeded::Timeout(timeout).into())),
+            None => future.await,
+        }
+    }
+}
+
+/// Memory pool of a query with a memory limit. The limit gets reserved up front from the
+/// shared pool sized by `runtime.max_memory`, so that concurrent queries can't use more
+/// than that in total. The query's operators then allocate from a pool of their own,
+/// which makes them spill or fail once they reach the limit.
+pub struct QueryMemory {
+    pub memory_manager: Arc<MemoryManager>,
+    _reservation: MemoryConsumerProxy,
+}
+
+impl QueryMemory {
+    pub async fn reserve(shared: Arc<MemoryManager>, max_bytes: u64) -> Result<Self> {
+        let memory_manager = MemoryManager::new(MemoryManagerConfig::try_new_limit(
+            max_bytes as usize,
+            1.0,
+        )?);
+
+        let mut reservation =
+            MemoryConsumerProxy::new("QueryMemory", MemoryConsumerId::new(0), shared);
+        reservation
+            .alloc(max_bytes as usize)
+            .await
+            .map_err(|_| QueryLimitExceeded::MemoryPoolFull(max_bytes))?;
+        register_query_memory_manager(&memory_manager, max_bytes as usize);
+
+        Ok(Self {
+            memory_manager,
+            _reservation: reservation,
+        })
+    }
+}
+
+/// Unwrap an error that's been passed through Arrow (e.g. by a record batch stream). The
+/// repartitioning operator also wraps errors in an `Arc` to send them to all partitions.
+fn root_error(err: &DataFusionError) -> &DataFusionError {
+    match err {
+        DataFusionError::ArrowError(ArrowError::ExternalError(e))
+        | DataFusionError::External(e) => match e
+            .downcast_ref::<DataFusionError>()
+            .or_else(|| e.downcast_ref::<Arc<DataFusionError>>().map(|e| e.as_ref()))
+        {
+            Some(inner) => root_error(inner),
+            None => err,
+        },
+        _ => err,
+    }
+}
+
+/// Find the limit that a query failed on, if any
+pub fn limit_exceeded(err: &DataFusionError) -> Option<&QueryLimitExceeded> {
+    match root_error(err) {
+        DataFusionError::ArrowError(ArrowError::ExternalError(e))
+        | DataFusionError::External(e) => e.downcast_ref::<QueryLimitExceeded>(),
+        _ => None,
+    }
+}
+
+/// Parse a PostgreSQL-style timeout: a plain number is in milliseconds, but a unit
+/// (`ms`, `s`, `min` or `h`) can also be specified. Zero disables the timeout.
+pub fn parse_timeout(value: &str) -> Result<Option<Duration>> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount = amount.parse::<f64>().map_err(|_| {
+        DataFusionError::Plan(format!("Invalid {STATEMENT_TIMEOUT} value {value:?}"))
+    })?;
+    let seconds = match unit.trim() {
+        "" | "ms" => amount / 1000.0,
+        "s" => amount,
+        "min" => amount * 60.0,
+        "h" => amount * 3600.0,
+        unit => {
+            return Err(DataFusionError::Plan(format!(
+                "Invalid {STATEMENT_TIMEOUT} unit {unit:?}"
+            )))
+        }
+    };
+
+    if seconds >= u64::MAX as f64 {
+        return Err(DataFusionError::Plan(format!(
+            "{STATEMENT_TIMEOUT} value {value:?} is too large"
+        )));
+    }
+
+    Ok(if seconds > 0.0 {
+        Some(Duration::from_secs_f64(seconds))
+    } else {
+        None
+    })
+}
+
+/// If this is a `SET statement_timeout = ...` statement, return the new timeout
+pub fn set_statement_timeout(statement: &Statement) -> Option<Result<Option<Duration>>> {
+    match statement {
+        Statement::SetVariable {
+            variable, value, ..
+        } if variable.to_string().to_lowercase() == STATEMENT_TIMEOUT => {
+            Some(match value.as_slice() {
+                [Expr::Value(Value::Number(n, _))] => parse_timeout(n),
+                [Expr::Value(Value::SingleQuotedString(s))] => parse_timeout(s),
+                [Expr::Identifier(i)] if i.value.to_lowercase() == "default" => Ok(None),
+                _ => Err(DataFusionError::Plan(format!(
+                    "Invalid {STATEMENT_TIMEOUT} value"
+                ))),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Same as `set_statement_timeout`, for statements parsed by Seafowl
+pub fn statement_timeout(statement: &DFStatement) -> Option<Result<Option<Duration>>> {
+    match statement {
+        DFStatement::Statement(s) => set_statement_timeout(s),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use datafusion::execution::memory_manager::{MemoryManager, MemoryManagerConfig};
+
+    use crate::datafusion::parser::DFParser;
+
+    use super::{
+        limit_exceeded, parse_timeout, statement_timeout, Limits, QueryLimitExceeded,
+        QueryMemory,
+    };
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(
+            parse_timeout("1500").unwrap(),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_timeout("5s").unwrap(), Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_timeout("2min").unwrap(),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_timeout("0").unwrap(), None);
+        assert!(parse_timeout("5 parsecs").is_err());
+        assert!(parse_timeout("soon").is_err());
+    }
+
+    #[test]
+    fn test_statement_timeout() {
+        let statements =
+            DFParser::parse_sql("SET statement_timeout = '10s'; SELECT 1").unwrap();
+
+        assert_eq!(
+            statement_timeout(&statements[0]).unwrap().unwrap(),
+            Some(Duration::from_secs(10))
+        );
+        assert!(statement_timeout(&statements[1]).is_none());
+    }
+
+    #[test]
+    fn test_requested_timeout_only_tightens() {
+        let limits = Limits {
+            timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            limits
+                .clone()
+                .with_requested_timeout(Some(Duration::from_secs(5)))
+                .timeout,
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            limits
+                .with_requested_timeout(Some(Duration::from_secs(60)))
+                .timeout,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            Limits::default()
+                .with_requested_timeout(Some(Duration::from_secs(60)))
+                .timeout,
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limits_exceeded() {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(10)),
+            max_rows: Some(10),
+            max_bytes: None,
+        };
+
+        assert!(limits.check_results(10, 1000).is_ok());
+        let err = limits.check_results(11, 1000).unwrap_err();
+        assert_eq!(limit_exceeded(&err), Some(&QueryLimitExceeded::MaxRows(10)));
+
+        let err = limits
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            limit_exceeded(&err),
+            Some(&QueryLimitExceeded::Timeout(Duration::from_millis(10)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_memory_reserved_from_shared_pool() {
+        let shared =
+            MemoryManager::new(MemoryManagerConfig::try_new_limit(1000, 1.0).unwrap());
+
+        let memory = QueryMemory::reserve(shared.clone(), 600).await.unwrap();
+        assert_eq!(shared.get_requester_total(), 600);
+
+        // Two queries limited to 600 bytes can't run at the same time in a 1000 byte pool
+        let err = QueryMemory::reserve(shared.clone(), 600)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            limit_exceeded(&err),
+            Some(&QueryLimitExceeded::MemoryPoolFull(600))
+        );
+
+        drop(memory);
+        assert_eq!(shared.get_requester_total(), 0);
+        assert!(QueryMemory::reserve(shared, 600).await.is_ok());
+    }
+}
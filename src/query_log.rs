@@ -0,0 +1,423 @@
+//! In-memory log of the statements executed through the frontends. The log is a
+//! bounded ring buffer (exposed as `system.queries`) that also keeps a handle for every
+//! statement that's still running, so that it can be cancelled by its ID.
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arrow::array::{ArrayRef, BooleanArray, Int64Array};
+use arrow::datatypes::DataType;
+use arrow::record_batch::RecordBatch;
+use chrono::Utc;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::logical_expr::{ScalarUDF, Volatility};
+use datafusion::physical_plan::functions::make_scalar_function;
+use datafusion::prelude::create_udf;
+use futures::future::{abortable, AbortHandle};
+use log::info;
+use parking_lot::{Mutex, RwLock};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::auth::Principal;
+use crate::metrics::{QUERIES, QUERY_BYTES, QUERY_DURATION, QUERY_ROWS};
+use crate::system_tables::{QUERIES as QUERIES_TABLE, SYSTEM_SCHEMA};
+
+pub type QueryId = i64;
+
+pub const QUERY_RUNNING: &str = "running";
+pub const QUERY_SUCCEEDED: &str = "succeeded";
+pub const QUERY_FAILED: &str = "failed";
+pub const QUERY_CANCELLED: &str = "cancelled";
+
+pub const PG_CANCEL_BACKEND: &str = "pg_cancel_backend";
+
+const DEFAULT_CAPACITY: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frontend {
+    Http,
+    Postgres,
+    Flight,
+}
+
+impl Frontend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Frontend::Http => "http",
+            Frontend::Postgres => "postgres",
+            Frontend::Flight => "flight",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryLogEntry {
+    pub id: QueryId,
+    pub frontend: Frontend,
+    pub query: String,
+    pub principal: Principal,
+    pub status: &'static str,
+    // Milliseconds since the epoch
+    pub start_time: i64,
+    pub end_time: Option<i64>,
+    pub rows: Option<i64>,
+    pub bytes: Option<i64>,
+    pub error: Option<String>,
+}
+
+pub struct QueryLog {
+    capacity: usize,
+    next_id: AtomicI64,
+    entries: RwLock<VecDeque<QueryLogEntry>>,
+    running: Mutex<HashMap<QueryId, AbortHandle>>,
+}
+
+impl Default for QueryLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl QueryLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_id: AtomicI64::new(1),
+            entries: RwLock::new(VecDeque::with_capacity(capacity)),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Snapshot of all entries currently in the log, oldest first
+    pub fn entries(&self) -> Vec<QueryLogEntry> {
+        self.entries.read().iter().cloned().collect()
+    }
+
+    /// Abort a running query, returning false if there's no such query
+    pub fn cancel(&self, id: QueryId) -> bool {
+        match self.running.lock().remove(&id) {
+            Some(handle) => {
+                info!("Cancelling query {}", id);
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn start(
+        &self,
+        frontend: Frontend,
+        query: &str,
+        principal: &Principal,
+        handle: AbortHandle,
+    ) -> QueryId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.running.lock().insert(id, handle);
+
+        let mut entries = self.entries.write();
+        if self.capacity > 0 && entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(QueryLogEntry {
+            id,
+            frontend,
+            query: query.to_string(),
+            principal: principal.clone(),
+            status: QUERY_RUNNING,
+            start_time: Utc::now().timestamp_millis(),
+            end_time: None,
+            rows: None,
+            bytes: None,
+            error: None,
+        });
+
+        id
+    }
+
+    fn finish(
+        &self,
+        id: QueryId,
+        frontend: Frontend,
+        duration: Duration,
+        // Number of rows and bytes the statement returned
+        result: std::result::Result<(i64, i64), &DataFusionError>,
+        cancelled: bool,
+    ) {
+        self.running.lock().remove(&id);
+
+        let (status, rows, bytes) = match result {
+            Ok((rows, bytes)) => (QUERY_SUCCEEDED, Some(rows), Some(bytes)),
+            Err(_) if cancelled => (QUERY_CANCELLED, None, None),
+            Err(_) => (QUERY_FAILED, None, None),
+        };
+        let end_time = Utc::now().timestamp_millis();
+        Self::record_metrics(frontend, status, duration, rows, bytes);
+
+        // The entry might have been evicted from the buffer while the query was running
+        let mut entries = self.entries.write();
+        if let Some(entry) = entries.iter_mut().rev().find(|e| e.id == id) {
+            entry.status = status;
+            entry.end_time = Some(end_time);
+            entry.rows = rows;
+            entry.bytes = bytes;
+            entry.error = result.err().map(|e| e.to_string());
+        }
+    }
+
+    fn record_metrics(
+        frontend: Frontend,
+        status: &str,
+        duration: Duration,
+        rows: Option<i64>,
+        bytes: Option<i64>,
+    ) {
+        QUERIES.with_label_values(&[frontend.name(), status]).inc();
+        QUERY_DURATION
+            .with_label_values(&[frontend.name(), status])
+            .observe(duration.as_secs_f64());
+        if let Some(rows) = rows {
+            QUERY_ROWS
+                .with_label_values(&[frontend.name()])
+                .inc_by(rows as u64);
+        }
+        if let Some(bytes) = bytes {
+            QUERY_BYTES
+                .with_label_values(&[frontend.name()])
+                .inc_by(bytes as u64);
+        }
+    }
+
+    /// Run a statement (planning and execution), recording it in the log. The statement
+    /// can be cancelled with `cancel` while it's running, in which case the future
+    /// (and the DataFusion stream it's polling) is dropped and an error is returned.
+    pub async fn track<F>(
+        &self,
+        frontend: Frontend,
+        query: &str,
+        principal: &Principal,
+        future: F,
+    ) -> Result<Vec<RecordBatch>>
+    where
+        F: Future<Output = Result<Vec<RecordBatch>>>,
+    {
+        self.run(frontend, query, principal, future, |batches| {
+            (
+                batches.iter().map(|b| b.num_rows() as i64).sum(),
+                batches
+                    .iter()
+                    .flat_map(|b| b.columns())
+                    .map(|c| c.get_array_memory_size() as i64)
+                    .sum(),
+            )
+        })
+        .await
+    }
+
+    /// Same as `track`, but for statements that send their results to the client as
+    /// they're produced instead of collecting them. The future returns the number of rows
+    /// and bytes it sent.
+    pub async fn track_streamed<F>(
+        &self,
+        frontend: Frontend,
+        query: &str,
+        principal: &Principal,
+        future: F,
+    ) -> Result<(i64, i64)>
+    where
+        F: Future<Output = Result<(i64, i64)>>,
+    {
+        self.run(frontend, query, principal, future, |size| *size)
+            .await
+    }
+
+    async fn run<F, T>(
+        &self,
+        frontend: Frontend,
+        query: &str,
+        principal: &Principal,
+        future: F,
+        result_size: impl Fn(&T) -> (i64, i64),
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let (future, handle) = abortable(future);
+        let id = self.start(frontend, query, principal, handle);
+        let start = Instant::now();
+
+        let (result, cancelled) = match future.await {
+            Ok(result) => (result, false),
+            Err(_) => (
+                Err(DataFusionError::Execution(format!(
+                    "Query {id} was cancelled"
+                ))),
+                true,
+            ),
+        };
+
+        let size = result.as_ref().map(result_size);
+        self.finish(id, frontend, start.elapsed(), size, cancelled);
+        result
+    }
+}
+
+/// Build the `pg_cancel_backend(query_id)` function, which cancels a running query
+/// from the query log and returns whether it was found
+pub fn pg_cancel_backend_udf(query_log: Arc<QueryLog>) -> ScalarUDF {
+    let cancel = make_scalar_function(move |args: &[ArrayRef]| {
+        let ids = args[0]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| {
+                DataFusionError::Internal(format!(
+                    "Expected {PG_CANCEL_BACKEND} argument to be an Int64"
+                ))
+            })?;
+
+        let result: BooleanArray = ids
+            .iter()
+            .map(|id| id.map(|id| query_log.cancel(id)))
+            .collect();
+        Ok(Arc::new(result) as ArrayRef)
+    });
+
+    create_udf(
+        PG_CANCEL_BACKEND,
+        vec![DataType::Int64],
+        Arc::new(DataType::Boolean),
+        Volatility::Volatile,
+        cancel,
+    )
+}
+
+/// Whether a statement reads the query log (`system.queries`) or cancels queries with
+/// `pg_cancel_backend`. The log contains other users' queries, so the frontends treat
+/// these statements as writes. We look at the tokens instead of the plan to also catch
+/// the ones in subqueries (but not in string literals); if the statement can't be
+/// tokenized, we assume it does access the log.
+pub fn accesses_query_log(sql: &str) -> bool {
+    let tokens = match Tokenizer::new(&GenericDialect {}, sql).tokenize() {
+        Ok(tokens) => tokens,
+        Err(_) => return true,
+    };
+    let tokens = tokens
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .collect::<Vec<_>>();
+
+    let is_word = |token: &Token, value: &str| matches!(token, Token::Word(word) if word.value.eq_ignore_ascii_case(value));
+    tokens.windows(3).any(|window| match window {
+        [name, Token::LParen, _] => is_word(name, PG_CANCEL_BACKEND),
+        [schema, Token::Period, table] => {
+            is_word(schema, SYSTEM_SCHEMA) && is_word(table, QUERIES_TABLE)
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use datafusion::error::Result;
+
+    use crate::auth::Principal;
+
+    use super::{
+        accesses_query_log, Frontend, QueryLog, QUERY_CANCELLED, QUERY_FAILED,
+        QUERY_RUNNING, QUERY_SUCCEEDED,
+    };
+
+    #[tokio::test]
+    async fn test_query_log_ring_buffer() {
+        let log = QueryLog::new(2);
+
+        for query in ["SELECT 1", "SELECT 2", "SELECT 3"] {
+            log.track(Frontend::Http, query, &Principal::Anonymous, async {
+                Ok(vec![])
+            })
+            .await
+            .unwrap();
+        }
+
+        let entries = log.entries();
+        assert_eq!(
+            entries.iter().map(|e| e.query.as_str()).collect::<Vec<_>>(),
+            vec!["SELECT 2", "SELECT 3"]
+        );
+        assert_eq!(entries[1].id, 3);
+        assert_eq!(entries[1].status, QUERY_SUCCEEDED);
+        assert_eq!(entries[1].rows, Some(0));
+        assert!(entries[1].end_time.is_some());
+
+        let result: Result<_> = log
+            .track(Frontend::Postgres, "SELECT 4", &Principal::Writer, async {
+                Err(datafusion::error::DataFusionError::Plan("oops".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+
+        let entries = log.entries();
+        assert_eq!(entries[1].status, QUERY_FAILED);
+        assert_eq!(entries[1].frontend, Frontend::Postgres);
+        assert_eq!(entries[1].principal, Principal::Writer);
+        assert!(entries[1].error.as_ref().unwrap().contains("oops"));
+    }
+
+    #[tokio::test]
+    async fn test_query_log_cancel() {
+        let log = Arc::new(QueryLog::default());
+
+        let log_clone = log.clone();
+        let task = tokio::spawn(async move {
+            log_clone
+                .track(
+                    Frontend::Http,
+                    "SELECT slow()",
+                    &Principal::Anonymous,
+                    async {
+                        tokio::time::sleep(Duration::from_secs(3600)).await;
+                        Ok(vec![])
+                    },
+                )
+                .await
+        });
+
+        // Wait for the query to show up in the log
+        while log.entries().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(log.entries()[0].status, QUERY_RUNNING);
+
+        assert!(log.cancel(1));
+        assert!(!log.cancel(1));
+
+        let result = task.await.unwrap();
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Query 1 was cancelled"));
+        assert_eq!(log.entries()[0].status, QUERY_CANCELLED);
+    }
+
+    #[test]
+    fn test_accesses_query_log() {
+        assert!(accesses_query_log("SELECT pg_cancel_backend(1)"));
+        assert!(accesses_query_log(
+            "SELECT * FROM t WHERE id IN (SELECT PG_CANCEL_BACKEND (id) FROM q)"
+        ));
+        assert!(accesses_query_log("SELECT * FROM system.queries"));
+        assert!(accesses_query_log(
+            "SELECT * FROM t JOIN \"default\".\"system\" . \"queries\" q ON t.id = q.query_id"
+        ));
+        assert!(!accesses_query_log("SELECT 'pg_cancel_backend(1)'"));
+        assert!(!accesses_query_log("SELECT pg_cancel_backend FROM t"));
+        assert!(!accesses_query_log("SELECT * FROM system.job_runs"));
+        assert!(!accesses_query_log("SELECT * FROM public.queries"));
+    }
+}
@@ -51,6 +51,7 @@
 pub struct RepositoryQueries {
     pub latest_table_versions: &'static str,
     pub all_table_versions: &'static str,
+    pub all_job_runs: &'static str,
 }
 
 #[macro_export]
@@ -114,7 +115,9 @@ impl Repository for $repo {
             "table".id AS table_id,
             desired_table_versions.id AS table_version_id,
             table_column.name AS column_name,
-            table_column.type AS column_type
+            table_column.type AS column_type,
+            table_column.distinct_count,
+            "table".writer_options
         FROM collection
         INNER JOIN "table" ON collection.id = "table".collection_id
         INNER JOIN desired_table_versions ON "table".id = desired_table_versions.table_id
@@ -163,6 +166,63 @@ impl Repository for $repo {
         Ok(partitions)
     }
 
+    async fn get_table_partition_distinct_sketches(
+        &self,
+        table_version_id: TableVersionId,
+    ) -> Result<Vec<TablePartitionDistinctSketchResult>, Error> {
+        let distinct_sketches = sqlx::query_as(
+            r#"SELECT
+            physical_partition.id AS table_partition_id,
+            physical_partition_column.name AS column_name,
+            physical_partition_column.distinct_sketch
+        FROM table_partition
+        INNER JOIN physical_partition ON physical_partition.id = table_partition.physical_partition_id
+        INNER JOIN physical_partition_column ON physical_partition_column.physical_partition_id = physical_partition.id
+        WHERE physical_partition_column.distinct_sketch IS NOT NULL AND table_partition.table_version_id = $1
+        ORDER BY table_partition_id, physical_partition_column.id
+        "#,
+        ).bind(table_version_id)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+        Ok(distinct_sketches)
+    }
+
+    async fn get_table_partition_bloom_filters(
+        &self,
+        table_version_id: TableVersionId,
+        column_names: &[String],
+    ) -> Result<Vec<TablePartitionBloomFilterResult>, Error> {
+        if column_names.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut builder: QueryBuilder<_> = QueryBuilder::new(r#"SELECT
+            physical_partition.id AS table_partition_id,
+            physical_partition_column.name AS column_name,
+            physical_partition_column.bloom_filter
+        FROM table_partition
+        INNER JOIN physical_partition ON physical_partition.id = table_partition.physical_partition_id
+        INNER JOIN physical_partition_column ON physical_partition_column.physical_partition_id = physical_partition.id
+        WHERE physical_partition_column.bloom_filter IS NOT NULL AND table_partition.table_version_id = "#);
+        builder.push_bind(table_version_id);
+
+        builder.push(" AND physical_partition_column.name IN (");
+        let mut separated = builder.separated(", ");
+        for column_name in column_names {
+            separated.push_bind(column_name);
+        }
+        separated.push_unseparated(")");
+
+        builder.push(" ORDER BY table_partition_id, physical_partition_column.id");
+
+        let bloom_filters = builder
+            .build_query_as()
+            .fetch_all(&self.executor)
+            .await
+            .map_err($repo::interpret_error)?;
+        Ok(bloom_filters)
+    }
+
     async fn create_database(&self, database_name: &str) -> Result<DatabaseId, Error> {
         let id = sqlx::query(r#"INSERT INTO database (name) VALUES ($1) RETURNING (id)"#)
             .bind(database_name)
@@ -227,13 +287,17 @@ impl Repository for $repo {
         collection_id: CollectionId,
         table_name: &str,
         schema: &Schema,
+        writer_options: &ParquetWriterOptions,
     ) -> Result<(TableId, TableVersionId), Error> {
+        let writer_options = serde_json::to_string(writer_options).expect("Couldn't serialize writer options!");
+
         // Create new (empty) table
         let new_table_id: i64 = sqlx::query(
-            r#"INSERT INTO "table" (collection_id, name) VALUES ($1, $2) RETURNING (id)"#,
+            r#"INSERT INTO "table" (collection_id, name, writer_options) VALUES ($1, $2, $3) RETURNING (id)"#,
         )
         .bind(collection_id)
         .bind(table_name)
+        .bind(writer_options)
         .fetch_one(&self.executor)
         .await.map_err($repo::interpret_error)?
         .try_get("id").map_err($repo::interpret_error)?;
@@ -288,6 +352,30 @@ impl Repository for $repo {
         Ok(delete_result.rows_affected())
     }
 
+    async fn delete_table_versions(
+        &self,
+        table_version_ids: Vec<TableVersionId>,
+    ) -> Result<u64, Error> {
+        if table_version_ids.is_empty() {
+            return Ok(0);
+        }
+
+        // We have to manually construct the query since SQLite doesn't have the proper Encode trait
+        let mut builder: QueryBuilder<_> = QueryBuilder::new(
+            "DELETE FROM table_version WHERE id IN (",
+        );
+        let mut separated = builder.separated(", ");
+        for id in table_version_ids.into_iter() {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+
+        let query = builder.build();
+        let delete_result = query.execute(&self.executor).await.map_err($repo::interpret_error)?;
+
+        Ok(delete_result.rows_affected())
+    }
+
     async fn create_partitions(
         &self,
         partitions: Vec<SeafowlPartition>,
@@ -321,14 +409,16 @@ impl Repository for $repo {
             .collect();
 
         let mut builder: QueryBuilder<_> =
-        QueryBuilder::new("INSERT INTO physical_partition_column(physical_partition_id, name, type, min_value, max_value, null_count) ");
+        QueryBuilder::new("INSERT INTO physical_partition_column(physical_partition_id, name, type, min_value, max_value, null_count, bloom_filter, distinct_sketch) ");
         builder.push_values(columns, |mut b, (rid, c)| {
             b.push_bind(rid)
                 .push_bind(c.name.as_ref())
                 .push_bind(c.r#type.as_ref())
                 .push_bind(c.min_value.as_ref())
                 .push_bind(c.max_value.as_ref())
-                .push_bind(c.null_count);
+                .push_bind(c.null_count)
+                .push_bind(c.bloom_filter.as_ref())
+                .push_bind(c.distinct_sketch.as_ref());
         });
 
         let query = builder.build();
@@ -408,14 +498,30 @@ impl Repository for $repo {
         .try_get("id").map_err($repo::interpret_error)?;
 
         sqlx::query(
-            "INSERT INTO table_column (table_version_id, name, type)
-            SELECT $2, name, type FROM table_column WHERE table_version_id = $1;",
+            "INSERT INTO table_column (table_version_id, name, type, distinct_count, distinct_count_table_version_id)
+            SELECT $2, name, type, distinct_count, distinct_count_table_version_id FROM table_column WHERE table_version_id = $1;",
         )
         .bind(from_version)
         .bind(new_version)
         .execute(&self.executor)
         .await.map_err($repo::interpret_error)?;
 
+        // Stop inheriting the distinct counts once the table has had more than
+        // MAX_DISTINCT_COUNT_AGE new versions since they were computed
+        sqlx::query(
+            "UPDATE table_column SET distinct_count = NULL, distinct_count_table_version_id = NULL
+            WHERE table_version_id = $1 AND distinct_count_table_version_id < (
+                SELECT id FROM table_version
+                WHERE table_id = (SELECT table_id FROM table_version WHERE id = $1)
+                ORDER BY id DESC
+                LIMIT 1 OFFSET $2
+            )",
+        )
+        .bind(new_version)
+        .bind(MAX_DISTINCT_COUNT_AGE)
+        .execute(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
         if inherit_partitions {
             sqlx::query(
                 "INSERT INTO table_partition (table_version_id, physical_partition_id)
@@ -430,6 +536,30 @@ impl Repository for $repo {
         Ok(new_version)
     }
 
+    async fn set_table_column_distinct_counts(
+        &self,
+        table_version_id: TableVersionId,
+        distinct_counts: &[(String, i64)],
+    ) -> Result<(), Error> {
+        // Don't leave the table with a mix of old and new counts if one of the updates fails
+        let mut tx = self.executor.begin().await.map_err($repo::interpret_error)?;
+
+        for (column_name, distinct_count) in distinct_counts {
+            sqlx::query(
+                "UPDATE table_column SET distinct_count = $1, distinct_count_table_version_id = $2
+                WHERE table_version_id = $2 AND name = $3",
+            )
+            .bind(distinct_count)
+            .bind(table_version_id)
+            .bind(column_name)
+            .execute(&mut tx)
+            .await.map_err($repo::interpret_error)?;
+        }
+
+        tx.commit().await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
     async fn get_all_table_versions(
         &self,
         database_name: &str,
@@ -491,6 +621,67 @@ impl Repository for $repo {
         Ok(table_partitions)
     }
 
+    async fn get_all_table_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<TableColumnsResult>> {
+        let table_columns = sqlx::query_as(
+            r#"
+            SELECT
+                collection.name AS collection_name,
+                "table".name AS table_name,
+                table_version.id AS table_version_id,
+                table_column.name AS column_name,
+                table_column.type AS column_type
+            FROM table_column
+            INNER JOIN table_version ON table_version.id = table_column.table_version_id
+            INNER JOIN "table" ON "table".id = table_version.table_id
+            INNER JOIN collection ON collection.id = "table".collection_id
+            INNER JOIN database ON database.id = collection.database_id
+            WHERE database.name = $1
+            ORDER BY collection_name, table_name, table_version_id, table_column.id;
+        "#)
+        .bind(database_name)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(table_columns)
+    }
+
+    async fn get_all_partition_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<PartitionColumnsResult>> {
+        let partition_columns = sqlx::query_as(
+            r#"
+            SELECT
+                collection.name AS collection_name,
+                "table".name AS table_name,
+                table_version.id AS table_version_id,
+                physical_partition.id AS table_partition_id,
+                physical_partition_column.name AS column_name,
+                physical_partition_column.type AS column_type,
+                physical_partition_column.min_value,
+                physical_partition_column.max_value,
+                physical_partition_column.null_count,
+                physical_partition_column.distinct_sketch
+            FROM table_version
+            INNER JOIN "table" ON "table".id = table_version.table_id
+            INNER JOIN collection ON collection.id = "table".collection_id
+            INNER JOIN database ON database.id = collection.database_id
+            INNER JOIN table_partition ON table_partition.table_version_id = table_version.id
+            INNER JOIN physical_partition ON physical_partition.id = table_partition.physical_partition_id
+            INNER JOIN physical_partition_column ON physical_partition_column.physical_partition_id = physical_partition.id
+            WHERE database.name = $1
+            ORDER BY collection_name, table_name, table_version_id, table_partition_id, physical_partition_column.id;
+        "#)
+        .bind(database_name)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(partition_columns)
+    }
+
     async fn move_table(
         &self,
         table_id: TableId,
@@ -513,14 +704,39 @@ impl Repository for $repo {
         database_id: DatabaseId,
         function_name: &str,
         details: &CreateFunctionDetails,
+        or_replace: bool,
     ) -> Result<FunctionId, Error> {
         let input_types = serde_json::to_string(&details.input_types).expect("Couldn't serialize input types!");
+        let return_columns = serde_json::to_string(&details.return_columns).expect("Couldn't serialize return columns!");
+        let limits = serde_json::to_string(&details.limits).expect("Couldn't serialize limits!");
+        let wasi = serde_json::to_string(&details.wasi).expect("Couldn't serialize WASI capabilities!");
 
-        let new_function_id: i64 = sqlx::query(
+        // Both SQLite and PostgreSQL support upserts with this syntax
+        let on_conflict = if or_replace {
             r#"
-        INSERT INTO "function" (database_id, name, entrypoint, language, input_types, return_type, data, volatility)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING (id);
-        "#)
+        ON CONFLICT (name, database_id, signature) DO UPDATE SET
+            entrypoint = EXCLUDED.entrypoint,
+            input_types = EXCLUDED.input_types,
+            language = EXCLUDED.language,
+            return_type = EXCLUDED.return_type,
+            data = EXCLUDED.data,
+            volatility = EXCLUDED.volatility,
+            kind = EXCLUDED.kind,
+            return_columns = EXCLUDED.return_columns,
+            limits = EXCLUDED.limits,
+            location = EXCLUDED.location,
+            sha256 = EXCLUDED.sha256,
+            wasi = EXCLUDED.wasi"#
+        } else {
+            ""
+        };
+
+        let new_function_id: i64 = sqlx::query(&format!(
+            r#"
+        INSERT INTO "function" (database_id, name, entrypoint, language, input_types, return_type, data, volatility, kind, return_columns, limits, location, sha256, wasi, signature)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15){on_conflict}
+        RETURNING (id);
+        "#))
             .bind(database_id)
             .bind(function_name)
             .bind(details.entrypoint.clone())
@@ -529,6 +745,13 @@ impl Repository for $repo {
             .bind(details.return_type.to_string())
             .bind(details.data.clone())
             .bind(details.volatility.to_string())
+            .bind(details.kind.to_string())
+            .bind(return_columns)
+            .bind(limits)
+            .bind(details.location.clone())
+            .bind(details.sha256.clone())
+            .bind(wasi)
+            .bind(details.signature())
             .fetch_one(&self.executor)
             .await.map_err($repo::interpret_error)?
             .try_get("id").map_err($repo::interpret_error)?;
@@ -550,9 +773,16 @@ impl Repository for $repo {
             input_types,
             return_type,
             data,
-            volatility
+            volatility,
+            kind,
+            return_columns,
+            limits,
+            location,
+            sha256,
+            wasi
         FROM function
-        WHERE database_id = $1;
+        WHERE database_id = $1
+        ORDER BY id;
         "#)
         .bind(database_id)
         .fetch_all(&self.executor)
@@ -561,6 +791,243 @@ impl Repository for $repo {
         Ok(functions)
     }
 
+    async fn get_all_function_locations(&self) -> Result<Vec<String>, Error> {
+        let locations = sqlx::query(
+            r#"SELECT DISTINCT location FROM "function" WHERE location IS NOT NULL"#
+        )
+            .fetch(&self.executor)
+            .map_ok(|row| row.get("location"))
+            .try_collect()
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(locations)
+    }
+
+    async fn create_external_table(
+        &self,
+        collection_id: CollectionId,
+        table_name: &str,
+        details: &ExternalTableDetails,
+        schema: &Schema,
+    ) -> Result<ExternalTableId, Error> {
+        let options = serde_json::to_string(&details.options).expect("Couldn't serialize external table options!");
+        let schema = schema_to_json(schema.arrow_schema.as_ref()).to_string();
+
+        let id = sqlx::query(
+            r#"
+        INSERT INTO external_table (collection_id, name, location, credentials, file_type, options, schema)
+        VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING (id);
+        "#)
+            .bind(collection_id)
+            .bind(table_name)
+            .bind(details.location.clone())
+            .bind(details.credentials.clone())
+            .bind(details.file_type.clone())
+            .bind(options)
+            .bind(schema)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?
+            .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_all_external_tables_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllExternalTablesResult>, Error> {
+        let external_tables = sqlx::query_as(
+            r#"
+        SELECT
+            collection.name AS collection_name,
+            external_table.id,
+            external_table.name,
+            external_table.location,
+            external_table.credentials,
+            external_table.file_type,
+            external_table.options,
+            external_table.schema
+        FROM external_table
+        INNER JOIN collection ON collection.id = external_table.collection_id
+        WHERE collection.database_id = $1
+        ORDER BY collection_name, external_table.name;
+        "#)
+        .bind(database_id)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(external_tables)
+    }
+
+    async fn drop_external_table(
+        &self,
+        external_table_id: ExternalTableId,
+    ) -> Result<(), Error> {
+        sqlx::query("DELETE FROM external_table WHERE id = $1 RETURNING id")
+            .bind(external_table_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> Result<TableId, Error> {
+        let schema = schema_to_json(schema.arrow_schema.as_ref()).to_string();
+
+        // Only replace existing views: if the name belongs to a regular table, the upsert
+        // doesn't touch any rows and we get a row not found error back
+        let on_conflict = if or_replace {
+            r#"
+        ON CONFLICT (name, collection_id) DO UPDATE SET
+            definition = EXCLUDED.definition,
+            schema = EXCLUDED.schema
+        WHERE "table".kind = 'view'"#
+        } else {
+            ""
+        };
+
+        let id = sqlx::query(&format!(
+            r#"
+        INSERT INTO "table" (collection_id, name, kind, definition, schema)
+        VALUES ($1, $2, 'view', $3, $4){on_conflict}
+        RETURNING (id);
+        "#))
+            .bind(collection_id)
+            .bind(view_name)
+            .bind(definition)
+            .bind(schema)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?
+            .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_all_views_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseViewsResult>, Error> {
+        let views = sqlx::query_as(
+            r#"
+        SELECT
+            collection.name AS collection_name,
+            "table".id,
+            "table".name,
+            "table".definition,
+            "table".schema
+        FROM "table"
+        INNER JOIN collection ON collection.id = "table".collection_id
+        WHERE collection.database_id = $1 AND "table".kind = 'view'
+        ORDER BY collection_name, "table".name;
+        "#)
+        .bind(database_id)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(views)
+    }
+
+    async fn set_materialized_view(
+        &self,
+        table_id: TableId,
+        definition: &str,
+        auto_refresh: bool,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+        UPDATE "table" SET kind = 'materialized_view', definition = $1, auto_refresh = $2
+        WHERE id = $3 AND kind != 'view'
+        RETURNING (id);
+        "#)
+            .bind(definition)
+            .bind(auto_refresh)
+            .bind(table_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(())
+    }
+
+    async fn get_all_materialized_views_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseMaterializedViewsResult>, Error> {
+        // Order by the table ID, so that a materialized view always comes after the
+        // materialized views it reads from
+        let views = sqlx::query_as(
+            r#"
+        SELECT
+            collection.name AS collection_name,
+            "table".id,
+            "table".name,
+            "table".definition,
+            "table".auto_refresh
+        FROM "table"
+        INNER JOIN collection ON collection.id = "table".collection_id
+        WHERE collection.database_id = $1 AND "table".kind = 'materialized_view'
+        ORDER BY "table".id;
+        "#)
+        .bind(database_id)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(views)
+    }
+
+    async fn create_job_run(&self, job_name: &str) -> Result<JobRunId, Error> {
+        let id = sqlx::query(
+            r#"INSERT INTO job_run (job_name, status) VALUES ($1, 'running') RETURNING (id)"#,
+        )
+        .bind(job_name)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?
+        .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn finish_job_run(
+        &self,
+        job_run_id: JobRunId,
+        status: &str,
+        duration_ms: i64,
+        error: Option<String>,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE job_run SET status = $1, duration_ms = $2, error = $3 WHERE id = $4 RETURNING id")
+            .bind(status)
+            .bind(duration_ms)
+            .bind(error)
+            .bind(job_run_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn get_all_job_runs(&self) -> Result<Vec<JobRunsResult>, Error> {
+        let job_runs = sqlx::query_as($repo::QUERIES.all_job_runs)
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(job_runs)
+    }
+
+    async fn delete_old_job_runs(&self, keep: u32) -> Result<u64, Error> {
+        let result = sqlx::query(
+            "DELETE FROM job_run WHERE id NOT IN (SELECT id FROM job_run ORDER BY id DESC LIMIT $1)",
+        )
+        .bind(keep as i64)
+        .execute(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(result.rows_affected())
+    }
+
     // Drop table/collection/database
     // Currently we actually delete these, though we could mark them as deleted
     // to allow for undeletion
@@ -583,6 +1050,14 @@ impl Repository for $repo {
         Ok(())
     }
 
+    async fn drop_function(&self, function_id: FunctionId) -> Result<(), Error> {
+        sqlx::query("DELETE FROM \"function\" WHERE id = $1 RETURNING id")
+            .bind(function_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
     async fn drop_database(&self, database_id: DatabaseId) -> Result<(), Error> {
         sqlx::query("DELETE FROM database WHERE id = $1 RETURNING id")
             .bind(database_id)
@@ -5,13 +5,18 @@ use async_trait::async_trait;
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 use crate::{
     data_types::{
-        CollectionId, DatabaseId, FunctionId, PhysicalPartitionId, TableId,
-        TableVersionId, Timestamp,
+        CollectionId, DatabaseId, ExternalTableDetails, ExternalTableId, FunctionId,
+        JobRunId, ParquetWriterOptions, PhysicalPartitionId, TableId, TableVersionId,
+        Timestamp,
     },
     provider::SeafowlPartition,
     schema::Schema,
 };
 
+/// Number of new table versions after which the distinct counts computed by an
+/// `ANALYZE TABLE` are considered out of date and stop being inherited
+pub const MAX_DISTINCT_COUNT_AGE: i64 = 10;
+
 #[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
 pub struct AllDatabaseColumnsResult {
     pub collection_name: String,
@@ -20,6 +25,8 @@ pub struct AllDatabaseColumnsResult {
     pub table_version_id: TableVersionId,
     pub column_name: String,
     pub column_type: String,
+    pub distinct_count: Option<i64>,
+    pub writer_options: Option<String>,
 }
 
 #[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
@@ -42,6 +49,39 @@ pub struct TablePartitionsResult {
     pub row_count: Option<i32>,
 }
 
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct TableColumnsResult {
+    pub collection_name: String,
+    pub table_name: String,
+    pub table_version_id: TableVersionId,
+    pub column_name: String,
+    pub column_type: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct PartitionColumnsResult {
+    pub collection_name: String,
+    pub table_name: String,
+    pub table_version_id: TableVersionId,
+    pub table_partition_id: i64,
+    pub column_name: String,
+    pub column_type: String,
+    pub min_value: Option<Vec<u8>>,
+    pub max_value: Option<Vec<u8>>,
+    pub null_count: Option<i32>,
+    pub distinct_sketch: Option<Vec<u8>>,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct JobRunsResult {
+    pub id: JobRunId,
+    pub job_name: String,
+    pub start_time: Timestamp,
+    pub duration_ms: Option<i64>,
+    pub status: String,
+    pub error: Option<String>,
+}
+
 #[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
 pub struct AllTablePartitionColumnsResult {
     pub table_partition_id: i64,
@@ -54,6 +94,20 @@ pub struct AllTablePartitionColumnsResult {
     pub null_count: Option<i32>,
 }
 
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct TablePartitionDistinctSketchResult {
+    pub table_partition_id: i64,
+    pub column_name: String,
+    pub distinct_sketch: Vec<u8>,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct TablePartitionBloomFilterResult {
+    pub table_partition_id: i64,
+    pub column_name: String,
+    pub bloom_filter: Vec<u8>,
+}
+
 #[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
 pub struct AllDatabaseFunctionsResult {
     pub name: String,
@@ -64,6 +118,42 @@ pub struct AllDatabaseFunctionsResult {
     pub return_type: String,
     pub data: String,
     pub volatility: String,
+    pub kind: String,
+    pub return_columns: String,
+    pub limits: String,
+    pub location: Option<String>,
+    pub sha256: Option<String>,
+    pub wasi: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllExternalTablesResult {
+    pub collection_name: String,
+    pub id: ExternalTableId,
+    pub name: String,
+    pub location: String,
+    pub credentials: Option<String>,
+    pub file_type: String,
+    pub options: String,
+    pub schema: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllDatabaseViewsResult {
+    pub collection_name: String,
+    pub id: TableId,
+    pub name: String,
+    pub definition: String,
+    pub schema: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllDatabaseMaterializedViewsResult {
+    pub collection_name: String,
+    pub id: TableId,
+    pub name: String,
+    pub definition: String,
+    pub auto_refresh: bool,
 }
 
 /// Wrapper for conversion of database-specific error codes into actual errors
@@ -98,6 +188,17 @@ pub trait Repository: Send + Sync + Debug {
         table_version_id: TableVersionId,
     ) -> Result<Vec<AllTablePartitionColumnsResult>, Error>;
 
+    async fn get_table_partition_distinct_sketches(
+        &self,
+        table_version_id: TableVersionId,
+    ) -> Result<Vec<TablePartitionDistinctSketchResult>, Error>;
+
+    async fn get_table_partition_bloom_filters(
+        &self,
+        table_version_id: TableVersionId,
+        column_names: &[String],
+    ) -> Result<Vec<TablePartitionBloomFilterResult>, Error>;
+
     async fn get_collection_id_by_name(
         &self,
         database_name: &str,
@@ -122,6 +223,7 @@ pub trait Repository: Send + Sync + Debug {
         collection_id: CollectionId,
         table_name: &str,
         schema: &Schema,
+        writer_options: &ParquetWriterOptions,
     ) -> Result<(TableId, TableVersionId), Error>;
 
     async fn delete_old_table_versions(
@@ -129,6 +231,11 @@ pub trait Repository: Send + Sync + Debug {
         table_id: Option<TableId>,
     ) -> Result<u64, Error>;
 
+    async fn delete_table_versions(
+        &self,
+        table_version_ids: Vec<TableVersionId>,
+    ) -> Result<u64, Error>;
+
     async fn create_partitions(
         &self,
         partition: Vec<SeafowlPartition>,
@@ -153,6 +260,12 @@ pub trait Repository: Send + Sync + Debug {
         inherit_partitions: bool,
     ) -> Result<TableVersionId, Error>;
 
+    async fn set_table_column_distinct_counts(
+        &self,
+        table_version_id: TableVersionId,
+        distinct_counts: &[(String, i64)],
+    ) -> Result<(), Error>;
+
     async fn get_all_table_versions(
         &self,
         database_name: &str,
@@ -164,6 +277,16 @@ pub trait Repository: Send + Sync + Debug {
         database_name: &str,
     ) -> Result<Vec<TablePartitionsResult>>;
 
+    async fn get_all_table_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<TableColumnsResult>>;
+
+    async fn get_all_partition_columns(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<PartitionColumnsResult>>;
+
     async fn move_table(
         &self,
         table_id: TableId,
@@ -171,11 +294,14 @@ pub trait Repository: Send + Sync + Debug {
         new_collection_id: Option<CollectionId>,
     ) -> Result<(), Error>;
 
+    // Create a function, or replace the function with the same name and input types
+    // (keeping its ID) if `or_replace` is set
     async fn create_function(
         &self,
         database_id: DatabaseId,
         function_name: &str,
         details: &CreateFunctionDetails,
+        or_replace: bool,
     ) -> Result<FunctionId, Error>;
 
     async fn get_all_functions_in_database(
@@ -183,10 +309,72 @@ pub trait Repository: Send + Sync + Debug {
         database_id: DatabaseId,
     ) -> Result<Vec<AllDatabaseFunctionsResult>, Error>;
 
+    async fn get_all_function_locations(&self) -> Result<Vec<String>, Error>;
+
+    async fn create_external_table(
+        &self,
+        collection_id: CollectionId,
+        table_name: &str,
+        details: &ExternalTableDetails,
+        schema: &Schema,
+    ) -> Result<ExternalTableId, Error>;
+
+    async fn get_all_external_tables_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllExternalTablesResult>, Error>;
+
+    async fn drop_external_table(
+        &self,
+        external_table_id: ExternalTableId,
+    ) -> Result<(), Error>;
+
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> Result<TableId, Error>;
+
+    async fn get_all_views_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseViewsResult>, Error>;
+
+    async fn set_materialized_view(
+        &self,
+        table_id: TableId,
+        definition: &str,
+        auto_refresh: bool,
+    ) -> Result<(), Error>;
+
+    async fn get_all_materialized_views_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseMaterializedViewsResult>, Error>;
+
+    async fn create_job_run(&self, job_name: &str) -> Result<JobRunId, Error>;
+
+    async fn finish_job_run(
+        &self,
+        job_run_id: JobRunId,
+        status: &str,
+        duration_ms: i64,
+        error: Option<String>,
+    ) -> Result<(), Error>;
+
+    async fn get_all_job_runs(&self) -> Result<Vec<JobRunsResult>, Error>;
+
+    async fn delete_old_job_runs(&self, keep: u32) -> Result<u64, Error>;
+
     async fn drop_table(&self, table_id: TableId) -> Result<(), Error>;
 
     async fn drop_collection(&self, collection_id: CollectionId) -> Result<(), Error>;
 
+    async fn drop_function(&self, function_id: FunctionId) -> Result<(), Error>;
+
     async fn drop_database(&self, database_id: DatabaseId) -> Result<(), Error>;
 }
 
@@ -198,9 +386,15 @@ pub mod tests {
         DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema,
     };
 
+    use std::collections::HashMap;
+
+    use arrow_integration_test::schema_from_json;
+
+    use crate::data_types::ExternalTableOptions;
     use crate::provider::PartitionColumn;
     use crate::wasm_udf::data_types::{
-        CreateFunctionDataType, CreateFunctionLanguage, CreateFunctionVolatility,
+        CreateFunctionColumn, CreateFunctionDataType, CreateFunctionKind,
+        CreateFunctionLanguage, CreateFunctionVolatility, WasiCapabilities, WasmLimits,
     };
 
     use super::*;
@@ -220,6 +414,8 @@ pub mod tests {
                     min_value: Arc::new(None),
                     max_value: Arc::new(None),
                     null_count: Some(1),
+                    bloom_filter: Arc::new(None),
+                    distinct_sketch: Arc::new(None),
                 },
                 PartitionColumn {
                     name: Arc::from("integer".to_string()),
@@ -230,6 +426,8 @@ pub mod tests {
                     min_value: Arc::new(Some([49, 50].to_vec())),
                     max_value: Arc::new(Some([52, 50].to_vec())),
                     null_count: Some(0),
+                    bloom_filter: Arc::new(None),
+                    distinct_sketch: Arc::new(None),
                 },
                 PartitionColumn {
                     name: Arc::from("varchar".to_string()),
@@ -237,6 +435,8 @@ pub mod tests {
                     min_value: Arc::new(None),
                     max_value: Arc::new(None),
                     null_count: None,
+                    bloom_filter: Arc::new(Some([1, 2, 3].to_vec())),
+                    distinct_sketch: Arc::new(Some([4, 5].to_vec())),
                 },
             ]),
         }
@@ -263,7 +463,12 @@ pub mod tests {
         };
 
         let (table_id, table_version_id) = repository
-            .create_table(collection_id, "testtable", &schema)
+            .create_table(
+                collection_id,
+                "testtable",
+                &schema,
+                &ParquetWriterOptions::default(),
+            )
             .await
             .expect("Error creating table");
 
@@ -277,8 +482,18 @@ pub mod tests {
         let new_version_id =
             test_create_append_partition(repository.clone(), table_version_id).await;
         test_create_functions(repository.clone(), database_id).await;
+        test_external_tables(repository.clone(), database_id).await;
+        test_views(repository.clone(), database_id).await;
+        test_materialized_views(repository.clone(), database_id, table_id).await;
         test_rename_table(repository.clone(), database_id, table_id, new_version_id)
             .await;
+        test_table_column_distinct_counts(
+            repository.clone(),
+            database_id,
+            new_version_id,
+        )
+        .await;
+        test_job_runs(repository.clone()).await;
         test_error_propagation(repository, table_id).await;
     }
 
@@ -297,6 +512,9 @@ pub mod tests {
         collection_name: String,
         table_name: String,
     ) -> Vec<AllDatabaseColumnsResult> {
+        let writer_options =
+            Some(serde_json::to_string(&ParquetWriterOptions::default()).unwrap());
+
         vec![
             AllDatabaseColumnsResult {
                 collection_name: collection_name.clone(),
@@ -305,6 +523,8 @@ pub mod tests {
                 table_version_id: version,
                 column_name: "date".to_string(),
                 column_type: "{\"children\":[],\"name\":\"date\",\"nullable\":false,\"type\":{\"name\":\"date\",\"unit\":\"MILLISECOND\"}}".to_string(),
+                distinct_count: None,
+                writer_options: writer_options.clone(),
             },
             AllDatabaseColumnsResult {
                 collection_name,
@@ -314,6 +534,8 @@ pub mod tests {
                 column_name: "value".to_string(),
                 column_type: "{\"children\":[],\"name\":\"value\",\"nullable\":false,\"type\":{\"name\":\"floatingpoint\",\"precision\":\"DOUBLE\"}}"
                     .to_string(),
+                distinct_count: None,
+                writer_options,
             },
         ]
     }
@@ -448,6 +670,42 @@ pub mod tests {
         ];
         assert_eq!(all_partitions, expected_partitions);
 
+        // The distinct value sketches are loaded separately too
+        let distinct_sketches = repository
+            .get_table_partition_distinct_sketches(table_version_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            distinct_sketches,
+            vec![TablePartitionDistinctSketchResult {
+                table_partition_id: *partition_id,
+                column_name: "varchar".to_string(),
+                distinct_sketch: [4, 5].to_vec(),
+            }]
+        );
+
+        // Only the requested Bloom filters get loaded
+        let bloom_filters = repository
+            .get_table_partition_bloom_filters(
+                table_version_id,
+                &["integer".to_string(), "varchar".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            bloom_filters,
+            vec![TablePartitionBloomFilterResult {
+                table_partition_id: *partition_id,
+                column_name: "varchar".to_string(),
+                bloom_filter: [1, 2, 3].to_vec(),
+            }]
+        );
+        assert!(repository
+            .get_table_partition_bloom_filters(table_version_id, &["integer".to_string()])
+            .await
+            .unwrap()
+            .is_empty());
+
         // Duplicate the table, check it has the same partitions
         let new_version_id = repository
             .create_new_table_version(table_version_id, true)
@@ -482,8 +740,49 @@ pub mod tests {
                     ],
                     return_type: CreateFunctionDataType::INT,
                     data: "data".to_string(),
+                    location: None,
+                    sha256: None,
                     volatility: CreateFunctionVolatility::Volatile,
+                    kind: CreateFunctionKind::Scalar,
+                    return_columns: vec![],
+                    limits: WasmLimits {
+                        max_fuel: Some(1000000),
+                        timeout_ms: None,
+                        max_memory: Some(16),
+                    },
+                    wasi: WasiCapabilities {
+                        enabled: true,
+                        clocks: true,
+                        ..WasiCapabilities::default()
+                    },
                 },
+                false,
+            )
+            .await
+            .unwrap();
+
+        let table_function_id = repository
+            .create_function(
+                database_id,
+                "testtablefun",
+                &CreateFunctionDetails {
+                    entrypoint: "entrypoint".to_string(),
+                    language: CreateFunctionLanguage::WasmMessagePack,
+                    input_types: vec![CreateFunctionDataType::TEXT],
+                    return_type: CreateFunctionDataType::TABLE,
+                    data: "data".to_string(),
+                    location: None,
+                    sha256: None,
+                    volatility: CreateFunctionVolatility::Volatile,
+                    kind: CreateFunctionKind::Scalar,
+                    return_columns: vec![CreateFunctionColumn {
+                        name: "token".to_string(),
+                        data_type: CreateFunctionDataType::TEXT,
+                    }],
+                    limits: WasmLimits::default(),
+                    wasi: WasiCapabilities::default(),
+                },
+                false,
             )
             .await
             .unwrap();
@@ -494,17 +793,363 @@ pub mod tests {
             .await
             .unwrap();
 
-        let expected_functions = vec![AllDatabaseFunctionsResult {
-            name: "testfun".to_string(),
-            id: function_id,
-            entrypoint: "entrypoint".to_string(),
-            language: "Wasm".to_string(),
-            input_types: r#"["float","bigint"]"#.to_string(),
-            return_type: "INT".to_string(),
-            data: "data".to_string(),
-            volatility: "Volatile".to_string(),
-        }];
+        let expected_functions = vec![
+            AllDatabaseFunctionsResult {
+                name: "testfun".to_string(),
+                id: function_id,
+                entrypoint: "entrypoint".to_string(),
+                language: "Wasm".to_string(),
+                input_types: r#"["float","bigint"]"#.to_string(),
+                return_type: "INT".to_string(),
+                data: "data".to_string(),
+                volatility: "Volatile".to_string(),
+                kind: "Scalar".to_string(),
+                return_columns: "[]".to_string(),
+                limits: r#"{"max_fuel":1000000,"max_memory":16}"#.to_string(),
+                location: None,
+                sha256: None,
+                wasi: r#"{"enabled":true,"clocks":true}"#.to_string(),
+            },
+            AllDatabaseFunctionsResult {
+                name: "testtablefun".to_string(),
+                id: table_function_id,
+                entrypoint: "entrypoint".to_string(),
+                language: "WasmMessagePack".to_string(),
+                input_types: r#"["text"]"#.to_string(),
+                return_type: "TABLE".to_string(),
+                data: "data".to_string(),
+                volatility: "Volatile".to_string(),
+                kind: "Scalar".to_string(),
+                return_columns: r#"[{"name":"token","type":"text"}]"#.to_string(),
+                limits: "{}".to_string(),
+                location: None,
+                sha256: None,
+                wasi: "{}".to_string(),
+            },
+        ];
         assert_eq!(all_functions, expected_functions);
+
+        // Functions can be overloaded by their input types...
+        let overload = |input_types: Vec<CreateFunctionDataType>, data: &str| {
+            CreateFunctionDetails {
+                entrypoint: "entrypoint".to_string(),
+                language: CreateFunctionLanguage::Wasm,
+                input_types,
+                return_type: CreateFunctionDataType::INT,
+                data: data.to_string(),
+                location: None,
+                sha256: None,
+                volatility: CreateFunctionVolatility::Volatile,
+                kind: CreateFunctionKind::Scalar,
+                return_columns: vec![],
+                limits: WasmLimits::default(),
+                wasi: WasiCapabilities::default(),
+            }
+        };
+        let overload_id = repository
+            .create_function(
+                database_id,
+                "testfun",
+                &overload(vec![CreateFunctionDataType::INT], "data"),
+                false,
+            )
+            .await
+            .unwrap();
+
+        // ...but not redefined with the same ones (even if they're spelled differently),
+        // unless they're being replaced
+        assert!(matches!(
+            repository
+                .create_function(
+                    database_id,
+                    "testfun",
+                    &overload(vec![CreateFunctionDataType::I32], "new_data"),
+                    false,
+                )
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+        assert_eq!(
+            repository
+                .create_function(
+                    database_id,
+                    "testfun",
+                    &CreateFunctionDetails {
+                        location: Some("seafowl://functions/abcdef.wasm".to_string()),
+                        sha256: Some("abcdef".to_string()),
+                        ..overload(vec![CreateFunctionDataType::INT], "")
+                    },
+                    true,
+                )
+                .await
+                .unwrap(),
+            overload_id
+        );
+
+        let all_functions = repository
+            .get_all_functions_in_database(database_id)
+            .await
+            .unwrap();
+        assert_eq!(all_functions.len(), 3);
+        assert_eq!(all_functions[2].id, overload_id);
+        assert_eq!(all_functions[2].input_types, r#"["int"]"#);
+        assert_eq!(all_functions[2].data, "");
+        assert_eq!(
+            all_functions[2].location,
+            Some("seafowl://functions/abcdef.wasm".to_string())
+        );
+        assert_eq!(all_functions[2].sha256, Some("abcdef".to_string()));
+        assert_eq!(
+            repository.get_all_function_locations().await.unwrap(),
+            vec!["seafowl://functions/abcdef.wasm".to_string()]
+        );
+
+        // Drop the overload
+        repository.drop_function(overload_id).await.unwrap();
+        assert!(repository.drop_function(overload_id).await.is_err());
+        assert!(repository
+            .get_all_function_locations()
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            repository
+                .get_all_functions_in_database(database_id)
+                .await
+                .unwrap(),
+            expected_functions
+        );
+    }
+
+    async fn test_external_tables(
+        repository: Arc<dyn Repository>,
+        database_id: DatabaseId,
+    ) {
+        let collection_id = repository
+            .get_collection_id_by_name("testdb", "testcol")
+            .await
+            .unwrap();
+
+        let details = ExternalTableDetails {
+            location: "s3://bucket/file.parquet".to_string(),
+            credentials: Some("key:secret".to_string()),
+            file_type: "PARQUET".to_string(),
+            options: ExternalTableOptions {
+                has_header: false,
+                delimiter: ',',
+                table_partition_cols: vec![],
+                file_compression_type: "".to_string(),
+                filter_suffix: true,
+                options: HashMap::new(),
+            },
+        };
+        let schema = Schema {
+            arrow_schema: Arc::new(ArrowSchema::new(vec![ArrowField::new(
+                "value",
+                ArrowDataType::Int32,
+                true,
+            )])),
+        };
+
+        let external_table_id = repository
+            .create_external_table(collection_id, "testexternal", &details, &schema)
+            .await
+            .unwrap();
+
+        // Can't have two external tables with the same name in a collection
+        assert!(matches!(
+            repository
+                .create_external_table(collection_id, "testexternal", &details, &schema)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+
+        let mut all_external_tables = repository
+            .get_all_external_tables_in_database(database_id)
+            .await
+            .unwrap();
+        assert_eq!(all_external_tables.len(), 1);
+
+        // The schema is stored as JSON, so parse it back instead of comparing strings
+        let result = all_external_tables.pop().unwrap();
+        assert_eq!(
+            schema_from_json(&serde_json::from_str(&result.schema).unwrap()).unwrap(),
+            *schema.arrow_schema
+        );
+        assert_eq!(
+            result,
+            AllExternalTablesResult {
+                collection_name: "testcol".to_string(),
+                id: external_table_id,
+                name: "testexternal".to_string(),
+                location: "s3://bucket/file.parquet".to_string(),
+                credentials: Some("key:secret".to_string()),
+                file_type: "PARQUET".to_string(),
+                options: r#"{"has_header":false,"delimiter":",","table_partition_cols":[],"file_compression_type":"","filter_suffix":true,"options":{}}"#.to_string(),
+                schema: result.schema.clone(),
+            }
+        );
+
+        repository
+            .drop_external_table(external_table_id)
+            .await
+            .unwrap();
+        assert!(repository
+            .get_all_external_tables_in_database(database_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    async fn test_views(repository: Arc<dyn Repository>, database_id: DatabaseId) {
+        let collection_id = repository
+            .get_collection_id_by_name("testdb", "testcol")
+            .await
+            .unwrap();
+
+        let schema = Schema {
+            arrow_schema: Arc::new(ArrowSchema::new(vec![ArrowField::new(
+                "value",
+                ArrowDataType::Float64,
+                false,
+            )])),
+        };
+
+        let view_id = repository
+            .create_view(
+                collection_id,
+                "testview",
+                "SELECT value FROM testtable",
+                &schema,
+                false,
+            )
+            .await
+            .unwrap();
+
+        // Can't create a view with the same name without OR REPLACE...
+        assert!(matches!(
+            repository
+                .create_view(collection_id, "testview", "SELECT 1", &schema, false)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+
+        // ...and can't replace a regular table with a view
+        assert!(matches!(
+            repository
+                .create_view(collection_id, "testtable", "SELECT 1", &schema, true)
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+
+        // Replacing the view keeps its ID
+        assert_eq!(
+            repository
+                .create_view(
+                    collection_id,
+                    "testview",
+                    "SELECT value * 2 AS value FROM testtable",
+                    &schema,
+                    true,
+                )
+                .await
+                .unwrap(),
+            view_id
+        );
+
+        let mut all_views = repository
+            .get_all_views_in_database(database_id)
+            .await
+            .unwrap();
+        assert_eq!(all_views.len(), 1);
+
+        let result = all_views.pop().unwrap();
+        assert_eq!(
+            schema_from_json(&serde_json::from_str(&result.schema).unwrap()).unwrap(),
+            *schema.arrow_schema
+        );
+        assert_eq!(
+            result,
+            AllDatabaseViewsResult {
+                collection_name: "testcol".to_string(),
+                id: view_id,
+                name: "testview".to_string(),
+                definition: "SELECT value * 2 AS value FROM testtable".to_string(),
+                schema: result.schema.clone(),
+            }
+        );
+
+        // Views don't have any versions or columns of their own
+        assert!(repository
+            .get_all_columns_in_database(database_id, None)
+            .await
+            .unwrap()
+            .iter()
+            .all(|c| c.table_name != "testview"));
+
+        repository.drop_table(view_id).await.unwrap();
+        assert!(repository
+            .get_all_views_in_database(database_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    async fn test_materialized_views(
+        repository: Arc<dyn Repository>,
+        database_id: DatabaseId,
+        table_id: TableId,
+    ) {
+        assert!(repository
+            .get_all_materialized_views_in_database(database_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        repository
+            .set_materialized_view(table_id, "SELECT 1 AS value", true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repository
+                .get_all_materialized_views_in_database(database_id)
+                .await
+                .unwrap(),
+            vec![AllDatabaseMaterializedViewsResult {
+                collection_name: "testcol".to_string(),
+                id: table_id,
+                name: "testtable".to_string(),
+                definition: "SELECT 1 AS value".to_string(),
+                auto_refresh: true,
+            }]
+        );
+
+        // A materialized view is still a regular table with versions and columns
+        assert!(repository
+            .get_all_columns_in_database(database_id, None)
+            .await
+            .unwrap()
+            .iter()
+            .any(|c| c.table_name == "testtable"));
+        assert!(repository
+            .get_all_views_in_database(database_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Can't turn a non-existent table into a materialized view
+        assert!(matches!(
+            repository
+                .set_materialized_view(-1, "SELECT 1 AS value", false)
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
     }
 
     async fn test_rename_table(
@@ -558,6 +1203,122 @@ pub mod tests {
         );
     }
 
+    async fn test_table_column_distinct_counts(
+        repository: Arc<dyn Repository>,
+        database_id: DatabaseId,
+        table_version_id: TableVersionId,
+    ) {
+        let distinct_counts = |table_version_id| {
+            let repository = repository.clone();
+            async move {
+                repository
+                    .get_all_columns_in_database(
+                        database_id,
+                        Some(vec![table_version_id]),
+                    )
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|c| (c.column_name, c.distinct_count))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        repository
+            .set_table_column_distinct_counts(
+                table_version_id,
+                &[("value".to_string(), 42)],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            distinct_counts(table_version_id).await,
+            vec![("date".to_string(), None), ("value".to_string(), Some(42))]
+        );
+
+        // New versions inherit the counts, until they get too old
+        let mut new_version_id = table_version_id;
+        for _ in 0..MAX_DISTINCT_COUNT_AGE {
+            new_version_id = repository
+                .create_new_table_version(new_version_id, true)
+                .await
+                .unwrap();
+            assert_eq!(
+                distinct_counts(new_version_id).await,
+                vec![("date".to_string(), None), ("value".to_string(), Some(42))]
+            );
+        }
+
+        let new_version_id = repository
+            .create_new_table_version(new_version_id, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            distinct_counts(new_version_id).await,
+            vec![("date".to_string(), None), ("value".to_string(), None)]
+        );
+
+        // Running ANALYZE again makes them current again
+        repository
+            .set_table_column_distinct_counts(
+                new_version_id,
+                &[("value".to_string(), 43)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            distinct_counts(new_version_id).await,
+            vec![("date".to_string(), None), ("value".to_string(), Some(43))]
+        );
+    }
+
+    async fn test_job_runs(repository: Arc<dyn Repository>) {
+        let first_id = repository.create_job_run("vacuum").await.unwrap();
+        let second_id = repository.create_job_run("vacuum").await.unwrap();
+
+        repository
+            .finish_job_run(first_id, "succeeded", 10, None)
+            .await
+            .unwrap();
+        repository
+            .finish_job_run(second_id, "failed", 20, Some("oops".to_string()))
+            .await
+            .unwrap();
+
+        let job_runs: Vec<(JobRunId, Option<i64>, String, Option<String>)> = repository
+            .get_all_job_runs()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.id, r.duration_ms, r.status, r.error))
+            .collect();
+
+        assert_eq!(
+            job_runs,
+            vec![
+                (first_id, Some(10), "succeeded".to_string(), None),
+                (
+                    second_id,
+                    Some(20),
+                    "failed".to_string(),
+                    Some("oops".to_string())
+                ),
+            ]
+        );
+
+        // Only keep the most recent run
+        assert_eq!(repository.delete_old_job_runs(1).await.unwrap(), 1);
+        let job_run_ids: Vec<JobRunId> = repository
+            .get_all_job_runs()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(job_run_ids, vec![second_id]);
+    }
+
     async fn test_error_propagation(repository: Arc<dyn Repository>, table_id: TableId) {
         // Nonexistent table ID
         assert!(matches!(
@@ -593,7 +1354,12 @@ pub mod tests {
 
         assert!(matches!(
             repository
-                .create_table(collection_id_2, "testtable2", &schema)
+                .create_table(
+                    collection_id_2,
+                    "testtable2",
+                    &schema,
+                    &ParquetWriterOptions::default(),
+                )
                 .await
                 .unwrap_err(),
             Error::UniqueConstraintViolation(_)
@@ -601,7 +1367,12 @@ pub mod tests {
 
         // Make a new table in the previous collection, try renaming
         let (new_table_id, _) = repository
-            .create_table(collection_id_1, "testtable2", &schema)
+            .create_table(
+                collection_id_1,
+                "testtable2",
+                &schema,
+                &ParquetWriterOptions::default(),
+            )
             .await
             .unwrap();
 
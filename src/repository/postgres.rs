@@ -1,5 +1,6 @@
 use std::{fmt::Debug, iter::zip, time::Duration};
 
+use arrow_integration_test::schema_to_json;
 use async_trait::async_trait;
 use futures::TryStreamExt;
 use sqlx::{
@@ -10,8 +11,8 @@ use sqlx::{
 
 use crate::{
     data_types::{
-        CollectionId, DatabaseId, FunctionId, PhysicalPartitionId, TableId,
-        TableVersionId,
+        CollectionId, DatabaseId, ExternalTableDetails, ExternalTableId, FunctionId,
+        JobRunId, ParquetWriterOptions, PhysicalPartitionId, TableId, TableVersionId,
     },
     implement_repository,
     provider::{PartitionColumn, SeafowlPartition},
@@ -23,8 +24,12 @@ use crate::{
 use super::{
     default::RepositoryQueries,
     interface::{
-        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, Error, Repository, Result,
-        TablePartitionsResult, TableVersionsResult,
+        AllDatabaseColumnsResult, AllDatabaseFunctionsResult,
+        AllDatabaseMaterializedViewsResult, AllDatabaseViewsResult,
+        AllExternalTablesResult, Error, JobRunsResult, PartitionColumnsResult,
+        Repository, Result, TableColumnsResult, TablePartitionBloomFilterResult,
+        TablePartitionDistinctSketchResult, TablePartitionsResult, TableVersionsResult,
+        MAX_DISTINCT_COUNT_AGE,
     },
 };
 
@@ -53,6 +58,15 @@ impl PostgresRepository {
             INNER JOIN "table" ON "table".id = table_version.table_id
             INNER JOIN collection ON collection.id = "table".collection_id
             INNER JOIN database ON database.id = collection.database_id"#,
+        all_job_runs: r#"SELECT
+                id,
+                job_name,
+                CAST(EXTRACT(EPOCH FROM start_time) AS INT8) AS start_time,
+                duration_ms,
+                status,
+                error
+            FROM job_run
+            ORDER BY id"#,
     };
 
     pub async fn try_new(
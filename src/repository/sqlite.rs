@@ -1,5 +1,6 @@
 use std::{fmt::Debug, iter::zip, str::FromStr};
 
+use arrow_integration_test::schema_to_json;
 use async_trait::async_trait;
 use futures::TryStreamExt;
 use sqlx::sqlite::SqliteJournalMode;
@@ -11,8 +12,8 @@ use sqlx::{
 
 use crate::{
     data_types::{
-        CollectionId, DatabaseId, FunctionId, PhysicalPartitionId, TableId,
-        TableVersionId,
+        CollectionId, DatabaseId, ExternalTableDetails, ExternalTableId, FunctionId,
+        JobRunId, ParquetWriterOptions, PhysicalPartitionId, TableId, TableVersionId,
     },
     provider::{PartitionColumn, SeafowlPartition},
     repository::interface::AllTablePartitionColumnsResult,
@@ -25,8 +26,12 @@ use crate::implement_repository;
 use super::{
     default::RepositoryQueries,
     interface::{
-        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, Error, Repository, Result,
-        TablePartitionsResult, TableVersionsResult,
+        AllDatabaseColumnsResult, AllDatabaseFunctionsResult,
+        AllDatabaseMaterializedViewsResult, AllDatabaseViewsResult,
+        AllExternalTablesResult, Error, JobRunsResult, PartitionColumnsResult,
+        Repository, Result, TableColumnsResult, TablePartitionBloomFilterResult,
+        TablePartitionDistinctSketchResult, TablePartitionsResult, TableVersionsResult,
+        MAX_DISTINCT_COUNT_AGE,
     },
 };
 
@@ -62,6 +67,15 @@ impl SqliteRepository {
             INNER JOIN "table" ON "table".id = table_version.table_id
             INNER JOIN collection ON collection.id = "table".collection_id
             INNER JOIN database ON database.id = collection.database_id"#,
+        all_job_runs: r#"SELECT
+                id,
+                job_name,
+                CAST(start_time AS INTEGER(8)) AS start_time,
+                duration_ms,
+                status,
+                error
+            FROM job_run
+            ORDER BY id"#,
     };
 
     pub async fn try_new(
@@ -72,7 +86,14 @@ impl SqliteRepository {
             .create_if_missing(true)
             .journal_mode(journal_mode);
 
-        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        // An in-memory database is shared between the connections with table-level locking
+        // (instead of WAL), so they don't give us any concurrency. Worse, a connection can
+        // briefly miss the changes another one has just made.
+        let max_connections = if dsn.contains(":memory:") { 1 } else { 10 };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
         let repo = Self { executor: pool };
         repo.setup().await;
         Ok(repo)
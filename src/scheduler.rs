@@ -0,0 +1,343 @@
+//! Runner for the maintenance jobs configured under `[[jobs]]`, for the partition GC and for
+//! the refreshes of the auto-refreshed materialized views. Each job gets its own task that
+//! sleeps until the next time its trigger fires and then executes the job, recording the
+//! outcome of every run in the catalog (exposed as `system.job_runs`).
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use cron::Schedule;
+use datafusion::error::Result;
+use log::{info, warn};
+use tokio::sync::broadcast::Receiver;
+
+use crate::config::schema::{Job, Misc};
+use crate::context::{DefaultSeafowlContext, SeafowlContext};
+use crate::repository::interface::AllDatabaseMaterializedViewsResult;
+use crate::utils::gc_partitions;
+
+pub const JOB_RUN_SUCCEEDED: &str = "succeeded";
+pub const JOB_RUN_FAILED: &str = "failed";
+
+/// Name under which the partition GC runs are recorded
+pub const GC_JOB_NAME: &str = "gc";
+
+/// Name under which the background materialized view refreshes run
+pub const MATERIALIZED_VIEW_REFRESH_JOB_NAME: &str = "refresh_materialized_views";
+
+/// Name under which the refreshes of a single materialized view are recorded
+pub fn materialized_view_refresh_job_name(
+    view: &AllDatabaseMaterializedViewsResult,
+) -> String {
+    format!("refresh {}.{}", view.collection_name, view.name)
+}
+
+/// When a job should run
+pub enum Trigger {
+    Cron(Box<Schedule>),
+    Every(Duration),
+}
+
+impl Trigger {
+    fn next_delay(&self) -> Option<Duration> {
+        match self {
+            Trigger::Cron(schedule) => schedule
+                .upcoming(Utc)
+                .next()
+                .map(|next| (next - Utc::now()).to_std().unwrap_or_default()),
+            Trigger::Every(interval) => Some(*interval),
+        }
+    }
+}
+
+/// Get the trigger for the partition GC: `misc.gc_schedule` takes precedence over the
+/// legacy `misc.gc_interval`; returns None if GC is disabled.
+pub fn gc_trigger(misc: &Misc) -> Option<Trigger> {
+    if let Some(gc_schedule) = &misc.gc_schedule {
+        // The schedule has already been validated when loading the config
+        let schedule =
+            Schedule::from_str(gc_schedule).expect("Invalid GC schedule in the config");
+        Some(Trigger::Cron(Box::new(schedule)))
+    } else if misc.gc_interval > 0 {
+        Some(Trigger::Every(Duration::from_secs(
+            (misc.gc_interval as u64) * 3600,
+        )))
+    } else {
+        None
+    }
+}
+
+async fn execute_job_sql(context: &DefaultSeafowlContext, sql: &str) -> Result<()> {
+    for statement in context.parse_query(sql).await? {
+        let logical = context
+            .create_logical_plan_from_statement(statement)
+            .await?;
+        let physical = context.create_physical_plan(&logical).await?;
+        context.collect(physical).await?;
+    }
+
+    Ok(())
+}
+
+/// Run a job once, recording the run in the catalog and then deleting all but the
+/// `job_run_history` most recent runs
+async fn record_job_run<F>(
+    context: &DefaultSeafowlContext,
+    job_name: &str,
+    job_run_history: u32,
+    job: F,
+) where
+    F: Future<Output = Result<()>>,
+{
+    let job_run_id = match context.table_catalog.create_job_run(job_name).await {
+        Ok(id) => id,
+        Err(e) => {
+            warn!("Failed to record the start of job {}: {:?}", job_name, e);
+            return;
+        }
+    };
+
+    info!("Running job {} (run {})", job_name, job_run_id);
+    let start = Instant::now();
+    let result = job.await;
+    let duration_ms = start.elapsed().as_millis() as i64;
+
+    let (status, error) = match result {
+        Ok(_) => {
+            info!("Job {} finished in {} ms", job_name, duration_ms);
+            (JOB_RUN_SUCCEEDED, None)
+        }
+        Err(e) => {
+            warn!("Job {} failed after {} ms: {}", job_name, duration_ms, e);
+            (JOB_RUN_FAILED, Some(e.to_string()))
+        }
+    };
+
+    if let Err(e) = context
+        .table_catalog
+        .finish_job_run(job_run_id, status, duration_ms, error)
+        .await
+    {
+        warn!("Failed to record the result of job {}: {:?}", job_name, e);
+    }
+
+    match context
+        .table_catalog
+        .delete_old_job_runs(job_run_history)
+        .await
+    {
+        Ok(0) => {}
+        Ok(deleted) => info!("Deleted {} old job run(s)", deleted),
+        Err(e) => warn!("Failed to delete old job runs: {:?}", e),
+    }
+}
+
+/// Run a SQL job once, recording the run in the catalog
+pub async fn run_job(context: &DefaultSeafowlContext, job: &Job, job_run_history: u32) {
+    record_job_run(
+        context,
+        &job.name,
+        job_run_history,
+        execute_job_sql(context, &job.sql),
+    )
+    .await
+}
+
+/// Run the GC (of partitions and unused function modules) once, recording the run in the catalog
+pub async fn run_gc(context: &DefaultSeafowlContext, job_run_history: u32) {
+    record_job_run(context, GC_JOB_NAME, job_run_history, async {
+        gc_partitions(context).await?;
+        context.gc_function_modules().await
+    })
+    .await
+}
+
+/// Refresh the materialized views created `WITH (auto_refresh = true)` that read from
+/// tables that changed since their last refresh, recording a run for each refreshed view
+pub async fn refresh_stale_materialized_views(
+    context: &DefaultSeafowlContext,
+    job_run_history: u32,
+) {
+    let views = match context
+        .table_catalog
+        .get_materialized_views(context.database_id)
+        .await
+    {
+        Ok(views) => views,
+        Err(e) => {
+            warn!("Failed to load the materialized views: {:?}", e);
+            return;
+        }
+    };
+
+    // The views are ordered by their table ID, so a materialized view reading from
+    // another one gets refreshed after it
+    for view in views.iter().filter(|view| view.auto_refresh) {
+        match context.is_materialized_view_stale(view).await {
+            Ok(true) => {
+                record_job_run(
+                    context,
+                    &materialized_view_refresh_job_name(view),
+                    job_run_history,
+                    async {
+                        context
+                            .refresh_materialized_view(view, true)
+                            .await
+                            .map(|_| ())
+                    },
+                )
+                .await
+            }
+            Ok(false) => {}
+            Err(e) => warn!(
+                "Failed to check whether materialized view {}.{} is stale: {}",
+                view.collection_name, view.name, e
+            ),
+        }
+    }
+}
+
+/// Keep refreshing the stale auto-refreshed materialized views after writes until we get a
+/// shutdown signal. There's only one of these tasks, so refreshes never run concurrently with
+/// each other, and all writes made during a refresh get picked up by the next one.
+pub async fn run_materialized_view_refreshes(
+    context: Arc<DefaultSeafowlContext>,
+    job_run_history: u32,
+    mut shutdown: Receiver<()>,
+) {
+    loop {
+        tokio::select! {
+            _ = context.materialized_view_refresh.notified() => {
+                refresh_stale_materialized_views(&context, job_run_history).await
+            }
+            _ = shutdown.recv() => {
+                info!(
+                    "Job {} received shutdown signal, exiting",
+                    MATERIALIZED_VIEW_REFRESH_JOB_NAME
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Keep running a job whenever its trigger fires until we get a shutdown signal
+pub async fn run_on_trigger<F, Fut>(
+    name: &str,
+    trigger: Trigger,
+    mut shutdown: Receiver<()>,
+    mut job: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    while let Some(delay) = trigger.next_delay() {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => job().await,
+            _ = shutdown.recv() => {
+                info!("Job {} received shutdown signal, exiting", name);
+                return;
+            }
+        }
+    }
+}
+
+/// Keep running the SQL job on its schedule until we get a shutdown signal
+pub async fn run_job_schedule(
+    context: Arc<DefaultSeafowlContext>,
+    job: Job,
+    job_run_history: u32,
+    shutdown: Receiver<()>,
+) {
+    // The schedule has already been validated when loading the config
+    let schedule =
+        Schedule::from_str(&job.schedule).expect("Invalid job schedule in the config");
+
+    run_on_trigger(
+        &job.name,
+        Trigger::Cron(Box::new(schedule)),
+        shutdown,
+        || run_job(&context, &job, job_run_history),
+    )
+    .await
+}
+
+/// Keep running the partition GC until we get a shutdown signal
+pub async fn run_gc_schedule(
+    context: Arc<DefaultSeafowlContext>,
+    trigger: Trigger,
+    job_run_history: u32,
+    shutdown: Receiver<()>,
+) {
+    run_on_trigger(GC_JOB_NAME, trigger, shutdown, || {
+        run_gc(&context, job_run_history)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_gc, run_job, GC_JOB_NAME, JOB_RUN_FAILED, JOB_RUN_SUCCEEDED};
+    use crate::config::schema::Job;
+    use crate::context::test_utils::in_memory_context;
+
+    #[tokio::test]
+    async fn test_run_job_records_runs() {
+        let context = in_memory_context().await;
+
+        run_job(
+            &context,
+            &Job {
+                name: "create".to_string(),
+                schedule: "* * * * * *".to_string(),
+                sql: "CREATE TABLE test_table AS SELECT 1 AS a".to_string(),
+            },
+            1000,
+        )
+        .await;
+
+        run_job(
+            &context,
+            &Job {
+                name: "broken".to_string(),
+                schedule: "* * * * * *".to_string(),
+                sql: "SELECT * FROM missing_table".to_string(),
+            },
+            1000,
+        )
+        .await;
+
+        let job_runs = context.table_catalog.get_all_job_runs().await.unwrap();
+        assert_eq!(job_runs.len(), 2);
+
+        assert_eq!(job_runs[0].job_name, "create");
+        assert_eq!(job_runs[0].status, JOB_RUN_SUCCEEDED);
+        assert_eq!(job_runs[0].error, None);
+        assert!(job_runs[0].duration_ms.is_some());
+
+        assert_eq!(job_runs[1].job_name, "broken");
+        assert_eq!(job_runs[1].status, JOB_RUN_FAILED);
+        assert!(job_runs[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("missing_table"));
+    }
+
+    #[tokio::test]
+    async fn test_run_gc_prunes_job_runs() {
+        let context = in_memory_context().await;
+
+        for _ in 0..3 {
+            run_gc(&context, 2).await;
+        }
+
+        let job_runs = context.table_catalog.get_all_job_runs().await.unwrap();
+        assert_eq!(job_runs.len(), 2);
+        assert!(job_runs
+            .iter()
+            .all(|r| r.job_name == GC_JOB_NAME && r.status == JOB_RUN_SUCCEEDED));
+    }
+}
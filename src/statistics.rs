@@ -0,0 +1,744 @@
+//! Per-column statistics that Seafowl computes itself while writing out partitions, instead of
+//! relying on the Parquet writer's (which don't cover e.g. timestamps or strings in DataFusion's
+//! reader). These end up in `physical_partition_column` and are used for partition pruning.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Float32Array, Float64Array, LargeBinaryArray,
+    LargeStringArray, StringArray,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use arrow_integration_test::field_to_json;
+use datafusion::error::Result;
+use datafusion::physical_plan::expressions::{MaxAccumulator, MinAccumulator};
+use datafusion::physical_plan::{ColumnStatistics, Statistics};
+use datafusion::scalar::ScalarValue;
+use datafusion_expr::Accumulator;
+use log::warn;
+
+use crate::bloom_filter::BloomFilter;
+use crate::context::scalar_value_to_bytes;
+use crate::metrics::PARTITION_COLUMN_STATS_FAILURES;
+use crate::provider::{PartitionColumn, SeafowlPartition, SeafowlPruningStatistics};
+
+// Bump this if the hashing or the layout change, so that old sketches get ignored
+const SKETCH_FORMAT_VERSION: u8 = 1;
+// 4096 registers, for a standard error of ~1.6%
+const HLL_PRECISION: u32 = 12;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// A HyperLogLog sketch for estimating the number of distinct values in a column. Sketches
+/// of different partitions can be merged to get the estimate for the whole table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    pub fn insert_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // Guard bit, so that the rank can't exceed 64 - HLL_PRECISION + 1
+        let rest = (hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Estimate the number of distinct values that were inserted
+    pub fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&register| 2f64.powi(-(register as i32)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Use linear counting for small cardinalities, where HLL is biased
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + HLL_REGISTERS);
+        bytes.push(SKETCH_FORMAT_VERSION);
+        bytes.extend_from_slice(&self.registers);
+        bytes
+    }
+
+    /// Deserialize a sketch, returning `None` if it's invalid or was written in a
+    /// different format
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first() {
+            Some((&SKETCH_FORMAT_VERSION, registers))
+                if registers.len() == HLL_REGISTERS =>
+            {
+                Some(Self {
+                    registers: registers.to_vec(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Hash every value of the array (`None` for nulls), in a way that's stable across Seafowl
+/// versions and platforms, since the hashes end up in the catalog (through Bloom filters and
+/// distinct count sketches). Returns `None` if the array's values can't be hashed.
+pub fn hash_array(array: &ArrayRef) -> Option<Vec<Option<u64>>> {
+    let data_type = array.data_type();
+
+    macro_rules! hash_values {
+        ($array_type:ty, |$value:ident| $to_bytes:expr) => {{
+            let array = array.as_any().downcast_ref::<$array_type>()?;
+            array
+                .iter()
+                .map(|value| value.map(|$value| hash_bytes(&$to_bytes[..])))
+                .collect()
+        }};
+    }
+
+    // These types are stored as a single native integer per value, which we hash in
+    // little-endian byte order so that the hashes don't depend on the platform
+    macro_rules! hash_native_values {
+        ($native_type:ty) => {{
+            let data = array.data();
+            let values = &data.buffers()[0].typed_data::<$native_type>()[data.offset()..];
+            (0..array.len())
+                .map(|i| {
+                    (!array.is_null(i)).then(|| hash_bytes(&values[i].to_le_bytes()))
+                })
+                .collect()
+        }};
+    }
+
+    let hashes: Vec<Option<u64>> = match data_type {
+        DataType::Int8 => hash_native_values!(i8),
+        DataType::UInt8 => hash_native_values!(u8),
+        DataType::Int16 => hash_native_values!(i16),
+        DataType::UInt16 => hash_native_values!(u16),
+        DataType::Int32 | DataType::Date32 | DataType::Time32(_) => {
+            hash_native_values!(i32)
+        }
+        DataType::UInt32 => hash_native_values!(u32),
+        DataType::Int64
+        | DataType::Date64
+        | DataType::Time64(_)
+        | DataType::Timestamp(_, _)
+        | DataType::Duration(_) => hash_native_values!(i64),
+        DataType::UInt64 => hash_native_values!(u64),
+        DataType::Decimal128(_, _) => hash_native_values!(i128),
+        DataType::Boolean => hash_values!(BooleanArray, |v| [v as u8]),
+        // Make sure equal floats hash the same way (-0.0 and 0.0, NaNs)
+        DataType::Float32 => hash_values!(Float32Array, |v| {
+            let v = if v.is_nan() {
+                f32::NAN
+            } else if v == 0.0 {
+                0.0
+            } else {
+                v
+            };
+            v.to_le_bytes()
+        }),
+        DataType::Float64 => hash_values!(Float64Array, |v| {
+            let v = if v.is_nan() {
+                f64::NAN
+            } else if v == 0.0 {
+                0.0
+            } else {
+                v
+            };
+            v.to_le_bytes()
+        }),
+        DataType::Utf8 => hash_values!(StringArray, |v| v.as_bytes()),
+        DataType::LargeUtf8 => hash_values!(LargeStringArray, |v| v.as_bytes()),
+        DataType::Binary => hash_values!(BinaryArray, |v| v),
+        DataType::LargeBinary => hash_values!(LargeBinaryArray, |v| v),
+        // Fall back to hashing the serialized values
+        _ => (0..array.len())
+            .map(|i| {
+                if array.is_null(i) {
+                    return Some(None);
+                }
+                let value = ScalarValue::try_from_array(array, i).ok()?;
+                scalar_value_to_bytes(&value).map(|bytes| Some(hash_bytes(&bytes)))
+            })
+            .collect::<Option<Vec<_>>>()?,
+    };
+
+    Some(hashes)
+}
+
+/// Hash a single value the same way `hash_array` does
+pub fn hash_scalar(value: &ScalarValue) -> Option<u64> {
+    hash_array(&value.to_array())?.pop()?
+}
+
+// FNV-1a, followed by the SplitMix64 finalizer to spread the bits
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut x = bytes.iter().fold(0xcbf29ce484222325, |hash: u64, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+struct ColumnStatsCollector {
+    field: Field,
+    min: Option<MinAccumulator>,
+    max: Option<MaxAccumulator>,
+    null_count: usize,
+    sketch: Option<HyperLogLog>,
+    bloom_filter_hashes: Option<Vec<u64>>,
+}
+
+impl ColumnStatsCollector {
+    fn new(field: &Field, bloom_filter: bool) -> Self {
+        let (min, max) = match (
+            MinAccumulator::try_new(field.data_type()),
+            MaxAccumulator::try_new(field.data_type()),
+        ) {
+            (Ok(min), Ok(max)) => (Some(min), Some(max)),
+            _ => {
+                Self::stats_failure(field, "unsupported type");
+                (None, None)
+            }
+        };
+
+        Self {
+            field: field.clone(),
+            min,
+            max,
+            null_count: 0,
+            sketch: Some(HyperLogLog::default()),
+            bloom_filter_hashes: bloom_filter.then(Vec::new),
+        }
+    }
+
+    fn stats_failure(field: &Field, reason: &str) {
+        warn!(
+            "Couldn't compute the min/max values of column {} ({}): {}",
+            field.name(),
+            field.data_type(),
+            reason
+        );
+        PARTITION_COLUMN_STATS_FAILURES
+            .with_label_values(&[&field.data_type().to_string()])
+            .inc();
+    }
+
+    fn update(&mut self, array: &ArrayRef) {
+        self.null_count += array.null_count();
+
+        if let (Some(min), Some(max)) = (&mut self.min, &mut self.max) {
+            let values = [array.clone()];
+            if let Err(error) = min
+                .update_batch(&values)
+                .and_then(|_| max.update_batch(&values))
+            {
+                Self::stats_failure(&self.field, &error.to_string());
+                (self.min, self.max) = (None, None);
+            }
+        }
+
+        if self.sketch.is_none() && self.bloom_filter_hashes.is_none() {
+            return;
+        }
+
+        match hash_array(array) {
+            Some(hashes) => {
+                for hash in hashes.into_iter().flatten() {
+                    if let Some(sketch) = &mut self.sketch {
+                        sketch.insert_hash(hash);
+                    }
+                    if let Some(bloom_filter_hashes) = &mut self.bloom_filter_hashes {
+                        bloom_filter_hashes.push(hash);
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "Couldn't hash the values of column {} ({})",
+                    self.field.name(),
+                    self.field.data_type()
+                );
+                self.sketch = None;
+                self.bloom_filter_hashes = None;
+            }
+        }
+    }
+
+    fn serialize(&self, value: Option<Result<ScalarValue>>) -> Option<Vec<u8>> {
+        match value? {
+            Ok(value) if value.is_null() => None,
+            Ok(value) => {
+                let bytes = scalar_value_to_bytes(&value);
+                if bytes.is_none() {
+                    Self::stats_failure(&self.field, "serialization failed");
+                }
+                bytes
+            }
+            Err(error) => {
+                Self::stats_failure(&self.field, &error.to_string());
+                None
+            }
+        }
+    }
+
+    fn finish(self) -> PartitionColumn {
+        let min_value = self.serialize(self.min.as_ref().map(|min| min.evaluate()));
+        let max_value = self.serialize(self.max.as_ref().map(|max| max.evaluate()));
+
+        let bloom_filter = self.bloom_filter_hashes.map(|mut hashes| {
+            // Size the filter by the number of distinct values
+            hashes.sort_unstable();
+            hashes.dedup();
+            BloomFilter::from_hashes(&hashes).to_bytes()
+        });
+
+        PartitionColumn {
+            name: Arc::from(self.field.name().to_string()),
+            r#type: Arc::from(field_to_json(&self.field).to_string()),
+            min_value: Arc::new(min_value),
+            max_value: Arc::new(max_value),
+            null_count: Some(self.null_count as i32),
+            bloom_filter: Arc::new(bloom_filter),
+            distinct_sketch: Arc::new(self.sketch.map(|sketch| sketch.to_bytes())),
+        }
+    }
+}
+
+/// Collects the statistics of all columns of a partition from the batches written to it
+pub struct PartitionStatsCollector {
+    row_count: usize,
+    columns: Vec<ColumnStatsCollector>,
+}
+
+impl PartitionStatsCollector {
+    pub fn new(schema: SchemaRef, bloom_filter_columns: &[String]) -> Self {
+        Self {
+            row_count: 0,
+            columns: schema
+                .fields()
+                .iter()
+                .map(|field| {
+                    ColumnStatsCollector::new(
+                        field,
+                        bloom_filter_columns.contains(field.name()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn update(&mut self, batch: &RecordBatch) {
+        self.row_count += batch.num_rows();
+        for (column, array) in self.columns.iter_mut().zip(batch.columns()) {
+            column.update(array);
+        }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    pub fn finish(self) -> Vec<PartitionColumn> {
+        self.columns
+            .into_iter()
+            .map(ColumnStatsCollector::finish)
+            .collect()
+    }
+}
+
+/// Merge the distinct value sketches of all partitions into per-column estimates for the
+/// whole table. Columns with partitions that don't have a sketch (e.g. ones written by older
+/// Seafowl versions) are left out, since we'd underestimate them.
+pub fn estimate_distinct_counts(
+    partitions: &[SeafowlPartition],
+    schema: &Schema,
+) -> Vec<(String, u64)> {
+    schema
+        .fields()
+        .iter()
+        .filter_map(|field| {
+            let mut sketch = HyperLogLog::default();
+            for partition in partitions {
+                // Partitions without the column only have NULLs in it
+                if let Some(column) = partition_column(partition, field.name()) {
+                    let partition_sketch = column
+                        .distinct_sketch
+                        .as_deref()
+                        .and_then(HyperLogLog::from_bytes)?;
+                    sketch.merge(&partition_sketch);
+                }
+            }
+            Some((field.name().clone(), sketch.estimate()))
+        })
+        .collect()
+}
+
+/// Aggregate the partition statistics into the table-level ones that DataFusion uses for
+/// planning (e.g. to pick the build side of a join). These are never exact, since the scan
+/// can skip row groups and the distinct counts are as of the last `ANALYZE TABLE`.
+pub fn table_statistics(
+    partitions: &[SeafowlPartition],
+    schema: &Schema,
+    distinct_counts: &HashMap<String, u64>,
+    total_byte_size: Option<usize>,
+) -> Statistics {
+    let column_statistics = schema
+        .fields()
+        .iter()
+        .map(|field| ColumnStatistics {
+            distinct_count: distinct_counts.get(field.name()).map(|c| *c as usize),
+            ..column_statistics(partitions, field)
+        })
+        .collect();
+
+    Statistics {
+        num_rows: Some(partitions.iter().map(|p| p.row_count as usize).sum()),
+        total_byte_size,
+        column_statistics: Some(column_statistics),
+        is_exact: false,
+    }
+}
+
+fn partition_column<'a>(
+    partition: &'a SeafowlPartition,
+    name: &str,
+) -> Option<&'a PartitionColumn> {
+    partition
+        .columns
+        .iter()
+        .find(|column| column.name.as_ref() == name)
+}
+
+fn column_statistics(partitions: &[SeafowlPartition], field: &Field) -> ColumnStatistics {
+    let mut null_count = Some(0);
+    // Min/max values of all partitions that have non-NULL values, or `None` if one of
+    // them is missing its statistics
+    let mut bounds = Some(vec![]);
+
+    for partition in partitions {
+        let column = match partition_column(partition, field.name()) {
+            Some(column) => column,
+            None => {
+                null_count = null_count.map(|n| n + partition.row_count as usize);
+                continue;
+            }
+        };
+
+        null_count = null_count
+            .zip(column.null_count)
+            .map(|(n, partition_nulls)| n + partition_nulls as usize);
+        if column.null_count == Some(partition.row_count) {
+            continue;
+        }
+
+        let parse = |value: &Arc<Option<Vec<u8>>>| {
+            SeafowlPruningStatistics::parse_bytes_value(value, field.data_type())
+                .ok()
+                .filter(|value| !value.is_null())
+        };
+        bounds = bounds.and_then(|mut bounds: Vec<(ScalarValue, ScalarValue)>| {
+            bounds.push((parse(&column.min_value)?, parse(&column.max_value)?));
+            Some(bounds)
+        });
+    }
+
+    let (min_value, max_value) =
+        match bounds {
+            Some(bounds) => (
+                bounds.iter().map(|(min, _)| min).cloned().reduce(|a, b| {
+                    if b < a {
+                        b
+                    } else {
+                        a
+                    }
+                }),
+                bounds.iter().map(|(_, max)| max).cloned().reduce(|a, b| {
+                    if b > a {
+                        b
+                    } else {
+                        a
+                    }
+                }),
+            ),
+            None => (None, None),
+        };
+
+    ColumnStatistics {
+        null_count,
+        min_value,
+        max_value,
+        distinct_count: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{
+        ArrayRef, Decimal128Array, Float64Array, Int64Array, StringArray,
+        TimestampNanosecondArray,
+    };
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::scalar::ScalarValue;
+
+    use crate::context::scalar_value_to_bytes;
+    use crate::provider::SeafowlPartition;
+
+    use super::{
+        estimate_distinct_counts, hash_array, hash_bytes, hash_scalar, table_statistics,
+        HyperLogLog, PartitionStatsCollector,
+    };
+
+    #[test]
+    fn test_hyperloglog() {
+        for count in [0, 1, 100, 10_000, 1_000_000] {
+            let mut sketch = HyperLogLog::default();
+            let values: ArrayRef = Arc::new(Int64Array::from_iter_values(0..count));
+            for hash in hash_array(&values).unwrap().into_iter().flatten() {
+                sketch.insert_hash(hash);
+            }
+
+            let sketch = HyperLogLog::from_bytes(&sketch.to_bytes()).unwrap();
+            let error = (sketch.estimate() as f64 - count as f64).abs();
+            assert!(
+                error <= count as f64 * 0.05,
+                "{count}: {}",
+                sketch.estimate()
+            );
+        }
+    }
+
+    #[test]
+    fn test_hyperloglog_merge() {
+        let mut sketches = vec![HyperLogLog::default(), HyperLogLog::default()];
+        for i in 0..20_000 {
+            // Half of the values are in both sketches
+            let hash = hash_scalar(&ScalarValue::Int64(Some(i))).unwrap();
+            sketches[(i % 2) as usize].insert_hash(hash);
+            if i < 10_000 {
+                sketches[((i + 1) % 2) as usize].insert_hash(hash);
+            }
+        }
+
+        let mut merged = sketches[0].clone();
+        merged.merge(&sketches[1]);
+        let error = (merged.estimate() as f64 - 20_000.0).abs();
+        assert!(error <= 1000.0, "{}", merged.estimate());
+    }
+
+    #[test]
+    fn test_hash_scalar_matches_hash_array() {
+        let values: ArrayRef = Arc::new(Float64Array::from(vec![Some(-0.0), None]));
+        assert_eq!(
+            hash_array(&values).unwrap(),
+            vec![hash_scalar(&ScalarValue::Float64(Some(0.0))), None]
+        );
+
+        let values: ArrayRef = Arc::new(StringArray::from(vec!["one"]));
+        assert_eq!(
+            hash_array(&values).unwrap(),
+            vec![hash_scalar(&ScalarValue::Utf8(Some("one".to_string())))]
+        );
+    }
+
+    #[test]
+    fn test_hash_array_is_platform_independent() {
+        // Sliced arrays hash the values at their offset, in little-endian byte order
+        let values: ArrayRef = Arc::new(Int64Array::from(vec![1, 258, 3]));
+        assert_eq!(
+            hash_array(&values.slice(1, 2)).unwrap(),
+            vec![
+                Some(hash_bytes(&[2, 1, 0, 0, 0, 0, 0, 0])),
+                Some(hash_bytes(&[3, 0, 0, 0, 0, 0, 0, 0]))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partition_stats() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "time",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                true,
+            ),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("amount", DataType::Decimal128(10, 2), true),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    Some(3_000_000_000),
+                    Some(1_000_000_000),
+                    None,
+                ])),
+                Arc::new(StringArray::from(vec![Some("b"), None, Some("a")])),
+                Arc::new(
+                    vec![Some(1250), Some(-300), Some(1250)]
+                        .into_iter()
+                        .collect::<Decimal128Array>()
+                        .with_precision_and_scale(10, 2)
+                        .unwrap(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        let mut collector = PartitionStatsCollector::new(schema, &["name".to_string()]);
+        collector.update(&batch);
+        collector.update(&batch.slice(0, 1));
+        assert_eq!(collector.row_count(), 4);
+        let columns = collector.finish();
+
+        let bytes = |value: ScalarValue| Arc::new(scalar_value_to_bytes(&value));
+        let distinct_count = |i: usize| {
+            HyperLogLog::from_bytes(columns[i].distinct_sketch.as_ref().as_ref().unwrap())
+                .unwrap()
+                .estimate()
+        };
+
+        assert_eq!(
+            columns[0].min_value,
+            bytes(ScalarValue::TimestampNanosecond(Some(1_000_000_000), None))
+        );
+        assert_eq!(
+            columns[0].max_value,
+            bytes(ScalarValue::TimestampNanosecond(Some(3_000_000_000), None))
+        );
+        assert_eq!(columns[0].null_count, Some(1));
+        assert_eq!(distinct_count(0), 2);
+        assert!(columns[0].bloom_filter.is_none());
+
+        assert_eq!(
+            columns[1].min_value,
+            bytes(ScalarValue::Utf8(Some("a".into())))
+        );
+        assert_eq!(
+            columns[1].max_value,
+            bytes(ScalarValue::Utf8(Some("b".into())))
+        );
+        assert_eq!(columns[1].null_count, Some(1));
+        assert_eq!(distinct_count(1), 2);
+        assert!(columns[1].bloom_filter.is_some());
+
+        assert_eq!(
+            columns[2].min_value,
+            bytes(ScalarValue::Decimal128(Some(-300), 10, 2))
+        );
+        assert_eq!(
+            columns[2].max_value,
+            bytes(ScalarValue::Decimal128(Some(1250), 10, 2))
+        );
+        assert_eq!(columns[2].null_count, Some(0));
+        assert_eq!(distinct_count(2), 2);
+    }
+
+    #[test]
+    fn test_table_statistics() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+
+        let make_partition = |batch: RecordBatch| {
+            let mut collector = PartitionStatsCollector::new(batch.schema(), &[]);
+            collector.update(&batch);
+            SeafowlPartition {
+                partition_id: None,
+                object_storage_id: Arc::from("some-file.parquet"),
+                row_count: collector.row_count() as i32,
+                columns: Arc::new(collector.finish()),
+            }
+        };
+
+        let partitions = vec![
+            make_partition(
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int64Array::from(vec![Some(3), Some(1), None])),
+                        Arc::new(StringArray::from(vec![
+                            Some("b"),
+                            Some("a"),
+                            Some("b"),
+                        ])),
+                    ],
+                )
+                .unwrap(),
+            ),
+            // The second partition doesn't have the `name` column
+            make_partition(
+                RecordBatch::try_new(
+                    Arc::new(Schema::new(vec![schema.field(0).clone()])),
+                    vec![Arc::new(Int64Array::from(vec![2, 5]))],
+                )
+                .unwrap(),
+            ),
+        ];
+
+        let distinct_counts = estimate_distinct_counts(&partitions, &schema);
+        assert_eq!(
+            distinct_counts,
+            vec![("id".to_string(), 4), ("name".to_string(), 2)]
+        );
+
+        let statistics = table_statistics(
+            &partitions,
+            &schema,
+            &distinct_counts.into_iter().collect(),
+            Some(1024),
+        );
+        assert_eq!(statistics.num_rows, Some(5));
+        assert_eq!(statistics.total_byte_size, Some(1024));
+        assert!(!statistics.is_exact);
+
+        let columns = statistics.column_statistics.unwrap();
+        assert_eq!(columns[0].min_value, Some(ScalarValue::Int64(Some(1))));
+        assert_eq!(columns[0].max_value, Some(ScalarValue::Int64(Some(5))));
+        assert_eq!(columns[0].null_count, Some(1));
+        assert_eq!(columns[0].distinct_count, Some(4));
+        assert_eq!(
+            columns[1].min_value,
+            Some(ScalarValue::Utf8(Some("a".to_string())))
+        );
+        assert_eq!(
+            columns[1].max_value,
+            Some(ScalarValue::Utf8(Some("b".to_string())))
+        );
+        assert_eq!(columns[1].null_count, Some(2));
+        assert_eq!(columns[1].distinct_count, Some(2));
+    }
+}
@@ -1,12 +1,18 @@
 //! Mechanism for creating virtual Seafowl system tables, inspired by influxdb_iox system tables
 //! and datafusion's information_schema.
 
-use crate::catalog::TableCatalog;
+use crate::catalog::{FunctionCatalog, TableCatalog};
+use crate::context::internal_object_store_url;
+use crate::provider::SeafowlPruningStatistics;
+use crate::query_log::QueryLog;
+use crate::statistics::HyperLogLog;
 use arrow::array::{
-    Int32Builder, Int64Builder, StringBuilder, StructBuilder, TimestampSecondBuilder,
+    BooleanBuilder, Int32Builder, Int64Builder, StringBuilder, StructBuilder,
+    TimestampMillisecondBuilder, TimestampSecondBuilder,
 };
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
 use arrow::record_batch::RecordBatch;
+use arrow_integration_test::field_from_json;
 use async_trait::async_trait;
 use datafusion::catalog::schema::SchemaProvider;
 use datafusion::common::DataFusionError;
@@ -16,23 +22,45 @@ use datafusion::execution::context::SessionState;
 use datafusion::physical_plan::memory::MemoryExec;
 use datafusion::physical_plan::ExecutionPlan;
 use datafusion_expr::{Expr, TableType};
+use futures::{stream, StreamExt};
+use itertools::Itertools;
+use object_store::path::Path;
 use std::any::Any;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub const SYSTEM_SCHEMA: &str = "system";
 const TABLE_VERSIONS: &str = "table_versions";
 const TABLE_PARTITIONS: &str = "table_partitions";
+const TABLE_COLUMNS: &str = "table_columns";
+const PARTITION_COLUMNS: &str = "partition_columns";
+const FUNCTIONS: &str = "functions";
+const STORAGE: &str = "storage";
+const JOB_RUNS: &str = "job_runs";
+pub const QUERIES: &str = "queries";
+
+// How many object store HEAD requests to have in flight when loading `system.storage`
+const STORAGE_HEAD_CONCURRENCY: usize = 16;
 
 pub struct SystemSchemaProvider {
     database: Arc<str>,
     table_catalog: Arc<dyn TableCatalog>,
+    function_catalog: Arc<dyn FunctionCatalog>,
+    query_log: Arc<QueryLog>,
 }
 
 impl SystemSchemaProvider {
-    pub fn new(database: Arc<str>, table_catalog: Arc<dyn TableCatalog>) -> Self {
+    pub fn new(
+        database: Arc<str>,
+        table_catalog: Arc<dyn TableCatalog>,
+        function_catalog: Arc<dyn FunctionCatalog>,
+        query_log: Arc<QueryLog>,
+    ) -> Self {
         Self {
             database,
             table_catalog,
+            function_catalog,
+            query_log,
         }
     }
 }
@@ -43,7 +71,16 @@ impl SchemaProvider for SystemSchemaProvider {
     }
 
     fn table_names(&self) -> Vec<String> {
-        vec![TABLE_VERSIONS.to_string(), TABLE_PARTITIONS.to_string()]
+        vec![
+            TABLE_VERSIONS.to_string(),
+            TABLE_PARTITIONS.to_string(),
+            TABLE_COLUMNS.to_string(),
+            PARTITION_COLUMNS.to_string(),
+            FUNCTIONS.to_string(),
+            STORAGE.to_string(),
+            JOB_RUNS.to_string(),
+            QUERIES.to_string(),
+        ]
     }
 
     fn table(&self, name: &str) -> Option<Arc<dyn TableProvider>> {
@@ -67,6 +104,53 @@ impl SchemaProvider for SystemSchemaProvider {
                     table: Arc::new(table),
                 }))
             }
+            TABLE_COLUMNS => {
+                let table = TableColumnsTable::new(
+                    self.database.clone(),
+                    self.table_catalog.clone(),
+                );
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            PARTITION_COLUMNS => {
+                let table = PartitionColumnsTable::new(
+                    self.database.clone(),
+                    self.table_catalog.clone(),
+                );
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            FUNCTIONS => {
+                let table = FunctionsTable::new(
+                    self.database.clone(),
+                    self.table_catalog.clone(),
+                    self.function_catalog.clone(),
+                );
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            STORAGE => {
+                let table =
+                    StorageTable::new(self.database.clone(), self.table_catalog.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            JOB_RUNS => {
+                let table = JobRunsTable::new(self.table_catalog.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            QUERIES => {
+                let table = QueriesTable::new(self.query_log.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
             _ => None,
         }
     }
@@ -74,7 +158,14 @@ impl SchemaProvider for SystemSchemaProvider {
     fn table_exist(&self, name: &str) -> bool {
         matches!(
             name.to_ascii_lowercase().as_str(),
-            TABLE_VERSIONS | TABLE_PARTITIONS
+            TABLE_VERSIONS
+                | TABLE_PARTITIONS
+                | TABLE_COLUMNS
+                | PARTITION_COLUMNS
+                | FUNCTIONS
+                | STORAGE
+                | JOB_RUNS
+                | QUERIES
         )
     }
 }
@@ -86,7 +177,7 @@ trait SeafowlSystemTable: Send + Sync {
     fn schema(&self) -> SchemaRef;
 
     /// Get the rows of the system table
-    async fn load_record_batch(&self) -> Result<RecordBatch>;
+    async fn load_record_batch(&self, state: &SessionState) -> Result<RecordBatch>;
 }
 
 /// Adapter that makes any `SeafowlSystemTable` a DataFusion `TableProvider`
@@ -114,13 +205,13 @@ where
     // TODO: Investigate streaming from sqlx instead of loading all the results in memory
     async fn scan(
         &self,
-        _ctx: &SessionState,
+        ctx: &SessionState,
         projection: Option<&Vec<usize>>,
         _filters: &[Expr],
         _limit: Option<usize>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         Ok(Arc::new(MemoryExec::try_new(
-            &[vec![self.table.load_record_batch().await?]],
+            &[vec![self.table.load_record_batch(ctx).await?]],
             self.table.schema(),
             projection.cloned(),
         )?))
@@ -162,7 +253,7 @@ impl SeafowlSystemTable for TableVersionsTable {
         self.schema.clone()
     }
 
-    async fn load_record_batch(&self) -> Result<RecordBatch> {
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
         let table_versions = self
             .table_catalog
             .get_all_table_versions(&self.database, None)
@@ -234,7 +325,7 @@ impl SeafowlSystemTable for TablePartitionsTable {
         self.schema.clone()
     }
 
-    async fn load_record_batch(&self) -> Result<RecordBatch> {
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
         let table_partitions = self
             .table_catalog
             .get_all_table_partitions(&self.database)
@@ -281,3 +372,647 @@ impl SeafowlSystemTable for TablePartitionsTable {
             .map_err(DataFusionError::from)
     }
 }
+
+// Parse the serialized Arrow field stored in the catalog and return its data type
+fn column_data_type(column_type: &str) -> Result<DataType> {
+    let field = serde_json::from_str::<serde_json::Value>(column_type)
+        .map_err(|e| DataFusionError::Internal(e.to_string()))
+        .and_then(|json| field_from_json(&json).map_err(DataFusionError::from))?;
+    Ok(field.data_type().clone())
+}
+
+// Table listing the columns of each table version for the given database
+struct TableColumnsTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    table_catalog: Arc<dyn TableCatalog>,
+}
+
+impl TableColumnsTable {
+    fn new(database: Arc<str>, table_catalog: Arc<dyn TableCatalog>) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("table_schema", DataType::Utf8, false),
+                Field::new("table_name", DataType::Utf8, false),
+                Field::new("table_version_id", DataType::Int64, false),
+                Field::new("column_name", DataType::Utf8, false),
+                Field::new("data_type", DataType::Utf8, false),
+            ])),
+            table_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for TableColumnsTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
+        let table_columns = self
+            .table_catalog
+            .get_all_table_columns(&self.database)
+            .await?;
+
+        let mut builder =
+            StructBuilder::from_fields(self.schema.fields().clone(), table_columns.len());
+
+        // Construct the table columns from the returned rows
+        for table_column in &table_columns {
+            builder
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(&table_column.collection_name);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(&table_column.table_name);
+            builder
+                .field_builder::<Int64Builder>(2)
+                .unwrap()
+                .append_value(table_column.table_version_id);
+            builder
+                .field_builder::<StringBuilder>(3)
+                .unwrap()
+                .append_value(&table_column.column_name);
+            builder
+                .field_builder::<StringBuilder>(4)
+                .unwrap()
+                .append_value(column_data_type(&table_column.column_type)?.to_string());
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the statistics of each partition column used for pruning, for the given database
+struct PartitionColumnsTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    table_catalog: Arc<dyn TableCatalog>,
+}
+
+impl PartitionColumnsTable {
+    fn new(database: Arc<str>, table_catalog: Arc<dyn TableCatalog>) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("table_schema", DataType::Utf8, false),
+                Field::new("table_name", DataType::Utf8, false),
+                Field::new("table_version_id", DataType::Int64, false),
+                Field::new("table_partition_id", DataType::Int64, false),
+                Field::new("column_name", DataType::Utf8, false),
+                Field::new("data_type", DataType::Utf8, false),
+                // Min/max values are rendered as strings, since the columns can be of any type
+                Field::new("min_value", DataType::Utf8, true),
+                Field::new("max_value", DataType::Utf8, true),
+                Field::new("null_count", DataType::Int32, true),
+                // Estimated from the partition column's HyperLogLog sketch
+                Field::new("distinct_count", DataType::Int64, true),
+            ])),
+            table_catalog,
+        }
+    }
+
+    fn format_value(value: &Option<Vec<u8>>, data_type: &DataType) -> Option<String> {
+        SeafowlPruningStatistics::parse_bytes_value(&Arc::new(value.clone()), data_type)
+            .ok()
+            .filter(|v| !v.is_null())
+            .map(|v| v.to_string())
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for PartitionColumnsTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
+        let partition_columns = self
+            .table_catalog
+            .get_all_partition_columns(&self.database)
+            .await?;
+
+        let mut builder = StructBuilder::from_fields(
+            self.schema.fields().clone(),
+            partition_columns.len(),
+        );
+
+        // Construct the table columns from the returned rows
+        for partition_column in &partition_columns {
+            let data_type = column_data_type(&partition_column.column_type)?;
+
+            builder
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(&partition_column.collection_name);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(&partition_column.table_name);
+            builder
+                .field_builder::<Int64Builder>(2)
+                .unwrap()
+                .append_value(partition_column.table_version_id);
+            builder
+                .field_builder::<Int64Builder>(3)
+                .unwrap()
+                .append_value(partition_column.table_partition_id);
+            builder
+                .field_builder::<StringBuilder>(4)
+                .unwrap()
+                .append_value(&partition_column.column_name);
+            builder
+                .field_builder::<StringBuilder>(5)
+                .unwrap()
+                .append_value(data_type.to_string());
+            builder
+                .field_builder::<StringBuilder>(6)
+                .unwrap()
+                .append_option(Self::format_value(
+                    &partition_column.min_value,
+                    &data_type,
+                ));
+            builder
+                .field_builder::<StringBuilder>(7)
+                .unwrap()
+                .append_option(Self::format_value(
+                    &partition_column.max_value,
+                    &data_type,
+                ));
+            builder
+                .field_builder::<Int32Builder>(8)
+                .unwrap()
+                .append_option(partition_column.null_count);
+            builder
+                .field_builder::<Int64Builder>(9)
+                .unwrap()
+                .append_option(
+                    partition_column
+                        .distinct_sketch
+                        .as_deref()
+                        .and_then(HyperLogLog::from_bytes)
+                        .map(|sketch| sketch.estimate() as i64),
+                );
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the user-defined functions registered in the given database
+struct FunctionsTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    table_catalog: Arc<dyn TableCatalog>,
+    function_catalog: Arc<dyn FunctionCatalog>,
+}
+
+impl FunctionsTable {
+    fn new(
+        database: Arc<str>,
+        table_catalog: Arc<dyn TableCatalog>,
+        function_catalog: Arc<dyn FunctionCatalog>,
+    ) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("function_id", DataType::Int64, false),
+                Field::new("function_name", DataType::Utf8, false),
+                Field::new("entrypoint", DataType::Utf8, false),
+                Field::new("language", DataType::Utf8, false),
+                Field::new("input_types", DataType::Utf8, false),
+                Field::new("return_type", DataType::Utf8, false),
+                Field::new("volatility", DataType::Utf8, false),
+                Field::new("kind", DataType::Utf8, false),
+            ])),
+            table_catalog,
+            function_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for FunctionsTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
+        let functions = match self
+            .table_catalog
+            .get_database_id_by_name(&self.database)
+            .await?
+        {
+            Some(database_id) => {
+                self.function_catalog
+                    .get_all_functions_in_database(database_id)
+                    .await?
+            }
+            None => vec![],
+        };
+
+        let mut builder =
+            StructBuilder::from_fields(self.schema.fields().clone(), functions.len());
+
+        // Construct the table columns from the returned rows
+        for function in &functions {
+            builder
+                .field_builder::<Int64Builder>(0)
+                .unwrap()
+                .append_value(function.function_id);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(&function.name);
+            builder
+                .field_builder::<StringBuilder>(2)
+                .unwrap()
+                .append_value(&function.details.entrypoint);
+            builder
+                .field_builder::<StringBuilder>(3)
+                .unwrap()
+                .append_value(function.details.language.to_string());
+            builder
+                .field_builder::<StringBuilder>(4)
+                .unwrap()
+                .append_value(function.details.input_types.iter().join(", "));
+            builder
+                .field_builder::<StringBuilder>(5)
+                .unwrap()
+                .append_value(function.details.return_type.to_string());
+            builder
+                .field_builder::<StringBuilder>(6)
+                .unwrap()
+                .append_value(function.details.volatility.to_string());
+            builder
+                .field_builder::<StringBuilder>(7)
+                .unwrap()
+                .append_value(if function.details.is_table_function() {
+                    "Table".to_string()
+                } else {
+                    function.details.kind.to_string()
+                });
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the object store footprint of each partition used by the given database's
+// tables. Partitions can be shared between table versions, so we list each one only once
+// per table and flag whether it's still in use by the latest table version.
+struct StorageTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    table_catalog: Arc<dyn TableCatalog>,
+}
+
+impl StorageTable {
+    fn new(database: Arc<str>, table_catalog: Arc<dyn TableCatalog>) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("table_schema", DataType::Utf8, false),
+                Field::new("table_name", DataType::Utf8, false),
+                Field::new("table_partition_id", DataType::Int64, false),
+                Field::new("object_storage_id", DataType::Utf8, false),
+                Field::new("row_count", DataType::Int32, false),
+                // Null if the object is missing from the store
+                Field::new("size_bytes", DataType::Int64, true),
+                Field::new("in_latest_version", DataType::Boolean, false),
+            ])),
+            table_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for StorageTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self, state: &SessionState) -> Result<RecordBatch> {
+        let store = state
+            .runtime_env
+            .object_store(internal_object_store_url())?;
+
+        let table_partitions = self
+            .table_catalog
+            .get_all_table_partitions(&self.database)
+            .await?;
+
+        let latest_versions: HashMap<(&String, &String), i64> = table_partitions
+            .iter()
+            .map(|p| ((&p.collection_name, &p.table_name), p.table_version_id))
+            .into_grouping_map()
+            .max();
+
+        // Collapse the partition references from all versions of a table
+        let partitions: Vec<_> = table_partitions
+            .iter()
+            .filter(|p| p.table_partition_id.is_some())
+            .map(|p| ((&p.collection_name, &p.table_name, p.table_partition_id), p))
+            .into_group_map()
+            .into_iter()
+            .sorted_by_key(|(key, _)| *key)
+            .collect();
+
+        let rows: Vec<_> = partitions
+            .into_iter()
+            .map(|((collection_name, table_name, partition_id), versions)| {
+                let latest_version = latest_versions[&(collection_name, table_name)];
+                (
+                    collection_name,
+                    table_name,
+                    partition_id.unwrap(),
+                    versions[0].object_storage_id.clone().unwrap(),
+                    versions[0].row_count.unwrap_or_default(),
+                    versions
+                        .iter()
+                        .any(|v| v.table_version_id == latest_version),
+                )
+            })
+            .collect();
+
+        // Look up the object sizes concurrently, keeping them in the order of the rows
+        let paths: Vec<Path> =
+            rows.iter().map(|row| Path::from(row.3.as_str())).collect();
+        let sizes: Vec<Option<i64>> = stream::iter(paths)
+            .map(|path| {
+                let store = store.clone();
+                async move { store.head(&path).await.ok().map(|meta| meta.size as i64) }
+            })
+            .buffered(STORAGE_HEAD_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut builder =
+            StructBuilder::from_fields(self.schema.fields().clone(), rows.len());
+
+        // Construct the table columns from the collected rows
+        for (row, size_bytes) in rows.into_iter().zip(sizes) {
+            builder
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(row.0);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(row.1);
+            builder
+                .field_builder::<Int64Builder>(2)
+                .unwrap()
+                .append_value(row.2);
+            builder
+                .field_builder::<StringBuilder>(3)
+                .unwrap()
+                .append_value(row.3);
+            builder
+                .field_builder::<Int32Builder>(4)
+                .unwrap()
+                .append_value(row.4);
+            builder
+                .field_builder::<Int64Builder>(5)
+                .unwrap()
+                .append_option(size_bytes);
+            builder
+                .field_builder::<BooleanBuilder>(6)
+                .unwrap()
+                .append_value(row.5);
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the runs of the scheduled jobs
+struct JobRunsTable {
+    schema: SchemaRef,
+    table_catalog: Arc<dyn TableCatalog>,
+}
+
+impl JobRunsTable {
+    fn new(table_catalog: Arc<dyn TableCatalog>) -> Self {
+        Self {
+            // This is dictated by the output of `get_all_job_runs`. Note that jobs aren't scoped
+            // down to a particular database.
+            schema: Arc::new(Schema::new(vec![
+                Field::new("job_run_id", DataType::Int64, false),
+                Field::new("job_name", DataType::Utf8, false),
+                Field::new(
+                    "start_time",
+                    DataType::Timestamp(TimeUnit::Second, None),
+                    false,
+                ),
+                Field::new("duration_ms", DataType::Int64, true),
+                Field::new("status", DataType::Utf8, false),
+                Field::new("error", DataType::Utf8, true),
+            ])),
+            table_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for JobRunsTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
+        let job_runs = self.table_catalog.get_all_job_runs().await?;
+
+        let mut builder =
+            StructBuilder::from_fields(self.schema.fields().clone(), job_runs.len());
+
+        // Construct the table columns from the returned rows
+        for job_run in &job_runs {
+            builder
+                .field_builder::<Int64Builder>(0)
+                .unwrap()
+                .append_value(job_run.id);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(&job_run.job_name);
+            builder
+                .field_builder::<TimestampSecondBuilder>(2)
+                .unwrap()
+                .append_value(job_run.start_time);
+            builder
+                .field_builder::<Int64Builder>(3)
+                .unwrap()
+                .append_option(job_run.duration_ms);
+            builder
+                .field_builder::<StringBuilder>(4)
+                .unwrap()
+                .append_value(&job_run.status);
+            builder
+                .field_builder::<StringBuilder>(5)
+                .unwrap()
+                .append_option(job_run.error.as_ref());
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the statements recently executed through the frontends
+struct QueriesTable {
+    schema: SchemaRef,
+    query_log: Arc<QueryLog>,
+}
+
+impl QueriesTable {
+    fn new(query_log: Arc<QueryLog>) -> Self {
+        Self {
+            // The query log is kept in memory by this Seafowl instance, so it doesn't
+            // include queries executed by other instances sharing the same catalog. It has
+            // everyone's queries, so reading it needs write access (see
+            // `query_log::accesses_query_log`).
+            schema: Arc::new(Schema::new(vec![
+                Field::new("query_id", DataType::Int64, false),
+                Field::new("frontend", DataType::Utf8, false),
+                Field::new("query", DataType::Utf8, false),
+                Field::new("principal", DataType::Utf8, false),
+                Field::new("status", DataType::Utf8, false),
+                Field::new(
+                    "start_time",
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    false,
+                ),
+                Field::new(
+                    "end_time",
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    true,
+                ),
+                Field::new("rows", DataType::Int64, true),
+                Field::new("bytes", DataType::Int64, true),
+                Field::new("error", DataType::Utf8, true),
+            ])),
+            query_log,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for QueriesTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self, _state: &SessionState) -> Result<RecordBatch> {
+        let entries = self.query_log.entries();
+
+        let mut builder =
+            StructBuilder::from_fields(self.schema.fields().clone(), entries.len());
+
+        for entry in &entries {
+            builder
+                .field_builder::<Int64Builder>(0)
+                .unwrap()
+                .append_value(entry.id);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(entry.frontend.name());
+            builder
+                .field_builder::<StringBuilder>(2)
+                .unwrap()
+                .append_value(&entry.query);
+            builder
+                .field_builder::<StringBuilder>(3)
+                .unwrap()
+                .append_value(entry.principal.name());
+            builder
+                .field_builder::<StringBuilder>(4)
+                .unwrap()
+                .append_value(entry.status);
+            builder
+                .field_builder::<TimestampMillisecondBuilder>(5)
+                .unwrap()
+                .append_value(entry.start_time);
+            builder
+                .field_builder::<TimestampMillisecondBuilder>(6)
+                .unwrap()
+                .append_option(entry.end_time);
+            builder
+                .field_builder::<Int64Builder>(7)
+                .unwrap()
+                .append_option(entry.rows);
+            builder
+                .field_builder::<Int64Builder>(8)
+                .unwrap()
+                .append_option(entry.bytes);
+            builder
+                .field_builder::<StringBuilder>(9)
+                .unwrap()
+                .append_option(entry.error.as_ref());
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::catalog::schema::SchemaProvider;
+
+    use crate::catalog::{MockFunctionCatalog, MockTableCatalog};
+    use crate::query_log::QueryLog;
+
+    use super::SystemSchemaProvider;
+
+    #[test]
+    fn test_all_system_tables_exist() {
+        let provider = SystemSchemaProvider::new(
+            Arc::from("testdb"),
+            Arc::new(MockTableCatalog::new()),
+            Arc::new(MockFunctionCatalog::new()),
+            Arc::new(QueryLog::default()),
+        );
+
+        for name in provider.table_names() {
+            assert!(provider.table_exist(&name), "{name} should exist");
+            assert!(provider.table(&name).is_some(), "{name} should exist");
+        }
+        assert!(!provider.table_exist("missing"));
+    }
+}
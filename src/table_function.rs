@@ -0,0 +1,729 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::arrow::array::ArrayRef;
+use datafusion::arrow::compute::take;
+use datafusion::arrow::datatypes::{Schema, SchemaRef};
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use datafusion::catalog::catalog::{
+    CatalogList, CatalogProvider, MemoryCatalogList, MemoryCatalogProvider,
+};
+use datafusion::catalog::schema::{MemorySchemaProvider, SchemaProvider};
+use datafusion::common::DFSchemaRef;
+use datafusion::datasource::{source_as_provider, TableProvider, TableType};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::{QueryPlanner, SessionState, TaskContext};
+use datafusion::physical_expr::{PhysicalExpr, PhysicalSortExpr};
+use datafusion::physical_plan::expressions::{self, Literal};
+use datafusion::physical_plan::planner::{DefaultPhysicalPlanner, ExtensionPlanner};
+use datafusion::physical_plan::projection::ProjectionExec;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayFormatType, ExecutionPlan, Partitioning, PhysicalPlanner,
+    SendableRecordBatchStream, Statistics,
+};
+use datafusion::scalar::ScalarValue;
+use datafusion::sql::planner::{ContextProvider, SqlToRel};
+use datafusion_expr::logical_plan::{CrossJoin, Extension, Join, JoinType};
+use datafusion_expr::utils::from_plan;
+use datafusion_expr::{
+    and, Expr, LogicalPlan, LogicalPlanBuilder, UserDefinedLogicalNode,
+};
+use futures::{stream, StreamExt};
+use sqlparser::ast::{
+    Expr as SQLExpr, FunctionArg, FunctionArgExpr, Ident, ObjectName, TableAlias,
+    TableFactor, UnaryOperator, Value,
+};
+
+use crate::catalog::DEFAULT_SCHEMA;
+use crate::datafusion::utils::normalize_ident;
+use crate::datafusion::visit::{visit_table_factor, VisitorMut};
+use crate::wasm_udf::wasm::WasmTableFunction;
+
+// Catalog that holds the table function calls in a query
+pub const TABLE_FUNCTION_CATALOG: &str = "table_functions";
+
+// A struct for walking the query AST and replacing table function calls (e.g.
+// `SELECT * FROM tokenize('some text')`) with references to tables standing in for them,
+// since DataFusion's planner doesn't support table functions. These tables go into a separate
+// catalog (see `build_catalog_list`), so that they don't leak into the catalog shared with
+// other queries. The functions only get called when the query is executed: calls with
+// constant arguments are run by the stand-in table's scan, while calls whose arguments
+// reference the tables preceding them in the FROM clause (e.g.
+// `FROM docs, tokenize(docs.text)`) get planned as a node calling the function for every
+// row of those tables (see `plan_lateral_calls`).
+pub struct TableFunctionProcessor {
+    functions: HashMap<String, Arc<WasmTableFunction>>,
+    calls: Vec<(Arc<WasmTableFunction>, Vec<FunctionArg>)>,
+}
+
+impl TableFunctionProcessor {
+    pub fn new(functions: HashMap<String, Arc<WasmTableFunction>>) -> Self {
+        Self {
+            functions,
+            calls: vec![],
+        }
+    }
+
+    pub fn has_calls(&self) -> bool {
+        !self.calls.is_empty()
+    }
+
+    fn call_name(index: usize) -> String {
+        format!("call_{index}")
+    }
+
+    // Only literals (and negated numbers) are supported as the arguments of table functions
+    // that aren't joined to other tables
+    fn argument_value(arg: &FunctionArg) -> Result<ScalarValue> {
+        let (negative, value) = match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(SQLExpr::Value(value))) => {
+                (false, value)
+            }
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(SQLExpr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr,
+            })) => match expr.as_ref() {
+                SQLExpr::Value(value @ Value::Number(_, _)) => (true, value),
+                _ => return Err(Self::unsupported_argument(arg)),
+            },
+            _ => return Err(Self::unsupported_argument(arg)),
+        };
+
+        match value {
+            Value::Number(n, _) => {
+                let n = if negative {
+                    format!("-{n}")
+                } else {
+                    n.to_string()
+                };
+                match n.parse::<i64>() {
+                    Ok(n) => Ok(ScalarValue::Int64(Some(n))),
+                    Err(_) => n
+                        .parse::<f64>()
+                        .map(|n| ScalarValue::Float64(Some(n)))
+                        .map_err(|_| Self::unsupported_argument(arg)),
+                }
+            }
+            Value::SingleQuotedString(s) => Ok(ScalarValue::Utf8(Some(s.clone()))),
+            Value::Boolean(b) => Ok(ScalarValue::Boolean(Some(*b))),
+            _ => Err(Self::unsupported_argument(arg)),
+        }
+    }
+
+    fn unsupported_argument(arg: &FunctionArg) -> DataFusionError {
+        DataFusionError::Plan(format!(
+            "Unsupported table function argument {arg}: only constants and columns of the \
+            tables preceding the function in the FROM clause are supported"
+        ))
+    }
+
+    /// Return a catalog list that has the tables standing in for the table function calls
+    /// in the query on top of the existing catalogs
+    pub fn build_catalog_list(
+        &self,
+        catalog_list: &Arc<dyn CatalogList>,
+    ) -> Result<Arc<dyn CatalogList>> {
+        let calls = MemorySchemaProvider::new();
+        for (index, (function, args)) in self.calls.iter().enumerate() {
+            let table = TableFunctionTable {
+                function: function.clone(),
+                args: args.clone(),
+            };
+            calls.register_table(Self::call_name(index), Arc::new(table))?;
+        }
+
+        let catalog = MemoryCatalogProvider::new();
+        catalog.register_schema(DEFAULT_SCHEMA, Arc::new(calls))?;
+
+        let new_catalog_list = MemoryCatalogList::new();
+        for name in catalog_list.catalog_names() {
+            if let Some(existing) = catalog_list.catalog(&name) {
+                new_catalog_list.register_catalog(name, existing);
+            }
+        }
+        new_catalog_list
+            .register_catalog(TABLE_FUNCTION_CATALOG.to_string(), Arc::new(catalog));
+
+        Ok(Arc::new(new_catalog_list))
+    }
+
+    /// Replace the joins with table function calls that reference the tables on the other
+    /// side of the join (`FROM docs, tokenize(docs.text)`, `JOIN tokenize(docs.text) ON ...`)
+    /// with nodes that call the function for every row of those tables
+    pub fn plan_lateral_calls<S: ContextProvider>(
+        &self,
+        plan: LogicalPlan,
+        query_planner: &SqlToRel<S>,
+    ) -> Result<LogicalPlan> {
+        if self
+            .calls
+            .iter()
+            .all(|(_, args)| args.iter().all(|arg| Self::argument_value(arg).is_ok()))
+        {
+            return Ok(plan);
+        }
+        Self::rewrite_lateral_calls(&plan, query_planner)
+    }
+
+    fn rewrite_lateral_calls<S: ContextProvider>(
+        plan: &LogicalPlan,
+        query_planner: &SqlToRel<S>,
+    ) -> Result<LogicalPlan> {
+        match plan {
+            LogicalPlan::CrossJoin(CrossJoin { left, right, .. }) => {
+                if let Some(table) = lateral_call(right) {
+                    let left = Self::rewrite_lateral_calls(left, query_planner)?;
+                    return table.plan_lateral(left, right.schema(), query_planner);
+                }
+            }
+            LogicalPlan::Join(Join {
+                left,
+                right,
+                on,
+                filter,
+                join_type: JoinType::Inner,
+                ..
+            }) => {
+                if let Some(table) = lateral_call(right) {
+                    let left = Self::rewrite_lateral_calls(left, query_planner)?;
+                    let call = table.plan_lateral(left, right.schema(), query_planner)?;
+
+                    // The join condition gets applied to the function's results instead
+                    let predicate = on
+                        .iter()
+                        .map(|(l, r)| Expr::Column(l.clone()).eq(Expr::Column(r.clone())))
+                        .chain(filter.clone())
+                        .reduce(and);
+                    return match predicate {
+                        Some(predicate) => {
+                            LogicalPlanBuilder::from(call).filter(predicate)?.build()
+                        }
+                        None => Ok(call),
+                    };
+                }
+            }
+            _ => {}
+        }
+
+        let inputs = plan
+            .inputs()
+            .into_iter()
+            .map(|input| Self::rewrite_lateral_calls(input, query_planner))
+            .collect::<Result<Vec<_>>>()?;
+        from_plan(plan, &plan.expressions(), &inputs)
+    }
+}
+
+impl<'ast> VisitorMut<'ast> for TableFunctionProcessor {
+    fn visit_table_factor(&mut self, table_factor: &'ast mut TableFactor) {
+        if let TableFactor::Table {
+            name, alias, args, ..
+        } = table_factor
+        {
+            let function = match name.0.as_slice() {
+                [function_name] if args.is_some() => self
+                    .functions
+                    .get(&normalize_ident(function_name))
+                    .map(|f| (function_name.clone(), f.clone())),
+                _ => None,
+            };
+
+            if let Some((function_name, function)) = function {
+                // Keep referring to the results by the function's name
+                if alias.is_none() {
+                    *alias = Some(TableAlias {
+                        name: function_name,
+                        columns: vec![],
+                    });
+                }
+
+                *name = ObjectName(vec![
+                    Ident::new(TABLE_FUNCTION_CATALOG),
+                    Ident::new(DEFAULT_SCHEMA),
+                    Ident::new(Self::call_name(self.calls.len())),
+                ]);
+                self.calls.push((function, args.take().unwrap()));
+            }
+        }
+        visit_table_factor(self, table_factor)
+    }
+}
+
+// Find the table standing in for a table function call that references other tables, if
+// that's what the plan scans
+fn lateral_call(plan: &LogicalPlan) -> Option<TableFunctionTable> {
+    match plan {
+        LogicalPlan::SubqueryAlias(alias) => lateral_call(&alias.input),
+        LogicalPlan::TableScan(scan) => source_as_provider(&scan.source)
+            .ok()?
+            .as_any()
+            .downcast_ref::<TableFunctionTable>()
+            .filter(|table| table.constant_args().is_err())
+            .cloned(),
+        _ => None,
+    }
+}
+
+/// Table standing in for a table function call in a query
+#[derive(Clone)]
+struct TableFunctionTable {
+    function: Arc<WasmTableFunction>,
+    args: Vec<FunctionArg>,
+}
+
+impl TableFunctionTable {
+    fn constant_args(&self) -> Result<Vec<ScalarValue>> {
+        self.args
+            .iter()
+            .map(TableFunctionProcessor::argument_value)
+            .collect()
+    }
+
+    // Plan a call that gets its arguments from the rows of another plan
+    fn plan_lateral<S: ContextProvider>(
+        &self,
+        input: LogicalPlan,
+        function_schema: &DFSchemaRef,
+        query_planner: &SqlToRel<S>,
+    ) -> Result<LogicalPlan> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => query_planner
+                    .sql_to_rex(expr.clone(), input.schema(), &mut HashMap::new()),
+                _ => Err(TableFunctionProcessor::unsupported_argument(arg)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LogicalPlan::Extension(Extension {
+            node: Arc::new(TableFunctionNode::try_new(
+                self.function.clone(),
+                Arc::new(input),
+                args,
+                function_schema.clone(),
+            )?),
+        }))
+    }
+}
+
+#[async_trait]
+impl TableProvider for TableFunctionTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.function.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        _ctx: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let args = self
+            .constant_args()?
+            .into_iter()
+            .map(|arg| Arc::new(Literal::new(arg)) as Arc<dyn PhysicalExpr>)
+            .collect();
+        let call = Arc::new(TableFunctionExec::new(self.function.clone(), None, args));
+
+        match projection {
+            Some(projection) => {
+                let schema = call.schema();
+                let exprs = projection
+                    .iter()
+                    .map(|i| -> Result<(Arc<dyn PhysicalExpr>, String)> {
+                        let name = schema.field(*i).name();
+                        Ok((expressions::col(name, &schema)?, name.to_string()))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Arc::new(ProjectionExec::try_new(exprs, call)?))
+            }
+            None => Ok(call),
+        }
+    }
+}
+
+/// Logical plan node that calls a table function for every row of its input, producing the
+/// input row's columns followed by the function's results for every row that it returns
+#[derive(Debug)]
+pub struct TableFunctionNode {
+    function: Arc<WasmTableFunction>,
+    input: Arc<LogicalPlan>,
+    args: Vec<Expr>,
+    function_schema: DFSchemaRef,
+    schema: DFSchemaRef,
+}
+
+impl TableFunctionNode {
+    fn try_new(
+        function: Arc<WasmTableFunction>,
+        input: Arc<LogicalPlan>,
+        args: Vec<Expr>,
+        function_schema: DFSchemaRef,
+    ) -> Result<Self> {
+        let schema = Arc::new(input.schema().join(&function_schema)?);
+        Ok(Self {
+            function,
+            input,
+            args,
+            function_schema,
+            schema,
+        })
+    }
+}
+
+impl UserDefinedLogicalNode for TableFunctionNode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![self.input.as_ref()]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        self.args.clone()
+    }
+
+    fn fmt_for_explain(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TableFunction: {}(", self.function.name())?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{arg}")?;
+        }
+        write!(f, ")")
+    }
+
+    fn from_template(
+        &self,
+        exprs: &[Expr],
+        inputs: &[LogicalPlan],
+    ) -> Arc<dyn UserDefinedLogicalNode> {
+        // The optimizer can prune the input's columns, so the schema has to be rebuilt
+        Arc::new(
+            Self::try_new(
+                self.function.clone(),
+                Arc::new(inputs[0].clone()),
+                exprs.to_vec(),
+                self.function_schema.clone(),
+            )
+            .expect("Table function results clash with the input's columns"),
+        )
+    }
+}
+
+/// Plans the table function nodes (see `TableFunctionNode`)
+pub struct TableFunctionPlanner {}
+
+#[async_trait]
+impl ExtensionPlanner for TableFunctionPlanner {
+    async fn plan_extension(
+        &self,
+        planner: &dyn PhysicalPlanner,
+        node: &dyn UserDefinedLogicalNode,
+        logical_inputs: &[&LogicalPlan],
+        physical_inputs: &[Arc<dyn ExecutionPlan>],
+        session_state: &SessionState,
+    ) -> Result<Option<Arc<dyn ExecutionPlan>>> {
+        let node = match node.as_any().downcast_ref::<TableFunctionNode>() {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+
+        let input = physical_inputs[0].clone();
+        let args = node
+            .args
+            .iter()
+            .map(|arg| {
+                planner.create_physical_expr(
+                    arg,
+                    logical_inputs[0].schema(),
+                    &input.schema(),
+                    session_state,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Arc::new(TableFunctionExec::new(
+            node.function.clone(),
+            Some(input),
+            args,
+        ))))
+    }
+}
+
+/// Query planner that also knows how to plan table function calls
+pub struct SeafowlQueryPlanner {}
+
+#[async_trait]
+impl QueryPlanner for SeafowlQueryPlanner {
+    async fn create_physical_plan(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        DefaultPhysicalPlanner::with_extension_planners(vec![Arc::new(
+            TableFunctionPlanner {},
+        )])
+        .create_physical_plan(logical_plan, session_state)
+        .await
+    }
+}
+
+/// Calls a table function for every row of the input (or once, if there's no input), with
+/// arguments computed from that row
+#[derive(Debug)]
+pub struct TableFunctionExec {
+    function: Arc<WasmTableFunction>,
+    input: Option<Arc<dyn ExecutionPlan>>,
+    args: Vec<Arc<dyn PhysicalExpr>>,
+    schema: SchemaRef,
+}
+
+impl TableFunctionExec {
+    pub fn new(
+        function: Arc<WasmTableFunction>,
+        input: Option<Arc<dyn ExecutionPlan>>,
+        args: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Self {
+        let schema = match &input {
+            Some(input) => Arc::new(Schema::new(
+                input
+                    .schema()
+                    .fields()
+                    .iter()
+                    .chain(function.schema().fields())
+                    .cloned()
+                    .collect(),
+            )),
+            None => function.schema(),
+        };
+
+        Self {
+            function,
+            input,
+            args,
+            schema,
+        }
+    }
+}
+
+impl ExecutionPlan for TableFunctionExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        let partitions = match &self.input {
+            Some(input) => input.output_partitioning().partition_count(),
+            None => 1,
+        };
+        Partitioning::UnknownPartitioning(partitions)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        self.input.iter().cloned().collect()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(Self::new(
+            self.function.clone(),
+            children.into_iter().next(),
+            self.args.clone(),
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let (function, args, schema) = (
+            self.function.clone(),
+            self.args.clone(),
+            self.schema.clone(),
+        );
+
+        let input = match &self.input {
+            Some(input) => input.execute(partition, context)?,
+            None => {
+                // Evaluate the (constant) arguments on a single empty row
+                let batch = RecordBatch::try_new_with_options(
+                    Arc::new(Schema::empty()),
+                    vec![],
+                    &RecordBatchOptions::new().with_row_count(Some(1)),
+                )?;
+                Box::pin(RecordBatchStreamAdapter::new(
+                    batch.schema(),
+                    stream::once(async { Ok(batch) }),
+                ))
+            }
+        };
+
+        let results = input.map(move |batch| {
+            batch.and_then(|batch| {
+                call_for_each_row(&function, &args, &schema, &batch)
+                    .map_err(ArrowError::from)
+            })
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            results,
+        )))
+    }
+
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TableFunctionExec: function={}", self.function.name())
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+fn call_for_each_row(
+    function: &WasmTableFunction,
+    args: &[Arc<dyn PhysicalExpr>],
+    schema: &SchemaRef,
+    batch: &RecordBatch,
+) -> Result<RecordBatch> {
+    let args = args
+        .iter()
+        .map(|arg| Ok(arg.evaluate(batch)?.into_array(batch.num_rows())))
+        .collect::<Result<Vec<ArrayRef>>>()?;
+    let (results, indices) = function.call_rows(&args, batch.num_rows())?;
+
+    // Repeat the input row for every row that the function returned for it
+    let mut columns = batch
+        .columns()
+        .iter()
+        .map(|column| take(column.as_ref(), &indices, None))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    columns.extend(results.columns().iter().cloned());
+
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ops::Deref;
+    use std::sync::Arc;
+
+    use crate::datafusion::parser::Statement;
+    use sqlparser::ast::{Query, Statement as SQLStatement};
+
+    use crate::datafusion::parser::DFParser;
+    use crate::datafusion::visit::VisitorMut;
+    use crate::wasm_udf::data_types::{
+        CreateFunctionColumn, CreateFunctionDataType, CreateFunctionLanguage,
+        WasiCapabilities, WasmLimits,
+    };
+    use crate::wasm_udf::wasm::create_table_function_from_wasm;
+
+    use super::TableFunctionProcessor;
+
+    fn parse_query(query: &str) -> Box<Query> {
+        let stmts = DFParser::parse_sql(query).unwrap();
+
+        if let Statement::Statement(stmt) = &stmts[0] {
+            if let SQLStatement::Query(query) = stmt.deref() {
+                query.clone()
+            } else {
+                panic!("Expected Query not matched!");
+            }
+        } else {
+            panic!("Expected Statement not matched!");
+        }
+    }
+
+    fn series_processor() -> TableFunctionProcessor {
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/messagepack_aggregate.wasm");
+
+        let function = create_table_function_from_wasm(
+            &CreateFunctionLanguage::WasmMessagePack,
+            &std::fs::read(wasm_filename).unwrap(),
+            "series",
+            &[CreateFunctionDataType::INT],
+            &[CreateFunctionColumn {
+                name: "i".to_string(),
+                data_type: CreateFunctionDataType::INT,
+            }],
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .unwrap();
+
+        TableFunctionProcessor::new(HashMap::from([(
+            "series".to_string(),
+            Arc::new(function),
+        )]))
+    }
+
+    #[test]
+    fn test_table_function_rewrite() {
+        let mut q = parse_query(
+            "SELECT * FROM series(3) JOIN SERIES(2) AS s ON series.i = s.i \
+            JOIN some_table('some_version') AS t ON t.i = s.i",
+        );
+
+        let mut processor = series_processor();
+        processor.visit_query(&mut q);
+
+        assert_eq!(processor.calls.len(), 2);
+        assert_eq!(
+            q.to_string(),
+            "SELECT * FROM table_functions.public.call_0 AS series \
+            JOIN table_functions.public.call_1 AS s ON series.i = s.i \
+            JOIN some_table('some_version') AS t ON t.i = s.i"
+        );
+    }
+
+    #[test]
+    fn test_table_function_non_constant_argument() {
+        let mut q = parse_query("SELECT * FROM series(1 + 2)");
+
+        let mut processor = series_processor();
+        processor.visit_query(&mut q);
+
+        let err =
+            TableFunctionProcessor::argument_value(&processor.calls[0].1[0]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Unsupported table function argument 1 + 2: only constants and \
+            columns of the tables preceding the function in the FROM clause are supported"
+        );
+    }
+}
@@ -3,10 +3,11 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use arrow::json::LineDelimitedWriter;
-use datafusion::error::Result;
+use datafusion::error::{DataFusionError, Result};
 use hex::encode;
 use itertools::Itertools;
 use log::{debug, info, warn};
@@ -14,7 +15,12 @@ use object_store::path::Path;
 use sha2::{Digest, Sha256};
 use tokio::{fs::File, io::AsyncWrite};
 
+use crate::config::schema::{Retention, RetentionPolicy};
 use crate::context::{DefaultSeafowlContext, SeafowlContext};
+use crate::data_types::{TableVersionId, Timestamp};
+use crate::metrics::{
+    GC_PARTITIONS_DELETED, GC_PARTITION_DELETE_FAILURES, TABLE_VERSIONS_DELETED,
+};
 use crate::provider::SeafowlPartition;
 
 // Run a one-off command and output its results to a writer
@@ -42,53 +48,162 @@ pub async fn run_one_off_command<W>(
     }
 }
 
-pub async fn gc_partitions(context: &DefaultSeafowlContext) {
-    match context
+/// Delete the partitions that no table version references anymore, from the object store
+/// and then the catalog. Partitions that fail to get deleted from the object store are
+/// kept in the catalog (so that the next run retries them) and fail the whole run.
+pub async fn gc_partitions(context: &DefaultSeafowlContext) -> Result<()> {
+    let mut object_storage_ids = context
         .partition_catalog
         .get_orphan_partition_store_ids()
+        .await?;
+    if object_storage_ids.is_empty() {
+        debug!("No orphan partitions to cleanup found");
+        return Ok(());
+    }
+    info!("Found {} orphan partition(s)", object_storage_ids.len());
+
+    let mut retain_map = vec![true; object_storage_ids.len()];
+    for (ind, object_storage_id) in object_storage_ids.iter().enumerate() {
+        context
+            .internal_object_store
+            .inner
+            .delete(&Path::from(object_storage_id.clone()))
+            .await
+            .map_err(|e| {
+                if format!("{e:?}").contains("NotFound") {
+                    // This is the way we match both object_store::Error::NotFound and the
+                    // corresponding local FS error (which doesn't get coerced into the above one):
+                    // Generic { store: "LocalFileSystem", source: UnableToDeleteFile { source: Os { code: 2, kind: NotFound, message: "No such file or directory" }, path: "/path/to/.parquet" } }
+                    // We're not able to do pattern matching here since UnableToDeleteFile
+                    // is pub(crate), and we can't use a guard to check on only the content of the
+                    // corresponding source and not source from other pattern alternatives:
+                    // https://stackoverflow.com/a/42355656
+                    info!(
+                        "Object {} not found in store; deleting from catalog",
+                        object_storage_id
+                    );
+                } else {
+                    warn!(
+                        "Failed to delete orphan partition {} from object store: {:?}",
+                        object_storage_id, e
+                    );
+                    GC_PARTITION_DELETE_FAILURES.inc();
+                    retain_map[ind] = false;
+                }
+            })
+            .ok();
+    }
+
+    // Scope down only to partitions which we managed to delete in the object store
+    let failures = retain_map.iter().filter(|keep| !**keep).count();
+    let mut keep = retain_map.iter();
+    object_storage_ids.retain(|_| *keep.next().unwrap());
+
+    let row_count = context
+        .partition_catalog
+        .delete_partitions(object_storage_ids)
+        .await?;
+    info!("Deleted {} orphan partition(s)", row_count);
+    GC_PARTITIONS_DELETED.inc_by(row_count);
+
+    if failures > 0 {
+        return Err(DataFusionError::Execution(format!(
+            "Failed to delete {failures} orphan partition(s) from the object store"
+        )));
+    }
+    Ok(())
+}
+
+// Pick the versions of a single table that fall outside of the retention policy. The latest
+// version is always kept, as is any version that satisfies at least one of the criteria.
+pub fn versions_beyond_retention(
+    mut versions: Vec<(TableVersionId, Timestamp)>,
+    policy: &RetentionPolicy,
+    now: Timestamp,
+) -> Vec<TableVersionId> {
+    if policy.is_empty() {
+        return vec![];
+    }
+
+    // Newest versions first
+    versions.sort_by(|(id_a, time_a), (id_b, time_b)| {
+        time_b.cmp(time_a).then(id_b.cmp(id_a))
+    });
+
+    versions
+        .into_iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(ind, (_, creation_time))| {
+            let keep_by_count = policy
+                .keep_versions
+                .map_or(false, |keep_versions| *ind < keep_versions as usize);
+            let keep_by_age = policy
+                .keep_duration_secs
+                .map_or(false, |secs| now - creation_time < secs as i64);
+            !keep_by_count && !keep_by_age
+        })
+        .map(|(_, (id, _))| id)
+        .collect()
+}
+
+pub async fn enforce_retention(context: &DefaultSeafowlContext, retention: &Retention) {
+    let all_versions = match context
+        .table_catalog
+        .get_all_table_versions(&context.database, None)
         .await
     {
-        Ok(mut object_storage_ids) if !object_storage_ids.is_empty() => {
-            info!("Found {} orphan partition(s)", object_storage_ids.len());
-
-            let mut retain_map = vec![true; object_storage_ids.len()];
-            for (ind, object_storage_id) in object_storage_ids.iter().enumerate() {
-                context
-                    .internal_object_store
-                    .inner
-                    .delete(&Path::from(object_storage_id.clone()))
-                    .await
-                    .map_err(|e| if format!("{e:?}").contains("NotFound") {
-                        // This is the way we match both object_store::Error::NotFound and the
-                        // corresponding local FS error (which doesn't get coerced into the above one):
-                        // Generic { store: "LocalFileSystem", source: UnableToDeleteFile { source: Os { code: 2, kind: NotFound, message: "No such file or directory" }, path: "/path/to/.parquet" } }
-                        // We're not able to do pattern matching here since UnableToDeleteFile
-                        // is pub(crate), and we can't use a guard to check on only the content of the
-                        // corresponding source and not source from other pattern alternatives:
-                        // https://stackoverflow.com/a/42355656
-                        info!("Object {} not found in store; deleting from catalog", object_storage_id);
-                    } else {
-                        warn!("Failed to delete orphan partition {} from object store: {:?}", object_storage_id, e);
-                        retain_map[ind] = false;
-                    })
-                    .ok();
-            }
+        Ok(all_versions) => all_versions,
+        Err(e) => {
+            warn!("Failed to fetch table versions: {:?}", e);
+            return;
+        }
+    };
 
-            // Scope down only to partitions which we managed to delete in the object store
-            let mut keep = retain_map.iter();
-            object_storage_ids.retain(|_| *keep.next().unwrap());
-
-            context
-                .partition_catalog
-                .delete_partitions(object_storage_ids)
-                .await
-                .map_or_else(
-                    |e| warn!("Failed to delete orphan partitions from catalog: {:?}", e),
-                    |row_count| info!("Deleted {} orphan partition(s)", row_count),
-                );
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before the Unix epoch")
+        .as_secs() as Timestamp;
+
+    let to_delete: Vec<TableVersionId> = all_versions
+        .into_iter()
+        .map(|v| {
+            (
+                (v.collection_name, v.table_name),
+                (v.table_version_id, v.creation_time),
+            )
+        })
+        .into_group_map()
+        .into_iter()
+        .flat_map(|((collection_name, table_name), versions)| {
+            versions_beyond_retention(
+                versions,
+                &retention.policy_for(&collection_name, &table_name),
+                now,
+            )
+        })
+        .collect();
+
+    if to_delete.is_empty() {
+        debug!("No table versions outside of the retention policy found");
+        return;
+    }
+
+    match context.table_catalog.delete_table_versions(to_delete).await {
+        Ok(row_count) => {
+            info!(
+                "Deleted {} table version(s) per retention policy",
+                row_count
+            );
+            TABLE_VERSIONS_DELETED
+                .with_label_values(&["retention"])
+                .inc_by(row_count);
+            // Purge the partitions that are no longer referenced by any version
+            if let Err(e) = gc_partitions(context).await {
+                warn!("Failed to delete orphan partitions: {:?}", e);
+            }
         }
-        Err(e) => warn!("Failed to fetch orphan partitions: {:?}", e),
-        _ => debug!("No orphan partitions to cleanup found"),
+        Err(e) => warn!("Failed to delete table versions: {:?}", e),
     }
 }
 
@@ -181,7 +296,8 @@ mod tests {
     use tempfile::NamedTempFile;
     use tokio::{fs::OpenOptions, io::AsyncWriteExt};
 
-    use super::{hash_file, run_one_off_command};
+    use super::{hash_file, run_one_off_command, versions_beyond_retention};
+    use crate::config::schema::RetentionPolicy;
     use crate::context::test_utils::in_memory_context;
 
     #[tokio::test]
@@ -197,6 +313,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_versions_beyond_retention() {
+        let versions = vec![(1, 100), (2, 200), (3, 300), (4, 400)];
+
+        // No policy: keep everything
+        assert_eq!(
+            versions_beyond_retention(
+                versions.clone(),
+                &RetentionPolicy::default(),
+                1000
+            ),
+            Vec::<i64>::new()
+        );
+
+        // Keep the 2 latest versions
+        assert_eq!(
+            versions_beyond_retention(
+                versions.clone(),
+                &RetentionPolicy {
+                    keep_versions: Some(2),
+                    keep_duration_secs: None,
+                },
+                1000
+            ),
+            vec![2, 1]
+        );
+
+        // Keep versions newer than 750s
+        assert_eq!(
+            versions_beyond_retention(
+                versions.clone(),
+                &RetentionPolicy {
+                    keep_versions: None,
+                    keep_duration_secs: Some(750),
+                },
+                1000
+            ),
+            vec![2, 1]
+        );
+
+        // Either criterion is enough to keep a version
+        assert_eq!(
+            versions_beyond_retention(
+                versions.clone(),
+                &RetentionPolicy {
+                    keep_versions: Some(3),
+                    keep_duration_secs: Some(750),
+                },
+                1000
+            ),
+            vec![1]
+        );
+
+        // The latest version is always kept
+        assert_eq!(
+            versions_beyond_retention(
+                versions,
+                &RetentionPolicy {
+                    keep_versions: None,
+                    keep_duration_secs: Some(1),
+                },
+                1000
+            ),
+            vec![3, 2, 1]
+        );
+    }
+
     #[tokio::test]
     async fn test_hash_file() {
         let file_path = NamedTempFile::new().unwrap().into_temp_path();
@@ -222,7 +222,7 @@ impl<'ast> VisitorMut<'ast> for TableVersionProcessor {
 #[cfg(test)]
 mod tests {
     use crate::data_types::TableVersionId;
-    use datafusion::sql::parser::Statement;
+    use crate::datafusion::parser::Statement;
     use rstest::rstest;
     use sqlparser::ast::Statement as SQLStatement;
     use std::ops::Deref;
@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use datafusion::catalog::catalog::CatalogProvider;
+use datafusion::error::{DataFusionError, Result};
+use sqlparser::ast::{ObjectName, Query, Statement, TableAlias, TableFactor};
+
+use crate::datafusion::parser::{DFParser, Statement as DFStatement};
+use crate::datafusion::utils::normalize_ident;
+use crate::datafusion::visit::{visit_query, visit_table_factor, VisitorMut};
+use crate::provider::SeafowlView;
+
+// Guard against views that (indirectly) reference themselves
+const MAX_VIEW_DEPTH: usize = 32;
+
+/// Parse the stored definition of a (materialized) view, returning `None` if it isn't
+/// a single query
+pub fn parse_single_query(sql: &str) -> Result<Option<Query>> {
+    let mut statements = DFParser::parse_sql(sql)?;
+
+    match (statements.pop_front(), statements.is_empty()) {
+        (Some(DFStatement::Statement(s)), true) => match *s {
+            Statement::Query(query) => Ok(Some(*query)),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+// A struct for walking the query AST and replacing references to views with their definition
+// (i.e. `SELECT * FROM some_view` becomes `SELECT * FROM (SELECT ...) AS some_view`). This
+// has to run before the table function and the version processors, so that the view's
+// definition can use time travel and table functions too. Names of CTEs in scope shadow the
+// views with the same name.
+pub struct ViewProcessor {
+    catalog: Arc<dyn CatalogProvider>,
+    default_catalog: String,
+    default_schema: String,
+    cte_names: Vec<Vec<String>>,
+    depth: usize,
+    error: Option<DataFusionError>,
+}
+
+impl ViewProcessor {
+    pub fn new(
+        catalog: Arc<dyn CatalogProvider>,
+        default_catalog: String,
+        default_schema: String,
+    ) -> Self {
+        Self {
+            catalog,
+            default_catalog,
+            default_schema,
+            cte_names: vec![],
+            depth: 0,
+            error: None,
+        }
+    }
+
+    /// Expand all views referenced by the query, including the ones referenced by other views
+    pub fn expand(&mut self, query: &mut Query) -> Result<()> {
+        self.visit_query(query);
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn get_view(&self, name: &ObjectName) -> Option<Arc<SeafowlView>> {
+        let (catalog, schema, table) = match name.0.as_slice() {
+            [table] => {
+                let table = normalize_ident(table);
+                if self.cte_names.iter().flatten().any(|cte| *cte == table) {
+                    return None;
+                }
+                (
+                    self.default_catalog.clone(),
+                    self.default_schema.clone(),
+                    table,
+                )
+            }
+            [schema, table] => (
+                self.default_catalog.clone(),
+                normalize_ident(schema),
+                normalize_ident(table),
+            ),
+            [catalog, schema, table] => (
+                normalize_ident(catalog),
+                normalize_ident(schema),
+                normalize_ident(table),
+            ),
+            _ => return None,
+        };
+
+        if catalog != self.default_catalog {
+            return None;
+        }
+
+        let provider = self.catalog.schema(&schema)?.table(&table)?;
+        provider
+            .as_any()
+            .downcast_ref::<SeafowlView>()
+            .map(|view| Arc::new(view.clone()))
+    }
+
+    pub fn parse_definition(view: &SeafowlView) -> Result<Query> {
+        parse_single_query(&view.definition)?
+            .ok_or_else(|| Self::invalid_definition(view))
+    }
+
+    fn invalid_definition(view: &SeafowlView) -> DataFusionError {
+        DataFusionError::Internal(format!(
+            "Definition of the view {}.{} isn't a query",
+            view.collection_name, view.name
+        ))
+    }
+}
+
+impl<'ast> VisitorMut<'ast> for ViewProcessor {
+    fn visit_query(&mut self, query: &'ast mut Query) {
+        let cte_names = query
+            .with
+            .iter()
+            .flat_map(|with| with.cte_tables.iter())
+            .map(|cte| normalize_ident(&cte.alias.name))
+            .collect();
+
+        self.cte_names.push(cte_names);
+        visit_query(self, query);
+        self.cte_names.pop();
+    }
+
+    fn visit_table_factor(&mut self, table_factor: &'ast mut TableFactor) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let view = match &*table_factor {
+            TableFactor::Table {
+                name,
+                args: None,
+                with_hints,
+                ..
+            } if with_hints.is_empty() => self.get_view(name),
+            _ => None,
+        };
+
+        let view = match view {
+            Some(view) => view,
+            None => return visit_table_factor(self, table_factor),
+        };
+
+        if self.depth >= MAX_VIEW_DEPTH {
+            self.error = Some(DataFusionError::Plan(format!(
+                "View {}.{} is nested too deeply (does it reference itself?)",
+                view.collection_name, view.name
+            )));
+            return;
+        }
+
+        let subquery = match Self::parse_definition(&view) {
+            Ok(subquery) => subquery,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+
+        if let TableFactor::Table { name, alias, .. } = table_factor {
+            // Keep referring to the results by the view's name
+            let alias = alias.take().unwrap_or_else(|| TableAlias {
+                name: name.0.last().unwrap().clone(),
+                columns: vec![],
+            });
+
+            *table_factor = TableFactor::Derived {
+                lateral: false,
+                subquery: Box::new(subquery),
+                alias: Some(alias),
+            };
+        }
+
+        // Expand the views referenced by this view's definition. The definition can't see
+        // the CTEs from the outer query.
+        let cte_names = std::mem::take(&mut self.cte_names);
+        self.depth += 1;
+        visit_table_factor(self, table_factor);
+        self.depth -= 1;
+        self.cte_names = cte_names;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use std::sync::Arc;
+
+    use crate::datafusion::parser::Statement;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::catalog::catalog::{CatalogProvider, MemoryCatalogProvider};
+    use datafusion::catalog::schema::{MemorySchemaProvider, SchemaProvider};
+    use sqlparser::ast::{Query, Statement as SQLStatement};
+
+    use crate::datafusion::parser::DFParser;
+    use crate::provider::SeafowlView;
+
+    use super::ViewProcessor;
+
+    fn parse_query(query: &str) -> Box<Query> {
+        let stmts = DFParser::parse_sql(query).unwrap();
+
+        if let Statement::Statement(stmt) = &stmts[0] {
+            if let SQLStatement::Query(query) = stmt.deref() {
+                query.clone()
+            } else {
+                panic!("Expected Query not matched!");
+            }
+        } else {
+            panic!("Expected Statement not matched!");
+        }
+    }
+
+    fn view_processor(views: &[(&str, &str)]) -> ViewProcessor {
+        let schema = MemorySchemaProvider::new();
+        for (index, (name, definition)) in views.iter().enumerate() {
+            schema
+                .register_table(
+                    name.to_string(),
+                    Arc::new(SeafowlView {
+                        view_id: index as i64,
+                        collection_name: Arc::from("public"),
+                        name: Arc::from(*name),
+                        definition: definition.to_string(),
+                        schema: Arc::new(Schema::new(vec![Field::new(
+                            "a",
+                            DataType::Int64,
+                            true,
+                        )])),
+                    }),
+                )
+                .unwrap();
+        }
+
+        let catalog = MemoryCatalogProvider::new();
+        catalog.register_schema("public", Arc::new(schema)).unwrap();
+
+        ViewProcessor::new(
+            Arc::new(catalog),
+            "default".to_string(),
+            "public".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_view_expansion() {
+        let mut processor = view_processor(&[
+            ("v1", "SELECT a FROM t1('2022-01-01T00:00:00Z')"),
+            ("v2", "SELECT a FROM v1 WHERE a > 1"),
+        ]);
+
+        let mut q = parse_query(
+            "WITH v1 AS (SELECT 1 AS a) SELECT * FROM v2 \
+            JOIN public.v1 AS other ON v2.a = other.a JOIN v1 ON v1.a = v2.a",
+        );
+        processor.expand(&mut q).unwrap();
+
+        assert_eq!(
+            q.to_string(),
+            "WITH v1 AS (SELECT 1 AS a) SELECT * FROM \
+            (SELECT a FROM (SELECT a FROM t1('2022-01-01T00:00:00Z')) AS v1 WHERE a > 1) AS v2 \
+            JOIN (SELECT a FROM t1('2022-01-01T00:00:00Z')) AS other ON v2.a = other.a \
+            JOIN v1 ON v1.a = v2.a"
+        );
+    }
+
+    #[test]
+    fn test_view_cycle() {
+        let mut processor =
+            view_processor(&[("v1", "SELECT a FROM v2"), ("v2", "SELECT a FROM v1")]);
+
+        let mut q = parse_query("SELECT * FROM v1");
+        let err = processor.expand(&mut q).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("is nested too deeply (does it reference itself?)"));
+    }
+}
@@ -3,6 +3,7 @@ use datafusion::error::DataFusionError;
 use datafusion::logical_expr::Volatility;
 use serde::de::{Deserializer, Error, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use strum_macros::{Display, EnumString};
 use wasmtime::ValType;
@@ -58,6 +59,37 @@ pub enum CreateFunctionDataType {
     BOOLEAN,
     DATE,
     TIMESTAMP,
+    // Only valid as the return type of table functions, whose columns are
+    // defined in `return_columns` instead
+    TABLE,
+}
+
+impl CreateFunctionDataType {
+    /// Collapse the type names that are aliases of each other (i.e. map to the same
+    /// Arrow type, like INT and I32) into a single one
+    fn canonical(&self) -> Self {
+        match self {
+            CreateFunctionDataType::I32 => CreateFunctionDataType::INT,
+            CreateFunctionDataType::I64 => CreateFunctionDataType::BIGINT,
+            CreateFunctionDataType::F32 | CreateFunctionDataType::FLOAT => {
+                CreateFunctionDataType::REAL
+            }
+            CreateFunctionDataType::F64 => CreateFunctionDataType::DOUBLE,
+            CreateFunctionDataType::CHAR | CreateFunctionDataType::VARCHAR => {
+                CreateFunctionDataType::TEXT
+            }
+            t => t.clone(),
+        }
+    }
+}
+
+/// Normalized signature of a function's input types, which tells its overloads apart in
+/// the catalog: a JSON array of the canonical type names (see the `function_signature`
+/// migration, which computes the same thing for the existing functions)
+pub fn function_signature(input_types: &[CreateFunctionDataType]) -> String {
+    let canonical: Vec<CreateFunctionDataType> =
+        input_types.iter().map(|t| t.canonical()).collect();
+    serde_json::to_string(&canonical).expect("Couldn't serialize input types!")
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, EnumString, Display, Clone)]
@@ -73,11 +105,26 @@ impl Default for CreateFunctionVolatility {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, EnumString, Display, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum CreateFunctionKind {
+    Scalar,
+    // Implemented by {entrypoint}_init, {entrypoint}_update, {entrypoint}_merge
+    // and {entrypoint}_finalize, with the state passed around as MessagePack
+    Aggregate,
+}
+impl Default for CreateFunctionKind {
+    fn default() -> Self {
+        CreateFunctionKind::Scalar
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, EnumString, Display, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum CreateFunctionLanguage {
     Wasm,
     WasmMessagePack,
+    WasmArrow,
 }
 impl Default for CreateFunctionLanguage {
     fn default() -> Self {
@@ -85,7 +132,7 @@ impl Default for CreateFunctionLanguage {
     }
 }
 
-fn parse_create_function_data_type(
+pub fn parse_create_function_data_type(
     raw: &str,
 ) -> Result<CreateFunctionDataType, strum::ParseError> {
     CreateFunctionDataType::from_str(&raw.to_ascii_uppercase())
@@ -137,6 +184,91 @@ where
         .map_err(|_| D::Error::custom(format!("unsupported data type: {s}")))
 }
 
+/// A column in the table returned by a table function
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CreateFunctionColumn {
+    pub name: String,
+    #[serde(rename = "type", deserialize_with = "deserialize_datatype")]
+    pub data_type: CreateFunctionDataType,
+}
+
+/// Resource limits for running WASM functions. These are set globally in the
+/// `[runtime.wasm_limits]` section of the config and can be overridden per function,
+/// although a function's limits can only be stricter than the global ones.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct WasmLimits {
+    // Fuel (roughly, the number of WASM instructions) that a single call can use up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fuel: Option<u64>,
+    // Wall-clock time (in milliseconds) that a single call can take
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    // Maximum size (in MiB) of the module's linear memory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<u64>,
+}
+
+impl WasmLimits {
+    /// Combine these limits with the ones requested for a function, using the stricter
+    /// one of each
+    pub fn restrict(&self, requested: &WasmLimits) -> WasmLimits {
+        let min = |configured: Option<u64>, requested: Option<u64>| match (
+            configured, requested,
+        ) {
+            (Some(configured), Some(requested)) => Some(configured.min(requested)),
+            (configured, requested) => configured.or(requested),
+        };
+
+        WasmLimits {
+            max_fuel: min(self.max_fuel, requested.max_fuel),
+            timeout_ms: min(self.timeout_ms, requested.timeout_ms),
+            max_memory: min(self.max_memory, requested.max_memory),
+        }
+    }
+}
+
+/// WASI capabilities of a function. Functions only get a WASI context if it's enabled here,
+/// and it doesn't give them access to anything (there's never any filesystem access) unless
+/// it's allowed here as well.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct WasiCapabilities {
+    // Link the WASI imports at all (modules built for a WASI target can't be loaded without
+    // them). The other capabilities can only be granted along with this one.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub enabled: bool,
+    // Environment variables visible to the function
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    // Use the real clocks instead of ones stuck at the Unix epoch
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub clocks: bool,
+    // Seed the random number generator from the OS instead of a fixed seed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub random: bool,
+}
+
+impl WasiCapabilities {
+    /// Capabilities that a function can actually use: immutable functions always have to
+    /// return the same result for the same arguments, so they don't get the real clocks or
+    /// random numbers
+    pub fn for_volatility(
+        &self,
+        volatility: &CreateFunctionVolatility,
+    ) -> WasiCapabilities {
+        match volatility {
+            CreateFunctionVolatility::Immutable => WasiCapabilities {
+                enabled: self.enabled,
+                env: self.env.clone(),
+                clocks: false,
+                random: false,
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct CreateFunctionDetails {
     pub entrypoint: String,
@@ -146,9 +278,37 @@ pub struct CreateFunctionDetails {
     pub input_types: Vec<CreateFunctionDataType>,
     #[serde(deserialize_with = "deserialize_datatype")]
     pub return_type: CreateFunctionDataType,
+    // Base64-encoded WASM module. Either this or `location` has to be specified.
+    #[serde(default)]
     pub data: String,
+    // URL of the WASM module (`seafowl://`, `s3://`, `https://`, ...), fetched through the
+    // registered object stores. The module gets copied into the internal object store
+    // (addressed by its SHA-256 digest) when the function is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    // Expected SHA-256 digest of the module (hex-encoded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
     #[serde(default)]
     pub volatility: CreateFunctionVolatility,
+    #[serde(default)]
+    pub kind: CreateFunctionKind,
+    #[serde(default)]
+    pub return_columns: Vec<CreateFunctionColumn>,
+    #[serde(default)]
+    pub limits: WasmLimits,
+    #[serde(default)]
+    pub wasi: WasiCapabilities,
+}
+
+impl CreateFunctionDetails {
+    pub fn is_table_function(&self) -> bool {
+        self.return_type == CreateFunctionDataType::TABLE
+    }
+
+    pub fn signature(&self) -> String {
+        function_signature(&self.input_types)
+    }
 }
 
 #[cfg(test)]
@@ -181,8 +341,121 @@ mod tests {
                 ],
                 return_type: CreateFunctionDataType::BIGINT,
                 data: "AGFzbQEAAAABGAVgA35".to_string(),
-                volatility: CreateFunctionVolatility::Volatile
+                location: None,
+                sha256: None,
+                volatility: CreateFunctionVolatility::Volatile,
+                kind: CreateFunctionKind::Scalar,
+                return_columns: vec![],
+                limits: WasmLimits::default(),
+                wasi: WasiCapabilities::default(),
             }
         )
     }
+
+    #[test]
+    fn test_create_table_function_details_parsing() {
+        let details: CreateFunctionDetails = serde_json::from_str(
+            r#"{
+            "entrypoint": "tokenize",
+            "language": "wasmMessagePack",
+            "input_types": ["text"],
+            "return_type": "table",
+            "return_columns": [{"name": "token", "type": "text"}, {"name": "position", "type": "INT"}],
+            "data": "AGFzbQEAAAABGAVgA35"
+        }"#,
+        )
+        .unwrap();
+
+        assert!(details.is_table_function());
+        assert_eq!(
+            details.return_columns,
+            vec![
+                CreateFunctionColumn {
+                    name: "token".to_string(),
+                    data_type: CreateFunctionDataType::TEXT
+                },
+                CreateFunctionColumn {
+                    name: "position".to_string(),
+                    data_type: CreateFunctionDataType::INT
+                }
+            ]
+        )
+    }
+
+    #[test]
+    fn test_wasm_limits_restrict() {
+        let configured = WasmLimits {
+            max_fuel: Some(1000),
+            timeout_ms: Some(100),
+            max_memory: None,
+        };
+        let requested: WasmLimits = serde_json::from_str(
+            r#"{"max_fuel": 1000000, "timeout_ms": 10, "max_memory": 16}"#,
+        )
+        .unwrap();
+
+        // Functions can only tighten the configured limits
+        assert_eq!(
+            configured.restrict(&requested),
+            WasmLimits {
+                max_fuel: Some(1000),
+                timeout_ms: Some(10),
+                max_memory: Some(16),
+            }
+        );
+        assert_eq!(configured.restrict(&WasmLimits::default()), configured);
+    }
+
+    #[test]
+    fn test_wasi_capabilities_for_volatility() {
+        let capabilities: WasiCapabilities = serde_json::from_str(
+            r#"{"enabled": true, "env": {"LANG": "C"}, "clocks": true, "random": true}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            capabilities.for_volatility(&CreateFunctionVolatility::Volatile),
+            capabilities
+        );
+
+        // Immutable functions only get deterministic clocks and random numbers
+        let immutable = capabilities.for_volatility(&CreateFunctionVolatility::Immutable);
+        assert!(immutable.enabled);
+        assert_eq!(immutable.env, capabilities.env);
+        assert!(!immutable.clocks);
+        assert!(!immutable.random);
+        assert_eq!(
+            serde_json::to_string(&immutable).unwrap(),
+            r#"{"enabled":true,"env":{"LANG":"C"}}"#
+        );
+    }
+
+    #[test]
+    fn test_function_signature() {
+        // Aliases of the same type give the same signature
+        assert_eq!(
+            function_signature(&[
+                CreateFunctionDataType::I32,
+                CreateFunctionDataType::VARCHAR
+            ]),
+            function_signature(&[
+                CreateFunctionDataType::INT,
+                CreateFunctionDataType::TEXT
+            ]),
+        );
+        assert_eq!(
+            function_signature(&[
+                CreateFunctionDataType::F32,
+                CreateFunctionDataType::DECIMAL {
+                    precision: 10,
+                    scale: 2
+                }
+            ]),
+            r#"["real",{"decimal":{"precision":10,"scale":2}}]"#
+        );
+        assert_ne!(
+            function_signature(&[CreateFunctionDataType::INT]),
+            function_signature(&[CreateFunctionDataType::BIGINT]),
+        );
+    }
 }
@@ -1,2 +1,4 @@
 pub mod data_types;
+pub mod module_cache;
+mod wasi;
 pub mod wasm;
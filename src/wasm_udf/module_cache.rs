@@ -0,0 +1,318 @@
+//! Cache of compiled WASM modules, so that we don't have to recompile a function's module
+//! every time it's loaded (which happens on every schema reload). Modules are keyed by the
+//! SHA-256 hash of their bytes and the engine they're compiled for, and are kept in memory
+//! and, optionally, on disk, which also speeds up cold starts.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use datafusion::error::{DataFusionError, Result};
+use hex::encode;
+use lazy_static::lazy_static;
+use log::warn;
+use parking_lot::{Mutex, RwLock};
+use sha2::{Digest, Sha256};
+use wasmtime::{Engine, Module};
+
+use super::data_types::WasmLimits;
+use super::wasm::{wasm_engine, EngineFeatures};
+
+// Extension of the files holding the serialized compiled modules
+const COMPILED_MODULE_EXTENSION: &str = "cwasm";
+
+// First word of the header line of the files holding the serialized compiled modules
+const COMPILED_MODULE_MAGIC: &str = "seafowl-cwasm";
+
+// Number of compiled modules kept in memory (the least recently used ones get evicted)
+const MAX_CACHED_MODULES: usize = 64;
+
+#[derive(Default)]
+struct CachedModules {
+    // Compiled modules, along with the "time" they were last used at
+    modules: HashMap<String, (Module, u64)>,
+    clock: u64,
+}
+
+impl CachedModules {
+    fn get(&mut self, key: &str) -> Option<Module> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.modules.get_mut(key).map(|(module, last_used)| {
+            *last_used = clock;
+            module.clone()
+        })
+    }
+
+    fn insert(&mut self, key: String, module: Module) {
+        self.clock += 1;
+        self.modules.insert(key, (module, self.clock));
+
+        if self.modules.len() > MAX_CACHED_MODULES {
+            if let Some(lru_key) = self
+                .modules
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone())
+            {
+                self.modules.remove(&lru_key);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct ModuleCache {
+    modules: Mutex<CachedModules>,
+    dir: RwLock<Option<PathBuf>>,
+}
+
+lazy_static! {
+    static ref MODULE_CACHE: ModuleCache = ModuleCache::default();
+}
+
+/// Persist compiled modules in this directory. Compiled modules get loaded as native code,
+/// so only Seafowl should be able to write to it.
+pub fn set_module_cache_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    *MODULE_CACHE.dir.write() = Some(dir.to_path_buf());
+    Ok(())
+}
+
+fn module_hash(module_bytes: &[u8]) -> String {
+    encode(Sha256::digest(module_bytes))
+}
+
+/// Key of a module compiled for the engine that enforces these limits
+fn module_key(hash: &str, limits: &WasmLimits) -> String {
+    format!("{hash}-{}", EngineFeatures::for_limits(limits).name())
+}
+
+/// Header identifying a compiled module: the Seafowl version that compiled it (and so the
+/// wasmtime version), what it was compiled from and the digest of the serialized module
+fn compiled_module_header(key: &str, serialized: &[u8]) -> String {
+    format!(
+        "{COMPILED_MODULE_MAGIC} {} {key} {}\n",
+        env!("CARGO_PKG_VERSION"),
+        encode(Sha256::digest(serialized))
+    )
+}
+
+fn load_compiled_module(engine: &Engine, key: &str, path: &Path) -> Option<Module> {
+    if !path.exists() {
+        return None;
+    }
+
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            warn!("Couldn't read the compiled WASM module {path:?}: {e:?}");
+            return None;
+        }
+    };
+
+    // Only load files that we've written ourselves for this module and that are intact
+    let serialized = contents
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|ix| contents.split_at(ix + 1))
+        .filter(|(header, serialized)| {
+            *header == compiled_module_header(key, serialized).as_bytes()
+        })
+        .map(|(_, serialized)| serialized);
+    let serialized = match serialized {
+        Some(serialized) => serialized,
+        None => {
+            warn!("Compiled WASM module {path:?} is stale or corrupt, recompiling it");
+            return None;
+        }
+    };
+
+    // SAFETY: we've checked that the module was serialized by this version of Seafowl with
+    // `Module::serialize` (and hasn't changed since). wasmtime also checks that the module was
+    // compiled by a compatible engine.
+    match unsafe { Module::deserialize(engine, serialized) } {
+        Ok(module) => Some(module),
+        Err(e) => {
+            warn!(
+                "Couldn't load the compiled WASM module {path:?}, recompiling it: {e:?}"
+            );
+            None
+        }
+    }
+}
+
+fn store_compiled_module(module: &Module, key: &str, path: &Path) -> Result<()> {
+    let serialized = module.serialize().map_err(|e| {
+        DataFusionError::Internal(format!("Error serializing WASM module: {e:?}"))
+    })?;
+    let mut contents = compiled_module_header(key, &serialized).into_bytes();
+    contents.extend(serialized);
+
+    // Write the module out to a temporary file first, so that other processes sharing the
+    // directory never see a partially written module
+    let temp_path = path.with_extension(format!(
+        "{COMPILED_MODULE_EXTENSION}.{}.tmp",
+        std::process::id()
+    ));
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Get the compiled version of a WASM module, compiling it if it's not in the cache,
+/// for the engine that enforces these limits
+pub(super) fn compile_module(module_bytes: &[u8], limits: &WasmLimits) -> Result<Module> {
+    let engine = wasm_engine(limits);
+    let key = module_key(&module_hash(module_bytes), limits);
+    if let Some(module) = MODULE_CACHE.modules.lock().get(&key) {
+        return Ok(module);
+    }
+
+    let path = MODULE_CACHE
+        .dir
+        .read()
+        .as_ref()
+        .map(|dir| dir.join(format!("{key}.{COMPILED_MODULE_EXTENSION}")));
+
+    let module = match path
+        .as_deref()
+        .and_then(|path| load_compiled_module(&engine, &key, path))
+    {
+        Some(module) => module,
+        None => {
+            let module = Module::from_binary(&engine, module_bytes).map_err(|e| {
+                DataFusionError::Internal(format!("Error loading WASM module: {e:?}"))
+            })?;
+
+            if let Some(path) = path {
+                // Failing to persist the module only makes the next cold start slower
+                if let Err(e) = store_compiled_module(&module, &key, &path) {
+                    warn!("Couldn't persist the compiled WASM module to {path:?}: {e:?}");
+                }
+            }
+            module
+        }
+    };
+
+    MODULE_CACHE.modules.lock().insert(key, module.clone());
+    Ok(module)
+}
+
+/// Forget all compiled versions of a module (given by the SHA-256 hash of its bytes), e.g.
+/// after the functions using it have been dropped or replaced
+pub fn evict_module(hash: &str) {
+    let prefix = format!("{}-", hash.to_lowercase());
+    MODULE_CACHE
+        .modules
+        .lock()
+        .modules
+        .retain(|key, _| !key.starts_with(&prefix));
+
+    if let Some(dir) = MODULE_CACHE.dir.read().as_ref() {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Couldn't list the compiled WASM modules in {dir:?}: {e:?}");
+                return;
+            }
+        };
+
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            let is_module = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map_or(false, |n| {
+                    n.starts_with(&prefix) && n.ends_with(COMPILED_MODULE_EXTENSION)
+                });
+            if is_module {
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("Couldn't delete the compiled WASM module {path:?}: {e:?}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use wasmtime::Module;
+
+    use super::{
+        compile_module, load_compiled_module, module_hash, module_key,
+        store_compiled_module, CachedModules, COMPILED_MODULE_EXTENSION,
+        MAX_CACHED_MODULES,
+    };
+    use crate::wasm_udf::data_types::WasmLimits;
+    use crate::wasm_udf::wasm::wasm_engine;
+
+    // (module (func (export "answer") (result i32) (i32.const 42)))
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00,
+        0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x0a, 0x01, 0x06, 0x61, 0x6e, 0x73,
+        0x77, 0x65, 0x72, 0x00, 0x00, 0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b,
+    ];
+
+    #[test]
+    fn test_compiled_module_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let limits = WasmLimits::default();
+        let engine = wasm_engine(&limits);
+        let key = module_key(&module_hash(MODULE), &limits);
+        let path = dir
+            .path()
+            .join(format!("{key}.{COMPILED_MODULE_EXTENSION}"));
+
+        assert!(load_compiled_module(&engine, &key, &path).is_none());
+
+        let module = compile_module(MODULE, &limits).unwrap();
+        store_compiled_module(&module, &key, &path).unwrap();
+
+        let loaded = load_compiled_module(&engine, &key, &path).unwrap();
+        assert!(loaded.get_export("answer").is_some());
+
+        // Modules compiled for an engine with different features get recompiled
+        let fuel_limits = WasmLimits {
+            max_fuel: Some(1000),
+            ..Default::default()
+        };
+        let fuel_key = module_key(&module_hash(MODULE), &fuel_limits);
+        assert!(
+            load_compiled_module(&wasm_engine(&fuel_limits), &fuel_key, &path).is_none()
+        );
+
+        // Tampering with the module makes us ignore it
+        let mut contents = std::fs::read(&path).unwrap();
+        let last = contents.len() - 1;
+        contents[last] ^= 0xff;
+        std::fs::write(&path, contents).unwrap();
+        assert!(load_compiled_module(&engine, &key, &path).is_none());
+
+        // Garbage in the cache directory gets ignored
+        std::fs::write(&path, b"not a module").unwrap();
+        assert!(load_compiled_module(&engine, &key, &path).is_none());
+    }
+
+    #[test]
+    fn test_cached_modules_lru() {
+        let module = Module::new(&wasm_engine(&WasmLimits::default()), MODULE).unwrap();
+        let mut cache = CachedModules::default();
+
+        for i in 0..MAX_CACHED_MODULES {
+            cache.insert(i.to_string(), module.clone());
+        }
+        // Use the oldest module, so that the second oldest one gets evicted instead
+        assert!(cache.get("0").is_some());
+        cache.insert("new".to_string(), module);
+
+        assert_eq!(cache.modules.len(), MAX_CACHED_MODULES);
+        assert!(cache.get("0").is_some());
+        assert!(cache.get("1").is_none());
+        assert!(cache.get("new").is_some());
+    }
+
+    #[test]
+    fn test_invalid_module() {
+        assert!(compile_module(b"not a module", &WasmLimits::default()).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! WASI context given to WASM functions. Functions only get one if their definition enables
+//! WASI (so that modules built for a WASI target can be loaded), and it's locked down:
+//! there's no filesystem access, the environment is empty, the clocks are stuck at the Unix
+//! epoch and the random numbers come from a fixed seed, unless the function's definition
+//! allows otherwise (see `WasiCapabilities`). The function's stdout and stderr get forwarded
+//! to our log, up to a rate limit.
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use cap_std::time::{Instant, SystemTime};
+use datafusion::error::{DataFusionError, Result};
+use lazy_static::lazy_static;
+use log::{info, warn, Level};
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use wasi_common::clocks::{WasiClocks, WasiMonotonicClock, WasiSystemClock};
+use wasi_common::pipe::WritePipe;
+use wasi_common::WasiCtx;
+use wasmtime_wasi::sync::{clocks_ctx, WasiCtxBuilder};
+
+use super::data_types::WasiCapabilities;
+
+// Seed of the random number generator for functions that don't get real randomness
+const DETERMINISTIC_SEED: u64 = 0;
+
+// Longest line of a function's output that we log (the rest of the line gets dropped)
+const MAX_LOG_LINE_LENGTH: usize = 4096;
+
+// Number of lines of a function's output (across all of its instances) that we log per
+// second, so that a chatty function can't flood the log
+const MAX_LOG_LINES_PER_SECOND: u32 = 100;
+
+/// Lines of a function's output logged in the current one-second window
+struct LogRate {
+    window_start: std::time::Instant,
+    logged: u32,
+    suppressed: u64,
+}
+
+lazy_static! {
+    static ref LOG_RATES: Mutex<HashMap<String, LogRate>> = Mutex::new(HashMap::new());
+}
+
+/// Check whether we can log another line of a function's output, returning the number of
+/// lines that got suppressed in the previous window once it's over
+fn check_log_rate(function_name: &str) -> (bool, u64) {
+    let now = std::time::Instant::now();
+    let mut rates = LOG_RATES.lock();
+    let rate = rates
+        .entry(function_name.to_string())
+        .or_insert_with(|| LogRate {
+            window_start: now,
+            logged: 0,
+            suppressed: 0,
+        });
+
+    let mut suppressed = 0;
+    if now.duration_since(rate.window_start) >= Duration::from_secs(1) {
+        suppressed = rate.suppressed;
+        *rate = LogRate {
+            window_start: now,
+            logged: 0,
+            suppressed: 0,
+        };
+    }
+
+    if rate.logged < MAX_LOG_LINES_PER_SECOND {
+        rate.logged += 1;
+        (true, suppressed)
+    } else {
+        rate.suppressed += 1;
+        (false, suppressed)
+    }
+}
+
+/// A system clock that always returns the Unix epoch
+struct DeterministicSystemClock;
+
+impl WasiSystemClock for DeterministicSystemClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self, _precision: Duration) -> SystemTime {
+        SystemTime::from_std(UNIX_EPOCH)
+    }
+}
+
+/// A monotonic clock that never advances
+struct DeterministicMonotonicClock(Instant);
+
+impl WasiMonotonicClock for DeterministicMonotonicClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self, _precision: Duration) -> Instant {
+        self.0
+    }
+}
+
+fn deterministic_clocks() -> WasiClocks {
+    let creation_time = Instant::from_std(std::time::Instant::now());
+    WasiClocks {
+        system: Box::new(DeterministicSystemClock),
+        monotonic: Box::new(DeterministicMonotonicClock(creation_time)),
+        creation_time,
+    }
+}
+
+/// Forwards a function's output stream to the log, line by line
+struct LogWriter {
+    function_name: String,
+    level: Level,
+    line: Vec<u8>,
+    truncated: bool,
+}
+
+impl LogWriter {
+    fn new(function_name: &str, level: Level) -> Self {
+        Self {
+            function_name: function_name.to_string(),
+            level,
+            line: vec![],
+            truncated: false,
+        }
+    }
+
+    fn log_line(&mut self) {
+        let (allowed, suppressed) = check_log_rate(&self.function_name);
+        if suppressed > 0 {
+            warn!(
+                "WASM function {:?}: suppressed {suppressed} lines of output",
+                self.function_name
+            );
+        }
+
+        if allowed {
+            let line = String::from_utf8_lossy(&self.line);
+            let truncated = if self.truncated { " [truncated]" } else { "" };
+            match self.level {
+                Level::Error | Level::Warn => {
+                    warn!("WASM function {:?}: {line}{truncated}", self.function_name)
+                }
+                _ => info!("WASM function {:?}: {line}{truncated}", self.function_name),
+            }
+        }
+        self.line.clear();
+        self.truncated = false;
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for byte in buf {
+            if *byte == b'\n' {
+                self.log_line();
+            } else if self.line.len() < MAX_LOG_LINE_LENGTH {
+                self.line.push(*byte);
+            } else {
+                self.truncated = true;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.line.is_empty() || self.truncated {
+            self.log_line();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Build the WASI context for an instance of a function, if it's allowed to use WASI
+pub(super) fn build_wasi_ctx(
+    function_name: &str,
+    capabilities: &WasiCapabilities,
+) -> Result<Option<WasiCtx>> {
+    if !capabilities.enabled {
+        return if *capabilities == WasiCapabilities::default() {
+            Ok(None)
+        } else {
+            Err(DataFusionError::Plan(format!(
+                "WASI capabilities of function {function_name:?} require WASI to be enabled"
+            )))
+        };
+    }
+
+    let env = capabilities
+        .env
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<Vec<_>>();
+
+    let mut wasi = WasiCtxBuilder::new()
+        .stdout(Box::new(WritePipe::new(LogWriter::new(
+            function_name,
+            Level::Info,
+        ))))
+        .stderr(Box::new(WritePipe::new(LogWriter::new(
+            function_name,
+            Level::Warn,
+        ))))
+        .envs(&env)
+        .map_err(|e| {
+            DataFusionError::Plan(format!(
+                "Invalid WASI environment for function {function_name:?}: {e:?}"
+            ))
+        })?
+        .build();
+
+    wasi.clocks = if capabilities.clocks {
+        clocks_ctx()
+    } else {
+        deterministic_clocks()
+    };
+    if !capabilities.random {
+        wasi.random = Box::new(StdRng::seed_from_u64(DETERMINISTIC_SEED));
+    }
+
+    Ok(Some(wasi))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use log::Level;
+
+    use super::{
+        build_wasi_ctx, check_log_rate, LogWriter, MAX_LOG_LINES_PER_SECOND,
+        MAX_LOG_LINE_LENGTH,
+    };
+    use crate::wasm_udf::data_types::WasiCapabilities;
+
+    #[test]
+    fn test_log_writer_splits_lines() {
+        let mut writer = LogWriter::new("some_function", Level::Info);
+
+        writer.write_all(b"first line\nsecond").unwrap();
+        assert_eq!(writer.line, b"second");
+
+        writer.write_all(b" line\n").unwrap();
+        assert!(writer.line.is_empty());
+
+        // Long lines get truncated
+        writer
+            .write_all(&vec![b'a'; MAX_LOG_LINE_LENGTH + 1])
+            .unwrap();
+        assert_eq!(writer.line.len(), MAX_LOG_LINE_LENGTH);
+        assert!(writer.truncated);
+
+        writer.flush().unwrap();
+        assert!(writer.line.is_empty());
+        assert!(!writer.truncated);
+    }
+
+    #[test]
+    fn test_log_rate_limit() {
+        for _ in 0..MAX_LOG_LINES_PER_SECOND {
+            assert_eq!(check_log_rate("chatty_function"), (true, 0));
+        }
+        assert_eq!(check_log_rate("chatty_function"), (false, 0));
+
+        // Other functions have their own limit
+        assert_eq!(check_log_rate("quiet_function"), (true, 0));
+    }
+
+    #[test]
+    fn test_wasi_disabled() {
+        assert!(build_wasi_ctx("f", &WasiCapabilities::default())
+            .unwrap()
+            .is_none());
+
+        // Capabilities can only be granted along with WASI itself
+        let clocks = WasiCapabilities {
+            clocks: true,
+            ..Default::default()
+        };
+        assert!(build_wasi_ctx("f", &clocks).is_err());
+        assert!(build_wasi_ctx(
+            "f",
+            &WasiCapabilities {
+                enabled: true,
+                ..clocks
+            }
+        )
+        .unwrap()
+        .is_some());
+    }
+}
@@ -1,30 +1,58 @@
 use arrow::{
-    array::{BooleanArray, PrimitiveArray, StringArray},
+    array::{new_empty_array, BinaryArray, BooleanArray, PrimitiveArray, StringArray},
+    compute::{cast, concat},
     datatypes::ArrowPrimitiveType,
+    ipc::{reader::StreamReader, writer::StreamWriter},
+    record_batch::RecordBatchOptions,
 };
 /// Creating DataFusion UDFs from WASM bytecode
 use datafusion::{
     arrow::{
-        array::{ArrayRef, Float32Array, Float64Array, Int32Array, Int64Array},
-        datatypes::{DataType, TimeUnit},
+        array::{
+            ArrayRef, Float32Array, Float64Array, Int32Array, Int64Array, UInt32Array,
+        },
+        datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+        record_batch::RecordBatch,
     },
     common::DataFusionError,
-    logical_expr::{ScalarFunctionImplementation, ScalarUDF, Volatility},
+    logical_expr::{
+        Accumulator, AccumulatorFunctionImplementation, AggregateState, AggregateUDF,
+        ReturnTypeFunction, ScalarFunctionImplementation, ScalarUDF, Signature,
+        StateTypeFunction, TypeSignature, Volatility,
+    },
+    physical_plan::ColumnarValue,
+    scalar::ScalarValue,
 };
 
 use datafusion::prelude::*;
 use datafusion::{error::Result, physical_plan::functions::make_scalar_function};
 
-use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc, Val, ValType};
+use wasmtime::{
+    Engine, Instance, InstanceAllocationStrategy, InstanceLimits, Memory, Module,
+    PoolingAllocationStrategy, ResourceLimiter, Store, TypedFunc, Val, ValType,
+};
 
-use super::data_types::{get_wasm_type, CreateFunctionDataType, CreateFunctionLanguage};
+use super::data_types::{
+    get_wasm_type, CreateFunctionColumn, CreateFunctionDataType, CreateFunctionLanguage,
+    WasiCapabilities, WasmLimits,
+};
+use super::module_cache::compile_module;
+use super::wasi::build_wasi_ctx;
+use crate::config::schema::MEBIBYTES;
 
 use wasi_common::WasiCtx;
-use wasmtime_wasi::sync::WasiCtxBuilder;
 
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::vec;
 
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
 use rmp_serde;
 use rmp_serde::Serializer;
 use serde;
@@ -35,6 +63,266 @@ use rmpv::Value;
 
 const SIZE_BYTE_COUNT: usize = std::mem::size_of::<i32>();
 
+// How often the epoch of the WASM engines gets incremented, i.e. the precision of the
+// WASM function timeouts
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+// Default number of instance slots in each engine's pool, i.e. how many WASM function calls
+// can run at the same time
+pub const DEFAULT_MAX_INSTANCES: u32 = 256;
+
+// Largest linear memory a pooled instance can have (the most a 32-bit module can address,
+// in 64 KiB pages). Per-function limits are enforced separately by `MemoryLimiter`.
+const MAX_MEMORY_PAGES: u64 = 65536;
+
+static MAX_INSTANCES: AtomicU32 = AtomicU32::new(DEFAULT_MAX_INSTANCES);
+
+/// Set the number of instance slots in the engines' pools. This has to happen before
+/// the first function gets created, since that's when the engines allocate their pools.
+pub fn set_max_wasm_instances(count: u32) {
+    MAX_INSTANCES.store(count, Ordering::Relaxed);
+}
+
+/// Engine features needed to enforce a function's limits. Fuel consumption and epoch
+/// interruption slow down every call and can only be enabled for a whole engine, so we
+/// keep a separate engine for each combination and only use them when there's a limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct EngineFeatures {
+    consume_fuel: bool,
+    epoch_interruption: bool,
+}
+
+impl EngineFeatures {
+    pub(super) fn for_limits(limits: &WasmLimits) -> Self {
+        Self {
+            consume_fuel: limits.max_fuel.is_some(),
+            epoch_interruption: limits.timeout_ms.is_some(),
+        }
+    }
+
+    /// Short name for the features, used to tell apart modules compiled for each engine
+    pub(super) fn name(&self) -> &'static str {
+        match (self.consume_fuel, self.epoch_interruption) {
+            (false, false) => "plain",
+            (true, false) => "fuel",
+            (false, true) => "epoch",
+            (true, true) => "fuel-epoch",
+        }
+    }
+
+    fn new_engine(&self) -> Engine {
+        // Every call instantiates the function's module, so that it gets a clean linear
+        // memory and globals. The pooling allocator makes that cheap: instances reuse
+        // preallocated slots, whose memories get reset when an instance is dropped.
+        let instance_limits = InstanceLimits {
+            count: MAX_INSTANCES.load(Ordering::Relaxed),
+            memory_pages: MAX_MEMORY_PAGES,
+            ..Default::default()
+        };
+        let mut config = wasmtime::Config::new();
+        config
+            .consume_fuel(self.consume_fuel)
+            .epoch_interruption(self.epoch_interruption)
+            .allocation_strategy(InstanceAllocationStrategy::Pooling {
+                strategy: PoolingAllocationStrategy::ReuseAffinity,
+                instance_limits,
+            });
+        let engine = Engine::new(&config).expect("Error creating the WASM engine");
+
+        if self.epoch_interruption {
+            let ticker = engine.clone();
+            std::thread::Builder::new()
+                .name(format!("wasm-{}-ticker", self.name()))
+                .spawn(move || loop {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker.increment_epoch();
+                })
+                .expect("Error starting the WASM epoch ticker");
+        }
+
+        engine
+    }
+}
+
+lazy_static! {
+    // Engines shared by all WASM functions, created on first use
+    static ref WASM_ENGINES: Mutex<HashMap<EngineFeatures, Engine>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Get the engine that can enforce these limits
+pub(super) fn wasm_engine(limits: &WasmLimits) -> Engine {
+    let features = EngineFeatures::for_limits(limits);
+    WASM_ENGINES
+        .lock()
+        .entry(features)
+        .or_insert_with(|| features.new_engine())
+        .clone()
+}
+
+/// Denies memory growth past the limit, remembering that it happened so that we can
+/// report it instead of whatever error the module raises when it can't allocate
+#[derive(Default)]
+struct MemoryLimiter {
+    max_bytes: Option<usize>,
+    exceeded: bool,
+}
+
+impl ResourceLimiter for MemoryLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> bool {
+        match self.max_bytes {
+            Some(max_bytes) if desired > max_bytes => {
+                self.exceeded = true;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> bool {
+        true
+    }
+}
+
+struct StoreState {
+    wasi: Option<WasiCtx>,
+    limits: WasmLimits,
+    memory_limiter: MemoryLimiter,
+    call_started: Instant,
+}
+
+/// Create a store that enforces the function's resource limits and gives it the WASI
+/// capabilities it's allowed to have
+fn new_store(
+    function_name: &str,
+    limits: &WasmLimits,
+    wasi: &WasiCapabilities,
+) -> Result<Store<StoreState>> {
+    // All instances in the store share this WASI context (if the function can use WASI)
+    let wasi = build_wasi_ctx(function_name, wasi)?;
+    let memory_limiter = MemoryLimiter {
+        max_bytes: limits
+            .max_memory
+            .map(|m| (m * MEBIBYTES).try_into().unwrap_or(usize::MAX)),
+        exceeded: false,
+    };
+
+    let mut store = Store::new(
+        &wasm_engine(limits),
+        StoreState {
+            wasi,
+            limits: limits.clone(),
+            memory_limiter,
+            call_started: Instant::now(),
+        },
+    );
+    store.limiter(|state| &mut state.memory_limiter);
+    Ok(store)
+}
+
+/// Instantiate the module, linking it to the WASI context in the store if there is one.
+/// Otherwise, modules that import WASI functions fail to instantiate.
+fn instantiate_module(
+    store: &mut Store<StoreState>,
+    module: &Module,
+) -> Result<Instance> {
+    let mut linker = wasmtime::Linker::new(store.engine());
+    if store.data().wasi.is_some() {
+        // Add both wasi_unstable and wasi_snapshot_preview1 WASI modules
+        wasmtime_wasi::add_to_linker(&mut linker, |s: &mut StoreState| {
+            s.wasi.as_mut().expect("WASI context is missing")
+        })
+        .map_err(|e| {
+            DataFusionError::Internal(format!("Error linking to WASI modules: {e:?}"))
+        })?;
+    }
+
+    linker.instantiate(&mut *store, module).map_err(|e| {
+        limit_exceeded(store).unwrap_or_else(|| {
+            DataFusionError::Internal(format!("Error instantiating WASM module: {e:?}"))
+        })
+    })
+}
+
+/// Reset the fuel and the deadline before calling into the module, since the limits apply
+/// to every call separately
+fn start_call(store: &mut Store<StoreState>) -> Result<()> {
+    let limits = store.data().limits.clone();
+
+    // Top the fuel up to the budget. Without a limit, the engine doesn't consume fuel.
+    if let Some(budget) = limits.max_fuel {
+        let remaining = store.consume_fuel(0).unwrap_or(0);
+        if remaining < budget {
+            store.add_fuel(budget - remaining).map_err(|e| {
+                DataFusionError::Internal(format!(
+                    "Error adding fuel to the WASM store: {e:?}"
+                ))
+            })?;
+        }
+    }
+
+    // Round up and add a tick, since the current one might be about to end
+    if let Some(timeout_ms) = limits.timeout_ms {
+        let tick_ms = EPOCH_TICK.as_millis() as u64;
+        store.set_epoch_deadline((timeout_ms + tick_ms - 1) / tick_ms + 1);
+    }
+    store.data_mut().call_started = Instant::now();
+    Ok(())
+}
+
+/// Check if a call into the module failed because it went over one of the resource limits
+fn limit_exceeded(store: &mut Store<StoreState>) -> Option<DataFusionError> {
+    let limits = store.data().limits.clone();
+
+    if store.data().memory_limiter.exceeded {
+        return Some(DataFusionError::ResourcesExhausted(format!(
+            "WASM function exceeded the memory limit of {} MiB",
+            limits.max_memory.unwrap_or_default()
+        )));
+    }
+
+    if let Some(max_fuel) = limits.max_fuel {
+        // Running out of fuel makes any further consumption fail
+        if store.consume_fuel(0).map(|r| r == 0).unwrap_or(true) {
+            return Some(DataFusionError::ResourcesExhausted(format!(
+                "WASM function ran out of fuel (limit of {max_fuel})"
+            )));
+        }
+    }
+
+    if let Some(timeout_ms) = limits.timeout_ms {
+        if store.data().call_started.elapsed() >= Duration::from_millis(timeout_ms) {
+            return Some(DataFusionError::ResourcesExhausted(format!(
+                "WASM function exceeded the timeout of {timeout_ms} ms"
+            )));
+        }
+    }
+
+    None
+}
+
+/// Add context to an error from a WASM function, unless it's a resource limit violation,
+/// which gets passed through as is
+fn wrap_wasm_error(
+    err: DataFusionError,
+    context: impl FnOnce(&DataFusionError) -> String,
+) -> DataFusionError {
+    match err {
+        DataFusionError::ResourcesExhausted(_) => err,
+        err => DataFusionError::Internal(context(&err)),
+    }
+}
+
 fn sql_type_to_arrow_type(t: &CreateFunctionDataType) -> Result<DataType> {
     match t {
         // legacy WASM-native type names
@@ -60,12 +348,15 @@ fn sql_type_to_arrow_type(t: &CreateFunctionDataType) -> Result<DataType> {
         CreateFunctionDataType::TIMESTAMP => {
             Ok(DataType::Timestamp(TimeUnit::Nanosecond, None))
         }
+        CreateFunctionDataType::TABLE => Err(DataFusionError::Plan(
+            "TABLE can only be used as the return type of a table function".to_string(),
+        )),
     }
 }
 
 fn get_wasm_module_exported_fn<Params, Results>(
     instance: &Instance,
-    store: &mut Store<WasiCtx>,
+    store: &mut Store<StoreState>,
     export_name: &str,
 ) -> Result<TypedFunc<Params, Results>>
 where
@@ -80,38 +371,38 @@ where
         })
 }
 
-struct WasmMessagePackUDFInstance {
-    store: Store<WasiCtx>,
+/// A WASM module instance for the UDF languages that pass their arguments and results through
+/// the module's memory (MessagePack and Arrow). Inputs and outputs are length-prefixed buffers
+/// allocated with the module's `alloc` export and freed with `dealloc`.
+struct WasmUDFInstance {
+    store: Store<StoreState>,
     alloc: TypedFunc<i32, i32>,
     dealloc: TypedFunc<(i32, i32), ()>,
-    udf: TypedFunc<i32, i32>,
+    // Aggregate functions are made up of multiple exports, so we keep all of them here
+    udfs: HashMap<String, TypedFunc<i32, i32>>,
     memory: Memory,
 }
 
-impl WasmMessagePackUDFInstance {
-    pub fn new(module_bytes: &[u8], function_name: &str) -> Result<Self> {
-        let engine = Engine::default();
-        let mut linker = wasmtime::Linker::new(&engine);
-        // Create a WASI context and put it in a Store; all instances in the store
-        // share this context. `WasiCtxBuilder` provides a number of ways to
-        // configure what the target program will have access to.
-        let wasi = WasiCtxBuilder::new().inherit_stderr().build();
-        let mut store = Store::new(&engine, wasi);
-        // Add both wasi_unstable and wasi_snapshot_preview1 WASI modules
-        wasmtime_wasi::add_to_linker(&mut linker, |s| s).map_err(|e| {
-            DataFusionError::Internal(format!("Error linking to WASI modules: {e:?}"))
-        })?;
-        // Instantiate WASM module.
-        let module = Module::from_binary(&engine, module_bytes).map_err(|e| {
-            DataFusionError::Internal(format!("Error loading WASM module: {e:?}"))
-        })?;
-        let instance = linker.instantiate(&mut store, &module).map_err(|e| {
-            DataFusionError::Internal(format!("Error instantiating WASM modules {e:?}"))
-        })?;
+impl WasmUDFInstance {
+    pub fn new(
+        name: &str,
+        module: &Module,
+        function_names: &[&str],
+        limits: &WasmLimits,
+        wasi: &WasiCapabilities,
+    ) -> Result<Self> {
+        let mut store = new_store(name, limits, wasi)?;
+        let instance = instantiate_module(&mut store, module)?;
 
         let alloc = get_wasm_module_exported_fn(&instance, &mut store, "alloc")?;
         let dealloc = get_wasm_module_exported_fn(&instance, &mut store, "dealloc")?;
-        let udf = get_wasm_module_exported_fn(&instance, &mut store, function_name)?;
+        let udfs = function_names
+            .iter()
+            .map(|name| {
+                get_wasm_module_exported_fn(&instance, &mut store, name)
+                    .map(|udf| (name.to_string(), udf))
+            })
+            .collect::<Result<_>>()?;
         let memory = instance.get_memory(&mut store, "memory").ok_or(
             DataFusionError::Internal(
                 "could not find module's exported memory".to_string(),
@@ -121,12 +412,12 @@ impl WasmMessagePackUDFInstance {
             store,
             alloc,
             dealloc,
-            udf,
+            udfs,
             memory,
         })
     }
 
-    fn read_udf_output(&mut self, udf_output_ptr: i32) -> Result<(Value, i32)> {
+    fn read_udf_output(&mut self, udf_output_ptr: i32) -> Result<(Vec<u8>, i32)> {
         let ptr: usize = udf_output_ptr.try_into().unwrap();
         let mut size_buffer = [0u8; SIZE_BYTE_COUNT];
         self.memory
@@ -153,28 +444,12 @@ impl WasmMessagePackUDFInstance {
                     "Error reading output buf ({size:?} bytes): {err:?}"
                 ))
             })?;
-        let output: Value =
-            rmp_serde::from_slice(output_buffer.as_ref()).map_err(|err| {
-                DataFusionError::Internal(format!(
-                    "Error messagepack decoding output buffer: {err:?}"
-                ))
-            })?;
         // return the entire size of the output buffer (including i32 size prefix) so it can be passed to dealloc() later
-        let result: (Value, i32) = (output, (size + SIZE_BYTE_COUNT).try_into().unwrap());
-        Ok(result)
+        Ok((output_buffer, (size + SIZE_BYTE_COUNT).try_into().unwrap()))
     }
 
-    fn write_udf_input(&mut self, input: &Value) -> Result<(i32, i32)> {
-        // serialize input using MessagePack
-        let mut udf_input_buf: Vec<u8> = vec![];
-        input
-            .serialize(&mut Serializer::new(&mut udf_input_buf))
-            .map_err(|err| {
-                DataFusionError::Internal(format!(
-                    "Error messagepack serializing input {err:?}"
-                ))
-            })?;
-        // Total input size will be serialized messagepack bytes prepended by the
+    fn write_udf_input(&mut self, udf_input_buf: &[u8]) -> Result<(i32, i32)> {
+        // Total input size will be the serialized input bytes prepended by the
         // size of the serialized input (one i32 == 4 bytes)
         let udf_input_size: usize = udf_input_buf.len() + SIZE_BYTE_COUNT;
         // allocate WASM memory for input buffer
@@ -197,11 +472,7 @@ impl WasmMessagePackUDFInstance {
             })?;
         // copy input buffer
         self.memory
-            .write(
-                &mut self.store,
-                ptr + SIZE_BYTE_COUNT,
-                udf_input_buf.as_ref(),
-            )
+            .write(&mut self.store, ptr + SIZE_BYTE_COUNT, udf_input_buf)
             .map_err(|err| {
                 DataFusionError::Internal(format!(
                     "Error copying input buffer to WASM memory: {err:?}"
@@ -211,14 +482,21 @@ impl WasmMessagePackUDFInstance {
         Ok((udf_input_ptr, udf_input_size.try_into().unwrap()))
     }
 
-    pub fn call(&mut self, input: Vec<Value>) -> Result<Value> {
-        let args_array = Value::Array(input);
-        let (udf_input_ptr, input_size) = self.write_udf_input(&args_array)?;
+    /// Pass a serialized input buffer to the UDF and return its serialized output
+    pub fn call_raw(&mut self, function_name: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let udf = self.udfs.get(function_name).cloned().ok_or_else(|| {
+            DataFusionError::Internal(format!(
+                "Export {function_name:?} wasn't loaded from the WASM module"
+            ))
+        })?;
+        start_call(&mut self.store)?;
+        let (udf_input_ptr, input_size) = self.write_udf_input(input)?;
         // invoke UDF
-        let udf_output_ptr =
-            self.udf.call(&mut self.store, udf_input_ptr).map_err(|e| {
+        let udf_output_ptr = udf.call(&mut self.store, udf_input_ptr).map_err(|e| {
+            limit_exceeded(&mut self.store).unwrap_or_else(|| {
                 DataFusionError::Internal(format!("Error invoking WASM UDF: {e:?}"))
-            })?;
+            })
+        })?;
         let (output, output_size) = self.read_udf_output(udf_output_ptr)?;
         // deallocate both input and output buffers
         self.dealloc
@@ -237,6 +515,84 @@ impl WasmMessagePackUDFInstance {
             })?;
         Ok(output)
     }
+
+    pub fn call(&mut self, function_name: &str, input: Vec<Value>) -> Result<Value> {
+        // serialize input using MessagePack
+        let mut udf_input_buf: Vec<u8> = vec![];
+        Value::Array(input)
+            .serialize(&mut Serializer::new(&mut udf_input_buf))
+            .map_err(|err| {
+                DataFusionError::Internal(format!(
+                    "Error messagepack serializing input {err:?}"
+                ))
+            })?;
+        let output_buffer = self.call_raw(function_name, &udf_input_buf)?;
+        rmp_serde::from_slice(output_buffer.as_ref()).map_err(|err| {
+            DataFusionError::Internal(format!(
+                "Error messagepack decoding output buffer: {err:?}"
+            ))
+        })
+    }
+}
+
+/// A function's compiled module, along with everything needed to instantiate it. Every call
+/// gets a fresh instance: instances can't be reset, so reusing one would let a call see the
+/// linear memory and globals left behind by another one (possibly from another query).
+/// Instantiation takes a slot from the engine's pool (see `EngineFeatures::new_engine`)
+/// instead of allocating new memory, and the slot is returned when the instance is dropped.
+struct FunctionModule {
+    name: String,
+    module: Module,
+    function_names: Vec<String>,
+    limits: WasmLimits,
+    wasi: WasiCapabilities,
+}
+
+impl FunctionModule {
+    pub fn new(
+        name: &str,
+        module_bytes: &[u8],
+        function_names: &[&str],
+        limits: &WasmLimits,
+        wasi: &WasiCapabilities,
+    ) -> Result<Self> {
+        let module = Self {
+            name: name.to_string(),
+            module: compile_module(module_bytes, limits)?,
+            function_names: function_names.iter().map(|n| n.to_string()).collect(),
+            limits: limits.clone(),
+            wasi: wasi.clone(),
+        };
+
+        // Make sure that the module can be instantiated and has all the exports we need
+        module.instantiate()?;
+        Ok(module)
+    }
+
+    pub fn instantiate(&self) -> Result<WasmUDFInstance> {
+        WasmUDFInstance::new(
+            &self.name,
+            &self.module,
+            &self
+                .function_names
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            &self.limits,
+            &self.wasi,
+        )
+    }
+}
+
+impl Debug for FunctionModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionModule")
+            .field("name", &self.name)
+            .field("function_names", &self.function_names)
+            .field("limits", &self.limits)
+            .field("wasi", &self.wasi)
+            .finish_non_exhaustive()
+    }
 }
 
 fn get_arrow_value<T>(
@@ -350,6 +706,9 @@ fn messagepack_encode_input_value(
             arrow::datatypes::TimestampNanosecondType,
         >(args, row_ix, col_ix)
         .map(Value::from),
+        CreateFunctionDataType::TABLE => Err(DataFusionError::Internal(
+            "Tables can't be passed to WASM functions".to_string(),
+        )),
     }
 }
 
@@ -533,6 +892,9 @@ fn messagepack_decode_results(
                     .map_err(|e| DataFusionError::Internal(format!("Error setting precision {:?} scale {:?} for decimal result: {:?}", *p, *s, e)))?;
                 Ok(Arc::new(arr) as ArrayRef)
             }),
+        CreateFunctionDataType::TABLE => Err(DataFusionError::Internal(
+            "Table function results have to be decoded column by column".to_string(),
+        )),
     }
 }
 
@@ -541,25 +903,35 @@ fn make_scalar_function_wasm_messagepack(
     function_name: &str,
     input_types: Vec<CreateFunctionDataType>,
     return_type: CreateFunctionDataType,
+    limits: WasmLimits,
+    wasi: WasiCapabilities,
 ) -> Result<ScalarFunctionImplementation> {
     // Similar to make_scalar_function_from_wasm, this function should verify
     // that the module can be loaded and the UDF export is found before
     // returning a Result.
     let function_name = function_name.to_owned();
-    let module_bytes = module_bytes.to_owned();
-    let _outer_instance = WasmMessagePackUDFInstance::new(&module_bytes, &function_name)
-        .map_err(|err| {
-            DataFusionError::Internal(format!(
+    let module = FunctionModule::new(
+        &function_name,
+        module_bytes,
+        &[function_name.as_str()],
+        &limits,
+        &wasi,
+    )
+    .map_err(|err| {
+        wrap_wasm_error(err, |err| {
+            format!(
                 "Error initializing WASM + MessagePack UDF {function_name:?}: {err:?}"
-            ))
-        })?;
+            )
+        })
+    })?;
     let inner = move |args: &[ArrayRef]| {
-        let mut instance = WasmMessagePackUDFInstance::new(&module_bytes, &function_name)
-            .map_err(|err| {
-                DataFusionError::Internal(format!(
+        let mut instance = module.instantiate().map_err(|err| {
+            wrap_wasm_error(err, |err| {
+                format!(
                     "Error initializing WASM + MessagePack UDF {function_name:?}: {err:?}"
-                ))
-            })?;
+                )
+            })
+        })?;
         // this is guaranteed by DataFusion based on the function's signature.
         if args.len() != input_types.len() {
             return Err(DataFusionError::Internal(format!(
@@ -587,11 +959,13 @@ fn make_scalar_function_wasm_messagepack(
                 )?);
             }
 
-            encoded_results.push(instance.call(params).map_err(|err| {
-                DataFusionError::Internal(format!(
-                    "Error invoking function {function_name:?}: {err:?}"
-                ))
-            })?);
+            encoded_results.push(instance.call(&function_name, params).map_err(
+                |err| {
+                    wrap_wasm_error(err, |err| {
+                        format!("Error invoking function {function_name:?}: {err:?}")
+                    })
+                },
+            )?);
         }
 
         messagepack_decode_results(&return_type, &encoded_results)
@@ -600,6 +974,133 @@ fn make_scalar_function_wasm_messagepack(
     Ok(make_scalar_function(inner))
 }
 
+/// Serialize the function's arguments as an Arrow IPC stream with a single batch,
+/// naming the columns `arg0`, `arg1`, ...
+fn arrow_ipc_encode_arguments(args: &[ArrayRef], num_rows: usize) -> Result<Vec<u8>> {
+    let schema = Arc::new(Schema::new(
+        args.iter()
+            .enumerate()
+            .map(|(ix, arg)| {
+                Field::new(&format!("arg{ix}"), arg.data_type().clone(), true)
+            })
+            .collect(),
+    ));
+    let batch = RecordBatch::try_new_with_options(
+        schema.clone(),
+        args.to_vec(),
+        &RecordBatchOptions::new().with_row_count(Some(num_rows)),
+    )?;
+
+    let mut buffer = vec![];
+    {
+        let mut writer = StreamWriter::try_new(&mut buffer, &schema)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+    }
+    Ok(buffer)
+}
+
+/// Deserialize the result of a function from an Arrow IPC stream, which has to have
+/// a single column (with any name)
+fn arrow_ipc_decode_result(output: &[u8]) -> Result<ArrayRef> {
+    let reader = StreamReader::try_new(Cursor::new(output), None)?;
+    let schema = reader.schema();
+    if schema.fields().len() != 1 {
+        return Err(DataFusionError::Internal(format!(
+            "Expected the result to have exactly one column, received {:?} instead",
+            schema.fields().len()
+        )));
+    }
+
+    let arrays = reader
+        .map(|batch| batch.map(|b| b.column(0).clone()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    match arrays.as_slice() {
+        [] => Ok(new_empty_array(schema.field(0).data_type())),
+        [array] => Ok(array.clone()),
+        arrays => Ok(concat(
+            &arrays.iter().map(|a| a.as_ref()).collect::<Vec<_>>(),
+        )?),
+    }
+}
+
+/// Build a DataFusion scalar function that passes whole batches of arguments to the WASM
+/// module. The function gets a length-prefixed Arrow IPC stream with one record batch of
+/// arguments and has to return an Arrow IPC stream with a single column of results in the
+/// same (length-prefixed) format. Since the arguments are plain Arrow arrays, NULLs are passed
+/// to the function as is.
+fn make_scalar_function_wasm_arrow(
+    module_bytes: &[u8],
+    function_name: &str,
+    input_types: Vec<CreateFunctionDataType>,
+    return_type: CreateFunctionDataType,
+    limits: WasmLimits,
+    wasi: WasiCapabilities,
+) -> Result<ScalarFunctionImplementation> {
+    let function_name = function_name.to_owned();
+    let df_return_type = sql_type_to_arrow_type(&return_type)?;
+    let module = FunctionModule::new(
+        &function_name,
+        module_bytes,
+        &[function_name.as_str()],
+        &limits,
+        &wasi,
+    )
+    .map_err(|err| {
+        wrap_wasm_error(err, |err| {
+            format!("Error initializing WASM + Arrow UDF {function_name:?}: {err:?}")
+        })
+    })?;
+
+    let inner = move |args: &[ArrayRef]| {
+        let mut instance = module.instantiate().map_err(|err| {
+            wrap_wasm_error(err, |err| {
+                format!("Error initializing WASM + Arrow UDF {function_name:?}: {err:?}")
+            })
+        })?;
+        // this is guaranteed by DataFusion based on the function's signature.
+        if args.len() != input_types.len() {
+            return Err(DataFusionError::Internal(format!(
+                "Wrong number of arguments for function {:?}: expected {:?}, received {:?}",
+                function_name, input_types.len(), args.len()
+            )));
+        }
+
+        // Length of the vectorized array
+        let array_len = args.first().map(|a| a.len()).unwrap_or(1);
+
+        let input = arrow_ipc_encode_arguments(args, array_len)?;
+        let output = instance.call_raw(&function_name, &input).map_err(|err| {
+            wrap_wasm_error(err, |err| {
+                format!("Error invoking function {function_name:?}: {err:?}")
+            })
+        })?;
+
+        let result = arrow_ipc_decode_result(&output).map_err(|err| {
+            DataFusionError::Internal(format!(
+                "Error decoding the result of function {function_name:?}: {err:?}"
+            ))
+        })?;
+
+        if result.len() != array_len {
+            return Err(DataFusionError::Internal(format!(
+                "Function {function_name:?} returned {:?} rows, expected {array_len:?}",
+                result.len()
+            )));
+        }
+
+        // Allow the function to return any type that can be cast to the declared one
+        // (e.g. a narrower integer or a string dictionary)
+        if result.data_type() == &df_return_type {
+            Ok(result)
+        } else {
+            Ok(cast(&result, &df_return_type)?)
+        }
+    };
+
+    Ok(make_scalar_function(inner))
+}
+
 /// Build a DataFusion scalar function from WASM module bytecode.
 /// Don't call this function directly; call create_udf_from_wasm instead
 /// (as this function doesn't do some validation)
@@ -608,15 +1109,14 @@ fn make_scalar_function_from_wasm(
     function_name: &str,
     input_types: Vec<ValType>,
     return_type: ValType,
+    limits: WasmLimits,
+    wasi: WasiCapabilities,
 ) -> Result<ScalarFunctionImplementation> {
-    let mut store = Store::<()>::default();
-    let module = Module::from_binary(store.engine(), module_bytes)
-        .map_err(|e| DataFusionError::Internal(format!("Error loading module: {e:?}")))?;
+    let mut store = new_store(function_name, &limits, &wasi)?;
+    let module = compile_module(module_bytes, &limits)?;
 
     // Pre-flight checks to make sure the function exists
-    let instance = Instance::new(&mut store, &module, &[]).map_err(|e| {
-        DataFusionError::Internal(format!("Error instantiating module: {e:?}"))
-    })?;
+    let instance = instantiate_module(&mut store, &module)?;
 
     let _func = instance
         .get_func(&mut store, function_name)
@@ -629,20 +1129,12 @@ fn make_scalar_function_from_wasm(
     // This means we have to create a store and load the function inside of this closure, discarding
     // the store after we're done.
 
-    // Capture the function name and the module code
+    // Capture the function name and the compiled module
     let function_name = function_name.to_owned();
-    let module_bytes = module_bytes.to_owned();
     let inner = move |args: &[ArrayRef]| {
         // Load the function again
-        let mut store = Store::<()>::default();
-
-        let module = Module::from_binary(store.engine(), &module_bytes).map_err(|e| {
-            DataFusionError::Internal(format!("Error loading module: {e:?}"))
-        })?;
-
-        let instance = Instance::new(&mut store, &module, &[]).map_err(|e| {
-            DataFusionError::Internal(format!("Error instantiating module: {e:?}"))
-        })?;
+        let mut store = new_store(&function_name, &limits, &wasi)?;
+        let instance = instantiate_module(&mut store, &module)?;
 
         let func = instance
             .get_func(&mut store, &function_name)
@@ -691,11 +1183,14 @@ fn make_scalar_function_from_wasm(
             }
 
             // Get the function to write its output to a slice of the results' buffer
+            start_call(&mut store)?;
             func.call(&mut store, &params, &mut results[row_ix..row_ix + 1])
                 .map_err(|e| {
-                    DataFusionError::Execution(format!(
-                        "Error executing function {function_name:?}: {e:?}"
-                    ))
+                    limit_exceeded(&mut store).unwrap_or_else(|| {
+                        DataFusionError::Execution(format!(
+                            "Error executing function {function_name:?}: {e:?}"
+                        ))
+                    })
                 })?;
         }
 
@@ -733,53 +1228,495 @@ fn make_scalar_function_from_wasm(
         Ok(array)
     };
 
-    Ok(make_scalar_function(inner))
+    Ok(make_scalar_function(inner))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_udf_from_wasm(
+    language: &CreateFunctionLanguage,
+    name: &str,
+    module_bytes: &[u8],
+    function_name: &str,
+    input_types: &Vec<CreateFunctionDataType>,
+    return_type: &CreateFunctionDataType,
+    volatility: Volatility,
+    limits: &WasmLimits,
+    wasi: &WasiCapabilities,
+) -> Result<ScalarUDF> {
+    let df_input_types = input_types
+        .iter()
+        .map(sql_type_to_arrow_type)
+        .collect::<Result<_>>()?;
+    let df_return_type = Arc::new(sql_type_to_arrow_type(return_type)?);
+
+    let function = match language {
+        CreateFunctionLanguage::Wasm => {
+            let converted_input_types = input_types
+                .iter()
+                .map(|t| get_wasm_type(t).unwrap())
+                .collect();
+            make_scalar_function_from_wasm(
+                module_bytes,
+                function_name,
+                // Convert input/output types. We only support the basic {I,F}{32,64} and not function references / V128
+                converted_input_types,
+                get_wasm_type(return_type)?,
+                limits.to_owned(),
+                wasi.to_owned(),
+            )?
+        }
+        CreateFunctionLanguage::WasmMessagePack => make_scalar_function_wasm_messagepack(
+            module_bytes,
+            function_name,
+            input_types.to_owned(),
+            return_type.to_owned(),
+            limits.to_owned(),
+            wasi.to_owned(),
+        )?,
+        CreateFunctionLanguage::WasmArrow => make_scalar_function_wasm_arrow(
+            module_bytes,
+            function_name,
+            input_types.to_owned(),
+            return_type.to_owned(),
+            limits.to_owned(),
+            wasi.to_owned(),
+        )?,
+    };
+
+    Ok(create_udf(
+        name,
+        df_input_types,
+        df_return_type,
+        volatility,
+        function,
+    ))
+}
+
+fn find_overload<'a>(
+    name: &str,
+    overloads: &'a [ScalarUDF],
+    arg_types: &[DataType],
+) -> Result<&'a ScalarUDF> {
+    overloads
+        .iter()
+        .find(|udf| {
+            matches!(&udf.signature.type_signature, TypeSignature::Exact(types) if types == arg_types)
+        })
+        .ok_or_else(|| {
+            DataFusionError::Plan(format!(
+                "No overload of function {name:?} accepts arguments of types {arg_types:?}"
+            ))
+        })
+}
+
+/// Combine the overloads of a scalar function (created with `create_udf_from_wasm`) into a
+/// single UDF that dispatches to the overload matching the types of its arguments.
+/// DataFusion coerces the arguments to the types of the first overload that can accept them.
+pub fn combine_overloaded_udfs(name: &str, overloads: Vec<ScalarUDF>) -> ScalarUDF {
+    // The combined function is only as stable as its least stable overload
+    let volatility = [Volatility::Volatile, Volatility::Stable]
+        .into_iter()
+        .find(|v| overloads.iter().any(|udf| udf.signature.volatility == *v))
+        .unwrap_or(Volatility::Immutable);
+    let signature = Signature::one_of(
+        overloads
+            .iter()
+            .map(|udf| udf.signature.type_signature.clone())
+            .collect(),
+        volatility,
+    );
+
+    let overloads = Arc::new(overloads);
+    let return_type: ReturnTypeFunction = {
+        let overloads = overloads.clone();
+        let name = name.to_string();
+        Arc::new(move |arg_types: &[DataType]| {
+            (find_overload(&name, &overloads, arg_types)?.return_type)(arg_types)
+        })
+    };
+    let fun: ScalarFunctionImplementation = {
+        let name = name.to_string();
+        Arc::new(move |args: &[ColumnarValue]| {
+            let arg_types = args.iter().map(|a| a.data_type()).collect::<Vec<_>>();
+            (find_overload(&name, &overloads, &arg_types)?.fun)(args)
+        })
+    };
+
+    ScalarUDF::new(name, &signature, &return_type, &fun)
+}
+
+fn aggregate_export(entrypoint: &str, step: &str) -> String {
+    format!("{entrypoint}_{step}")
+}
+
+const AGGREGATE_INIT: &str = "init";
+const AGGREGATE_UPDATE: &str = "update";
+const AGGREGATE_MERGE: &str = "merge";
+const AGGREGATE_FINALIZE: &str = "finalize";
+
+/// Accumulator for WASM + MessagePack aggregate functions. The aggregate's state is an
+/// arbitrary MessagePack value that's threaded through the module's exports:
+///
+/// - `{entrypoint}_init([])` returns the initial state
+/// - `{entrypoint}_update([state, arg1, arg2, ...])` adds a row to the state
+/// - `{entrypoint}_merge([state, other_state])` combines two partial states
+/// - `{entrypoint}_finalize([state])` returns the result of the aggregate
+#[derive(Debug)]
+struct WasmMessagePackAccumulator {
+    // Shared by all accumulators of the function, with all four exports loaded
+    module: Arc<FunctionModule>,
+    entrypoint: String,
+    input_types: Arc<Vec<CreateFunctionDataType>>,
+    return_type: CreateFunctionDataType,
+    state: Value,
+}
+
+impl WasmMessagePackAccumulator {
+    // Get an instance to run one of the aggregate's steps on the current state
+    fn instantiate(&self, step: &str) -> Result<(WasmUDFInstance, String)> {
+        let export = aggregate_export(&self.entrypoint, step);
+        Ok((self.module.instantiate()?, export))
+    }
+}
+
+impl Accumulator for WasmMessagePackAccumulator {
+    fn state(&self) -> Result<Vec<AggregateState>> {
+        let state = rmp_serde::to_vec(&self.state).map_err(|err| {
+            DataFusionError::Internal(format!(
+                "Error messagepack serializing aggregate state {err:?}"
+            ))
+        })?;
+        Ok(vec![AggregateState::Scalar(ScalarValue::Binary(Some(
+            state,
+        )))])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let (mut instance, export) = self.instantiate(AGGREGATE_UPDATE)?;
+        let array_len = values.first().map(|v| v.len()).unwrap_or_default();
+
+        for row_ix in 0..array_len {
+            // Like the built-in aggregates, ignore rows with NULLs in them
+            if values.iter().any(|v| v.is_null(row_ix)) {
+                continue;
+            }
+
+            let mut params: Vec<Value> = Vec::with_capacity(values.len() + 1);
+            params.push(std::mem::replace(&mut self.state, Value::Nil));
+            for col_ix in 0..values.len() {
+                params.push(messagepack_encode_input_value(
+                    self.input_types.get(col_ix).unwrap(),
+                    values,
+                    row_ix,
+                    col_ix,
+                )?);
+            }
+            self.state = instance.call(&export, params)?;
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let states = states
+            .first()
+            .and_then(|s| s.as_any().downcast_ref::<BinaryArray>())
+            .ok_or_else(|| {
+                DataFusionError::Internal(
+                    "Expected aggregate states to be a binary array".to_string(),
+                )
+            })?;
+        let (mut instance, export) = self.instantiate(AGGREGATE_MERGE)?;
+
+        for other_state in states.iter().flatten() {
+            let other_state: Value =
+                rmp_serde::from_slice(other_state).map_err(|err| {
+                    DataFusionError::Internal(format!(
+                        "Error messagepack decoding aggregate state: {err:?}"
+                    ))
+                })?;
+            let state = std::mem::replace(&mut self.state, Value::Nil);
+            self.state = instance.call(&export, vec![state, other_state])?;
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        let (mut instance, export) = self.instantiate(AGGREGATE_FINALIZE)?;
+        let result = instance.call(&export, vec![self.state.clone()])?;
+        let array = messagepack_decode_results(&self.return_type, &[result])?;
+        ScalarValue::try_from_array(&array, 0)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+fn check_messagepack_language(
+    language: &CreateFunctionLanguage,
+    function_type: &str,
+) -> Result<()> {
+    if *language != CreateFunctionLanguage::WasmMessagePack {
+        return Err(DataFusionError::Plan(format!(
+            "{function_type} are only supported with language 'wasmMessagePack'"
+        )));
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_udaf_from_wasm(
+    language: &CreateFunctionLanguage,
+    name: &str,
+    module_bytes: &[u8],
+    entrypoint: &str,
+    input_types: &[CreateFunctionDataType],
+    return_type: &CreateFunctionDataType,
+    volatility: Volatility,
+    limits: &WasmLimits,
+    wasi: &WasiCapabilities,
+) -> Result<AggregateUDF> {
+    check_messagepack_language(language, "Aggregate functions")?;
+
+    let df_input_types = input_types
+        .iter()
+        .map(sql_type_to_arrow_type)
+        .collect::<Result<_>>()?;
+    let df_return_type = Arc::new(sql_type_to_arrow_type(return_type)?);
+
+    // Make sure that the module has all of the aggregate's exports and get its initial
+    // state, which we reuse for every group
+    let exports = [
+        AGGREGATE_INIT,
+        AGGREGATE_UPDATE,
+        AGGREGATE_MERGE,
+        AGGREGATE_FINALIZE,
+    ]
+    .map(|step| aggregate_export(entrypoint, step));
+    let (module, initial_state) = FunctionModule::new(
+        name,
+        module_bytes,
+        &exports.iter().map(String::as_str).collect::<Vec<_>>(),
+        limits,
+        wasi,
+    )
+    .and_then(|module| {
+        let state = module.instantiate()?.call(&exports[0], vec![])?;
+        Ok((Arc::new(module), state))
+    })
+    .map_err(|err| {
+        wrap_wasm_error(err, |err| {
+            format!("Error initializing WASM + MessagePack UDAF {name:?}: {err:?}")
+        })
+    })?;
+
+    let entrypoint = entrypoint.to_string();
+    let input_types = Arc::new(input_types.to_vec());
+    let accumulator_return_type = return_type.to_owned();
+    let accumulator: AccumulatorFunctionImplementation = Arc::new(move |_: &DataType| {
+        Ok(Box::new(WasmMessagePackAccumulator {
+            module: module.clone(),
+            entrypoint: entrypoint.clone(),
+            input_types: input_types.clone(),
+            return_type: accumulator_return_type.clone(),
+            state: initial_state.clone(),
+        }) as Box<dyn Accumulator>)
+    });
+    let return_type: ReturnTypeFunction =
+        Arc::new(move |_: &[DataType]| Ok(df_return_type.clone()));
+    // The state is passed between partitions as serialized MessagePack
+    let state_type: StateTypeFunction =
+        Arc::new(|_: &DataType| Ok(Arc::new(vec![DataType::Binary])));
+
+    Ok(AggregateUDF::new(
+        name,
+        &Signature::exact(df_input_types, volatility),
+        &return_type,
+        &accumulator,
+        &state_type,
+    ))
+}
+
+/// A WASM + MessagePack function that returns a table. It gets called with
+/// `[arg1, arg2, ...]` and returns an array of rows, each one being an array with
+/// the values of `return_columns`.
+#[derive(Debug)]
+pub struct WasmTableFunction {
+    module: FunctionModule,
+    function_name: String,
+    input_types: Vec<CreateFunctionDataType>,
+    return_columns: Vec<CreateFunctionColumn>,
+    schema: SchemaRef,
+}
+
+impl WasmTableFunction {
+    pub fn name(&self) -> &str {
+        &self.function_name
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Call the function with constant arguments and collect the rows it returns
+    pub fn call(&self, args: &[ScalarValue]) -> Result<RecordBatch> {
+        let args = args.iter().map(|arg| arg.to_array()).collect::<Vec<_>>();
+        let (batch, _) = self.call_rows(&args, 1)?;
+        Ok(batch)
+    }
+
+    /// Call the function once for each row of the arguments and collect the rows it
+    /// returns, along with the index of the argument row that each of them came from
+    pub fn call_rows(
+        &self,
+        args: &[ArrayRef],
+        num_rows: usize,
+    ) -> Result<(RecordBatch, UInt32Array)> {
+        if args.len() != self.input_types.len() {
+            return Err(DataFusionError::Plan(format!(
+                "Wrong number of arguments for function {:?}: expected {:?}, received {:?}",
+                self.function_name,
+                self.input_types.len(),
+                args.len()
+            )));
+        }
+
+        let args = args
+            .iter()
+            .zip(&self.input_types)
+            .map(|(arg, input_type)| -> Result<ArrayRef> {
+                Ok(cast(arg, &sql_type_to_arrow_type(input_type)?)?)
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+
+        let wrap_error = |err| {
+            wrap_wasm_error(err, |err| {
+                format!("Error invoking function {:?}: {err:?}", self.function_name)
+            })
+        };
+
+        // Transpose the rows into columns, so that we can reuse the scalar UDF decoding
+        let mut columns: Vec<Vec<Value>> = vec![vec![]; self.return_columns.len()];
+        let mut indices = vec![];
+
+        let mut instance = self.module.instantiate().map_err(wrap_error)?;
+        for row_ix in 0..num_rows {
+            let params = (0..args.len())
+                .map(|col_ix| {
+                    messagepack_encode_input_value(
+                        self.input_types.get(col_ix).unwrap(),
+                        &args,
+                        row_ix,
+                        col_ix,
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let rows = match instance
+                .call(&self.function_name, params)
+                .map_err(wrap_error)?
+            {
+                Value::Array(rows) => rows,
+                other => {
+                    return Err(DataFusionError::Internal(format!(
+                        "Expected to find an array of rows, received {other:?} instead"
+                    )))
+                }
+            };
+
+            indices.extend(std::iter::repeat(row_ix as u32).take(rows.len()));
+            for row in rows {
+                match row {
+                    Value::Array(values) if values.len() == columns.len() => {
+                        for (column, value) in columns.iter_mut().zip(values) {
+                            column.push(value);
+                        }
+                    }
+                    other => {
+                        return Err(DataFusionError::Internal(format!(
+                        "Expected to find a row with {:?} values, received {other:?} instead",
+                        columns.len()
+                    )))
+                    }
+                }
+            }
+        }
+
+        let arrays = self
+            .return_columns
+            .iter()
+            .zip(columns)
+            .map(|(column, values)| {
+                messagepack_decode_results(&column.data_type, &values)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((
+            RecordBatch::try_new(self.schema.clone(), arrays)?,
+            UInt32Array::from(indices),
+        ))
+    }
 }
 
-pub fn create_udf_from_wasm(
+pub fn create_table_function_from_wasm(
     language: &CreateFunctionLanguage,
-    name: &str,
     module_bytes: &[u8],
     function_name: &str,
-    input_types: &Vec<CreateFunctionDataType>,
-    return_type: &CreateFunctionDataType,
-    volatility: Volatility,
-) -> Result<ScalarUDF> {
-    let df_input_types = input_types
+    input_types: &[CreateFunctionDataType],
+    return_columns: &[CreateFunctionColumn],
+    limits: &WasmLimits,
+    wasi: &WasiCapabilities,
+) -> Result<WasmTableFunction> {
+    check_messagepack_language(language, "Table functions")?;
+
+    if return_columns.is_empty() {
+        return Err(DataFusionError::Plan(
+            "Table functions have to return at least one column".to_string(),
+        ));
+    }
+
+    // Validate the argument types
+    input_types
         .iter()
         .map(sql_type_to_arrow_type)
-        .collect::<Result<_>>()?;
-    let df_return_type = Arc::new(sql_type_to_arrow_type(return_type)?);
+        .collect::<Result<Vec<_>>>()?;
 
-    let function = match language {
-        CreateFunctionLanguage::Wasm => {
-            let converted_input_types = input_types
-                .iter()
-                .map(|t| get_wasm_type(t).unwrap())
-                .collect();
-            make_scalar_function_from_wasm(
-                module_bytes,
-                function_name,
-                // Convert input/output types. We only support the basic {I,F}{32,64} and not function references / V128
-                converted_input_types,
-                get_wasm_type(return_type)?,
-            )?
-        }
-        CreateFunctionLanguage::WasmMessagePack => make_scalar_function_wasm_messagepack(
-            module_bytes,
-            function_name,
-            input_types.to_owned(),
-            return_type.to_owned(),
-        )?,
-    };
+    let schema = Arc::new(Schema::new(
+        return_columns
+            .iter()
+            .map(|c| -> Result<Field> {
+                Ok(Field::new(
+                    &c.name,
+                    sql_type_to_arrow_type(&c.data_type)?,
+                    false,
+                ))
+            })
+            .collect::<Result<_>>()?,
+    ));
+
+    // Make sure the module can be loaded and has the export we need
+    let module = FunctionModule::new(
+        function_name,
+        module_bytes,
+        &[function_name],
+        limits,
+        wasi,
+    )
+    .map_err(|err| {
+        wrap_wasm_error(err, |err| {
+            format!(
+                "Error initializing WASM + MessagePack table function {function_name:?}: {err:?}"
+            )
+        })
+    })?;
 
-    Ok(create_udf(
-        name,
-        df_input_types,
-        df_return_type,
-        volatility,
-        function,
-    ))
+    Ok(WasmTableFunction {
+        module,
+        function_name: function_name.to_string(),
+        input_types: input_types.to_vec(),
+        return_columns: return_columns.to_vec(),
+        schema,
+    })
 }
 
 #[cfg(test)]
@@ -832,6 +1769,8 @@ f95f3c90f2533d2267773eac66313f1d00803ff725303d03fd3fbe17a6d1\
             &vec![CreateFunctionDataType::F32],
             &CreateFunctionDataType::F32,
             Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
         )
         .unwrap();
 
@@ -844,6 +1783,8 @@ f95f3c90f2533d2267773eac66313f1d00803ff725303d03fd3fbe17a6d1\
             &vec![CreateFunctionDataType::F32],
             &CreateFunctionDataType::F32,
             Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
         )
         .unwrap();
 
@@ -856,6 +1797,8 @@ f95f3c90f2533d2267773eac66313f1d00803ff725303d03fd3fbe17a6d1\
             &vec![CreateFunctionDataType::F32],
             &CreateFunctionDataType::F32,
             Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
         )
         .unwrap();
 
@@ -961,6 +1904,8 @@ c40201087f230041206b2203240020032002370318200320013703102003\
             ],
             &CreateFunctionDataType::I64,
             Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
         )
         .unwrap();
 
@@ -977,6 +1922,8 @@ c40201087f230041206b2203240020032002370318200320013703102003\
             ],
             &CreateFunctionDataType::I64,
             Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
         )
         .unwrap();
         ctx.register_udf(speck_encrypt_block);
@@ -1043,6 +1990,12 @@ c40201087f230041206b2203240020032002370318200320013703102003\
             input_types,
             return_type,
             Volatility::Immutable,
+            &WasmLimits::default(),
+            // The module is built for a WASI target
+            &WasiCapabilities {
+                enabled: true,
+                ..Default::default()
+            },
         )
         .map(|udf| {
             ctx.register_udf(udf);
@@ -1517,4 +2470,535 @@ c40201087f230041206b2203240020032002370318200320013703102003\
 
         assert_batches_eq!(expected, &results);
     }
+
+    #[tokio::test]
+    async fn test_wasm_messagepack_overloaded_udf() {
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/messagepack_rust.wasm");
+        let wasm_module = get_file_as_byte_vec(&wasm_filename);
+
+        let overload = |entrypoint: &str, data_type: CreateFunctionDataType| {
+            create_udf_from_wasm(
+                &CreateFunctionLanguage::WasmMessagePack,
+                "plus",
+                &wasm_module,
+                entrypoint,
+                &vec![data_type.clone(), data_type.clone()],
+                &data_type,
+                Volatility::Immutable,
+                &WasmLimits::default(),
+                // The module is built for a WASI target
+                &WasiCapabilities {
+                    enabled: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        let mut ctx = SessionContext::new();
+        ctx.register_udf(combine_overloaded_udfs(
+            "plus",
+            vec![
+                overload("add_i64", CreateFunctionDataType::BIGINT),
+                overload("concat2", CreateFunctionDataType::TEXT),
+            ],
+        ));
+
+        let results = ctx
+            .sql("SELECT plus(1, 2) AS sum, plus('foo', 'bar') AS concat")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+-----+--------+",
+            "| sum | concat |",
+            "+-----+--------+",
+            "| 3   | foobar |",
+            "+-----+--------+",
+        ];
+
+        assert_batches_eq!(expected, &results);
+    }
+
+    fn messagepack_aggregate_module() -> Vec<u8> {
+        // See messagepack_aggregate.wat for the source
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/messagepack_aggregate.wasm");
+        get_file_as_byte_vec(&wasm_filename)
+    }
+
+    #[tokio::test]
+    async fn test_wasm_messagepack_aggregate() {
+        let mut ctx = SessionContext::new();
+        ctx.register_udaf(
+            create_udaf_from_wasm(
+                &CreateFunctionLanguage::WasmMessagePack,
+                "wasm_count",
+                &messagepack_aggregate_module(),
+                "count",
+                &[CreateFunctionDataType::INT],
+                &CreateFunctionDataType::BIGINT,
+                Volatility::Immutable,
+                &WasmLimits::default(),
+                &WasiCapabilities::default(),
+            )
+            .unwrap(),
+        );
+
+        let results = ctx
+            .sql(
+                "SELECT k, wasm_count(CAST(v AS INT)) AS count
+                FROM (VALUES ('a', 1), ('a', 2), ('b', 3), ('a', NULL), ('b', 5)) d (k, v)
+                GROUP BY k ORDER BY k",
+            )
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+---+-------+",
+            "| k | count |",
+            "+---+-------+",
+            "| a | 2     |",
+            "| b | 2     |",
+            "+---+-------+",
+        ];
+
+        assert_batches_eq!(expected, &results);
+    }
+
+    #[test]
+    fn test_wasm_messagepack_aggregate_merge() {
+        let udaf = create_udaf_from_wasm(
+            &CreateFunctionLanguage::WasmMessagePack,
+            "wasm_count",
+            &messagepack_aggregate_module(),
+            "count",
+            &[CreateFunctionDataType::INT],
+            &CreateFunctionDataType::BIGINT,
+            Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .unwrap();
+
+        let values: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None, Some(3)]));
+        let mut partial = (udaf.accumulator)(&DataType::Int64).unwrap();
+        partial.update_batch(&[values.clone()]).unwrap();
+        let mut total = (udaf.accumulator)(&DataType::Int64).unwrap();
+        total.update_batch(&[values]).unwrap();
+
+        // Merge the partial state into the other accumulator
+        let state = match partial.state().unwrap().pop().unwrap() {
+            AggregateState::Scalar(state) => state.to_array(),
+            _ => panic!("Expected a scalar aggregate state"),
+        };
+        total.merge_batch(&[state]).unwrap();
+
+        assert_eq!(total.evaluate().unwrap(), ScalarValue::Int64(Some(4)));
+    }
+
+    #[test]
+    fn test_wasm_messagepack_aggregate_wrong_language() {
+        let err = create_udaf_from_wasm(
+            &CreateFunctionLanguage::Wasm,
+            "wasm_count",
+            &messagepack_aggregate_module(),
+            "count",
+            &[CreateFunctionDataType::INT],
+            &CreateFunctionDataType::BIGINT,
+            Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Aggregate functions are only supported with language 'wasmMessagePack'"
+        );
+    }
+
+    #[test]
+    fn test_wasm_messagepack_table_function() {
+        let function = create_table_function_from_wasm(
+            &CreateFunctionLanguage::WasmMessagePack,
+            &messagepack_aggregate_module(),
+            "series",
+            &[CreateFunctionDataType::INT],
+            &[CreateFunctionColumn {
+                name: "i".to_string(),
+                data_type: CreateFunctionDataType::INT,
+            }],
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .unwrap();
+
+        // The argument gets cast to the function's input type
+        let results = function.call(&[ScalarValue::Int64(Some(3))]).unwrap();
+
+        let expected = vec![
+            "+---+", "| i |", "+---+", "| 0 |", "| 1 |", "| 2 |", "+---+",
+        ];
+        assert_batches_eq!(expected, &[results]);
+
+        let results = function.call(&[ScalarValue::Int64(Some(0))]).unwrap();
+        assert_eq!(results.num_rows(), 0);
+
+        assert!(function.call(&[]).is_err());
+    }
+
+    #[test]
+    fn test_function_module_instances() {
+        let module = FunctionModule::new(
+            "series",
+            &messagepack_aggregate_module(),
+            &["series"],
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .unwrap();
+
+        // Every instance starts out with a clean linear memory
+        let mut first = module.instantiate().unwrap();
+        first.memory.data_mut(&mut first.store)[0] = 42;
+        let mut second = module.instantiate().unwrap();
+        assert_eq!(second.memory.data_mut(&mut second.store)[0], 0);
+
+        // Including the ones that reuse the pool slot of a dropped instance
+        drop(first);
+        drop(second);
+        let mut third = module.instantiate().unwrap();
+        assert_eq!(third.memory.data_mut(&mut third.store)[0], 0);
+        drop(third);
+
+        // Slots go back to the pool when instances are dropped
+        for _ in 0..DEFAULT_MAX_INSTANCES + 1 {
+            module.instantiate().unwrap();
+        }
+
+        // A missing export fails up front
+        assert!(FunctionModule::new(
+            "missing",
+            &messagepack_aggregate_module(),
+            &["missing"],
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .is_err());
+    }
+
+    fn register_wasm_arrow_udf(
+        input_types: &Vec<CreateFunctionDataType>,
+        return_type: &CreateFunctionDataType,
+    ) -> Result<SessionContext> {
+        // See arrow_identity.wat for the source
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/arrow_identity.wasm");
+        let wasm_module = get_file_as_byte_vec(&wasm_filename);
+        let mut ctx = SessionContext::new();
+
+        create_udf_from_wasm(
+            &CreateFunctionLanguage::WasmArrow,
+            "identity",
+            &wasm_module,
+            "identity",
+            input_types,
+            return_type,
+            Volatility::Immutable,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .map(|udf| {
+            ctx.register_udf(udf);
+            ctx
+        })
+    }
+
+    #[tokio::test]
+    async fn test_wasm_arrow_identity() {
+        // The function returns an INT, which gets cast to the declared return type
+        let ctx = register_wasm_arrow_udf(
+            &vec![CreateFunctionDataType::INT],
+            &CreateFunctionDataType::BIGINT,
+        )
+        .unwrap();
+
+        let results = ctx
+            .sql(
+                "SELECT v, identity(CAST(v AS INT)) AS result
+                FROM (VALUES (1), (NULL), (3)) d (v)",
+            )
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+---+--------+",
+            "| v | result |",
+            "+---+--------+",
+            "| 1 | 1      |",
+            "|   |        |",
+            "| 3 | 3      |",
+            "+---+--------+",
+        ];
+
+        assert_batches_eq!(expected, &results);
+        assert_eq!(results[0].schema().field(1).data_type(), &DataType::Int64);
+    }
+
+    #[tokio::test]
+    async fn test_wasm_arrow_text() {
+        let ctx = register_wasm_arrow_udf(
+            &vec![CreateFunctionDataType::TEXT],
+            &CreateFunctionDataType::TEXT,
+        )
+        .unwrap();
+
+        let results = ctx
+            .sql("SELECT identity(v) AS result FROM (VALUES ('foo'), ('bar')) d (v)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+--------+",
+            "| result |",
+            "+--------+",
+            "| foo    |",
+            "| bar    |",
+            "+--------+",
+        ];
+
+        assert_batches_eq!(expected, &results);
+    }
+
+    #[tokio::test]
+    async fn test_wasm_arrow_multiple_result_columns() {
+        // With two arguments, the function returns a batch with two columns
+        let ctx = register_wasm_arrow_udf(
+            &vec![CreateFunctionDataType::INT, CreateFunctionDataType::INT],
+            &CreateFunctionDataType::INT,
+        )
+        .unwrap();
+
+        let err = ctx
+            .sql("SELECT identity(CAST(1 AS INT), CAST(2 AS INT))")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains(
+            "Expected the result to have exactly one column, received 2 instead"
+        ));
+    }
+
+    fn call_limited_udf(
+        language: CreateFunctionLanguage,
+        function_name: &str,
+        limits: WasmLimits,
+    ) -> DataFusionError {
+        // See limits.wat for the source
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/limits.wasm");
+
+        let udf = create_udf_from_wasm(
+            &language,
+            function_name,
+            &get_file_as_byte_vec(&wasm_filename),
+            function_name,
+            &vec![CreateFunctionDataType::INT],
+            &CreateFunctionDataType::INT,
+            Volatility::Volatile,
+            &limits,
+            &WasiCapabilities::default(),
+        )
+        .unwrap();
+
+        let args: ArrayRef = Arc::new(Int32Array::from(vec![1]));
+        (udf.fun)(&[ColumnarValue::Array(args)]).unwrap_err()
+    }
+
+    #[test]
+    fn test_wasm_fuel_limit() {
+        let err = call_limited_udf(
+            CreateFunctionLanguage::Wasm,
+            "spin",
+            WasmLimits {
+                max_fuel: Some(10000),
+                ..Default::default()
+            },
+        );
+
+        assert!(matches!(err, DataFusionError::ResourcesExhausted(_)));
+        assert_eq!(
+            err.to_string(),
+            "Resources exhausted: WASM function ran out of fuel (limit of 10000)"
+        );
+    }
+
+    #[test]
+    fn test_wasm_timeout() {
+        let err = call_limited_udf(
+            CreateFunctionLanguage::WasmMessagePack,
+            "spin",
+            WasmLimits {
+                timeout_ms: Some(50),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "Resources exhausted: WASM function exceeded the timeout of 50 ms"
+        );
+    }
+
+    #[test]
+    fn test_wasm_memory_limit() {
+        let err = call_limited_udf(
+            CreateFunctionLanguage::WasmMessagePack,
+            "grow",
+            WasmLimits {
+                max_memory: Some(1),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "Resources exhausted: WASM function exceeded the memory limit of 1 MiB"
+        );
+    }
+
+    fn call_wasi_udf(
+        function_name: &str,
+        return_type: CreateFunctionDataType,
+        wasi: &WasiCapabilities,
+    ) -> ArrayRef {
+        // See wasi.wat for the source
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/wasi.wasm");
+
+        let udf = create_udf_from_wasm(
+            &CreateFunctionLanguage::Wasm,
+            function_name,
+            &get_file_as_byte_vec(&wasm_filename),
+            function_name,
+            &vec![CreateFunctionDataType::INT],
+            &return_type,
+            Volatility::Volatile,
+            &WasmLimits::default(),
+            wasi,
+        )
+        .unwrap();
+
+        let args: ArrayRef = Arc::new(Int32Array::from(vec![1, 2]));
+        match (udf.fun)(&[ColumnarValue::Array(args)]).unwrap() {
+            ColumnarValue::Array(array) => array,
+            ColumnarValue::Scalar(_) => panic!("Expected an array"),
+        }
+    }
+
+    #[test]
+    fn test_wasi_disabled() {
+        // See wasi.wat for the source
+        let mut wasm_filename = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        wasm_filename.push_str("/resources/test/wasi.wasm");
+
+        let err = create_udf_from_wasm(
+            &CreateFunctionLanguage::Wasm,
+            "now",
+            &get_file_as_byte_vec(&wasm_filename),
+            "now",
+            &vec![CreateFunctionDataType::INT],
+            &CreateFunctionDataType::BIGINT,
+            Volatility::Volatile,
+            &WasmLimits::default(),
+            &WasiCapabilities::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Error instantiating WASM module"));
+    }
+
+    #[test]
+    fn test_wasi_no_capabilities() {
+        let wasi = WasiCapabilities {
+            enabled: true,
+            ..Default::default()
+        };
+
+        // The clock is stuck at the epoch...
+        let now = call_wasi_udf("now", CreateFunctionDataType::BIGINT, &wasi);
+        assert_eq!(
+            now.as_any().downcast_ref::<Int64Array>().unwrap().values(),
+            &[0, 0]
+        );
+
+        // ...random numbers are the same in every instance...
+        let random = call_wasi_udf("random", CreateFunctionDataType::BIGINT, &wasi);
+        assert_eq!(
+            &random,
+            &call_wasi_udf("random", CreateFunctionDataType::BIGINT, &wasi)
+        );
+
+        // ...and the environment is empty
+        let env_count = call_wasi_udf("env_count", CreateFunctionDataType::INT, &wasi);
+        assert_eq!(
+            env_count
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .unwrap()
+                .values(),
+            &[0, 0]
+        );
+    }
+
+    #[test]
+    fn test_wasi_capabilities() {
+        let wasi = WasiCapabilities {
+            enabled: true,
+            env: [("LANG".to_string(), "C".to_string())].into(),
+            clocks: true,
+            random: true,
+        };
+
+        let now = call_wasi_udf("now", CreateFunctionDataType::BIGINT, &wasi);
+        assert!(now
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap()
+            .values()
+            .iter()
+            .all(|t| *t > 0));
+
+        let random = call_wasi_udf("random", CreateFunctionDataType::BIGINT, &wasi);
+        assert_ne!(
+            &random,
+            &call_wasi_udf("random", CreateFunctionDataType::BIGINT, &wasi)
+        );
+
+        let env_count = call_wasi_udf("env_count", CreateFunctionDataType::INT, &wasi);
+        assert_eq!(
+            env_count
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .unwrap()
+                .values(),
+            &[1, 1]
+        );
+    }
 }
@@ -1,4 +1,6 @@
 use crate::statements::*;
+use seafowl::data_types::ParquetWriterOptions;
+use seafowl::scheduler::{refresh_stale_materialized_views, JOB_RUN_SUCCEEDED};
 
 #[tokio::test]
 async fn test_create_table() {
@@ -74,6 +76,89 @@ async fn test_create_table_as() {
     assert_batches_eq!(expected, &results);
 }
 
+#[tokio::test]
+async fn test_create_table_with_writer_options() {
+    let context = make_context_with_pg().await;
+
+    let plan = context
+        .plan_query(
+            "CREATE TABLE test_table (id VARCHAR, value INT) WITH (
+            compression = 'Snappy', row_group_size = 1000,
+            bloom_filter_columns = 'id', dictionary = false)",
+        )
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    // The options are persisted in the catalog
+    context.reload_schema().await.unwrap();
+    assert_eq!(
+        *context
+            .try_get_seafowl_table("test_table")
+            .unwrap()
+            .writer_options,
+        ParquetWriterOptions {
+            compression: Some("snappy".to_string()),
+            row_group_size: Some(1000),
+            bloom_filter_columns: vec!["id".to_string()],
+            dictionary: Some(false),
+        }
+    );
+
+    // ...and used when writing the partitions
+    let plan = context
+        .plan_query("INSERT INTO test_table VALUES ('one', 1), ('two', 2)")
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM test_table ORDER BY value")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+-----+-------+",
+        "| id  | value |",
+        "+-----+-------+",
+        "| one | 1     |",
+        "| two | 2     |",
+        "+-----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    for (query, error) in [
+        (
+            "CREATE TABLE other_table (id INT) WITH (compression = 'lzma')",
+            "Unsupported compression \"lzma\"",
+        ),
+        (
+            "CREATE TABLE other_table (id INT) WITH (compression = 'lzo')",
+            "Unsupported compression \"lzo\"",
+        ),
+        (
+            "CREATE TABLE other_table (id INT) WITH (row_group_size = 0)",
+            "Invalid row group size 0",
+        ),
+        (
+            "CREATE TABLE other_table (id INT) WITH (bloom_filter_columns = 'name')",
+            "Bloom filter column \"name\" doesn't exist",
+        ),
+        (
+            "CREATE TABLE other_table (id INT) WITH (page_size = 1024)",
+            "Unsupported table option page_size = 1024",
+        ),
+        (
+            "CREATE TABLE other_table WITH (compression = 'lz4') AS SELECT 1",
+            "Table options are only supported when creating an empty table",
+        ),
+    ] {
+        let err = context.plan_query(query).await.unwrap_err();
+        assert_contains!(err.to_string(), error);
+    }
+}
+
 #[tokio::test]
 async fn test_create_table_move_and_drop() {
     // Create two tables, insert some data into them
@@ -335,6 +420,374 @@ async fn test_create_table_drop_schema() {
     assert_batches_eq!(expected, &results);
 }
 
+#[tokio::test]
+async fn test_create_view_query_and_drop() {
+    let context = make_context_with_pg().await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .collect(
+            context
+                .plan_query(
+                    "CREATE VIEW test_view AS SELECT some_int_value, some_value AS value \
+                    FROM test_table WHERE some_int_value > 1111",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM test_view ORDER BY some_int_value")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+----------------+-------+",
+        "| some_int_value | value |",
+        "+----------------+-------+",
+        "| 2222           | 43    |",
+        "| 3333           | 44    |",
+        "+----------------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The view shows up in the information_schema
+    let plan = context
+        .plan_query(
+            "SELECT table_name, table_type FROM information_schema.tables \
+            WHERE table_schema = 'public' ORDER BY table_name",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+------------+------------+",
+        "| table_name | table_type |",
+        "+------------+------------+",
+        "| test_table | BASE TABLE |",
+        "| test_view  | VIEW       |",
+        "+------------+------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            "SELECT column_name, data_type FROM information_schema.columns \
+            WHERE table_name = 'test_view' ORDER BY ordinal_position",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+----------------+-----------+",
+        "| column_name    | data_type |",
+        "+----------------+-----------+",
+        "| some_int_value | Int64     |",
+        "| value          | Float32   |",
+        "+----------------+-----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Can't create a view with an existing name without OR REPLACE, or replace a table
+    let err = context
+        .plan_query("CREATE VIEW test_view AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "Table \"test_view\" already exists");
+
+    let err = context
+        .plan_query("CREATE OR REPLACE VIEW test_table AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "Table \"test_table\" already exists");
+
+    // Replace the view with one that references another view
+    context
+        .collect(
+            context
+                .plan_query(
+                    "CREATE VIEW test_view_2 AS SELECT some_int_value FROM test_table",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    context
+        .collect(
+            context
+                .plan_query(
+                    "CREATE OR REPLACE VIEW test_view AS \
+                    SELECT COUNT(*) AS count FROM test_view_2",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Views pick up new data written to the tables they reference
+    context
+        .collect(
+            context
+                .plan_query("INSERT INTO test_table (some_int_value) VALUES (4444)")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let plan = context.plan_query("SELECT * FROM test_view").await.unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+-------+",
+        "| count |",
+        "+-------+",
+        "| 4     |",
+        "+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // DROP TABLE/VIEW only work on the right kind of table
+    let err = context
+        .plan_query("DROP VIEW test_table")
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "\"test_table\" is not a view");
+
+    context
+        .collect(
+            context
+                .plan_query("DROP VIEW test_view, test_view_2")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let err = context
+        .plan_query("SELECT * FROM test_view")
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "test_view");
+
+    let err = context.plan_query("DROP VIEW test_view").await.unwrap_err();
+    assert_contains!(err.to_string(), "test_view");
+
+    context
+        .collect(
+            context
+                .plan_query("DROP VIEW IF EXISTS test_view")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let results = list_tables_query(&context).await;
+
+    let expected = vec![
+        "+--------------------+-------------+",
+        "| table_schema       | table_name  |",
+        "+--------------------+-------------+",
+        "| information_schema | columns     |",
+        "| information_schema | df_settings |",
+        "| information_schema | tables      |",
+        "| information_schema | views       |",
+        "| public             | test_table  |",
+        "+--------------------+-------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+}
+
+#[tokio::test]
+async fn test_create_and_refresh_materialized_view() {
+    let context = make_context_with_pg().await;
+    create_table_and_insert(&context, "test_table").await;
+
+    for query in [
+        "CREATE MATERIALIZED VIEW test_mv AS \
+        SELECT COUNT(*) AS count, SUM(some_int_value) AS total FROM test_table",
+        "CREATE MATERIALIZED VIEW test_mv_auto WITH (auto_refresh = true) AS \
+        SELECT COUNT(*) AS count FROM test_table",
+        "CREATE MATERIALIZED VIEW test_mv_auto_2 WITH (auto_refresh = true) AS \
+        SELECT count * 2 AS double_count FROM test_mv_auto",
+    ] {
+        context
+            .collect(context.plan_query(query).await.unwrap())
+            .await
+            .unwrap();
+    }
+
+    let err = context
+        .plan_query("CREATE MATERIALIZED VIEW test_mv_3 WITH (refresh = 1) AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_contains!(
+        err.to_string(),
+        "Unsupported materialized view option refresh = 1"
+    );
+
+    // Materialized views are regular tables
+    let plan = context
+        .plan_query(
+            "SELECT table_name, table_type FROM information_schema.tables \
+            WHERE table_schema = 'public' ORDER BY table_name",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+----------------+------------+",
+        "| table_name     | table_type |",
+        "+----------------+------------+",
+        "| test_mv        | BASE TABLE |",
+        "| test_mv_auto   | BASE TABLE |",
+        "| test_mv_auto_2 | BASE TABLE |",
+        "| test_table     | BASE TABLE |",
+        "+----------------+------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Writes don't wait for the views to get refreshed, they only wake up the background
+    // task doing it
+    context
+        .collect(
+            context
+                .plan_query("INSERT INTO test_table (some_int_value) VALUES (4444)")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    tokio::time::timeout(
+        Duration::from_secs(1),
+        context.materialized_view_refresh.notified(),
+    )
+    .await
+    .unwrap();
+
+    let plan = context
+        .plan_query("SELECT count FROM test_mv_auto")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+-------+",
+        "| count |",
+        "+-------+",
+        "| 3     |",
+        "+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Only the views with auto_refresh pick up the new data in the background, including the
+    // one that reads from another materialized view
+    refresh_stale_materialized_views(&context, 1000).await;
+
+    let job_runs = context.table_catalog.get_all_job_runs().await.unwrap();
+    assert_eq!(
+        job_runs
+            .iter()
+            .map(|run| (run.job_name.as_str(), run.status.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("refresh public.test_mv_auto", JOB_RUN_SUCCEEDED),
+            ("refresh public.test_mv_auto_2", JOB_RUN_SUCCEEDED)
+        ]
+    );
+
+    // Nothing changed since, so there's nothing more to refresh
+    refresh_stale_materialized_views(&context, 1000).await;
+    assert_eq!(
+        context
+            .table_catalog
+            .get_all_job_runs()
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let plan = context
+        .plan_query(
+            "SELECT test_mv.*, test_mv_auto.count AS auto_count, double_count \
+            FROM test_mv, test_mv_auto, test_mv_auto_2",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+-------+-------+------------+--------------+",
+        "| count | total | auto_count | double_count |",
+        "+-------+-------+------------+--------------+",
+        "| 3     | 6666  | 4          | 8            |",
+        "+-------+-------+------------+--------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    context
+        .collect(
+            context
+                .plan_query("REFRESH MATERIALIZED VIEW test_mv")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let plan = context.plan_query("SELECT * FROM test_mv").await.unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+-------+-------+",
+        "| count | total |",
+        "+-------+-------+",
+        "| 4     | 11110 |",
+        "+-------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Each refresh makes a new table version; the views that were already up to date
+    // didn't get refreshed again
+    let plan = context
+        .plan_query(
+            "SELECT table_name, COUNT(*) AS versions FROM system.table_versions \
+            WHERE table_name LIKE 'test_mv%' GROUP BY table_name ORDER BY table_name",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+----------------+----------+",
+        "| table_name     | versions |",
+        "+----------------+----------+",
+        "| test_mv        | 2        |",
+        "| test_mv_auto   | 2        |",
+        "| test_mv_auto_2 | 2        |",
+        "+----------------+----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("REFRESH MATERIALIZED VIEW test_table")
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "\"test_table\" is not a materialized view");
+}
+
 #[tokio::test]
 async fn test_create_table_schema_already_exists() {
     let context = make_context_with_pg().await;
@@ -422,11 +875,11 @@ async fn test_create_external_table_http() {
 
     let context = make_context_with_pg().await;
 
-    // Try creating a table in a non-staging schema
+    // Try creating a table in the system schema
     let err = context
         .plan_query(
             format!(
-                "CREATE EXTERNAL TABLE public.file
+                "CREATE EXTERNAL TABLE system.file
         STORED AS PARQUET
         LOCATION '{url}'"
             )
@@ -436,9 +889,9 @@ async fn test_create_external_table_http() {
         .unwrap_err();
     assert!(err
         .to_string()
-        .contains("Can only create external tables in the staging schema"));
+        .contains("Can't create external tables in the system schema"));
 
-    // Create a table normally
+    // Create a table normally (in the staging schema)
     let plan = context
         .plan_query(
             format!(
@@ -452,6 +905,20 @@ async fn test_create_external_table_http() {
         .unwrap();
     context.collect(plan).await.unwrap();
 
+    // Create a table in a regular schema, which gets persisted in the catalog
+    let plan = context
+        .plan_query(
+            format!(
+                "CREATE EXTERNAL TABLE public.file
+        STORED AS PARQUET
+        LOCATION '{url}'"
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
     // Test we see the table in the information_schema
     let results = list_tables_query(&context).await;
 
@@ -463,17 +930,13 @@ async fn test_create_external_table_http() {
         "| information_schema | df_settings |",
         "| information_schema | tables      |",
         "| information_schema | views       |",
+        "| public             | file        |",
         "| staging            | file        |",
         "+--------------------+-------------+",
     ];
     assert_batches_eq!(expected, &results);
 
     // Test standard query
-    let plan = context
-        .plan_query("SELECT * FROM staging.file")
-        .await
-        .unwrap();
-    let results = context.collect(plan).await.unwrap();
     let expected = vec![
         "+-------+",
         "| col_1 |",
@@ -484,6 +947,31 @@ async fn test_create_external_table_http() {
         "+-------+",
     ];
 
+    for table in ["staging.file", "public.file"] {
+        let plan = context
+            .plan_query(format!("SELECT * FROM {table}").as_str())
+            .await
+            .unwrap();
+        let results = context.collect(plan).await.unwrap();
+        assert_batches_eq!(expected, &results);
+    }
+
+    // Drop the persisted table
+    let plan = context.plan_query("DROP TABLE public.file").await.unwrap();
+    context.collect(plan).await.unwrap();
+
+    let results = list_tables_query(&context).await;
+    let expected = vec![
+        "+--------------------+-------------+",
+        "| table_schema       | table_name  |",
+        "+--------------------+-------------+",
+        "| information_schema | columns     |",
+        "| information_schema | df_settings |",
+        "| information_schema | tables      |",
+        "| information_schema | views       |",
+        "| staging            | file        |",
+        "+--------------------+-------------+",
+    ];
     assert_batches_eq!(expected, &results);
 
     // Test we can't hit the Seafowl object store directly via CREATE EXTERNAL TABLE
@@ -508,4 +996,43 @@ async fn test_create_external_table_http() {
     assert!(err
         .to_string()
         .contains("No suitable object store found for seafowl://file"));
+
+    // The location can come from an environment variable instead, which only gets read when
+    // the table is loaded, so that it isn't stored in the catalog
+    std::env::set_var("SEAFOWL_TEST_EXTERNAL_TABLE_URL", &url);
+    let plan = context
+        .plan_query(
+            "CREATE EXTERNAL TABLE public.env_file STORED AS PARQUET \
+            LOCATION 'env:SEAFOWL_TEST_EXTERNAL_TABLE_URL'",
+        )
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM public.env_file")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+    let expected = vec![
+        "+-------+",
+        "| col_1 |",
+        "+-------+",
+        "| 1     |",
+        "| 2     |",
+        "| 3     |",
+        "+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let tables = context
+        .table_catalog
+        .get_all_external_tables(context.database_id)
+        .await
+        .unwrap();
+    assert_eq!(tables.len(), 1);
+    assert_eq!(
+        tables[0].details.location,
+        "env:SEAFOWL_TEST_EXTERNAL_TABLE_URL"
+    );
 }
@@ -490,10 +490,10 @@ async fn test_delete_with_string_filter_exact_match() {
         .await
         .unwrap();
 
-    // For some reason, the initial pruning in a DELETE doesn't discard the two
-    // partitions that definitely don't match (partition != 'two'), so we end up
-    // with a new partition (if we delete where value = 2, this does result in the other
-    // two partitions being kept as-is, so could have something to do with strings)
+    // The pruning discards the two partitions that definitely don't match (partition != 'two'),
+    // so they get kept as-is and only the matching partition gets rewritten (to an empty one)
+    assert_eq!(partitions.len(), 3);
+    assert_eq!(partitions[2].row_count, 0);
     let results =
         scan_partition(&context, None, partitions[0].clone(), "test_table").await;
     let expected = vec![
@@ -501,11 +501,29 @@ async fn test_delete_with_string_filter_exact_match() {
         "| partition | value |",
         "+-----------+-------+",
         "| one       | 1     |",
+        "+-----------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let results =
+        scan_partition(&context, None, partitions[1].clone(), "test_table").await;
+    let expected = vec![
+        "+-----------+-------+",
+        "| partition | value |",
+        "+-----------+-------+",
         "| three     | 3     |",
         "+-----------+-------+",
     ];
     assert_batches_eq!(expected, &results);
 
+    let expected = vec![
+        "+-----------+-------+",
+        "| partition | value |",
+        "+-----------+-------+",
+        "| one       | 1     |",
+        "| three     | 3     |",
+        "+-----------+-------+",
+    ];
     let plan = context
         .plan_query("SELECT * FROM test_table ORDER BY value ASC")
         .await
@@ -514,6 +532,119 @@ async fn test_delete_with_string_filter_exact_match() {
     assert_batches_eq!(expected, &results);
 }
 
+#[tokio::test]
+async fn test_bloom_filter_pruning() {
+    let context = make_context_with_pg().await;
+
+    context
+        .collect(
+            context
+                .plan_query(
+                    "CREATE TABLE test_table (id VARCHAR, value INT) \
+                    WITH (bloom_filter_columns = 'id')",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Create 3 partitions with overlapping ranges of ids, so that only the Bloom
+    // filters can tell them apart
+    for values in [
+        "('a', 1), ('z', 2)",
+        "('b', 3), ('m', 4), ('y', 5)",
+        "('c', 6), ('x', 7)",
+    ] {
+        context
+            .collect(
+                context
+                    .plan_query(&format!("INSERT INTO test_table VALUES {values}"))
+                    .await
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    context.reload_schema().await.unwrap();
+    let table = context.try_get_seafowl_table("test_table").unwrap();
+    let partitions = context
+        .partition_catalog
+        .load_table_partitions(table.table_version_id)
+        .await
+        .unwrap();
+    assert_eq!(partitions.len(), 3);
+
+    // The Bloom filters only get loaded on demand, for the columns that have them
+    assert!(partitions[1].columns[0].bloom_filter.is_none());
+    let bloom_filters = context
+        .partition_catalog
+        .load_bloom_filters(
+            table.table_version_id,
+            vec!["id".to_string(), "value".to_string()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(bloom_filters.keys().collect::<Vec<_>>(), vec!["id"]);
+    assert_eq!(bloom_filters["id"].len(), 3);
+
+    // Only the second partition can contain the id
+    for query in [
+        "SELECT * FROM test_table WHERE id = 'm'",
+        "SELECT * FROM test_table WHERE id IN ('m', 'n')",
+    ] {
+        let plan = context
+            .plan_query(&format!("EXPLAIN {query}"))
+            .await
+            .unwrap();
+        let results = context.collect(plan).await.unwrap();
+        let formatted = arrow::util::pretty::pretty_format_batches(results.as_slice())
+            .unwrap()
+            .to_string();
+        assert_contains!(
+            formatted,
+            format!("partitions=[{}]", partitions[1].object_storage_id)
+        );
+
+        let results = context
+            .collect(context.plan_query(query).await.unwrap())
+            .await
+            .unwrap();
+        let expected = vec![
+            "+----+-------+",
+            "| id | value |",
+            "+----+-------+",
+            "| m  | 4     |",
+            "+----+-------+",
+        ];
+        assert_batches_eq!(expected, &results);
+    }
+
+    // The DELETE only needs to rewrite the second partition
+    context
+        .collect(
+            context
+                .plan_query("DELETE FROM test_table WHERE id = 'm'")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    context.reload_schema().await.unwrap();
+    let table = context.try_get_seafowl_table("test_table").unwrap();
+    let new_partitions = context
+        .partition_catalog
+        .load_table_partitions(table.table_version_id)
+        .await
+        .unwrap();
+    assert_eq!(new_partitions.len(), 3);
+    assert!(new_partitions.contains(&partitions[0]));
+    assert!(!new_partitions.contains(&partitions[1]));
+    assert!(new_partitions.contains(&partitions[2]));
+}
+
 #[tokio::test]
 async fn test_update_statement() {
     let context = make_context_with_pg().await;
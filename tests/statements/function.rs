@@ -1,3 +1,5 @@
+use sha2::{Digest, Sha256};
+
 use crate::statements::*;
 
 #[tokio::test]
@@ -158,3 +160,400 @@ async fn test_create_and_run_function_uppercase_type_names() {
         "Error during planning: Function \"sintau\" already exists"
     );
 }
+
+#[tokio::test]
+async fn test_create_and_run_aggregate_and_table_functions() {
+    let context = make_context_with_pg().await;
+
+    // Source: resources/test/messagepack_aggregate.wat
+    let data = "AGFzbQEAAAABCwJgAX8Bf2ACf38AAwoJAAEAAAAAAAAABQMBABAGBwF/AUGACAsHYAgGbWVtb3J5AgAFYWxsb2MAAAdkZWFsbG9jAAEKY291bnRfaW5pdAAEDGNvdW50X3VwZGF0ZQAFC2NvdW50X21lcmdlAAYOY291bnRfZmluYWxpemUABwZzZXJpZXMACAqSAgkRAQF/IwAhASMAIABqJAAgAQsCAAspAQF/QQoQACEBIAFBBjYCACABQcQBOgAEIAFBBDoABSABIAA2AgYgAQtAAQF/QQkQACEBIAFBBTYCACABQc4BOgAEIAEgAEEYdjoABSABIABBEHY6AAYgASAAQQh2OgAHIAEgADoACCABCwYAQQAQAgsMACAAKAIHQQFqEAILDwAgACgCByAAKAINahACCwkAIAAoAgcQAwtiAQN/IAAtAAUhAUEFIAFBAXRqEAAhAiACQQEgAUEBdGo2AgAgAkGQASABcjoABAJAA0AgAyABTw0BIAIgA0EBdGpBkQE6AAUgAiADQQF0aiADOgAGIANBAWohAwwACwsgAgsAnAEEbmFtZQEgAwAFYWxsb2MCC3dyaXRlX3N0YXRlAwl3cml0ZV91MzICWQcAAgAEc2l6ZQEDcHRyAgIABXZhbHVlAQNwdHIDAgAFdmFsdWUBA3B0cgUBAAVpbnB1dAYBAAVpbnB1dAcBAAVpbnB1dAgEAAVpbnB1dAEBbgIDcHRyAwFpAw8BCAIABGRvbmUBBHJvd3MHBwEABGhlYXA=";
+
+    context
+        .collect(
+            context
+                .plan_query(&format!(
+                    r#"CREATE AGGREGATE FUNCTION wasm_count AS '
+    {{
+        "entrypoint": "count",
+        "language": "wasmMessagePack",
+        "input_types": ["int"],
+        "return_type": "bigint",
+        "data": "{data}"
+    }}';"#
+                ))
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    context
+        .collect(
+            context
+                .plan_query(&format!(
+                    r#"CREATE FUNCTION series RETURNS TABLE (i INT) AS '
+    {{
+        "entrypoint": "series",
+        "language": "wasmMessagePack",
+        "input_types": ["int"],
+        "data": "{data}"
+    }}';"#
+                ))
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let results = context
+        .collect(
+            context
+                .plan_query(
+                    "SELECT wasm_count(s.i) AS count, MAX(series.i) AS max
+                    FROM series(5) CROSS JOIN series(3) AS s",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+-------+-----+",
+        "| count | max |",
+        "+-------+-----+",
+        "| 15    | 4   |",
+        "+-------+-----+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    // Arguments can come from the tables preceding the function
+    let results = context
+        .collect(
+            context
+                .plan_query(
+                    "SELECT s.i AS n, t.i FROM series(4) AS s, series(s.i) AS t
+                    WHERE t.i < 2 ORDER BY n, t.i",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+---+---+",
+        "| n | i |",
+        "+---+---+",
+        "| 1 | 0 |",
+        "| 2 | 0 |",
+        "| 2 | 1 |",
+        "| 3 | 0 |",
+        "| 3 | 1 |",
+        "+---+---+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    let results = context
+        .collect(
+            context
+                .plan_query(
+                    "SELECT s.i AS n, t.i FROM series(3) AS s JOIN series(s.i + 1) AS t ON t.i = s.i
+                    ORDER BY n",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+---+---+",
+        "| n | i |",
+        "+---+---+",
+        "| 0 | 0 |",
+        "| 1 | 1 |",
+        "| 2 | 2 |",
+        "+---+---+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    let results = context
+        .collect(
+            context
+                .plan_query(
+                    "SELECT function_name, kind, return_type FROM system.functions ORDER BY function_name",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+---------------+-----------+-------------+",
+        "| function_name | kind      | return_type |",
+        "+---------------+-----------+-------------+",
+        "| series        | Table     | TABLE       |",
+        "| wasm_count    | Aggregate | BIGINT      |",
+        "+---------------+-----------+-------------+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+}
+
+#[tokio::test]
+async fn test_create_or_replace_overload_and_drop_function() {
+    let context = make_context_with_pg().await;
+
+    let data = "AGFzbQEAAAABDQJgAX0BfWADfX9/AX0DBQQAAAABBQQBAUREBxgDBnNpbnRhdQAABGV4cDIAAQRsb2cyAAIKjgEEKQECfUMAAAA/IgIgACAAjpMiACACk4siAZMgAZZBAEEYEAMgAiAAk5gLGQAgACAAjiIAk0EYQSwQA7wgAKhBF3RqvgslAQF/IAC8IgFBF3ZB/wBrsiABQQl0s0MAAIBPlUEsQcQAEAOSCyIBAX0DQCADIACUIAEqAgCSIQMgAUEEaiIBIAJrDQALIAMLC0oBAEEAC0Q/x2FC2eATQUuqKsJzsqY9QAHJQH6V0DZv+V88kPJTPSJndz6sZjE/HQCAP/clMD0D/T++F6bRPkzcNL/Tgrg//IiKNwBqBG5hbWUBHwQABnNpbnRhdQEEZXhwMgIEbG9nMgMIZXZhbHBvbHkCNwQAAwABeAECeDECBGhhbGYBAQABeAICAAF4AQJ4aQMEAAF4AQVzdGFydAIDZW5kAwZyZXN1bHQDCQEDAQAEbG9vcA==";
+    let create_function = |or_replace: &str, input_type: &str, volatility: &str| {
+        format!(
+            r#"CREATE {or_replace} FUNCTION sintau AS '
+    {{
+        "entrypoint": "sintau",
+        "language": "wasm",
+        "input_types": ["{input_type}"],
+        "return_type": "float",
+        "volatility": "{volatility}",
+        "data": "{data}"
+    }}';"#
+        )
+    };
+
+    for query in [
+        create_function("", "float", "volatile"),
+        // Replace the function with the same input types
+        create_function("OR REPLACE", "float", "immutable"),
+        // Add an overload with different input types
+        create_function("", "double", "volatile"),
+    ] {
+        context
+            .collect(context.plan_query(&query).await.unwrap())
+            .await
+            .unwrap();
+    }
+
+    let results = context
+        .collect(
+            context
+                .plan_query(
+                    "SELECT function_name, input_types, volatility FROM system.functions \
+                    ORDER BY input_types",
+                )
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+---------------+-------------+------------+",
+        "| function_name | input_types | volatility |",
+        "+---------------+-------------+------------+",
+        "| sintau        | DOUBLE      | Volatile   |",
+        "| sintau        | FLOAT       | Immutable  |",
+        "+---------------+-------------+------------+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    // The overload gets picked based on the argument types
+    let results = context
+        .collect(
+            context
+                .plan_query("SELECT ROUND(sintau(CAST(0.1 AS REAL)) * 100) AS sintau")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+--------+",
+        "| sintau |",
+        "+--------+",
+        "| 59     |",
+        "+--------+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    // Drop a single overload, then the whole function
+    context
+        .collect(
+            context
+                .plan_query("DROP FUNCTION sintau(DOUBLE)")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let results = context
+        .collect(
+            context
+                .plan_query("SELECT input_types FROM system.functions")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+-------------+",
+        "| input_types |",
+        "+-------------+",
+        "| FLOAT       |",
+        "+-------------+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    context
+        .collect(context.plan_query("DROP FUNCTION sintau").await.unwrap())
+        .await
+        .unwrap();
+
+    let err = context
+        .plan_query("SELECT sintau(CAST(0.1 AS REAL))")
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid function 'sintau'"));
+
+    let err = context
+        .plan_query("DROP FUNCTION sintau")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Function \"sintau\" not found"
+    );
+
+    context
+        .collect(
+            context
+                .plan_query("DROP FUNCTION IF EXISTS sintau")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_create_function_from_location() {
+    let context = make_context_with_pg().await;
+
+    let data = "AGFzbQEAAAABDQJgAX0BfWADfX9/AX0DBQQAAAABBQQBAUREBxgDBnNpbnRhdQAABGV4cDIAAQRsb2cyAAIKjgEEKQECfUMAAAA/IgIgACAAjpMiACACk4siAZMgAZZBAEEYEAMgAiAAk5gLGQAgACAAjiIAk0EYQSwQA7wgAKhBF3RqvgslAQF/IAC8IgFBF3ZB/wBrsiABQQl0s0MAAIBPlUEsQcQAEAOSCyIBAX0DQCADIACUIAEqAgCSIQMgAUEEaiIBIAJrDQALIAMLC0oBAEEAC0Q/x2FC2eATQUuqKsJzsqY9QAHJQH6V0DZv+V88kPJTPSJndz6sZjE/HQCAP/clMD0D/T++F6bRPkzcNL/Tgrg//IiKNwBqBG5hbWUBHwQABnNpbnRhdQEEZXhwMgIEbG9nMgMIZXZhbHBvbHkCNwQAAwABeAECeDECBGhhbGYBAQABeAICAAF4AQJ4aQMEAAF4AQVzdGFydAIDZW5kAwZyZXN1bHQDCQEDAQAEbG9vcA==";
+    let module = base64::decode(data).unwrap();
+    let digest = hex::encode(Sha256::digest(&module));
+    context
+        .internal_object_store
+        .inner
+        .put(&Path::from("uploads/sintau.wasm"), module.into())
+        .await
+        .unwrap();
+
+    let create_function = |sha256: &str| {
+        format!(
+            r#"CREATE FUNCTION sintau AS '
+    {{
+        "entrypoint": "sintau",
+        "language": "wasm",
+        "input_types": ["float"],
+        "return_type": "float",
+        "location": "seafowl://uploads/sintau.wasm",
+        "sha256": "{sha256}"
+    }}';"#
+        )
+    };
+
+    let err = context
+        .plan_query(&create_function("0123456789abcdef"))
+        .await
+        .unwrap_err();
+    assert_contains!(
+        err.to_string(),
+        "SHA-256 digest mismatch for the UDF module"
+    );
+
+    context
+        .collect(context.plan_query(&create_function(&digest)).await.unwrap())
+        .await
+        .unwrap();
+
+    // The module gets copied into the internal object store and the function points to it
+    context
+        .internal_object_store
+        .inner
+        .delete(&Path::from("uploads/sintau.wasm"))
+        .await
+        .unwrap();
+    context
+        .internal_object_store
+        .inner
+        .head(&Path::from(format!("functions/{digest}.wasm")))
+        .await
+        .unwrap();
+
+    let results = context
+        .collect(
+            context
+                .plan_query("SELECT ROUND(sintau(CAST(0.1 AS REAL)) * 100) AS sintau")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let expected = vec![
+        "+--------+",
+        "| sintau |",
+        "+--------+",
+        "| 59     |",
+        "+--------+",
+    ];
+
+    assert_batches_eq!(expected, &results);
+
+    // Dropping the function deletes its copy of the module
+    context
+        .collect(context.plan_query("DROP FUNCTION sintau").await.unwrap())
+        .await
+        .unwrap();
+    assert!(context
+        .internal_object_store
+        .inner
+        .head(&Path::from(format!("functions/{digest}.wasm")))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_create_function_location_not_allowed() {
+    let context = make_context_with_pg().await;
+
+    for location in ["file:///etc/passwd", "http://localhost:8080/module.wasm"] {
+        let err = context
+            .plan_query(&format!(
+                r#"CREATE FUNCTION sintau AS '
+    {{
+        "entrypoint": "sintau",
+        "language": "wasm",
+        "input_types": ["float"],
+        "return_type": "float",
+        "location": "{location}"
+    }}';"#
+            ))
+            .await
+            .unwrap_err();
+        assert_contains!(
+            err.to_string(),
+            format!("Loading function modules from {location:?} isn't allowed")
+        );
+    }
+}
@@ -1,3 +1,6 @@
+use datafusion::physical_plan::Statistics;
+use datafusion::scalar::ScalarValue;
+
 use crate::statements::*;
 
 #[tokio::test]
@@ -13,16 +16,22 @@ async fn test_information_schema() {
     let results = context.collect(plan).await.unwrap();
 
     let expected = vec![
-        "+---------------+--------------------+------------------+------------+",
-        "| table_catalog | table_schema       | table_name       | table_type |",
-        "+---------------+--------------------+------------------+------------+",
-        "| default       | information_schema | columns          | VIEW       |",
-        "| default       | information_schema | df_settings      | VIEW       |",
-        "| default       | system             | table_partitions | VIEW       |",
-        "| default       | system             | table_versions   | VIEW       |",
-        "| default       | information_schema | tables           | VIEW       |",
-        "| default       | information_schema | views            | VIEW       |",
-        "+---------------+--------------------+------------------+------------+",
+        "+---------------+--------------------+-------------------+------------+",
+        "| table_catalog | table_schema       | table_name        | table_type |",
+        "+---------------+--------------------+-------------------+------------+",
+        "| default       | information_schema | columns           | VIEW       |",
+        "| default       | information_schema | df_settings       | VIEW       |",
+        "| default       | system             | functions         | VIEW       |",
+        "| default       | system             | job_runs          | VIEW       |",
+        "| default       | system             | partition_columns | VIEW       |",
+        "| default       | system             | queries           | VIEW       |",
+        "| default       | system             | storage           | VIEW       |",
+        "| default       | system             | table_columns     | VIEW       |",
+        "| default       | system             | table_partitions  | VIEW       |",
+        "| default       | system             | table_versions    | VIEW       |",
+        "| default       | information_schema | tables            | VIEW       |",
+        "| default       | information_schema | views             | VIEW       |",
+        "+---------------+--------------------+-------------------+------------+",
     ];
 
     assert_batches_eq!(expected, &results);
@@ -42,20 +51,66 @@ async fn test_information_schema() {
     let results = context.collect(plan).await.unwrap();
 
     let expected = vec![
-        "+--------------+------------------+--------------------+-------------------------+-------------+",
-        "| table_schema | table_name       | column_name        | data_type               | is_nullable |",
-        "+--------------+------------------+--------------------+-------------------------+-------------+",
-        "| system       | table_partitions | table_schema       | Utf8                    | NO          |",
-        "| system       | table_partitions | table_name         | Utf8                    | NO          |",
-        "| system       | table_partitions | table_version_id   | Int64                   | NO          |",
-        "| system       | table_partitions | table_partition_id | Int64                   | YES         |",
-        "| system       | table_partitions | object_storage_id  | Utf8                    | YES         |",
-        "| system       | table_partitions | row_count          | Int32                   | YES         |",
-        "| system       | table_versions   | table_schema       | Utf8                    | NO          |",
-        "| system       | table_versions   | table_name         | Utf8                    | NO          |",
-        "| system       | table_versions   | table_version_id   | Int64                   | NO          |",
-        "| system       | table_versions   | creation_time      | Timestamp(Second, None) | NO          |",
-        "+--------------+------------------+--------------------+-------------------------+-------------+",
+        "+--------------+-------------------+--------------------+------------------------------+-------------+",
+        "| table_schema | table_name        | column_name        | data_type                    | is_nullable |",
+        "+--------------+-------------------+--------------------+------------------------------+-------------+",
+        "| system       | functions         | function_id        | Int64                        | NO          |",
+        "| system       | functions         | function_name      | Utf8                         | NO          |",
+        "| system       | functions         | entrypoint         | Utf8                         | NO          |",
+        "| system       | functions         | language           | Utf8                         | NO          |",
+        "| system       | functions         | input_types        | Utf8                         | NO          |",
+        "| system       | functions         | return_type        | Utf8                         | NO          |",
+        "| system       | functions         | volatility         | Utf8                         | NO          |",
+        "| system       | functions         | kind               | Utf8                         | NO          |",
+        "| system       | job_runs          | job_run_id         | Int64                        | NO          |",
+        "| system       | job_runs          | job_name           | Utf8                         | NO          |",
+        "| system       | job_runs          | start_time         | Timestamp(Second, None)      | NO          |",
+        "| system       | job_runs          | duration_ms        | Int64                        | YES         |",
+        "| system       | job_runs          | status             | Utf8                         | NO          |",
+        "| system       | job_runs          | error              | Utf8                         | YES         |",
+        "| system       | partition_columns | table_schema       | Utf8                         | NO          |",
+        "| system       | partition_columns | table_name         | Utf8                         | NO          |",
+        "| system       | partition_columns | table_version_id   | Int64                        | NO          |",
+        "| system       | partition_columns | table_partition_id | Int64                        | NO          |",
+        "| system       | partition_columns | column_name        | Utf8                         | NO          |",
+        "| system       | partition_columns | data_type          | Utf8                         | NO          |",
+        "| system       | partition_columns | min_value          | Utf8                         | YES         |",
+        "| system       | partition_columns | max_value          | Utf8                         | YES         |",
+        "| system       | partition_columns | null_count         | Int32                        | YES         |",
+        "| system       | partition_columns | distinct_count     | Int64                        | YES         |",
+        "| system       | queries           | query_id           | Int64                        | NO          |",
+        "| system       | queries           | frontend           | Utf8                         | NO          |",
+        "| system       | queries           | query              | Utf8                         | NO          |",
+        "| system       | queries           | principal          | Utf8                         | NO          |",
+        "| system       | queries           | status             | Utf8                         | NO          |",
+        "| system       | queries           | start_time         | Timestamp(Millisecond, None) | NO          |",
+        "| system       | queries           | end_time           | Timestamp(Millisecond, None) | YES         |",
+        "| system       | queries           | rows               | Int64                        | YES         |",
+        "| system       | queries           | bytes              | Int64                        | YES         |",
+        "| system       | queries           | error              | Utf8                         | YES         |",
+        "| system       | storage           | table_schema       | Utf8                         | NO          |",
+        "| system       | storage           | table_name         | Utf8                         | NO          |",
+        "| system       | storage           | table_partition_id | Int64                        | NO          |",
+        "| system       | storage           | object_storage_id  | Utf8                         | NO          |",
+        "| system       | storage           | row_count          | Int32                        | NO          |",
+        "| system       | storage           | size_bytes         | Int64                        | YES         |",
+        "| system       | storage           | in_latest_version  | Boolean                      | NO          |",
+        "| system       | table_columns     | table_schema       | Utf8                         | NO          |",
+        "| system       | table_columns     | table_name         | Utf8                         | NO          |",
+        "| system       | table_columns     | table_version_id   | Int64                        | NO          |",
+        "| system       | table_columns     | column_name        | Utf8                         | NO          |",
+        "| system       | table_columns     | data_type          | Utf8                         | NO          |",
+        "| system       | table_partitions  | table_schema       | Utf8                         | NO          |",
+        "| system       | table_partitions  | table_name         | Utf8                         | NO          |",
+        "| system       | table_partitions  | table_version_id   | Int64                        | NO          |",
+        "| system       | table_partitions  | table_partition_id | Int64                        | YES         |",
+        "| system       | table_partitions  | object_storage_id  | Utf8                         | YES         |",
+        "| system       | table_partitions  | row_count          | Int32                        | YES         |",
+        "| system       | table_versions    | table_schema       | Utf8                         | NO          |",
+        "| system       | table_versions    | table_name         | Utf8                         | NO          |",
+        "| system       | table_versions    | table_version_id   | Int64                        | NO          |",
+        "| system       | table_versions    | creation_time      | Timestamp(Second, None)      | NO          |",
+        "+--------------+-------------------+--------------------+------------------------------+-------------+",
     ];
     assert_batches_eq!(expected, &results);
 }
@@ -120,6 +175,149 @@ async fn test_create_table_and_insert() {
     assert_batches_eq!(expected, &results);
 }
 
+#[tokio::test]
+async fn test_system_table_columns_and_storage() {
+    let context = make_context_with_pg().await;
+    create_table_and_insert(&context, "test_table").await;
+
+    let plan = context
+        .plan_query(
+            "SELECT table_version_id, column_name, data_type FROM system.table_columns \
+            WHERE table_name = 'test_table' AND table_version_id = 2",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+------------------+------------------+-----------------------------+",
+        "| table_version_id | column_name      | data_type                   |",
+        "+------------------+------------------+-----------------------------+",
+        "| 2                | some_time        | Timestamp(Nanosecond, None) |",
+        "| 2                | some_value       | Float32                     |",
+        "| 2                | some_other_value | Decimal128(38, 10)          |",
+        "| 2                | some_bool_value  | Boolean                     |",
+        "| 2                | some_int_value   | Int64                       |",
+        "+------------------+------------------+-----------------------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            "SELECT table_partition_id, min_value, max_value, null_count \
+            FROM system.partition_columns \
+            WHERE table_name = 'test_table' AND column_name = 'some_int_value'",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+--------------------+-----------+-----------+------------+",
+        "| table_partition_id | min_value | max_value | null_count |",
+        "+--------------------+-----------+-----------+------------+",
+        "| 1                  | 1111      | 3333      | 0          |",
+        "+--------------------+-----------+-----------+------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Seafowl computes the statistics for all column types itself
+    let plan = context
+        .plan_query(
+            "SELECT column_name, min_value IS NOT NULL AS has_min, \
+            max_value IS NOT NULL AS has_max, null_count, distinct_count \
+            FROM system.partition_columns \
+            WHERE table_name = 'test_table' ORDER BY column_name",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+----------------+---------+---------+------------+----------------+",
+        "| column_name    | has_min | has_max | null_count | distinct_count |",
+        "+----------------+---------+---------+------------+----------------+",
+        "| some_int_value | true    | true    | 0          | 3              |",
+        "| some_time      | true    | true    | 0          | 3              |",
+        "| some_value     | true    | true    | 0          | 3              |",
+        "+----------------+---------+---------+------------+----------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            "SELECT table_name, table_partition_id, row_count, \
+            size_bytes > 0 AS has_size, in_latest_version FROM system.storage",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+------------+--------------------+-----------+----------+-------------------+",
+        "| table_name | table_partition_id | row_count | has_size | in_latest_version |",
+        "+------------+--------------------+-----------+----------+-------------------+",
+        "| test_table | 1                  | 3         | true     | true              |",
+        "+------------+--------------------+-----------+----------+-------------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+}
+
+#[tokio::test]
+async fn test_table_statistics_and_analyze() {
+    let context = make_context_with_pg().await;
+    create_table_and_insert(&context, "test_table").await;
+
+    // Get the statistics that the table scan reports to the planner
+    async fn scan_statistics(context: &DefaultSeafowlContext) -> Statistics {
+        let mut plan = context
+            .plan_query("SELECT some_int_value, some_value FROM test_table")
+            .await
+            .unwrap();
+        while let Some(child) = plan.children().first().cloned() {
+            plan = child;
+        }
+        plan.statistics()
+    }
+
+    let statistics = scan_statistics(&context).await;
+    assert_eq!(statistics.num_rows, Some(3));
+    let columns = statistics.column_statistics.unwrap();
+    assert_eq!(columns[0].min_value, Some(ScalarValue::Int64(Some(1111))));
+    assert_eq!(columns[0].max_value, Some(ScalarValue::Int64(Some(3333))));
+    assert_eq!(columns[0].null_count, Some(0));
+    assert_eq!(columns[0].distinct_count, None);
+    assert_eq!(columns[1].min_value, Some(ScalarValue::Float32(Some(42.0))));
+    assert_eq!(columns[1].max_value, Some(ScalarValue::Float32(Some(44.0))));
+
+    // Estimate the distinct counts
+    let plan = context
+        .plan_query("ANALYZE TABLE test_table")
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    let columns = scan_statistics(&context).await.column_statistics.unwrap();
+    assert_eq!(columns[0].distinct_count, Some(3));
+    assert_eq!(columns[1].distinct_count, Some(3));
+
+    // The statistics get aggregated across partitions (the new one doesn't have
+    // some_int_value at all), with the distinct counts carried over to the new version
+    let plan = context
+        .plan_query("INSERT INTO test_table (some_value) VALUES (45)")
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    let statistics = scan_statistics(&context).await;
+    assert_eq!(statistics.num_rows, Some(4));
+    let columns = statistics.column_statistics.unwrap();
+    assert_eq!(columns[0].min_value, Some(ScalarValue::Int64(Some(1111))));
+    assert_eq!(columns[0].null_count, Some(1));
+    assert_eq!(columns[0].distinct_count, Some(3));
+    assert_eq!(columns[1].max_value, Some(ScalarValue::Float32(Some(45.0))));
+}
+
 #[tokio::test]
 async fn test_table_time_travel() {
     let context = make_context_with_pg().await;
@@ -370,6 +568,26 @@ async fn test_table_time_travel() {
         "+--------------+------------+------------------+-----------------------------+",
     ];
     assert_batches_eq!(expected, &results);
+
+    //
+    // Time travel also works inside of a view's definition
+    //
+
+    let plan = context
+        .plan_query(
+            format!(
+                "CREATE VIEW test_view AS SELECT * FROM test_table('{}')",
+                timestamp_to_rfc3339(version_timestamps[&3])
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    let plan = context.plan_query("SELECT * FROM test_view").await.unwrap();
+    let results = context.collect(plan).await.unwrap();
+    assert_eq!(version_results[&3], results);
 }
 
 #[cfg(feature = "remote-tables")]
@@ -567,7 +785,7 @@ async fn test_remote_table_querying(
             "|               |     TableScan: staging.remote_table projection=[c, date field], full_filters=[staging.remote_table.date field > Utf8(\"2022-11-01\") OR staging.remote_table.c = Utf8(\"two\"), staging.remote_table.a > Int64(2) OR staging.remote_table.e < TimestampNanosecond(1667599865000000000, None)], fetch=2 |",
             "| physical_plan | ProjectionExec: expr=[date field@1 as date field, c@0 as c]                                                                                                                                                                                                                                        |",
             "|               |   GlobalLimitExec: skip=0, fetch=2                                                                                                                                                                                                                                                                 |",
-            "|               |     MemoryExec: partitions=1, partition_sizes=[1]                                                                                                                                                                                                                                                  |",
+            "|               |     RemoteScanExec: partitions=1                                                                                                                                                                                                                                                                   |",
             "|               |                                                                                                                                                                                                                                                                                                    |",
             "+---------------+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
         ]
@@ -581,13 +799,87 @@ async fn test_remote_table_querying(
             "|               |     TableScan: staging.remote_table projection=[c, date field], full_filters=[staging.remote_table.date field > Date32(\"19297\") OR staging.remote_table.c = Utf8(\"two\"), staging.remote_table.a > Int32(2) OR staging.remote_table.e < TimestampNanosecond(1667599865000000000, None)], fetch=2 |",
             "| physical_plan | ProjectionExec: expr=[date field@1 as date field, c@0 as c]                                                                                                                                                                                                                                     |",
             "|               |   GlobalLimitExec: skip=0, fetch=2                                                                                                                                                                                                                                                              |",
-            "|               |     ProjectionExec: expr=[c@0 as c, date field@1 as date field]                                                                                                                                                                                                                                 |",
-            "|               |       MemoryExec: partitions=1, partition_sizes=[1]                                                                                                                                                                                                                                             |",
+            "|               |     RemoteScanExec: partitions=1                                                                                                                                                                                                                                                                |",
             "|               |                                                                                                                                                                                                                                                                                                 |",
             "+---------------+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
         ]
     };
     assert_batches_eq!(expected, &results);
+
+    //
+    // Aggregations and joins over tables from the same remote database get pushed down to it
+    //
+    let plan = context
+        .plan_query(
+            "SELECT t1.c, COUNT(*) AS count, MAX(t2.a) AS max_a \
+            FROM staging.remote_table t1 JOIN staging.remote_table t2 ON t1.a = t2.a \
+            WHERE t2.a > 1 GROUP BY t1.c ORDER BY t1.c",
+        )
+        .await
+        .unwrap();
+    let plan_repr = format!(
+        "{}",
+        datafusion::physical_plan::displayable(plan.as_ref()).indent()
+    );
+    assert_contains!(&plan_repr, "RemoteScanExec: partitions=1");
+    assert!(!plan_repr.contains("AggregateExec"));
+    assert!(!plan_repr.contains("HashJoinExec"));
+
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+-------+-------+-------+",
+        "| c     | count | max_a |",
+        "+-------+-------+-------+",
+        "| four  | 1     | 4     |",
+        "| three | 1     | 3     |",
+        "| two   | 1     | 2     |",
+        "+-------+-------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    //
+    // Scans of a partitioned remote table get split into multiple queries
+    //
+    let plan = context
+        .plan_query(
+            format!(
+                "CREATE EXTERNAL TABLE partitioned_remote_table {table_column_schema}
+                STORED AS TABLE
+                OPTIONS ('name' '{table_name}', 'partition_on' 'a', 'partitions' '3')
+                LOCATION '{dsn}'"
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    context.collect(plan).await.unwrap();
+
+    let plan = context
+        .plan_query(
+            "SELECT a, c FROM staging.partitioned_remote_table WHERE a > 1 ORDER BY a",
+        )
+        .await
+        .unwrap();
+    assert_contains!(
+        format!(
+            "{}",
+            datafusion::physical_plan::displayable(plan.as_ref()).indent()
+        ),
+        "RemoteScanExec: partitions=3"
+    );
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = vec![
+        "+---+-------+",
+        "| a | c     |",
+        "+---+-------+",
+        "| 2 | two   |",
+        "| 3 | three |",
+        "| 4 | four  |",
+        "+---+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
 }
 
 #[cfg(feature = "delta-tables")]